@@ -0,0 +1,52 @@
+use chip8_core::{Chip8, Config};
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+
+const TICKS: u64 = 10_000;
+
+// Draws, stores and loads in a tight loop, the memory-heavy instructions
+// that the bounds checks guard
+const ROM: [u8; 14] = [
+    0xA3, 0x00, // LD I, 0x300
+    0xD0, 0x1F, // DRW V0, V1, 15
+    0xF7, 0x55, // LD [I], V7
+    0xF7, 0x65, // LD V7, [I]
+    0xF0, 0x33, // LD B, V0
+    0x70, 0x01, // ADD V0, 1
+    0x12, 0x00, // JP 0x200
+];
+
+fn machine(mem_size: usize) -> Chip8 {
+    let mut chip8 = Chip8::with_config(Config {
+        mem_size,
+        ..Config::default()
+    });
+    chip8.load(&ROM).unwrap();
+    chip8
+}
+
+fn bench_tick(c: &mut Criterion) {
+    // 4K machines can't skip the checks, so run_unchecked there shows the
+    // cost of falling back
+    for (name, mem_size) in [("64K", Config::MAX_MEM_SIZE), ("4K", 4096)] {
+        c.bench_function(&format!("tick {}", name), |b| {
+            let mut chip8 = machine(mem_size);
+            b.iter(|| {
+                for _ in 0..TICKS {
+                    chip8.tick().unwrap();
+                }
+                black_box(chip8.pc())
+            })
+        });
+        c.bench_function(&format!("run_unchecked {}", name), |b| {
+            let mut chip8 = machine(mem_size);
+            b.iter(|| {
+                chip8.run_unchecked(TICKS).unwrap();
+                black_box(chip8.pc())
+            })
+        });
+    }
+}
+
+criterion_group!(benches, bench_tick);
+criterion_main!(benches);
@@ -0,0 +1,452 @@
+use crate::symbols::Symbols;
+use crate::{MEM_SIZE, START_ADDR};
+use std::fmt;
+
+/// Output of a successful assembly
+#[derive(Clone, Debug)]
+pub struct Assembled {
+    pub rom: Vec<u8>,
+    pub symbols: Symbols,
+}
+
+/// Assembly failure, `line` and `column` are 1-based
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl AsmError {
+    fn new(line: usize, column: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Arg {
+    V(u16),
+    I,
+    IndI, // [I]
+    Dt,
+    St,
+    K,
+    F,
+    Hf, // large font
+    R,  // RPL user flags
+    B,
+    Long, // XO-CHIP F000, address in the next word
+    Num(u32),
+    Label(String),
+}
+
+struct Operand {
+    arg: Arg,
+    col: usize,
+}
+
+struct Stmt {
+    line: usize,
+    col: usize,
+    mnemonic: String,
+    operands: Vec<Operand>,
+}
+
+/// Assemble source into a ROM image that loads at 0x200
+///
+/// The dialect is the one printed by `disasm` (Cowgod's mnemonics):
+///
+/// ```text
+/// ; comments start with a semicolon
+/// start:  LD V0, 0x0A     ; numbers: 10, 0x0A, #0A, $0A, 0b1010
+///         LD I, sprite    ; labels can be used anywhere a number can
+///         DRW V0, V1, 5
+/// loop:   JP loop
+/// sprite: DB 0xF0, 0x90, 0xF0
+/// ```
+///
+/// `DB` and `DW` emit raw bytes and big-endian words. The SCHIP and XO-CHIP
+/// mnemonics are accepted too; `LD I, 0x1234` with a number past 0xFFF
+/// becomes XO-CHIP's four byte long load.
+pub fn assemble(src: &str) -> Result<Assembled, AsmError> {
+    let mut symbols = Symbols::new();
+    let mut stmts = Vec::new();
+    let mut addr = START_ADDR as usize;
+
+    // Pass 1: collect labels and work out where everything goes
+    for (i, text) in src.lines().enumerate() {
+        let line = i + 1;
+        let text = text.split(';').next().unwrap_or("");
+        let mut rest = text;
+
+        let stmt = loop {
+            let trimmed = rest.trim_start();
+            if trimmed.is_empty() {
+                break None;
+            }
+            let col = text.len() - trimmed.len() + 1;
+            let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+            let word = &trimmed[..end];
+
+            if let Some(name) = word.strip_suffix(':') {
+                if !is_ident(name) {
+                    return Err(AsmError::new(
+                        line,
+                        col,
+                        format!("invalid label '{}'", name),
+                    ));
+                }
+                if !symbols.insert(name, addr as u16) {
+                    return Err(AsmError::new(
+                        line,
+                        col,
+                        format!("duplicate label '{}'", name),
+                    ));
+                }
+                rest = &trimmed[end..];
+                continue;
+            }
+
+            let operands_text = &trimmed[end..];
+            let operands_col = col + end;
+            break Some(Stmt {
+                line,
+                col,
+                mnemonic: word.to_ascii_uppercase(),
+                operands: parse_operands(line, operands_col, operands_text)?,
+            });
+        };
+
+        if let Some(stmt) = stmt {
+            addr += match stmt.mnemonic.as_str() {
+                "DB" => stmt.operands.len(),
+                "DW" => stmt.operands.len() * 2,
+                _ if long_load(&stmt).is_some() => 4,
+                _ => 2,
+            };
+            if addr > MEM_SIZE {
+                return Err(AsmError::new(
+                    line,
+                    stmt.col,
+                    "program does not fit in memory",
+                ));
+            }
+            stmts.push(stmt);
+        }
+    }
+
+    // Pass 2: encode with every label known
+    let mut rom = Vec::with_capacity(addr - START_ADDR as usize);
+    for stmt in &stmts {
+        let resolve = |op: &Operand, max: u32| -> Result<u16, AsmError> {
+            let val = match &op.arg {
+                Arg::Num(n) => *n,
+                Arg::Label(name) => symbols.get(name).ok_or_else(|| {
+                    AsmError::new(stmt.line, op.col, format!("unknown label '{}'", name))
+                })? as u32,
+                _ => {
+                    return Err(AsmError::new(
+                        stmt.line,
+                        op.col,
+                        "expected a number or label",
+                    ))
+                }
+            };
+            if val > max {
+                return Err(AsmError::new(
+                    stmt.line,
+                    op.col,
+                    format!("value 0x{:X} is larger than 0x{:X}", val, max),
+                ));
+            }
+            Ok(val as u16)
+        };
+
+        if let Some(target) = long_load(stmt) {
+            rom.extend_from_slice(&[0xF0, 0x00]);
+            rom.extend_from_slice(&resolve(target, 0xFFFF)?.to_be_bytes());
+            continue;
+        }
+        match stmt.mnemonic.as_str() {
+            "DB" => {
+                for op in &stmt.operands {
+                    rom.push(resolve(op, 0xFF)? as u8);
+                }
+            }
+            "DW" => {
+                for op in &stmt.operands {
+                    rom.extend_from_slice(&resolve(op, 0xFFFF)?.to_be_bytes());
+                }
+            }
+            _ => {
+                let op = encode(stmt, &resolve)?;
+                rom.extend_from_slice(&op.to_be_bytes());
+            }
+        }
+    }
+
+    Ok(Assembled { rom, symbols })
+}
+
+/// The address operand of `LD I, n` when `n` only fits XO-CHIP's long form.
+/// Labels never need it, the program has to fit in 4K.
+fn long_load(stmt: &Stmt) -> Option<&Operand> {
+    match (stmt.mnemonic.as_str(), stmt.operands.as_slice()) {
+        ("LD", [i, target]) if i.arg == Arg::I => match target.arg {
+            Arg::Num(n) if n > 0xFFF => Some(target),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn encode(
+    stmt: &Stmt,
+    resolve: &dyn Fn(&Operand, u32) -> Result<u16, AsmError>,
+) -> Result<u16, AsmError> {
+    let ops = &stmt.operands;
+    let args: Vec<&Arg> = ops.iter().map(|o| &o.arg).collect();
+    let addr = |i: usize| resolve(&ops[i], 0xFFF);
+    let byte = |i: usize| resolve(&ops[i], 0xFF);
+    let nibble = |i: usize| resolve(&ops[i], 0xF);
+    let imm = |a: &Arg| matches!(a, Arg::Num(_) | Arg::Label(_));
+
+    let op = match (stmt.mnemonic.as_str(), args.as_slice()) {
+        ("NOP", []) => 0x0000,
+        ("CLS", []) => 0x00E0,
+        ("RET", []) => 0x00EE,
+        ("LOW", []) => 0x00FE,
+        ("HIGH", []) => 0x00FF,
+        ("SCD", [a]) if imm(a) => 0x00C0 | nibble(0)?,
+        ("SCU", [a]) if imm(a) => 0x00D0 | nibble(0)?,
+        ("SCR", []) => 0x00FB,
+        ("SCL", []) => 0x00FC,
+        ("EXIT", []) => 0x00FD,
+        ("SYS", [a]) if imm(a) => addr(0)?,
+        ("JP", [a]) if imm(a) => 0x1000 | addr(0)?,
+        ("JP", [Arg::V(0), a]) if imm(a) => 0xB000 | addr(1)?,
+        ("CALL", [a]) if imm(a) => 0x2000 | addr(0)?,
+        ("SE", [Arg::V(x), Arg::V(y)]) => 0x5000 | x << 8 | y << 4,
+        ("SE", [Arg::V(x), a]) if imm(a) => 0x3000 | x << 8 | byte(1)?,
+        ("SAVE", [Arg::V(x), Arg::V(y)]) => 0x5002 | x << 8 | y << 4,
+        ("LOAD", [Arg::V(x), Arg::V(y)]) => 0x5003 | x << 8 | y << 4,
+        ("SNE", [Arg::V(x), Arg::V(y)]) => 0x9000 | x << 8 | y << 4,
+        ("SNE", [Arg::V(x), a]) if imm(a) => 0x4000 | x << 8 | byte(1)?,
+        ("LD", [Arg::V(x), Arg::V(y)]) => 0x8000 | x << 8 | y << 4,
+        ("LD", [Arg::V(x), Arg::Dt]) => 0xF007 | x << 8,
+        ("LD", [Arg::V(x), Arg::K]) => 0xF00A | x << 8,
+        ("LD", [Arg::V(x), Arg::IndI]) => 0xF065 | x << 8,
+        ("LD", [Arg::V(x), Arg::R]) => 0xF085 | x << 8,
+        ("LD", [Arg::V(x), a]) if imm(a) => 0x6000 | x << 8 | byte(1)?,
+        ("LD", [Arg::I, a]) if imm(a) => 0xA000 | addr(1)?,
+        ("LD", [Arg::I, Arg::Long]) => 0xF000,
+        ("LD", [Arg::Dt, Arg::V(x)]) => 0xF015 | x << 8,
+        ("LD", [Arg::St, Arg::V(x)]) => 0xF018 | x << 8,
+        ("LD", [Arg::F, Arg::V(x)]) => 0xF029 | x << 8,
+        ("LD", [Arg::Hf, Arg::V(x)]) => 0xF030 | x << 8,
+        ("LD", [Arg::B, Arg::V(x)]) => 0xF033 | x << 8,
+        ("LD", [Arg::IndI, Arg::V(x)]) => 0xF055 | x << 8,
+        ("LD", [Arg::R, Arg::V(x)]) => 0xF075 | x << 8,
+        ("ADD", [Arg::V(x), Arg::V(y)]) => 0x8004 | x << 8 | y << 4,
+        ("ADD", [Arg::V(x), a]) if imm(a) => 0x7000 | x << 8 | byte(1)?,
+        ("ADD", [Arg::I, Arg::V(x)]) => 0xF01E | x << 8,
+        ("OR", [Arg::V(x), Arg::V(y)]) => 0x8001 | x << 8 | y << 4,
+        ("AND", [Arg::V(x), Arg::V(y)]) => 0x8002 | x << 8 | y << 4,
+        ("XOR", [Arg::V(x), Arg::V(y)]) => 0x8003 | x << 8 | y << 4,
+        ("SUB", [Arg::V(x), Arg::V(y)]) => 0x8005 | x << 8 | y << 4,
+        ("SHR", [Arg::V(x)]) => 0x8006 | x << 8,
+        ("SHR", [Arg::V(x), Arg::V(y)]) => 0x8006 | x << 8 | y << 4,
+        ("SUBN", [Arg::V(x), Arg::V(y)]) => 0x8007 | x << 8 | y << 4,
+        ("SHL", [Arg::V(x)]) => 0x800E | x << 8,
+        ("SHL", [Arg::V(x), Arg::V(y)]) => 0x800E | x << 8 | y << 4,
+        ("RND", [Arg::V(x), a]) if imm(a) => 0xC000 | x << 8 | byte(1)?,
+        ("DRW", [Arg::V(x), Arg::V(y), a]) if imm(a) => 0xD000 | x << 8 | y << 4 | nibble(2)?,
+        ("SKP", [Arg::V(x)]) => 0xE09E | x << 8,
+        ("SKNP", [Arg::V(x)]) => 0xE0A1 | x << 8,
+        ("PLANE", [a]) if imm(a) => 0xF001 | nibble(0)? << 8,
+        ("AUDIO", []) => 0xF002,
+        ("PITCH", [Arg::V(x)]) => 0xF03A | x << 8,
+        (
+            "NOP" | "CLS" | "RET" | "LOW" | "HIGH" | "SCD" | "SCU" | "SCR" | "SCL" | "EXIT" | "SYS"
+            | "JP" | "CALL" | "SE" | "SNE" | "SAVE" | "LOAD" | "LD" | "ADD" | "OR" | "AND" | "XOR"
+            | "SUB" | "SHR" | "SUBN" | "SHL" | "RND" | "DRW" | "SKP" | "SKNP" | "PLANE" | "AUDIO"
+            | "PITCH",
+            _,
+        ) => {
+            return Err(AsmError::new(
+                stmt.line,
+                stmt.col,
+                format!("invalid operands for {}", stmt.mnemonic),
+            ))
+        }
+        (m, _) => {
+            return Err(AsmError::new(
+                stmt.line,
+                stmt.col,
+                format!("unknown mnemonic '{}'", m),
+            ))
+        }
+    };
+    Ok(op)
+}
+
+/// Split a comma separated operand list, keeping track of each operand's column
+fn parse_operands(line: usize, start_col: usize, text: &str) -> Result<Vec<Operand>, AsmError> {
+    let mut operands = Vec::new();
+    if text.trim().is_empty() {
+        return Ok(operands);
+    }
+
+    let mut col = start_col;
+    for part in text.split(',') {
+        let trimmed = part.trim_start();
+        let op_col = col + part.len() - trimmed.len();
+        let word = trimmed.trim_end();
+        if word.is_empty() {
+            return Err(AsmError::new(line, op_col, "missing operand"));
+        }
+        operands.push(Operand {
+            arg: parse_arg(word).ok_or_else(|| {
+                AsmError::new(line, op_col, format!("invalid operand '{}'", word))
+            })?,
+            col: op_col,
+        });
+        col += part.len() + 1;
+    }
+    Ok(operands)
+}
+
+fn parse_arg(word: &str) -> Option<Arg> {
+    let upper = word.to_ascii_uppercase();
+    let arg = match upper.as_str() {
+        "I" => Arg::I,
+        "[I]" => Arg::IndI,
+        "DT" => Arg::Dt,
+        "ST" => Arg::St,
+        "K" => Arg::K,
+        "F" => Arg::F,
+        "HF" => Arg::Hf,
+        "R" => Arg::R,
+        "B" => Arg::B,
+        "LONG" => Arg::Long,
+        _ if upper.len() == 2 && upper.starts_with('V') => {
+            Arg::V(u16::from_str_radix(&upper[1..], 16).ok()?)
+        }
+        _ => match parse_number(word) {
+            Some(n) => Arg::Num(n),
+            None if is_ident(word) => Arg::Label(word.to_string()),
+            None => return None,
+        },
+    };
+    Some(arg)
+}
+
+fn parse_number(word: &str) -> Option<u32> {
+    let lower = word.to_ascii_lowercase();
+    if let Some(hex) = lower
+        .strip_prefix("0x")
+        .or_else(|| lower.strip_prefix('#'))
+        .or_else(|| lower.strip_prefix('$'))
+    {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        u32::from_str_radix(bin, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+fn is_ident(word: &str) -> bool {
+    let mut chars = word.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::disassemble_op;
+
+    #[test]
+    fn round_trips_through_disassembler() {
+        let ops: [u16; 50] = [
+            0x0000, 0x00E0, 0x00EE, 0x00C3, 0x00D4, 0x00FB, 0x00FC, 0x00FD, 0x00FE, 0x00FF, 0x1234,
+            0x2345, 0x3A12, 0x4B34, 0x5120, 0x5122, 0x5123, 0x6A2F, 0x7C01, 0x8120, 0x8121, 0x8122,
+            0x8123, 0x8124, 0x8125, 0x8126, 0x8127, 0x812E, 0x9120, 0xA123, 0xB321, 0xC1FF, 0xD125,
+            0xE19E, 0xE2A1, 0xF000, 0xF201, 0xF002, 0xF307, 0xF40A, 0xF515, 0xF618, 0xF71E, 0xF829,
+            0xF330, 0xF53A, 0xF933, 0xFA55, 0xF375, 0xF485,
+        ];
+        let src: String = ops
+            .iter()
+            .map(|&op| disassemble_op(op) + "\n")
+            .collect::<String>()
+            + "LD VB, [I]\n";
+
+        let out = assemble(&src).unwrap();
+        let words: Vec<u16> = out
+            .rom
+            .chunks(2)
+            .map(|w| u16::from_be_bytes([w[0], w[1]]))
+            .collect();
+        assert_eq!(&words[..ops.len()], &ops);
+        assert_eq!(words[ops.len()], 0xFB65);
+    }
+
+    #[test]
+    fn labels_and_data() {
+        let src = "\
+start:  LD I, sprite   ; forward reference
+        jp start
+sprite: db 0xF0, #90, $F0, 0b1
+        DW 0x1234
+";
+        let out = assemble(src).unwrap();
+        assert_eq!(
+            out.rom,
+            vec![0xA2, 0x04, 0x12, 0x00, 0xF0, 0x90, 0xF0, 0x01, 0x12, 0x34]
+        );
+        assert_eq!(out.symbols.get("start"), Some(0x200));
+        assert_eq!(out.symbols.get("sprite"), Some(0x204));
+    }
+
+    #[test]
+    fn long_load() {
+        let out = assemble("LD I, 0x1234\nLD I, 0x234\nend: EXIT").unwrap();
+        assert_eq!(
+            out.rom,
+            vec![0xF0, 0x00, 0x12, 0x34, 0xA2, 0x34, 0x00, 0xFD]
+        );
+        assert_eq!(out.symbols.get("end"), Some(0x206));
+    }
+
+    #[test]
+    fn errors_have_line_and_column() {
+        let err = assemble("CLS\n  LD V0, 0x100").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 10));
+
+        let err = assemble("  FOO V1").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 3));
+        assert_eq!(err.message, "unknown mnemonic 'FOO'");
+
+        let err = assemble("JP nowhere").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 4));
+
+        let err = assemble("a: CLS\na: RET").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 1));
+
+        let err = assemble("ADD V1, I").unwrap_err();
+        assert_eq!(err.message, "invalid operands for ADD");
+    }
+}
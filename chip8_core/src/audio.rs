@@ -0,0 +1,84 @@
+use crate::Chip8;
+
+/// XO-CHIP audio patterns are 16 bytes, 128 one-bit samples played high bit first
+pub const AUDIO_PATTERN_SIZE: usize = 16;
+
+/// Square wave played until a game loads its own pattern, 500Hz at the
+/// default pitch
+pub(crate) const DEFAULT_PATTERN: [u8; AUDIO_PATTERN_SIZE] = [0xF0; AUDIO_PATTERN_SIZE];
+/// Pitch register value that plays the pattern at 4000 samples a second
+pub(crate) const DEFAULT_PITCH: u8 = 64;
+
+impl Chip8 {
+    /// Fill `buf` with mono samples at `rate` Hz for the current sound
+    ///
+    /// While the sound timer runs the audio pattern loops at the speed set by
+    /// the pitch register, lit bits are 1.0 and clear ones -1.0; otherwise
+    /// it's silence. Playback carries on from where the last call stopped, so
+    /// call it from the host's audio callback with each buffer it asks for.
+    pub fn fill_audio_buffer(&mut self, rate: u32, buf: &mut [f32]) {
+        if self.st == 0 {
+            buf.fill(0.0);
+            return;
+        }
+        let bits = (AUDIO_PATTERN_SIZE * 8) as f64;
+        let step = self.playback_rate() / rate as f64;
+        for sample in buf {
+            let bit = self.audio_pos as usize;
+            let on = self.audio_pattern[bit / 8] & (0x80 >> (bit % 8)) != 0;
+            *sample = if on { 1.0 } else { -1.0 };
+            self.audio_pos = (self.audio_pos + step) % bits;
+        }
+    }
+
+    /// Pattern samples played per second, `4000 * 2^((pitch - 64) / 48)`
+    pub fn playback_rate(&self) -> f64 {
+        4000.0 * 2f64.powf((self.pitch as f64 - 64.0) / 48.0)
+    }
+
+    /// The XO-CHIP audio pattern, set by `F002`
+    pub fn audio_pattern(&self) -> &[u8; AUDIO_PATTERN_SIZE] {
+        &self.audio_pattern
+    }
+
+    /// The XO-CHIP pitch register, set by `Fx3A`
+    pub fn pitch(&self) -> u8 {
+        self.pitch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Chip8, Config, Variant};
+
+    #[test]
+    fn pattern_playback() {
+        let mut c8 = Chip8::with_config(Config {
+            variant: Variant::XoChip,
+            ..Config::default()
+        });
+        // LD I, 0x20C; AUDIO; LD V0, 112; PITCH V0; LD ST, V0; JP 0x20A;
+        // then a pattern with only the first 2 bits lit
+        let mut rom = vec![
+            0xA2, 0x0C, 0xF0, 0x02, 0x60, 0x70, 0xF0, 0x3A, 0xF0, 0x18, 0x12, 0x0A, 0xC0,
+        ];
+        rom.extend([0; 15]);
+        c8.load(&rom).unwrap();
+
+        let mut buf = [0.5; 4];
+        c8.fill_audio_buffer(8000, &mut buf);
+        assert_eq!(buf, [0.0; 4]);
+
+        for _ in 0..5 {
+            c8.tick().unwrap();
+        }
+        assert_eq!(c8.pitch(), 112);
+        // Pitch 112 is one octave up, 8000 samples a second
+        assert_eq!(c8.playback_rate(), 8000.0);
+        c8.fill_audio_buffer(8000, &mut buf);
+        assert_eq!(buf, [1.0, 1.0, -1.0, -1.0]);
+        // Sample rates above the pattern's repeat samples
+        c8.fill_audio_buffer(16000, &mut buf);
+        assert_eq!(buf, [-1.0; 4]);
+    }
+}
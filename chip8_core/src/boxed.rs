@@ -0,0 +1,33 @@
+//! Cloning for the boxed extension points
+//!
+//! `Chip8` is `Clone`, so everything it keeps in a `Box<dyn Trait>` (the
+//! random source, opcode handlers, exec hooks, flag storage and the mapper)
+//! has to be cloneable too. `Clone` itself can't be a supertrait of a trait
+//! object, so each trait gets a helper supertrait with a `clone_box` method
+//! instead. It's implemented automatically for any `Clone` type, so
+//! implementors only need `#[derive(Clone)]`.
+
+/// Declare `$helper` as the cloning supertrait of `$target`, see the module
+/// docs
+macro_rules! clone_box {
+    ($helper:ident, $target:ident) => {
+        #[doc = concat!("Lets `Chip8` stay `Clone` with a boxed `", stringify!($target), "` inside.")]
+        ///
+        /// Implemented automatically for any `Clone` type.
+        pub trait $helper {
+            fn clone_box(&self) -> Box<dyn $target>;
+        }
+
+        impl<T: $target + Clone + 'static> $helper for T {
+            fn clone_box(&self) -> Box<dyn $target> {
+                Box::new(self.clone())
+            }
+        }
+
+        impl Clone for Box<dyn $target> {
+            fn clone(&self) -> Self {
+                self.clone_box()
+            }
+        }
+    };
+}
@@ -0,0 +1,89 @@
+use crate::Chip8;
+
+/// One subroutine call that hasn't returned yet, see `call_frames`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CallFrame {
+    /// Where the `CALL` jumped to, `None` if the stack entry came from a
+    /// save state or was pushed by something other than `CALL`
+    pub entry: Option<u16>,
+    /// Where `RET` goes back to
+    pub return_addr: u16,
+}
+
+impl Chip8 {
+    /// Calls in progress, outermost first, pairing each return address on
+    /// `stack` with the subroutine it called
+    pub fn call_frames(&self) -> Vec<CallFrame> {
+        self.stack()
+            .iter()
+            .enumerate()
+            .map(|(slot, &return_addr)| CallFrame {
+                entry: self.call_targets.get(slot).copied().flatten(),
+                return_addr,
+            })
+            .collect()
+    }
+
+    /// Entry point of the subroutine the PC is in, `None` at the top level
+    pub fn current_subroutine(&self) -> Option<u16> {
+        self.call_frames().last().and_then(|frame| frame.entry)
+    }
+
+    /// Every address `CALL` has jumped to since reset, ascending
+    ///
+    /// Kept after the calls return, so the nearest one at or below an
+    /// address is a good guess at which subroutine it belongs to.
+    pub fn subroutines(&self) -> impl Iterator<Item = u16> + '_ {
+        self.subroutines.iter().copied()
+    }
+
+    /// Note a `CALL` to `target`, after its return address was pushed
+    pub(crate) fn record_call(&mut self, target: u16) {
+        if let Some(slot) = self.call_targets.get_mut(self.sp as usize - 1) {
+            *slot = Some(target);
+        }
+        self.subroutines.insert(target);
+    }
+
+    /// Drop the call targets when the stack is replaced wholesale
+    pub(crate) fn forget_calls(&mut self) {
+        self.call_targets = vec![None; self.stack.len()];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_and_history() {
+        let mut c8 = Chip8::new();
+        // CALL 0x204; JP 0x202; CALL 0x208; RET; RET
+        c8.load(&[0x22, 0x04, 0x12, 0x02, 0x22, 0x08, 0x00, 0xEE, 0x00, 0xEE])
+            .unwrap();
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        assert_eq!(
+            c8.call_frames(),
+            vec![
+                CallFrame {
+                    entry: Some(0x204),
+                    return_addr: 0x202
+                },
+                CallFrame {
+                    entry: Some(0x208),
+                    return_addr: 0x206
+                },
+            ]
+        );
+        assert_eq!(c8.current_subroutine(), Some(0x208));
+
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        assert_eq!(c8.current_subroutine(), None);
+        assert_eq!(c8.subroutines().collect::<Vec<_>>(), [0x204, 0x208]);
+
+        c8.reset();
+        assert_eq!(c8.subroutines().count(), 0);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::{Chip8, ConditionError};
+use std::fmt;
+use std::str::FromStr;
+
+/// A comparison on machine state, e.g. `V3 == 0x1F` or `I >= 0x300`
+///
+/// Either side is a register (`V0`-`VF`, `I`, `PC`, `DT`, `ST`, `SP`) or a
+/// number, hexadecimal with a `0x` prefix or decimal. Used for conditional
+/// breakpoints and watches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Condition {
+    lhs: Operand,
+    cmp: Comparison,
+    rhs: Operand,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operand {
+    V(usize),
+    I,
+    Pc,
+    Dt,
+    St,
+    Sp,
+    Value(u16),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Condition {
+    /// Whether the condition holds for `c8` as it is now
+    pub fn eval(&self, c8: &Chip8) -> bool {
+        let (lhs, rhs) = (self.lhs.value(c8), self.rhs.value(c8));
+        match self.cmp {
+            Comparison::Eq => lhs == rhs,
+            Comparison::Ne => lhs != rhs,
+            Comparison::Lt => lhs < rhs,
+            Comparison::Le => lhs <= rhs,
+            Comparison::Gt => lhs > rhs,
+            Comparison::Ge => lhs >= rhs,
+        }
+    }
+}
+
+impl Operand {
+    fn value(self, c8: &Chip8) -> u16 {
+        match self {
+            Operand::V(idx) => c8.v_reg()[idx] as u16,
+            Operand::I => c8.i_reg(),
+            Operand::Pc => c8.pc(),
+            Operand::Dt => c8.dt() as u16,
+            Operand::St => c8.st() as u16,
+            Operand::Sp => c8.sp(),
+            Operand::Value(val) => val,
+        }
+    }
+}
+
+impl FromStr for Condition {
+    type Err = ConditionError;
+
+    fn from_str(s: &str) -> Result<Self, ConditionError> {
+        // The operator is the first run of comparison characters, so spaces
+        // around it are optional
+        let is_cmp = |c: char| "=!<>".contains(c);
+        let start = s.find(is_cmp).ok_or(ConditionError::Missing)?;
+        let len = s[start..].find(|c| !is_cmp(c)).unwrap_or(s.len() - start);
+        let (lhs, op, rhs) = (&s[..start], &s[start..start + len], &s[start + len..]);
+        let cmp = match op {
+            "==" => Comparison::Eq,
+            "!=" => Comparison::Ne,
+            "<" => Comparison::Lt,
+            "<=" => Comparison::Le,
+            ">" => Comparison::Gt,
+            ">=" => Comparison::Ge,
+            _ => return Err(ConditionError::Operator(op.to_string())),
+        };
+        Ok(Condition {
+            lhs: lhs.parse()?,
+            cmp,
+            rhs: rhs.parse()?,
+        })
+    }
+}
+
+impl FromStr for Operand {
+    type Err = ConditionError;
+
+    fn from_str(s: &str) -> Result<Self, ConditionError> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(ConditionError::Missing);
+        }
+        let bad = || ConditionError::Operand(s.to_string());
+        let operand = match s.to_ascii_uppercase().as_str() {
+            "I" => Operand::I,
+            "PC" => Operand::Pc,
+            "DT" => Operand::Dt,
+            "ST" => Operand::St,
+            "SP" => Operand::Sp,
+            reg if reg.len() == 2 && reg.starts_with('V') => {
+                Operand::V(usize::from_str_radix(&reg[1..], 16).map_err(|_| bad())?)
+            }
+            num => match num.strip_prefix("0X") {
+                Some(hex) => Operand::Value(u16::from_str_radix(hex, 16).map_err(|_| bad())?),
+                None => Operand::Value(num.parse().map_err(|_| bad())?),
+            },
+        };
+        Ok(operand)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.cmp {
+            Comparison::Eq => "==",
+            Comparison::Ne => "!=",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+        };
+        write!(f, "{} {} {}", self.lhs, op, self.rhs)
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::V(idx) => write!(f, "V{:X}", idx),
+            Operand::I => write!(f, "I"),
+            Operand::Pc => write!(f, "PC"),
+            Operand::Dt => write!(f, "DT"),
+            Operand::St => write!(f, "ST"),
+            Operand::Sp => write!(f, "SP"),
+            Operand::Value(val) => write!(f, "0x{:X}", val),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_print() {
+        let cond: Condition = "v3==0x1f".parse().unwrap();
+        assert_eq!(cond.to_string(), "V3 == 0x1F");
+        assert_eq!(
+            "I >= 768".parse::<Condition>().unwrap().to_string(),
+            "I >= 0x300"
+        );
+
+        assert_eq!("V3".parse::<Condition>(), Err(ConditionError::Missing));
+        assert_eq!("V3 == ".parse::<Condition>(), Err(ConditionError::Missing));
+        assert_eq!(
+            "V3 =< 1".parse::<Condition>(),
+            Err(ConditionError::Operator("=<".to_string()))
+        );
+        assert_eq!(
+            "VG == 1".parse::<Condition>(),
+            Err(ConditionError::Operand("VG".to_string()))
+        );
+    }
+
+    #[test]
+    fn eval() {
+        let mut c8 = Chip8::new();
+        c8.set_v(3, 0x1F).unwrap();
+        assert!("V3 == 0x1F".parse::<Condition>().unwrap().eval(&c8));
+        assert!(!"V3 < V0".parse::<Condition>().unwrap().eval(&c8));
+        assert!("PC > 0x1FF".parse::<Condition>().unwrap().eval(&c8));
+    }
+}
@@ -0,0 +1,247 @@
+use crate::{ConfigError, Quirks, UnknownVariant, FONT_END, MEM_SIZE, STACK_SIZE, START_ADDR};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Construction-time machine parameters, see `Chip8::with_config`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Bytes of RAM, from `MIN_MEM_SIZE` up to 64K. Defaults to 4K.
+    pub mem_size: usize,
+    /// Number of nested CALLs allowed. Defaults to 16; some historical
+    /// interpreters allowed more.
+    pub stack_size: usize,
+    /// Fill RAM, V registers and the screen with garbage from this seed on
+    /// power-on and reset instead of zeros, like real hardware. Helps ROM
+    /// authors catch reads of uninitialised state. Defaults to off.
+    pub power_on_seed: Option<u32>,
+    /// Seed for `RND`, so the same inputs always give the same run. Defaults
+    /// to off, which uses the rand crate (or a fixed seed without it).
+    pub rng_seed: Option<u64>,
+    /// Which opcodes are available. Defaults to CHIP-8; use `Variant::config`
+    /// to get the matching quirks and memory size too.
+    pub variant: Variant,
+    /// Interpreter differences to emulate, can be changed later with
+    /// `Chip8::set_quirks`. Defaults to none.
+    pub quirks: Quirks,
+    /// What happens when the PC runs past the end of RAM. Defaults to an error.
+    pub pc_overflow: PcOverflow,
+    /// Where ROMs are loaded and execution starts, also after `reset`.
+    /// Defaults to 0x200; ETI-660 programs start at 0x600.
+    pub start_addr: u16,
+    /// How many executed instructions `Chip8::recent_instructions` keeps.
+    /// Defaults to 32, 0 turns it off.
+    pub history_size: usize,
+}
+
+/// Policy for a program counter that runs off the end of RAM
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PcOverflow {
+    /// `tick` fails with `Chip8Error::AddressOutOfRange`
+    #[default]
+    Error,
+    /// Carry on from address 0, as if the address bus was only as wide as RAM
+    Wrap,
+    /// Stop there like a program that jumps to itself: `tick` keeps
+    /// succeeding without running anything and emits `Event::Halt` once
+    Trap,
+}
+
+/// Interpreter families, each with its own quirks, memory size and opcodes
+///
+/// `Chip8::with_variant` (or `Variant::config`) sets all of them in one go.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Variant {
+    /// This interpreter's defaults: CHIP-8 plus the SCHIP opcodes, no quirks
+    #[default]
+    Chip8,
+    /// The original 1977 interpreter
+    CosmacVip,
+    /// The HP-48 port that SCHIP grew out of
+    Chip48,
+    /// SCHIP 1.1 as found on modern emulators
+    #[cfg_attr(feature = "serde", serde(rename = "schip"))]
+    SuperChip,
+    /// Octo's XO-CHIP: long loads, register ranges, planes and scroll up,
+    /// with 64K of RAM
+    XoChip,
+    /// The VIP with the HIRES CHIP-8 patch, programs starting `1260` get a
+    /// 64x64 display
+    HiresChip8,
+    /// The ETI-660 kit computer, VIP quirks with programs at 0x600
+    Eti660,
+    /// SCHIP plus Mega-CHIP's 256x192 colour mode, with 64K of RAM (so no
+    /// addresses past that)
+    #[cfg(feature = "megachip")]
+    MegaChip,
+}
+
+impl Variant {
+    /// Every variant, in declaration order
+    pub const ALL: &'static [Variant] = &[
+        Variant::Chip8,
+        Variant::CosmacVip,
+        Variant::Chip48,
+        Variant::SuperChip,
+        Variant::XoChip,
+        Variant::HiresChip8,
+        Variant::Eti660,
+        #[cfg(feature = "megachip")]
+        Variant::MegaChip,
+    ];
+
+    /// Names of `ALL`, the same ones settings files and save states use
+    pub const NAMES: &'static [&'static str] = &[
+        "chip8",
+        "cosmac-vip",
+        "chip48",
+        "schip",
+        "xo-chip",
+        "hires-chip8",
+        "eti660",
+        #[cfg(feature = "megachip")]
+        "mega-chip",
+    ];
+
+    /// The variant's name, e.g. `schip`
+    pub fn name(self) -> &'static str {
+        Self::NAMES[self as usize]
+    }
+
+    /// The quirks ROMs written for this interpreter expect
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Variant::Chip8 => Quirks::default(),
+            Variant::CosmacVip | Variant::HiresChip8 | Variant::Eti660 => Quirks {
+                shift_vy: true,
+                memory_increment: true,
+                jump_vx: false,
+                clip_sprites: true,
+                vf_reset: true,
+                half_pixel_scroll: false,
+            },
+            Variant::Chip48 | Variant::SuperChip => Quirks {
+                jump_vx: true,
+                clip_sprites: true,
+                ..Quirks::default()
+            },
+            #[cfg(feature = "megachip")]
+            Variant::MegaChip => Variant::SuperChip.quirks(),
+            Variant::XoChip => Quirks {
+                shift_vy: true,
+                memory_increment: true,
+                ..Quirks::default()
+            },
+        }
+    }
+
+    /// Whether the SCHIP opcodes (00FE/00FF and friends) are available
+    pub fn has_schip(self) -> bool {
+        !matches!(
+            self,
+            Variant::CosmacVip | Variant::Chip48 | Variant::HiresChip8 | Variant::Eti660
+        )
+    }
+
+    /// A complete config for this interpreter
+    pub fn config(self) -> Config {
+        Config {
+            mem_size: match self {
+                Variant::XoChip => Config::MAX_MEM_SIZE,
+                #[cfg(feature = "megachip")]
+                Variant::MegaChip => Config::MAX_MEM_SIZE,
+                _ => MEM_SIZE,
+            },
+            variant: self,
+            quirks: self.quirks(),
+            start_addr: match self {
+                Variant::Eti660 => 0x600,
+                _ => START_ADDR,
+            },
+            ..Config::default()
+        }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = UnknownVariant;
+
+    fn from_str(s: &str) -> Result<Self, UnknownVariant> {
+        let idx = Self::NAMES.iter().position(|name| *name == s);
+        idx.map(|idx| Self::ALL[idx])
+            .ok_or_else(|| UnknownVariant(s.to_string()))
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            mem_size: MEM_SIZE,
+            stack_size: STACK_SIZE,
+            power_on_seed: None,
+            rng_seed: None,
+            variant: Variant::Chip8,
+            quirks: Quirks::default(),
+            pc_overflow: PcOverflow::Error,
+            start_addr: START_ADDR,
+            history_size: 32,
+        }
+    }
+}
+
+impl Config {
+    /// Largest address space a 16 bit PC/I can reach
+    pub const MAX_MEM_SIZE: usize = 0x10000;
+    /// Smallest RAM that holds the fonts and one instruction at 0x200
+    pub const MIN_MEM_SIZE: usize = START_ADDR as usize + 2;
+
+    /// Check the config can build a machine, see `Chip8::try_with_config`
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !(Self::MIN_MEM_SIZE..=Self::MAX_MEM_SIZE).contains(&self.mem_size) {
+            return Err(ConfigError::MemSize(self.mem_size));
+        }
+        if self.stack_size == 0 {
+            return Err(ConfigError::StackSize);
+        }
+        let max = self.mem_size - 2;
+        if !(FONT_END..=max).contains(&(self.start_addr as usize)) {
+            return Err(ConfigError::StartAddr {
+                addr: self.start_addr,
+                max,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variant_names() {
+        for (idx, variant) in Variant::ALL.iter().enumerate() {
+            assert_eq!(*variant as usize, idx);
+            assert_eq!(variant.name().parse(), Ok(*variant));
+            #[cfg(feature = "serde")]
+            assert_eq!(
+                serde_json::to_string(variant).unwrap(),
+                format!("\"{}\"", variant.name())
+            );
+        }
+        assert_eq!(Variant::SuperChip.to_string(), "schip");
+        assert_eq!(
+            "eti-660".parse::<Variant>(),
+            Err(UnknownVariant("eti-660".to_string()))
+        );
+    }
+}
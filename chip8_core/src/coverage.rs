@@ -0,0 +1,74 @@
+use crate::opcodes::OPCODES;
+use crate::{decode, Chip8, ExecHook};
+use std::collections::BTreeMap;
+
+/// Which opcode patterns (`8xy4`, `Fx55`, ...) a ROM executed and how often
+///
+/// Add one with `Chip8::add_exec_hook` and read it back with
+/// `Chip8::exec_hook::<Coverage>()`, e.g. to check a test ROM covers the
+/// instructions it claims to. Instructions that fault aren't counted.
+#[derive(Clone, Debug, Default)]
+pub struct Coverage {
+    counts: BTreeMap<String, u64>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Times instructions matching `pattern` ran, as written in `OPCODES`
+    pub fn count(&self, pattern: &str) -> u64 {
+        self.counts.get(pattern).copied().unwrap_or(0)
+    }
+
+    /// Patterns that ran at least once with their counts, in pattern order
+    pub fn executed(&self) -> Vec<(&str, u64)> {
+        self.counts
+            .iter()
+            .map(|(pattern, count)| (pattern.as_str(), *count))
+            .collect()
+    }
+
+    /// Patterns in `OPCODES` that never ran, whatever the variant
+    pub fn missed(&self) -> Vec<&'static str> {
+        OPCODES
+            .iter()
+            .map(|info| info.pattern)
+            .filter(|pattern| !self.counts.contains_key(*pattern))
+            .collect()
+    }
+}
+
+impl ExecHook for Coverage {
+    fn after(&mut self, _chip8: &mut Chip8, op: u16) {
+        *self.counts.entry(decode(op).pattern()).or_default() += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_patterns() {
+        let mut c8 = Chip8::new();
+        c8.add_exec_hook(Box::new(Coverage::new()));
+        // LD V0, 1; ADD V0, 1; ADD V1, 2; LD V2, V0; JP 0x200
+        c8.load(&[0x60, 0x01, 0x70, 0x01, 0x71, 0x02, 0x82, 0x00, 0x12, 0x00])
+            .unwrap();
+        for _ in 0..7 {
+            c8.tick().unwrap();
+        }
+        let coverage = c8.exec_hook::<Coverage>().unwrap();
+        assert_eq!(
+            coverage.executed(),
+            [("1nnn", 1), ("6xnn", 2), ("7xnn", 3), ("8xy0", 1)]
+        );
+        assert_eq!(coverage.count("7xnn"), 3);
+        assert_eq!(coverage.count("Dxyn"), 0);
+        let missed = coverage.missed();
+        assert!(missed.contains(&"Dxyn") && !missed.contains(&"8xy0"));
+        assert_eq!(missed.len(), OPCODES.len() - 4);
+    }
+}
@@ -0,0 +1,86 @@
+use crate::Chip8;
+use std::any::Any;
+
+/// Extension point for opcodes the core doesn't implement
+///
+/// Registered handlers are asked, in registration order, to execute any
+/// opcode the interpreter doesn't recognise. They can't override built-in
+/// instructions. `execute` returns false to pass the opcode on to the next
+/// handler; if nobody claims it the opcode is treated as unknown.
+///
+/// When called, the PC already points past the opcode.
+pub trait OpcodeHandler: HandlerClone + Any {
+    fn execute(&mut self, chip8: &mut Chip8, op: u16) -> bool;
+}
+
+clone_box!(HandlerClone, OpcodeHandler);
+
+impl Chip8 {
+    /// Add a handler for otherwise unknown opcodes
+    pub fn register_opcodes(&mut self, handler: Box<dyn OpcodeHandler>) {
+        self.handlers.push(handler);
+    }
+
+    /// Find a registered handler by type, e.g. to read its results
+    pub fn opcode_handler<T: OpcodeHandler>(&self) -> Option<&T> {
+        self.handlers
+            .iter()
+            .find_map(|h| (h.as_ref() as &dyn Any).downcast_ref::<T>())
+    }
+
+    /// Offer `op` to the registered handlers, returns true if one ran it
+    pub(crate) fn run_opcode_handlers(&mut self, op: u16) -> bool {
+        // Handlers get mutable access to the machine, so take them out while they run
+        let mut handlers = std::mem::take(&mut self.handlers);
+        let handled = handlers.iter_mut().any(|h| h.execute(self, op));
+        // A handler may have registered more handlers, keep those too
+        handlers.append(&mut self.handlers);
+        self.handlers = handlers;
+        handled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chip8Error;
+
+    /// 0x01NN adds NN to V0
+    #[derive(Clone)]
+    struct AddV0;
+
+    impl OpcodeHandler for AddV0 {
+        fn execute(&mut self, chip8: &mut Chip8, op: u16) -> bool {
+            if op & 0xFF00 != 0x0100 {
+                return false;
+            }
+            chip8.v_reg[0] = chip8.v_reg[0].wrapping_add(op as u8);
+            true
+        }
+    }
+
+    #[test]
+    fn custom_opcode_runs() {
+        let mut c8 = Chip8::new();
+        c8.register_opcodes(Box::new(AddV0));
+        c8.load(&[0x01, 0x05, 0x01, 0x03]).unwrap();
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        assert_eq!(c8.v_reg[0], 8);
+        assert!(c8.opcode_handler::<AddV0>().is_some());
+    }
+
+    #[test]
+    fn unclaimed_opcode_is_unknown() {
+        let mut c8 = Chip8::new();
+        c8.register_opcodes(Box::new(AddV0));
+        c8.load(&[0x02, 0x05]).unwrap();
+        assert_eq!(
+            c8.tick(),
+            Err(Chip8Error::UnknownOpcode {
+                op: 0x0205,
+                pc: 0x200
+            })
+        );
+    }
+}
@@ -0,0 +1,362 @@
+use crate::{Chip8, Chip8Error, Condition, PokeError, State};
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// Which memory accesses a watchpoint stops on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    /// Data reads: `Dxyn` sprites, `Fx65` and XO-CHIP's `5xy3` and `F002`
+    Read,
+    /// `Fx33`, `Fx55` and XO-CHIP's `5xy2`
+    Write,
+    ReadWrite,
+}
+
+impl Access {
+    fn covers(self, access: Access) -> bool {
+        self == Access::ReadWrite || self == access
+    }
+}
+
+/// The memory access that set off a watchpoint, see `watch_hit`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchHit {
+    pub addr: u16,
+    /// Address of the instruction that made the access
+    pub pc: u16,
+    /// `Access::Read` or `Access::Write`
+    pub access: Access,
+}
+
+/// Where `tick` stops, see `add_breakpoint`, `add_watch` and `add_watchpoint`
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Breaks {
+    at: BTreeMap<u16, Option<Condition>>,   // Breakpoints by address
+    watches: Vec<(Condition, bool)>,        // Watched conditions and their last value
+    watchpoints: Vec<(Range<u16>, Access)>, // Watched memory
+    pending: Option<WatchHit>,              // First watched access this instruction
+    hit: Option<WatchHit>,                  // Watched access that stopped the last tick
+}
+
+impl Chip8 {
+    /// Run until the PC reaches `addr`, giving up after `max_ticks` instructions
+    ///
+    /// A temporary breakpoint for "run to cursor". At least one instruction
+    /// runs, so if the PC is already at `addr` this runs until it comes back.
+    /// Returns how many instructions ran, or `None` if the cap was hit first.
+    pub fn run_to(&mut self, addr: u16, max_ticks: u64) -> Result<Option<u64>, Chip8Error> {
+        for ticks in 1..=max_ticks {
+            self.tick()?;
+            if self.pc == addr {
+                return Ok(Some(ticks));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Stop `tick` when the PC reaches `addr`, it returns `State::Break`
+    ///
+    /// Breakpoints and watches are kept across `reset` and ROM loads.
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breaks.at.insert(addr, None);
+    }
+
+    /// Like `add_breakpoint`, but only stop if `cond` holds when the PC
+    /// gets there
+    pub fn add_conditional_breakpoint(&mut self, addr: u16, cond: Condition) {
+        self.breaks.at.insert(addr, Some(cond));
+    }
+
+    /// Returns false if there was no breakpoint at `addr`
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool {
+        self.breaks.at.remove(&addr).is_some()
+    }
+
+    pub fn clear_breakpoints(&mut self) {
+        self.breaks.at.clear();
+    }
+
+    /// Breakpoint addresses in ascending order, with their conditions
+    pub fn breakpoints(&self) -> impl Iterator<Item = (u16, Option<&Condition>)> + '_ {
+        self.breaks
+            .at
+            .iter()
+            .map(|(&addr, cond)| (addr, cond.as_ref()))
+    }
+
+    /// Stop `tick` after any instruction that makes `cond` true, wherever
+    /// it is, e.g. to find what sets `V3 == 0x1F`
+    ///
+    /// It has to become false again before it can stop a second time.
+    pub fn add_watch(&mut self, cond: Condition) {
+        let now = cond.eval(self);
+        self.breaks.watches.push((cond, now));
+    }
+
+    /// Returns false if nothing was watching `cond`
+    pub fn remove_watch(&mut self, cond: &Condition) -> bool {
+        let watches = &mut self.breaks.watches;
+        let before = watches.len();
+        watches.retain(|(watched, _)| watched != cond);
+        watches.len() != before
+    }
+
+    pub fn watches(&self) -> impl Iterator<Item = &Condition> + '_ {
+        self.breaks.watches.iter().map(|(cond, _)| cond)
+    }
+
+    /// Stop `tick` after an instruction that reads or writes any of `range`
+    ///
+    /// Opcode fetches don't count as reads. `watch_hit` says which access
+    /// it was.
+    pub fn add_watchpoint(&mut self, range: Range<u16>, access: Access) {
+        self.breaks.watchpoints.push((range, access));
+    }
+
+    /// Returns false if nothing was watching exactly `range`
+    pub fn remove_watchpoint(&mut self, range: Range<u16>) -> bool {
+        let watchpoints = &mut self.breaks.watchpoints;
+        let before = watchpoints.len();
+        watchpoints.retain(|(watched, _)| *watched != range);
+        watchpoints.len() != before
+    }
+
+    pub fn watchpoints(&self) -> impl Iterator<Item = (Range<u16>, Access)> + '_ {
+        self.breaks.watchpoints.iter().cloned()
+    }
+
+    /// The watchpoint access the last `tick` stopped on, if it did
+    pub fn watch_hit(&self) -> Option<WatchHit> {
+        self.breaks.hit
+    }
+
+    /// Note an access to `addr` if a watchpoint covers it
+    pub(crate) fn check_watchpoints(&mut self, addr: u16, access: Access) {
+        if self.breaks.pending.is_some() || self.breaks.watchpoints.is_empty() {
+            return;
+        }
+        let mut watched = self.breaks.watchpoints.iter();
+        if watched.any(|(range, on)| range.contains(&addr) && on.covers(access)) {
+            self.breaks.pending = Some(WatchHit {
+                addr,
+                pc: self.op_pc(),
+                access,
+            });
+        }
+    }
+
+    /// Check breakpoints, watches and watchpoints after an instruction
+    pub(crate) fn hit_break(&mut self) -> bool {
+        let mut hit = match self.breaks.at.get(&self.pc) {
+            Some(Some(cond)) => cond.eval(self),
+            Some(None) => true,
+            None => false,
+        };
+        if !self.breaks.watches.is_empty() {
+            let mut watches = std::mem::take(&mut self.breaks.watches);
+            for (cond, last) in &mut watches {
+                let now = cond.eval(self);
+                hit |= now && !*last;
+                *last = now;
+            }
+            self.breaks.watches = watches;
+        }
+        self.breaks.hit = self.breaks.pending.take();
+        hit || self.breaks.hit.is_some()
+    }
+
+    /// Run a single instruction, even one with a breakpoint on it
+    ///
+    /// The same as `tick`, which never stops before the instruction at the PC.
+    pub fn step(&mut self) -> Result<State, Chip8Error> {
+        self.tick()
+    }
+
+    /// Run until a breakpoint is hit, giving up after `max_ticks` instructions
+    ///
+    /// Moves off a breakpoint at the current PC first. Returns how many
+    /// instructions ran, or `None` if the cap was hit, the program exited or
+    /// it's waiting on `Fx0A` for a key no one will press.
+    pub fn continue_until_break(&mut self, max_ticks: u64) -> Result<Option<u64>, Chip8Error> {
+        for ticks in 1..=max_ticks {
+            match self.tick()? {
+                State::Break => return Ok(Some(ticks)),
+                State::Halted | State::WaitingForKey => break,
+                _ => (),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Set register V`idx`, for patching state while paused
+    pub fn set_v(&mut self, idx: usize, val: u8) -> Result<(), PokeError> {
+        let reg = self.v_reg.get_mut(idx).ok_or(PokeError::Register(idx))?;
+        *reg = val;
+        Ok(())
+    }
+
+    /// Move the PC, which must leave room for a whole opcode in RAM
+    pub fn set_pc(&mut self, addr: u16) -> Result<(), PokeError> {
+        if addr as usize + 1 >= self.ram.len() {
+            return Err(PokeError::Address(addr));
+        }
+        self.pc = addr;
+        self.blocked = false;
+        self.state = State::Running;
+        Ok(())
+    }
+
+    /// Point I at `addr`, which must be inside RAM
+    pub fn set_i(&mut self, addr: u16) -> Result<(), PokeError> {
+        if addr as usize >= self.ram.len() {
+            return Err(PokeError::Address(addr));
+        }
+        self.i_reg = addr;
+        Ok(())
+    }
+
+    pub fn set_dt(&mut self, val: u8) {
+        self.dt = val;
+    }
+
+    pub fn set_st(&mut self, val: u8) {
+        self.st = val;
+    }
+
+    /// Overwrite RAM from `addr`, all of `data` must fit
+    pub fn poke(&mut self, addr: u16, data: &[u8]) -> Result<(), PokeError> {
+        let start = addr as usize;
+        let dest = self
+            .ram
+            .get_mut(start..start + data.len())
+            .ok_or(PokeError::Address(addr))?;
+        dest.copy_from_slice(data);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_to_address() {
+        let mut c8 = Chip8::new();
+        // CALL 0x206; JP 0x202; NOP; RET
+        c8.load(&[0x22, 0x06, 0x12, 0x02, 0x00, 0x00, 0x00, 0xEE])
+            .unwrap();
+        assert_eq!(c8.run_to(0x206, 10).unwrap(), Some(1));
+        assert_eq!(c8.run_to(0x202, 10).unwrap(), Some(1));
+        // 0x202 jumps to itself, so it's reached again straight away
+        assert_eq!(c8.run_to(0x202, 10).unwrap(), Some(1));
+        assert_eq!(c8.run_to(0x206, 10).unwrap(), None);
+        assert_eq!(c8.instruction_count(), 13);
+    }
+
+    #[test]
+    fn breakpoints() {
+        let mut c8 = Chip8::new();
+        // ADD V0, 1; ADD V1, 1; JP 0x200
+        c8.load(&[0x70, 0x01, 0x71, 0x01, 0x12, 0x00]).unwrap();
+        c8.add_breakpoint(0x204);
+        assert_eq!(c8.step(), Ok(State::Running));
+        assert_eq!(c8.step(), Ok(State::Break));
+        assert_eq!(c8.state(), State::Break);
+        assert_eq!(c8.pc(), 0x204);
+
+        // Continuing runs the instruction under the breakpoint first
+        assert_eq!(c8.continue_until_break(10), Ok(Some(3)));
+        assert_eq!(c8.v_reg()[..2], [2, 2]);
+        assert_eq!(c8.tick_many(10), Ok(3));
+
+        assert!(c8.remove_breakpoint(0x204));
+        assert!(!c8.remove_breakpoint(0x204));
+        assert_eq!(c8.breakpoints().count(), 0);
+        assert_eq!(c8.continue_until_break(10), Ok(None));
+    }
+
+    #[test]
+    fn continue_stops_waiting_for_key() {
+        let mut c8 = Chip8::new();
+        // ADD V0, 1; LD V1, K
+        c8.load(&[0x70, 0x01, 0xF1, 0x0A]).unwrap();
+        c8.add_breakpoint(0x204);
+        assert_eq!(c8.continue_until_break(100), Ok(None));
+        assert_eq!(c8.state(), State::WaitingForKey);
+        assert_eq!(c8.instruction_count(), 2);
+    }
+
+    #[test]
+    fn conditions() {
+        let mut c8 = Chip8::new();
+        // ADD V0, 1; JP 0x200
+        c8.load(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+        c8.add_conditional_breakpoint(0x202, "V0 == 3".parse().unwrap());
+        assert_eq!(c8.continue_until_break(100), Ok(Some(5)));
+        assert_eq!(c8.v_reg()[0], 3);
+        c8.clear_breakpoints();
+
+        let watch: Condition = "V0 >= 0x10".parse().unwrap();
+        c8.add_watch(watch);
+        assert_eq!(c8.continue_until_break(100), Ok(Some(26)));
+        assert_eq!(c8.v_reg()[0], 0x10);
+        // Still true, so it doesn't stop again until V0 wraps round
+        assert_eq!(c8.continue_until_break(100), Ok(None));
+        assert!(c8.remove_watch(&watch));
+        assert_eq!(c8.watches().count(), 0);
+    }
+
+    #[test]
+    fn watchpoints() {
+        let mut c8 = Chip8::new();
+        // LD I, 0x300; LD V0, 5; LD B, V0; LD [I], V0; DRW V0, V0, 2;
+        // LD V1, [I]
+        c8.load(&[
+            0xA3, 0x00, 0x60, 0x05, 0xF0, 0x33, 0xF0, 0x55, 0xD0, 0x02, 0xF1, 0x65,
+        ])
+        .unwrap();
+        c8.add_watchpoint(0x301..0x302, Access::Write);
+        c8.add_watchpoint(0x301..0x302, Access::Read);
+        // The fetch of 0x2xx opcodes doesn't count
+        c8.add_watchpoint(0x200..0x20C, Access::Read);
+
+        assert_eq!(c8.continue_until_break(10), Ok(Some(3)));
+        assert_eq!(
+            c8.watch_hit(),
+            Some(WatchHit {
+                addr: 0x301,
+                pc: 0x204,
+                access: Access::Write
+            })
+        );
+        // LD [I], V0 only writes 0x300
+        assert_eq!(c8.continue_until_break(10), Ok(Some(2)));
+        assert_eq!(c8.watch_hit().unwrap().access, Access::Read);
+        assert_eq!(c8.watch_hit().unwrap().pc, 0x208);
+
+        assert!(c8.remove_watchpoint(0x301..0x302));
+        assert_eq!(c8.watchpoints().count(), 1);
+        assert_eq!(c8.step(), Ok(State::Running));
+        assert_eq!(c8.watch_hit(), None);
+    }
+
+    #[test]
+    fn patch_state() {
+        let mut c8 = Chip8::new();
+        c8.set_v(0xF, 7).unwrap();
+        assert_eq!(c8.v_reg()[0xF], 7);
+        assert_eq!(c8.set_v(16, 1), Err(PokeError::Register(16)));
+
+        c8.set_i(0xFFF).unwrap();
+        assert_eq!(c8.set_i(0x1000), Err(PokeError::Address(0x1000)));
+        assert_eq!(c8.i_reg(), 0xFFF);
+
+        // LD V1, 0x23 at 0x300
+        c8.poke(0x300, &[0x61, 0x23]).unwrap();
+        assert_eq!(c8.poke(0xFFF, &[0, 0]), Err(PokeError::Address(0xFFF)));
+        assert_eq!(c8.set_pc(0xFFF), Err(PokeError::Address(0xFFF)));
+        c8.set_pc(0x300).unwrap();
+        c8.tick().unwrap();
+        assert_eq!(c8.v_reg()[1], 0x23);
+    }
+}
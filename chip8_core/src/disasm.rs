@@ -0,0 +1,228 @@
+use crate::sprites::find_sprites;
+use crate::{decode, Instruction, Symbols};
+use std::collections::BTreeSet;
+use std::fmt::{self, Write};
+
+/// Convert a single opcode into a mnemonic, e.g. `0x6A2F` -> `LD VA, 0x2F`
+///
+/// Syntax follows Cowgod's Chip-8 technical reference. Anything that isn't a
+/// known instruction is shown as a raw data word.
+pub fn disassemble_op(op: u16) -> String {
+    decode(op).to_string()
+}
+
+/// One line of a listing, see `disassemble`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DisasmLine {
+    pub addr: u16,
+    /// Raw bytes: two for an instruction, four for XO-CHIP's `F000 NNNN`,
+    /// one for data
+    pub bytes: Vec<u8>,
+    /// Mnemonic, e.g. `LD VA, 0x2F`, or `DB 0b00111100` for data
+    pub text: String,
+    /// Sprite data (or a trailing odd byte) rather than code
+    pub data: bool,
+}
+
+impl fmt::Display for DisasmLine {
+    /// `0x206  3C    DB 0b00111100  ; ..####..`, data shows its pixels
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex: String = self.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        write!(f, "0x{:03X}  {:<4}  {}", self.addr, hex, self.text)?;
+        if self.data {
+            write!(f, "  ; {}", sprite_row(self.bytes[0]))?;
+        }
+        Ok(())
+    }
+}
+
+/// Decode a ROM loaded at `base`, one `DisasmLine` per instruction or data
+/// byte
+///
+/// Bytes found to be sprite data are kept as `DB` with their bit pattern
+/// instead of being decoded as (nonsense) instructions.
+pub fn disassemble(rom: &[u8], base: u16) -> Vec<DisasmLine> {
+    let mut data = BTreeSet::new();
+    for sprite in find_sprites(rom, base) {
+        data.extend(sprite.addr..sprite.addr + sprite.height as u16);
+    }
+
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    while offset < rom.len() {
+        let addr = base.wrapping_add(offset as u16);
+        let line = if data.contains(&addr) || offset + 1 == rom.len() {
+            let byte = rom[offset];
+            DisasmLine {
+                addr,
+                bytes: vec![byte],
+                text: format!("DB 0b{:08b}", byte),
+                data: true,
+            }
+        } else {
+            let op = u16::from_be_bytes([rom[offset], rom[offset + 1]]);
+            match rom.get(offset + 2..offset + 4) {
+                // XO-CHIP's long load takes its address from the next word
+                Some(&[hi, lo]) if op == 0xF000 => DisasmLine {
+                    addr,
+                    bytes: rom[offset..offset + 4].to_vec(),
+                    text: format!("LD I, 0x{:04X}", u16::from_be_bytes([hi, lo])),
+                    data: false,
+                },
+                _ => DisasmLine {
+                    addr,
+                    bytes: rom[offset..offset + 2].to_vec(),
+                    text: disassemble_op(op),
+                    data: false,
+                },
+            }
+        };
+        offset += line.bytes.len();
+        lines.push(line);
+    }
+    lines
+}
+
+/// Disassemble a whole ROM loaded at `base` into a listing, one
+/// `DisasmLine` per line of text
+pub fn disassemble_rom(rom: &[u8], base: u16) -> String {
+    let mut out = String::new();
+    for line in disassemble(rom, base) {
+        let _ = writeln!(out, "{}", line);
+    }
+    out
+}
+
+/// Name the `CALL` and `JP` targets in a listing, `sub_2D4` for
+/// subroutines and `label_21A` for everything else
+///
+/// Only targets that are the start of a line get a name, so jumps out of
+/// the ROM or into the middle of an instruction are left as addresses.
+pub fn infer_labels(lines: &[DisasmLine]) -> Symbols {
+    let starts: BTreeSet<u16> = lines.iter().map(|line| line.addr).collect();
+    let mut calls = BTreeSet::new();
+    let mut jumps = BTreeSet::new();
+    for line in lines.iter().filter(|line| !line.data) {
+        match decode(u16::from_be_bytes([line.bytes[0], line.bytes[1]])) {
+            Instruction::Call { nnn } => calls.insert(nnn),
+            Instruction::Jump { nnn } => jumps.insert(nnn),
+            _ => false,
+        };
+    }
+
+    let mut symbols = Symbols::new();
+    for &addr in calls.iter().filter(|addr| starts.contains(addr)) {
+        symbols.insert(&format!("sub_{:03X}", addr), addr);
+    }
+    for &addr in jumps
+        .difference(&calls)
+        .filter(|addr| starts.contains(addr))
+    {
+        symbols.insert(&format!("label_{:03X}", addr), addr);
+    }
+    symbols
+}
+
+/// Like `disassemble_rom`, with a `name:` line before each address in
+/// `symbols` and jump and call targets shown by name
+pub fn disassemble_labeled(rom: &[u8], base: u16, symbols: &Symbols) -> String {
+    let mut out = String::new();
+    for mut line in disassemble(rom, base) {
+        if let Some(name) = symbols.name_at(line.addr) {
+            let _ = writeln!(out, "{}:", name);
+        }
+        if !line.data && line.bytes.len() == 2 {
+            let target = match decode(u16::from_be_bytes([line.bytes[0], line.bytes[1]])) {
+                Instruction::Call { nnn } | Instruction::Jump { nnn } => symbols.name_at(nnn),
+                _ => None,
+            };
+            if let Some(name) = target {
+                let (mnemonic, _) = line.text.split_once(' ').unwrap_or_default();
+                line.text = format!("{} {}", mnemonic, name);
+            }
+        }
+        let _ = writeln!(out, "{}", line);
+    }
+    out
+}
+
+/// Draw a sprite byte as `#` for set pixels and `.` for clear ones
+fn sprite_row(byte: u8) -> String {
+    (0..8)
+        .map(|bit| if byte & (0x80 >> bit) != 0 { '#' } else { '.' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SpriteRef;
+
+    #[test]
+    fn known_opcodes() {
+        assert_eq!(disassemble_op(0x00E0), "CLS");
+        assert_eq!(disassemble_op(0x1234), "JP 0x234");
+        assert_eq!(disassemble_op(0x6A2F), "LD VA, 0x2F");
+        assert_eq!(disassemble_op(0x8AB4), "ADD VA, VB");
+        assert_eq!(disassemble_op(0xD125), "DRW V1, V2, 5");
+        assert_eq!(disassemble_op(0xF365), "LD V3, [I]");
+        assert_eq!(disassemble_op(0x00FF), "HIGH");
+        assert_eq!(disassemble_op(0x00C5), "SCD 5");
+        assert_eq!(disassemble_op(0x5123), "LOAD V1, V2");
+        assert_eq!(disassemble_op(0xF000), "LD I, LONG");
+        assert_eq!(disassemble_op(0xF53A), "PITCH V5");
+    }
+
+    #[test]
+    fn unknown_opcodes_are_data() {
+        assert_eq!(disassemble_op(0x5121), "DW 0x5121");
+        assert_eq!(disassemble_op(0xFFFF), "DW 0xFFFF");
+    }
+
+    #[test]
+    fn sprites_are_shown_as_data() {
+        // LD I, 0x206; DRW V0, V1, 2; JP 0x204; then a two row sprite
+        let rom = [0xA2, 0x06, 0xD0, 0x12, 0x12, 0x04, 0x3C, 0x81];
+        assert_eq!(
+            find_sprites(&rom, 0x200),
+            vec![SpriteRef {
+                addr: 0x206,
+                height: 2
+            }]
+        );
+        let listing = disassemble_rom(&rom, 0x200);
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(lines[0], "0x200  A206  LD I, 0x206");
+        assert_eq!(lines[3], "0x206  3C    DB 0b00111100  ; ..####..");
+        assert_eq!(lines[4], "0x207  81    DB 0b10000001  ; #......#");
+    }
+
+    #[test]
+    fn labels_for_jump_and_call_targets() {
+        // CALL 0x206; JP 0x202; JP 0x300; RET
+        let rom = [0x22, 0x06, 0x12, 0x02, 0x13, 0x00, 0x00, 0xEE];
+        let symbols = infer_labels(&disassemble(&rom, 0x200));
+        assert_eq!(
+            symbols.by_addr(),
+            vec![(0x202, "label_202"), (0x206, "sub_206")]
+        );
+
+        let listing = disassemble_labeled(&rom, 0x200, &symbols);
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(lines[0], "0x200  2206  CALL sub_206");
+        assert_eq!(lines[1], "label_202:");
+        assert_eq!(lines[2], "0x202  1202  JP label_202");
+        assert_eq!(lines[3], "0x204  1300  JP 0x300");
+        assert_eq!(lines[4], "sub_206:");
+    }
+
+    #[test]
+    fn long_load_is_one_line() {
+        // LD I, 0x1234 (F000 1234); CLS
+        let lines = disassemble(&[0xF0, 0x00, 0x12, 0x34, 0x00, 0xE0], 0x200);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].bytes, vec![0xF0, 0x00, 0x12, 0x34]);
+        assert_eq!(lines[0].to_string(), "0x200  F0001234  LD I, 0x1234");
+        assert_eq!((lines[1].addr, lines[1].text.as_str()), (0x204, "CLS"));
+    }
+}
@@ -0,0 +1,322 @@
+use crate::{Config, Instruction, Variant, FONT_END};
+use std::fmt;
+
+/// Faults raised while running a program
+///
+/// `pc` is the address of the instruction that caused the fault.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chip8Error {
+    /// CALL with every stack slot already in use
+    StackOverflow { pc: u16 },
+    /// RET with nothing on the stack
+    StackUnderflow { pc: u16 },
+    /// Memory access outside of the configured RAM
+    AddressOutOfRange { addr: u16, pc: u16 },
+    /// An opcode no instruction or registered handler claimed
+    UnknownOpcode { op: u16, pc: u16 },
+    /// An `Instruction` given to `Chip8::execute` with a register past VF or
+    /// an operand too wide for its opcode
+    InvalidInstruction { instr: Instruction, pc: u16 },
+}
+
+impl fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Chip8Error::StackOverflow { pc } => write!(f, "stack overflow at 0x{:03X}", pc),
+            Chip8Error::StackUnderflow { pc } => write!(f, "stack underflow at 0x{:03X}", pc),
+            Chip8Error::AddressOutOfRange { addr, pc } => {
+                write!(f, "address 0x{:03X} is out of range at 0x{:03X}", addr, pc)
+            }
+            Chip8Error::UnknownOpcode { op, pc } => {
+                write!(f, "unknown opcode 0x{:04X} at 0x{:03X}", op, pc)
+            }
+            Chip8Error::InvalidInstruction { instr, pc } => {
+                write!(f, "{:?} has an operand out of range at 0x{:03X}", instr, pc)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {}
+
+/// Reasons a ROM can't be loaded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadError {
+    /// The ROM has no bytes at all, usually a failed download or wrong file
+    Empty,
+    /// The ROM doesn't fit between the start address and the end of RAM
+    TooLarge { size: usize, max: usize },
+    /// A segment runs past the end of RAM
+    SegmentOutOfRange { addr: u16, size: usize },
+    /// Two segments share at least one byte, given by their start addresses
+    SegmentOverlap { first: u16, second: u16 },
+    /// Saved persistent data doesn't match the declared persistent region
+    PersistentMismatch,
+    /// The machine to load into couldn't be built
+    Config(ConfigError),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Empty => write!(f, "ROM is empty"),
+            LoadError::TooLarge { size, max } => write!(
+                f,
+                "ROM is {} bytes but at most {} bytes fit in memory",
+                size, max
+            ),
+            LoadError::SegmentOutOfRange { addr, size } => write!(
+                f,
+                "segment of {} bytes at 0x{:03X} runs past the end of memory",
+                size, addr
+            ),
+            LoadError::SegmentOverlap { first, second } => write!(
+                f,
+                "segments at 0x{:03X} and 0x{:03X} overlap",
+                first, second
+            ),
+            LoadError::PersistentMismatch => {
+                write!(f, "saved data doesn't match the persistent region")
+            }
+            LoadError::Config(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<ConfigError> for LoadError {
+    fn from(e: ConfigError) -> Self {
+        LoadError::Config(e)
+    }
+}
+
+/// Reasons a `Config` can't build a machine
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    /// RAM smaller than `Config::MIN_MEM_SIZE` or bigger than 64K
+    MemSize(usize),
+    /// No room on the stack for even one CALL
+    StackSize,
+    /// The start address is inside the fonts or leaves no room for an
+    /// instruction before `max`, the last address one fits at
+    StartAddr { addr: u16, max: usize },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::MemSize(size) => write!(
+                f,
+                "mem_size must be between 0x{:X} and 0x{:X}, got 0x{:X}",
+                Config::MIN_MEM_SIZE,
+                Config::MAX_MEM_SIZE,
+                size
+            ),
+            ConfigError::StackSize => write!(f, "stack_size must be at least 1"),
+            ConfigError::StartAddr { addr, max } => write!(
+                f,
+                "start_addr must be between 0x{:X} and 0x{:X}, got 0x{:X}",
+                FONT_END, max, addr
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Reasons another emulator's save state can't be imported
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImportError {
+    /// The file isn't in the expected format
+    Parse(String),
+    /// A field this core needs is missing
+    Missing(&'static str),
+    /// The state uses a feature this core doesn't emulate
+    Unsupported(&'static str),
+    /// A field holds a value that doesn't fit this machine
+    OutOfRange(&'static str),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Parse(msg) => write!(f, "unable to parse save state: {}", msg),
+            ImportError::Missing(field) => write!(f, "save state has no '{}' field", field),
+            ImportError::Unsupported(what) => {
+                write!(f, "save state uses {}, which isn't supported", what)
+            }
+            ImportError::OutOfRange(field) => {
+                write!(f, "save state field '{}' is out of range", field)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Reasons a save state can't be restored
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StateError {
+    /// The file isn't a well formed save state
+    Parse(String),
+    /// The save was written by a newer version of the format
+    Version(u32),
+    /// The save came from a machine with a different config, the field
+    /// named doesn't fit this one
+    Mismatch(&'static str),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::Parse(msg) => write!(f, "invalid save state: {}", msg),
+            StateError::Version(v) => {
+                write!(f, "save state format version {} is not supported", v)
+            }
+            StateError::Mismatch(field) => {
+                write!(f, "save state '{}' doesn't match this machine", field)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+/// Reasons a ROM package can't be read
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PackageError {
+    /// The file isn't a well formed package
+    Parse(String),
+    /// The package was written by a newer version of the format
+    Version(u32),
+}
+
+impl fmt::Display for PackageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PackageError::Parse(msg) => write!(f, "invalid package: {}", msg),
+            PackageError::Version(v) => write!(f, "package format version {} is not supported", v),
+        }
+    }
+}
+
+impl std::error::Error for PackageError {}
+
+/// Reasons a debugger edit to the machine state is rejected
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PokeError {
+    /// There's no V register with this index
+    Register(usize),
+    /// The address (or the end of the data written there) is past the end of RAM
+    Address(u16),
+}
+
+impl fmt::Display for PokeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PokeError::Register(idx) => write!(f, "no register V{:X}", idx),
+            PokeError::Address(addr) => write!(f, "address 0x{:03X} is outside RAM", addr),
+        }
+    }
+}
+
+impl std::error::Error for PokeError {}
+
+/// Reasons a ROM database can't be read
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DatabaseError {
+    /// Not a list of programs in the community database's format
+    Parse(String),
+}
+
+impl fmt::Display for DatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatabaseError::Parse(msg) => write!(f, "invalid ROM database: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for DatabaseError {}
+
+/// Reasons a settings file can't be used
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SettingsError {
+    /// Not valid TOML, or an entry of the wrong type or with an unknown name
+    Parse(String),
+    /// A host key bound to something other than CHIP-8 keys 0-F
+    Key { key: String, button: u8 },
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Parse(msg) => write!(f, "invalid settings: {}", msg),
+            SettingsError::Key { key, button } => {
+                write!(f, "key '{}' is bound to {}, keys go up to 0xF", key, button)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+/// Reasons a breakpoint or watch condition doesn't parse
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConditionError {
+    /// No comparison, or nothing on one side of it
+    Missing,
+    /// Not one of `==`, `!=`, `<`, `<=`, `>` or `>=`
+    Operator(String),
+    /// Neither a register nor a number
+    Operand(String),
+}
+
+impl fmt::Display for ConditionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConditionError::Missing => write!(f, "expected a comparison like 'V3 == 0x1F'"),
+            ConditionError::Operator(op) => write!(f, "unknown comparison '{}'", op),
+            ConditionError::Operand(arg) => write!(f, "'{}' is not a register or number", arg),
+        }
+    }
+}
+
+impl std::error::Error for ConditionError {}
+
+/// A name that isn't in `Variant::NAMES`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownVariant(pub String);
+
+impl fmt::Display for UnknownVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown variant '{}', use one of {}",
+            self.0,
+            Variant::NAMES.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UnknownVariant {}
+
+/// A `Recording` event for a key that isn't on the keypad
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReplayError {
+    pub instruction: u64,
+    pub key: u8,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "recorded key 0x{:X} at instruction {} isn't on the keypad",
+            self.key, self.instruction
+        )
+    }
+}
+
+impl std::error::Error for ReplayError {}
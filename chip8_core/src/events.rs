@@ -0,0 +1,80 @@
+use crate::Chip8;
+
+/// Something a frontend may want to react to, instead of polling for it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// The screen changed (CLS or DRW)
+    Draw,
+    /// The sound timer started (`true`) or stopped (`false`)
+    Sound(bool),
+    /// The game jumped to itself or exited (`00FD`) at `pc`, it won't do
+    /// anything more
+    Halt { pc: u16 },
+    /// `Fx0A` at `pc` is waiting for a key press
+    KeyWait { pc: u16 },
+}
+
+impl Chip8 {
+    /// Start or stop queueing events for `take_events`
+    pub fn record_events(&mut self, enable: bool) {
+        self.events = if enable { Some(Vec::new()) } else { None };
+    }
+
+    /// Events since the last call, oldest first
+    pub fn take_events(&mut self) -> Vec<Event> {
+        match &mut self.events {
+            Some(events) => std::mem::take(events),
+            None => Vec::new(),
+        }
+    }
+
+    pub(crate) fn emit(&mut self, event: Event) {
+        #[cfg(feature = "trace")]
+        tracing::debug!(?event, "event");
+        if let Some(events) = &mut self.events {
+            // Games draw many sprites a frame, one redraw covers them all
+            if event == Event::Draw && events.last() == Some(&Event::Draw) {
+                return;
+            }
+            events.push(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_reported_once() {
+        let mut c8 = Chip8::new();
+        // CLS; DRW V0, V0, 1; LD V0, 2; LD ST, V0; LD V1, K
+        c8.load(&[0x00, 0xE0, 0xD0, 0x01, 0x60, 0x02, 0xF0, 0x18, 0xF1, 0x0A])
+            .unwrap();
+        c8.record_events(true);
+        for _ in 0..6 {
+            c8.tick().unwrap();
+        }
+        assert_eq!(
+            c8.take_events(),
+            vec![
+                Event::Draw,
+                Event::Sound(true),
+                Event::KeyWait { pc: 0x208 }
+            ]
+        );
+        c8.tick_timers();
+        c8.tick_timers();
+        assert_eq!(c8.take_events(), vec![Event::Sound(false)]);
+    }
+
+    #[test]
+    fn halt() {
+        let mut c8 = Chip8::new();
+        c8.load(&[0x12, 0x00]).unwrap();
+        c8.record_events(true);
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        assert_eq!(c8.take_events(), vec![Event::Halt { pc: 0x200 }]);
+    }
+}
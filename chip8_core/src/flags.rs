@@ -0,0 +1,105 @@
+use crate::Chip8;
+
+/// Number of SCHIP RPL user flags, `Fx75` saves V0 through Vx into them
+pub const FLAG_COUNT: usize = 16;
+
+/// Somewhere to keep the RPL user flags between sessions, see
+/// `Chip8::set_flag_storage`
+///
+/// On the HP48 the flags lived in the calculator's memory, so games use
+/// them for high scores. A frontend stores them wherever suits it: a file
+/// next to the ROM, localStorage.
+pub trait FlagStorage: FlagClone {
+    /// The flags saved last time, `None` if nothing was saved yet
+    fn load(&mut self) -> Option<Vec<u8>>;
+    /// Called with all the flags after every `Fx75`
+    fn save(&mut self, flags: &[u8]);
+}
+
+clone_box!(FlagClone, FlagStorage);
+
+impl Chip8 {
+    /// Keep the RPL flags in `storage`, loading whatever it saved last time
+    ///
+    /// The storage stays installed across `reset`, and the flags themselves
+    /// survive a reset like they did on the calculator.
+    pub fn set_flag_storage(&mut self, mut storage: Box<dyn FlagStorage>) {
+        if let Some(saved) = storage.load() {
+            self.set_flags(&saved);
+        }
+        self.flag_storage = Some(storage);
+    }
+
+    pub fn clear_flag_storage(&mut self) {
+        self.flag_storage = None;
+    }
+
+    /// The RPL user flags
+    pub fn flags(&self) -> &[u8] {
+        &self.flags
+    }
+
+    /// Overwrite the flags, extra bytes are ignored and missing ones cleared
+    pub fn set_flags(&mut self, flags: &[u8]) {
+        self.flags = [0; FLAG_COUNT];
+        let len = flags.len().min(FLAG_COUNT);
+        self.flags[..len].copy_from_slice(&flags[..len]);
+    }
+
+    /// Fx75, save V0 through Vx to the flags
+    pub(crate) fn save_flags(&mut self, x: usize) {
+        self.flags[..=x].copy_from_slice(&self.v_reg[..=x]);
+        if let Some(storage) = &mut self.flag_storage {
+            storage.save(&self.flags);
+        }
+    }
+
+    /// Fx85, load V0 through Vx from the flags
+    pub(crate) fn load_flags(&mut self, x: usize) {
+        self.v_reg[..=x].copy_from_slice(&self.flags[..=x]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Option<Vec<u8>>>>);
+
+    impl FlagStorage for Shared {
+        fn load(&mut self) -> Option<Vec<u8>> {
+            self.0.borrow().clone()
+        }
+
+        fn save(&mut self, flags: &[u8]) {
+            *self.0.borrow_mut() = Some(flags.to_vec());
+        }
+    }
+
+    #[test]
+    fn flags_persist() {
+        let storage = Shared::default();
+        let mut c8 = Chip8::new();
+        c8.set_flag_storage(Box::new(storage.clone()));
+        // LD V0, 42; LD V1, 7; LD R, V1
+        c8.load(&[0x60, 0x2A, 0x61, 0x07, 0xF1, 0x75]).unwrap();
+        for _ in 0..3 {
+            c8.tick().unwrap();
+        }
+        assert_eq!(&c8.flags()[..3], &[42, 7, 0]);
+        assert_eq!(storage.0.borrow().as_deref(), Some(c8.flags()));
+
+        // A new session picks them up again; LD V1, R
+        let mut c8 = Chip8::new();
+        c8.set_flag_storage(Box::new(storage));
+        c8.load(&[0xF1, 0x85]).unwrap();
+        c8.tick().unwrap();
+        assert_eq!(&c8.v_reg()[..2], &[42, 7]);
+
+        c8.reset();
+        assert_eq!(&c8.flags()[..2], &[42, 7]);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{Chip8, Chip8Error, State};
+
+/// Everything a frontend needs to present one 60Hz frame
+pub struct Frame<'a> {
+    /// Packed rows, `width / 8` bytes each with the leftmost pixel in the
+    /// high bit (see `pixel` for one at a time)
+    pub pixels: &'a [u8],
+    /// XO-CHIP's second plane, laid out the same
+    pub plane2: &'a [u8],
+    pub width: usize,
+    pub height: usize,
+    /// Whether the screen was drawn to during the frame, so it can skip redraws
+    pub changed: bool,
+    /// Whether the beeper is on
+    pub sound: bool,
+    /// Frames since reset
+    pub index: u64,
+    /// What the program did during the frame
+    pub stats: FrameStats,
+}
+
+/// Counts for one frame, for performance and diagnostic overlays
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Instructions executed
+    pub instructions: u32,
+    /// `Dxyn` sprites drawn
+    pub draws: u32,
+    /// Pixels those sprites turned on or off, clipped ones aren't counted
+    pub pixels_flipped: u32,
+    /// Draws that turned a pixel off and set VF
+    pub collisions: u32,
+    /// Whether the sound timer was running
+    pub sound: bool,
+}
+
+impl Frame<'_> {
+    /// Whether the pixel at (x, y) is lit
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        let i = x + y * self.width;
+        self.pixels[i / 8] & (0x80 >> (i % 8)) != 0
+    }
+
+    /// Colour index 0-3 of the pixel at (x, y), see `Chip8::get_display_colors`
+    pub fn color(&self, x: usize, y: usize) -> u8 {
+        let i = x + y * self.width;
+        let mask = 0x80 >> (i % 8);
+        (self.pixels[i / 8] & mask != 0) as u8 | ((self.plane2[i / 8] & mask != 0) as u8) << 1
+    }
+}
+
+impl Chip8 {
+    /// Run `n` instructions in one call, stopping at the first fault or
+    /// breakpoint
+    ///
+    /// Returns how many instructions executed, which is fewer than `n` at a
+    /// breakpoint or while `PcOverflow::Trap` has the PC parked.
+    pub fn tick_many(&mut self, n: u32) -> Result<u32, Chip8Error> {
+        let start = self.instructions;
+        for _ in 0..n {
+            if self.tick()? == State::Break {
+                break;
+            }
+        }
+        Ok((self.instructions - start) as u32)
+    }
+
+    /// Run `ticks` instructions and a timer tick, then return the frame
+    /// with its `FrameStats`
+    ///
+    /// Stopping at a breakpoint skips the timer tick, so time stands still
+    /// while the debugger has the machine.
+    pub fn run_frame(&mut self, ticks: u32) -> Result<Frame<'_>, Chip8Error> {
+        self.tick_many(ticks)?;
+        if self.state() != State::Break {
+            self.tick_timers();
+        }
+        Ok(self.latest_frame())
+    }
+
+    /// The frame as of the last `tick_timers`
+    pub fn latest_frame(&self) -> Frame<'_> {
+        Frame {
+            pixels: self.screen.bytes(),
+            plane2: self.plane2.bytes(),
+            width: self.screen_width(),
+            height: self.screen_height(),
+            changed: self.frame_changed,
+            sound: self.st > 0,
+            index: self.frames,
+            stats: self.last_stats,
+        }
+    }
+
+    /// Render the screen into `buf` as 4 bytes per pixel, in RGBA order
+    ///
+    /// Colours are `0xRRGGBBAA`. `buf` must hold exactly
+    /// `screen_width() * screen_height() * 4` bytes, the layout canvas
+    /// `ImageData` and most texture APIs take.
+    pub fn get_display_rgba(&self, fg: u32, bg: u32, buf: &mut [u8]) {
+        let bytes = self.screen.bytes();
+        assert_eq!(
+            buf.len(),
+            bytes.len() * 8 * 4,
+            "RGBA buffer doesn't match the screen size"
+        );
+        let (fg, bg) = (fg.to_be_bytes(), bg.to_be_bytes());
+        for (pixels, byte) in buf.chunks_exact_mut(8 * 4).zip(bytes) {
+            for (bit, px) in pixels.chunks_exact_mut(4).enumerate() {
+                let on = byte & (0x80 >> bit) != 0;
+                px.copy_from_slice(if on { &fg } else { &bg });
+            }
+        }
+    }
+
+    /// Like `get_display_rgba`, into a new buffer
+    pub fn display_rgba(&self, fg: u32, bg: u32) -> Vec<u8> {
+        let mut buf = vec![0; self.screen.bytes().len() * 8 * 4];
+        self.get_display_rgba(fg, bg, &mut buf);
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Variant;
+
+    #[test]
+    fn run_frame() {
+        let mut c8 = Chip8::new();
+        // LD V0, 3; LD ST, V0; DRW V1, V1, 1; JP 0x206
+        c8.load(&[0x60, 0x03, 0xF0, 0x18, 0xD1, 0x11, 0x12, 0x06])
+            .unwrap();
+        let frame = c8.run_frame(3).unwrap();
+        assert_eq!(frame.index, 1);
+        assert!(frame.changed);
+        assert!(frame.sound);
+        assert!(frame.pixel(0, 0));
+
+        let frame = c8.run_frame(3).unwrap();
+        assert_eq!(frame.index, 2);
+        assert!(!frame.changed);
+    }
+
+    #[test]
+    fn run_frame_stops_at_breakpoint() {
+        let mut c8 = Chip8::new();
+        // LD V0, 3; LD DT, V0; ADD V1, 1; JP 0x204
+        c8.load(&[0x60, 0x03, 0xF0, 0x15, 0x71, 0x01, 0x12, 0x04])
+            .unwrap();
+        c8.add_breakpoint(0x204);
+        c8.run_frame(10).unwrap();
+        assert_eq!(c8.state(), State::Break);
+        assert_eq!((c8.dt(), c8.frame_count()), (3, 0));
+    }
+
+    #[test]
+    fn frame_stats() {
+        let mut c8 = Chip8::with_variant(Variant::Chip48);
+        // LD V0, 62; LD ST, V0; DRW V1, V1, 1 twice; DRW V0, V1, 1 which is
+        // clipped to 2 of the 4 pixels in the "0" glyph's 0xF0 top row
+        c8.load(&[0x60, 0x3E, 0xF0, 0x18, 0xD1, 0x11, 0xD1, 0x11, 0xD0, 0x11])
+            .unwrap();
+        let stats = c8.run_frame(5).unwrap().stats;
+        assert_eq!(
+            stats,
+            FrameStats {
+                instructions: 5,
+                draws: 3,
+                pixels_flipped: 10,
+                collisions: 1,
+                sound: true,
+            }
+        );
+        assert_eq!(c8.run_frame(0).unwrap().stats.instructions, 0);
+    }
+
+    #[test]
+    fn tick_many() {
+        let mut c8 = Chip8::new();
+        // ADD V0, 1; JP 0x200
+        c8.load(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+        assert_eq!(c8.tick_many(10), Ok(10));
+        assert_eq!(c8.v_reg()[0], 5);
+        assert_eq!(c8.frame_count(), 0);
+
+        let mut c8 = Chip8::new();
+        c8.load(&[0x00, 0xEE]).unwrap();
+        assert_eq!(
+            c8.tick_many(10),
+            Err(Chip8Error::StackUnderflow { pc: 0x200 })
+        );
+    }
+
+    #[test]
+    fn rgba_output() {
+        let mut c8 = Chip8::new();
+        // DRW V0, V0, 1 with I at the "0" glyph, top row 0xF0
+        c8.load(&[0xD0, 0x01]).unwrap();
+        c8.tick().unwrap();
+        let rgba = c8.display_rgba(0x33FF66FF, 0x000000FF);
+        assert_eq!(rgba.len(), 64 * 32 * 4);
+        assert_eq!(&rgba[12..20], &[0x33, 0xFF, 0x66, 0xFF, 0, 0, 0, 0xFF]);
+    }
+}
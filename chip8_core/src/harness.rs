@@ -0,0 +1,181 @@
+use crate::custom::OpcodeHandler;
+use crate::Chip8;
+
+/// Self-checking test ROM support
+///
+/// Register with `Chip8::register_opcodes(Box::new(TestHarness::new()))` and
+/// these otherwise unused `0NNN` opcodes become available to the ROM:
+///
+/// | Opcode | Meaning                                  |
+/// |--------|------------------------------------------|
+/// | `0100` | PASS - the test finished successfully    |
+/// | `0101` | FAIL - the test failed                   |
+/// | `02XY` | ASSERT - record a failure unless Vx == Vy |
+/// | `03X0` | LOG - record the value of Vx             |
+///
+/// In the assembler they can be written as data words, e.g. `DW 0x0100`.
+/// After PASS or FAIL the machine spins on that opcode; read the outcome with
+/// `chip8.opcode_handler::<TestHarness>()`.
+#[derive(Clone, Debug, Default)]
+pub struct TestHarness {
+    report: TestReport,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    Fail,
+}
+
+/// An ASSERT that didn't hold
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AssertFailure {
+    pub pc: u16,
+    pub x: u8,
+    pub y: u8,
+    pub vx: u8,
+    pub vy: u8,
+}
+
+/// A LOG entry
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LogEntry {
+    pub pc: u16,
+    pub x: u8,
+    pub value: u8,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TestReport {
+    /// Set once the ROM executes PASS or FAIL
+    pub outcome: Option<Outcome>,
+    pub assertions: u32,
+    pub failures: Vec<AssertFailure>,
+    pub log: Vec<LogEntry>,
+}
+
+impl TestReport {
+    pub fn finished(&self) -> bool {
+        self.outcome.is_some()
+    }
+
+    /// Passed only if the ROM said so and every assertion held
+    pub fn passed(&self) -> bool {
+        self.outcome == Some(Outcome::Pass) && self.failures.is_empty()
+    }
+}
+
+impl TestHarness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn report(&self) -> &TestReport {
+        &self.report
+    }
+}
+
+impl OpcodeHandler for TestHarness {
+    fn execute(&mut self, chip8: &mut Chip8, op: u16) -> bool {
+        let pc = chip8.op_pc();
+        let x = ((op & 0x00F0) >> 4) as u8;
+        let y = (op & 0x000F) as u8;
+
+        match op & 0xFF00 {
+            0x0100 if op == 0x0100 || op == 0x0101 => {
+                self.report.outcome = Some(if op == 0x0100 {
+                    Outcome::Pass
+                } else {
+                    Outcome::Fail
+                });
+                // park on this opcode so the ROM doesn't run on into garbage
+                chip8.pc = pc;
+            }
+            0x0200 => {
+                let vx = chip8.v_reg[x as usize];
+                let vy = chip8.v_reg[y as usize];
+                self.report.assertions += 1;
+                if vx != vy {
+                    self.report
+                        .failures
+                        .push(AssertFailure { pc, x, y, vx, vy });
+                }
+            }
+            0x0300 if y == 0 => {
+                self.report.log.push(LogEntry {
+                    pc,
+                    x,
+                    value: chip8.v_reg[x as usize],
+                });
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+
+    fn run(src: &str) -> TestReport {
+        let mut c8 = Chip8::new();
+        c8.register_opcodes(Box::new(TestHarness::new()));
+        c8.load(&assemble(src).unwrap().rom).unwrap();
+        for _ in 0..100 {
+            c8.tick().unwrap();
+        }
+        c8.opcode_handler::<TestHarness>().unwrap().report().clone()
+    }
+
+    #[test]
+    fn passing_rom() {
+        let report = run("
+            LD V1, 5
+            LD V2, 2
+            ADD V2, 3
+            DW 0x0212   ; ASSERT V1 == V2
+            DW 0x0320   ; LOG V2
+            DW 0x0100   ; PASS
+        ");
+        assert!(report.passed());
+        assert_eq!(report.assertions, 1);
+        assert_eq!(
+            report.log,
+            vec![LogEntry {
+                pc: 0x208,
+                x: 2,
+                value: 5
+            }]
+        );
+    }
+
+    #[test]
+    fn failed_assertion() {
+        let report = run("
+            LD V1, 5
+            DW 0x0210   ; ASSERT V1 == V0
+            DW 0x0100   ; PASS
+        ");
+        assert!(report.finished());
+        assert!(!report.passed());
+        assert_eq!(
+            report.failures,
+            vec![AssertFailure {
+                pc: 0x202,
+                x: 1,
+                y: 0,
+                vx: 5,
+                vy: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn explicit_fail() {
+        let report = run("DW 0x0101");
+        assert_eq!(report.outcome, Some(Outcome::Fail));
+        assert!(!report.passed());
+    }
+}
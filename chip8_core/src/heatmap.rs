@@ -0,0 +1,47 @@
+use crate::Chip8;
+
+impl Chip8 {
+    /// How many times the instruction at each RAM address was executed since
+    /// reset, indexed by address
+    ///
+    /// Only the address of an opcode's first byte is counted, so the odd
+    /// entries of code that's never misaligned stay zero. Addresses that were
+    /// never executed are usually data.
+    pub fn heat_map(&self) -> &[u32] {
+        &self.heat
+    }
+
+    pub fn clear_heat_map(&mut self) {
+        self.heat.fill(0);
+    }
+
+    pub(crate) fn record_heat(&mut self) {
+        // A mapper can run code from past the end of RAM, which isn't counted
+        let pc = self.op_pc() as usize;
+        if let Some(count) = self.heat.get_mut(pc) {
+            *count = count.saturating_add(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_executed_addresses() {
+        let mut c8 = Chip8::new();
+        // LD V0, 0; ADD V0, 1; SE V0, 3; JP 0x202; JP 0x208
+        c8.load(&[0x60, 0x00, 0x70, 0x01, 0x30, 0x03, 0x12, 0x02, 0x12, 0x08])
+            .unwrap();
+        for _ in 0..10 {
+            c8.tick().unwrap();
+        }
+        let heat = c8.heat_map();
+        assert_eq!(heat.len(), 4096);
+        assert_eq!(&heat[0x200..0x20A], &[1, 0, 3, 0, 3, 0, 2, 0, 1, 0]);
+
+        c8.reset();
+        assert!(c8.heat_map().iter().all(|&count| count == 0));
+    }
+}
@@ -0,0 +1,91 @@
+use crate::Chip8;
+use std::collections::VecDeque;
+
+/// The last few instructions executed, oldest first
+#[derive(Clone, Debug, Default)]
+pub(crate) struct History {
+    entries: VecDeque<(u16, u16)>, // (pc, op)
+    size: usize,
+}
+
+impl History {
+    pub(crate) fn new(size: usize) -> Self {
+        History {
+            entries: VecDeque::with_capacity(size),
+            size,
+        }
+    }
+
+    fn record(&mut self, pc: u16, op: u16) {
+        if self.size == 0 {
+            return;
+        }
+        if self.entries.len() == self.size {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((pc, op));
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Chip8 {
+    /// `(pc, op)` of the last `Config::history_size` instructions, oldest
+    /// first
+    ///
+    /// Includes the one that faulted, so after an `UnknownOpcode` the
+    /// entries before it show how the program got there.
+    pub fn recent_instructions(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
+        self.history.entries.iter().copied()
+    }
+
+    /// Remember the last `size` instructions from now on, 0 turns it off
+    pub fn set_history_size(&mut self, size: usize) {
+        let entries = &mut self.history.entries;
+        entries.drain(..entries.len().saturating_sub(size));
+        self.history.size = size;
+    }
+
+    pub(crate) fn record_history(&mut self, op: u16) {
+        let pc = self.op_pc();
+        self.history.record(pc, op);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chip8Error;
+
+    #[test]
+    fn how_it_got_there() {
+        let mut c8 = Chip8::new();
+        c8.set_history_size(3);
+        // LD V0, 1; JP 0x206; NOP; CALL 0x20A; NOP; then 0xFFFF at 0x20A
+        c8.load(&[
+            0x60, 0x01, 0x12, 0x06, 0x00, 0x00, 0x22, 0x0A, 0x00, 0x00, 0xFF, 0xFF,
+        ])
+        .unwrap();
+        for _ in 0..3 {
+            c8.tick().unwrap();
+        }
+        assert_eq!(
+            c8.tick(),
+            Err(Chip8Error::UnknownOpcode {
+                op: 0xFFFF,
+                pc: 0x20A
+            })
+        );
+        assert_eq!(
+            c8.recent_instructions().collect::<Vec<_>>(),
+            [(0x202, 0x1206), (0x206, 0x220A), (0x20A, 0xFFFF)]
+        );
+
+        c8.set_history_size(1);
+        assert_eq!(c8.recent_instructions().count(), 1);
+        c8.reset();
+        assert_eq!(c8.recent_instructions().count(), 0);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::Chip8;
+use std::any::Any;
+
+/// Called around every instruction, for tracers, coverage tools, profilers
+/// and cheats
+///
+/// Hooks run in registration order. The PC already points past `op` in
+/// both calls. `after` is skipped when the instruction faults, the fault
+/// comes back from `tick` as usual.
+pub trait ExecHook: HookClone + Any {
+    fn before(&mut self, _chip8: &mut Chip8, _op: u16) {}
+    fn after(&mut self, _chip8: &mut Chip8, _op: u16) {}
+}
+
+clone_box!(HookClone, ExecHook);
+
+impl Chip8 {
+    /// Add a hook to run before and after each instruction
+    pub fn add_exec_hook(&mut self, hook: Box<dyn ExecHook>) {
+        self.hooks.push(hook);
+    }
+
+    /// Find a registered hook by type, e.g. to read what it collected
+    pub fn exec_hook<T: ExecHook>(&self) -> Option<&T> {
+        self.hooks
+            .iter()
+            .find_map(|h| (h.as_ref() as &dyn Any).downcast_ref::<T>())
+    }
+
+    pub fn clear_exec_hooks(&mut self) {
+        self.hooks.clear();
+    }
+
+    /// Call `f` on each hook
+    pub(crate) fn run_exec_hooks(&mut self, f: impl Fn(&mut dyn ExecHook, &mut Chip8)) {
+        if self.hooks.is_empty() {
+            return;
+        }
+        // Hooks get mutable access to the machine, so take them out while they run
+        let mut hooks = std::mem::take(&mut self.hooks);
+        for hook in &mut hooks {
+            f(hook.as_mut(), self);
+        }
+        // A hook may have added more hooks, keep those too
+        hooks.append(&mut self.hooks);
+        self.hooks = hooks;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    /// Addresses executed, and V0 pinned to 9 like an infinite lives cheat
+    #[derive(Clone, Default)]
+    struct Coverage {
+        seen: BTreeSet<u16>,
+        ops: Vec<u16>,
+    }
+
+    impl ExecHook for Coverage {
+        fn before(&mut self, chip8: &mut Chip8, op: u16) {
+            self.seen.insert(chip8.pc() - 2);
+            self.ops.push(op);
+        }
+
+        fn after(&mut self, chip8: &mut Chip8, _op: u16) {
+            chip8.v_reg[0] = 9;
+        }
+    }
+
+    #[test]
+    fn before_and_after() {
+        let mut c8 = Chip8::new();
+        c8.add_exec_hook(Box::new(Coverage::default()));
+        // LD V0, 1; ADD V0, 1; JP 0x200
+        c8.load(&[0x60, 0x01, 0x70, 0x01, 0x12, 0x00]).unwrap();
+        for _ in 0..4 {
+            c8.tick().unwrap();
+        }
+        let coverage = c8.exec_hook::<Coverage>().unwrap();
+        assert_eq!(coverage.seen, BTreeSet::from([0x200, 0x202, 0x204]));
+        assert_eq!(coverage.ops, [0x6001, 0x7001, 0x1200, 0x6001]);
+        assert_eq!(c8.v_reg()[0], 9);
+
+        c8.clear_exec_hooks();
+        c8.tick().unwrap();
+        assert_eq!(c8.v_reg()[0], 10);
+    }
+}
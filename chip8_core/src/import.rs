@@ -0,0 +1,175 @@
+use crate::error::ImportError;
+use crate::screen::pack;
+use crate::{Chip8, State, HIRES_HEIGHT, HIRES_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH, V_REG_SIZE};
+use serde::Deserialize;
+
+/// Octo's emulator state, as produced by `JSON.stringify` on its `Emulator`
+///
+/// Only the fields we can map are listed, anything else is ignored.
+#[derive(Deserialize)]
+struct OctoState {
+    pc: Option<u16>,
+    i: Option<u16>,
+    v: Option<Vec<u8>>,
+    r: Option<Vec<u16>>,
+    m: Option<Vec<u8>>,
+    dt: Option<u8>,
+    st: Option<u8>,
+    // Display planes, one byte per pixel
+    p: Option<Vec<Vec<u8>>>,
+    hires: Option<bool>,
+    flags: Option<Vec<u8>>,
+}
+
+impl Chip8 {
+    /// Replace the machine state with a state saved by Octo
+    ///
+    /// Memory, registers, stack, timers, the SCHIP flags, hires mode and both
+    /// display planes are carried over. Hires states are rejected on variants
+    /// without SCHIP, and so is memory beyond this machine's RAM unless it's
+    /// all zero. Like `restore`, a successful import leaves the machine
+    /// running with plane 1 selected. Nothing is changed if the import fails.
+    pub fn import_octo_state(&mut self, json: &str) -> Result<(), ImportError> {
+        let state: OctoState =
+            serde_json::from_str(json).map_err(|e| ImportError::Parse(e.to_string()))?;
+
+        let hires = state.hires.unwrap_or(false);
+        if hires && !self.variant.has_schip() {
+            return Err(ImportError::Unsupported("hires mode"));
+        }
+
+        let pc = state.pc.ok_or(ImportError::Missing("pc"))?;
+        let v = state.v.ok_or(ImportError::Missing("v"))?;
+        if v.len() != V_REG_SIZE {
+            return Err(ImportError::OutOfRange("v"));
+        }
+        let stack = state.r.unwrap_or_default();
+        if stack.len() > self.stack.len() {
+            return Err(ImportError::OutOfRange("r"));
+        }
+        let mem = state.m.ok_or(ImportError::Missing("m"))?;
+        if mem.iter().skip(self.ram.len()).any(|b| *b != 0) {
+            return Err(ImportError::OutOfRange("m"));
+        }
+        if pc as usize >= self.ram.len() {
+            return Err(ImportError::OutOfRange("pc"));
+        }
+
+        let blank = if hires {
+            vec![false; HIRES_WIDTH * HIRES_HEIGHT]
+        } else {
+            vec![false; SCREEN_WIDTH * SCREEN_HEIGHT]
+        };
+        let mut screens = [blank.clone(), blank];
+        for (screen, plane) in screens.iter_mut().zip(state.p.iter().flatten()) {
+            // Octo leaves an unused plane empty
+            if plane.is_empty() {
+                continue;
+            }
+            if plane.len() < screen.len() {
+                return Err(ImportError::OutOfRange("p"));
+            }
+            for (pixel, value) in screen.iter_mut().zip(plane) {
+                *pixel = *value != 0;
+            }
+        }
+
+        self.pc = pc;
+        self.i_reg = state.i.unwrap_or(0);
+        self.v_reg.copy_from_slice(&v);
+        self.stack.fill(0);
+        self.stack[..stack.len()].copy_from_slice(&stack);
+        self.sp = stack.len() as u16;
+        self.forget_calls();
+        self.ram.fill(0);
+        let len = mem.len().min(self.ram.len());
+        self.ram[..len].copy_from_slice(&mem[..len]);
+        self.dt = state.dt.unwrap_or(0);
+        self.st = state.st.unwrap_or(0);
+        if let Some(flags) = &state.flags {
+            self.set_flags(flags);
+        }
+        self.set_hires(hires);
+        self.screen.set_bytes(&pack(&screens[0]));
+        self.plane2.set_bytes(&pack(&screens[1]));
+        self.planes = 1;
+        #[cfg(feature = "unchecked")]
+        {
+            self.unchecked = false;
+        }
+        self.blocked = false;
+        self.state = State::Running;
+        self.screen_changed = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(extra: &str) -> String {
+        let mut mem = vec![0u8; 4096];
+        mem[0x200] = 0x12;
+        let plane = vec![1u8; 64 * 32];
+        format!(
+            r#"{{"pc":514,"i":80,"v":[1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16],"r":[518],
+                "m":{:?},"dt":5,"st":0,"p":[{:?},[]]{}}}"#,
+            mem, plane, extra
+        )
+    }
+
+    #[test]
+    fn import_octo() {
+        let mut c8 = Chip8::new();
+        c8.import_octo_state(&state("")).unwrap();
+        assert_eq!(c8.pc(), 0x202);
+        assert_eq!(c8.i_reg(), 0x50);
+        assert_eq!(c8.v_reg()[0xF], 16);
+        assert_eq!(c8.dt(), 5);
+        assert_eq!(c8.ram()[0x200], 0x12);
+        assert!(c8.get_display_colors().iter().all(|px| *px == 1));
+        assert_eq!(c8.pop().unwrap(), 0x206);
+    }
+
+    #[test]
+    fn import_hires_and_flags() {
+        let mut c8 = Chip8::new();
+        // EXIT, so the import has something to clear
+        c8.load(&[0x00, 0xFD]).unwrap();
+        c8.tick().unwrap();
+        assert_eq!(c8.state(), State::Halted);
+        let mut json = state(r#","hires":true,"flags":[7,8]"#);
+        // A hires plane is 128x64, light the last pixel
+        let mut plane = vec![0u8; 128 * 64];
+        plane[128 * 64 - 1] = 1;
+        json = json.replace(
+            &format!("{:?}", vec![1u8; 64 * 32]),
+            &format!("{:?}", plane),
+        );
+        c8.import_octo_state(&json).unwrap();
+        assert_eq!((c8.screen_width(), c8.screen_height()), (128, 64));
+        assert_eq!(c8.get_display_colors()[128 * 64 - 1], 1);
+        assert_eq!(&c8.flags()[..3], &[7, 8, 0]);
+        assert_eq!(c8.state(), State::Running);
+    }
+
+    #[test]
+    fn unsupported_fields() {
+        let mut c8 = Chip8::with_config(crate::Variant::CosmacVip.config());
+        assert_eq!(
+            c8.import_octo_state(&state(r#","hires":true"#)),
+            Err(ImportError::Unsupported("hires mode"))
+        );
+        assert_eq!(
+            c8.import_octo_state(r#"{"v":[]}"#),
+            Err(ImportError::Missing("pc"))
+        );
+        assert!(matches!(
+            c8.import_octo_state("nope"),
+            Err(ImportError::Parse(_))
+        ));
+        // Failed imports leave the machine alone
+        assert_eq!(c8.pc(), 0x200);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::Chip8;
+use std::collections::BTreeMap;
+
+/// When a scheduled key event applies
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum At {
+    /// Just before the instruction with this index (counting from 0 at reset)
+    Instruction(u64),
+    /// At the start of this frame, i.e. once `tick_timers` has run this many times
+    Frame(u64),
+}
+
+/// Pending key events by instruction and by frame count
+#[derive(Clone, Default)]
+pub(crate) struct InputQueue {
+    by_instruction: BTreeMap<u64, Vec<(usize, bool)>>,
+    by_frame: BTreeMap<u64, Vec<(usize, bool)>>,
+}
+
+impl Chip8 {
+    /// Instructions executed since the last reset
+    pub fn instruction_count(&self) -> u64 {
+        self.instructions
+    }
+
+    /// Frames (`tick_timers` calls) since the last reset
+    pub fn frame_count(&self) -> u64 {
+        self.frames
+    }
+
+    /// Press or release key `idx` at an exact point in the future
+    ///
+    /// Events scheduled for the same point apply in the order they were added.
+    /// Ones scheduled for a point already passed apply at the next instruction.
+    /// Used for deterministic replays and scripted tests.
+    pub fn schedule_key(&mut self, at: At, idx: usize, pressed: bool) {
+        assert!(idx < self.keys.len(), "no key {:X}", idx);
+        let (count, queue) = match at {
+            At::Instruction(n) => (n, &mut self.input_queue.by_instruction),
+            At::Frame(n) => (n, &mut self.input_queue.by_frame),
+        };
+        queue.entry(count).or_default().push((idx, pressed));
+    }
+
+    /// Number of key events still waiting to be applied
+    pub fn scheduled_keys(&self) -> usize {
+        let queue = &self.input_queue;
+        queue.by_instruction.values().map(Vec::len).sum::<usize>()
+            + queue.by_frame.values().map(Vec::len).sum::<usize>()
+    }
+
+    pub(crate) fn apply_scheduled_input(&mut self) {
+        let (instructions, frames) = (self.instructions, self.frames);
+        let queue = &mut self.input_queue;
+        let mut due = Vec::new();
+        // Frame events first, they were due before any instruction in the frame
+        while let Some(entry) = queue.by_frame.first_entry() {
+            if *entry.key() > frames {
+                break;
+            }
+            due.extend(entry.remove());
+        }
+        while let Some(entry) = queue.by_instruction.first_entry() {
+            if *entry.key() > instructions {
+                break;
+            }
+            due.extend(entry.remove());
+        }
+        for (idx, pressed) in due {
+            self.keys[idx] = pressed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_apply_at_instruction() {
+        let mut c8 = Chip8::new();
+        // LD V1, K; JP 0x202
+        c8.load(&[0xF1, 0x0A, 0x12, 0x02]).unwrap();
+        c8.schedule_key(At::Instruction(3), 0x7, true);
+        for _ in 0..3 {
+            c8.tick().unwrap();
+        }
+        assert_eq!(c8.pc(), 0x200);
+        c8.tick().unwrap();
+        assert_eq!(c8.pc(), 0x202);
+        assert_eq!(c8.v_reg()[1], 0x7);
+        assert_eq!(c8.instruction_count(), 4);
+        assert_eq!(c8.scheduled_keys(), 0);
+    }
+
+    #[test]
+    fn keys_apply_at_frame() {
+        let mut c8 = Chip8::new();
+        c8.schedule_key(At::Frame(2), 0xA, true);
+        c8.schedule_key(At::Frame(3), 0xA, false);
+        c8.tick_timers();
+        assert!(!c8.keys[0xA]);
+        c8.tick_timers();
+        assert!(c8.keys[0xA]);
+        c8.tick_timers();
+        assert!(!c8.keys[0xA]);
+        assert_eq!(c8.frame_count(), 3);
+    }
+}
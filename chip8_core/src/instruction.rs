@@ -0,0 +1,481 @@
+use std::fmt;
+
+/// A decoded opcode with its operands, see `decode`
+///
+/// Covers CHIP-8, SCHIP and XO-CHIP. Decoding doesn't know which variant is
+/// running, so `Chip8::execute` still rejects extension opcodes the machine
+/// doesn't have. Register fields are indices into V0 - VF.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    /// 0000, does nothing
+    Nop,
+    /// 00E0
+    Clear,
+    /// 00EE
+    Return,
+    /// 00Cn (SCHIP)
+    ScrollDown { n: u8 },
+    /// 00Dn (XO-CHIP)
+    ScrollUp { n: u8 },
+    /// 00FB (SCHIP)
+    ScrollRight,
+    /// 00FC (SCHIP)
+    ScrollLeft,
+    /// 00FD (SCHIP)
+    Exit,
+    /// 00FE (SCHIP)
+    LowRes,
+    /// 00FF (SCHIP)
+    HighRes,
+    /// 0nnn, a machine code routine on the VIP. Not emulated, so it's left
+    /// to custom handlers and fails with `UnknownOpcode` otherwise.
+    Sys { nnn: u16 },
+    /// 1nnn
+    Jump { nnn: u16 },
+    /// 2nnn
+    Call { nnn: u16 },
+    /// 3xnn
+    SkipEqImm { x: usize, nn: u8 },
+    /// 4xnn
+    SkipNeImm { x: usize, nn: u8 },
+    /// 5xy0
+    SkipEqReg { x: usize, y: usize },
+    /// 5xy2 (XO-CHIP)
+    SaveRange { x: usize, y: usize },
+    /// 5xy3 (XO-CHIP)
+    LoadRange { x: usize, y: usize },
+    /// 6xnn
+    LoadImm { x: usize, nn: u8 },
+    /// 7xnn
+    AddImm { x: usize, nn: u8 },
+    /// 8xy0
+    Move { x: usize, y: usize },
+    /// 8xy1
+    Or { x: usize, y: usize },
+    /// 8xy2
+    And { x: usize, y: usize },
+    /// 8xy3
+    Xor { x: usize, y: usize },
+    /// 8xy4
+    Add { x: usize, y: usize },
+    /// 8xy5
+    Sub { x: usize, y: usize },
+    /// 8xy6
+    ShiftRight { x: usize, y: usize },
+    /// 8xy7
+    SubReverse { x: usize, y: usize },
+    /// 8xyE
+    ShiftLeft { x: usize, y: usize },
+    /// 9xy0
+    SkipNeReg { x: usize, y: usize },
+    /// Annn
+    LoadI { nnn: u16 },
+    /// Bnnn, `x` is only used with the jump quirk
+    JumpOffset { x: usize, nnn: u16 },
+    /// Cxnn
+    Random { x: usize, nn: u8 },
+    /// Dxyn
+    Draw { x: usize, y: usize, n: u8 },
+    /// Ex9E
+    SkipKey { x: usize },
+    /// ExA1
+    SkipNotKey { x: usize },
+    /// F000 NNNN (XO-CHIP), the address is the next word
+    LoadILong,
+    /// Fn01 (XO-CHIP)
+    SelectPlanes { n: u8 },
+    /// F002 (XO-CHIP)
+    LoadAudio,
+    /// Fx07
+    GetDelay { x: usize },
+    /// Fx0A
+    WaitKey { x: usize },
+    /// Fx15
+    SetDelay { x: usize },
+    /// Fx18
+    SetSound { x: usize },
+    /// Fx1E
+    AddI { x: usize },
+    /// Fx29
+    Font { x: usize },
+    /// Fx30 (SCHIP)
+    BigFont { x: usize },
+    /// Fx33
+    Bcd { x: usize },
+    /// Fx3A (XO-CHIP)
+    SetPitch { x: usize },
+    /// Fx55
+    Store { x: usize },
+    /// Fx65
+    Load { x: usize },
+    /// Fx75 (SCHIP)
+    SaveFlags { x: usize },
+    /// Fx85 (SCHIP)
+    LoadFlags { x: usize },
+    /// Anything else, with the raw opcode
+    Unknown(u16),
+}
+
+/// Turn an opcode into an `Instruction`
+///
+/// Pure, every `u16` decodes to something (`Unknown` if nothing else).
+pub fn decode(op: u16) -> Instruction {
+    use Instruction::*;
+    let x = ((op & 0x0F00) >> 8) as usize;
+    let y = ((op & 0x00F0) >> 4) as usize;
+    let n = (op & 0x000F) as u8;
+    let nn = (op & 0x00FF) as u8;
+    let nnn = op & 0x0FFF;
+
+    match (op >> 12, n) {
+        (0, _) => match op {
+            0x0000 => Nop,
+            0x00E0 => Clear,
+            0x00EE => Return,
+            0x00C0..=0x00CF => ScrollDown { n },
+            0x00D0..=0x00DF => ScrollUp { n },
+            0x00FB => ScrollRight,
+            0x00FC => ScrollLeft,
+            0x00FD => Exit,
+            0x00FE => LowRes,
+            0x00FF => HighRes,
+            _ => Sys { nnn },
+        },
+        (1, _) => Jump { nnn },
+        (2, _) => Call { nnn },
+        (3, _) => SkipEqImm { x, nn },
+        (4, _) => SkipNeImm { x, nn },
+        (5, 0) => SkipEqReg { x, y },
+        (5, 2) => SaveRange { x, y },
+        (5, 3) => LoadRange { x, y },
+        (6, _) => LoadImm { x, nn },
+        (7, _) => AddImm { x, nn },
+        (8, 0) => Move { x, y },
+        (8, 1) => Or { x, y },
+        (8, 2) => And { x, y },
+        (8, 3) => Xor { x, y },
+        (8, 4) => Add { x, y },
+        (8, 5) => Sub { x, y },
+        (8, 6) => ShiftRight { x, y },
+        (8, 7) => SubReverse { x, y },
+        (8, 0xE) => ShiftLeft { x, y },
+        (9, 0) => SkipNeReg { x, y },
+        (0xA, _) => LoadI { nnn },
+        (0xB, _) => JumpOffset { x, nnn },
+        (0xC, _) => Random { x, nn },
+        (0xD, _) => Draw { x, y, n },
+        (0xE, _) if nn == 0x9E => SkipKey { x },
+        (0xE, _) if nn == 0xA1 => SkipNotKey { x },
+        (0xF, _) => match (x, nn) {
+            (0, 0x00) => LoadILong,
+            (_, 0x01) => SelectPlanes { n: x as u8 },
+            (0, 0x02) => LoadAudio,
+            (_, 0x07) => GetDelay { x },
+            (_, 0x0A) => WaitKey { x },
+            (_, 0x15) => SetDelay { x },
+            (_, 0x18) => SetSound { x },
+            (_, 0x1E) => AddI { x },
+            (_, 0x29) => Font { x },
+            (_, 0x30) => BigFont { x },
+            (_, 0x33) => Bcd { x },
+            (_, 0x3A) => SetPitch { x },
+            (_, 0x55) => Store { x },
+            (_, 0x65) => Load { x },
+            (_, 0x75) => SaveFlags { x },
+            (_, 0x85) => LoadFlags { x },
+            _ => Unknown(op),
+        },
+        _ => Unknown(op),
+    }
+}
+
+impl Instruction {
+    /// Encode back into the opcode `decode` made this from
+    pub fn op(self) -> u16 {
+        use Instruction::*;
+        let xy = |base: u16, x: usize, y: usize| base | (x as u16) << 8 | (y as u16) << 4;
+        let xnn = |base: u16, x: usize, nn: u8| base | (x as u16) << 8 | nn as u16;
+        let fx = |low: u16, x: usize| 0xF000 | (x as u16) << 8 | low;
+        match self {
+            Nop => 0x0000,
+            Clear => 0x00E0,
+            Return => 0x00EE,
+            ScrollDown { n } => 0x00C0 | n as u16,
+            ScrollUp { n } => 0x00D0 | n as u16,
+            ScrollRight => 0x00FB,
+            ScrollLeft => 0x00FC,
+            Exit => 0x00FD,
+            LowRes => 0x00FE,
+            HighRes => 0x00FF,
+            Sys { nnn } => nnn,
+            Jump { nnn } => 0x1000 | nnn,
+            Call { nnn } => 0x2000 | nnn,
+            SkipEqImm { x, nn } => xnn(0x3000, x, nn),
+            SkipNeImm { x, nn } => xnn(0x4000, x, nn),
+            SkipEqReg { x, y } => xy(0x5000, x, y),
+            SaveRange { x, y } => xy(0x5002, x, y),
+            LoadRange { x, y } => xy(0x5003, x, y),
+            LoadImm { x, nn } => xnn(0x6000, x, nn),
+            AddImm { x, nn } => xnn(0x7000, x, nn),
+            Move { x, y } => xy(0x8000, x, y),
+            Or { x, y } => xy(0x8001, x, y),
+            And { x, y } => xy(0x8002, x, y),
+            Xor { x, y } => xy(0x8003, x, y),
+            Add { x, y } => xy(0x8004, x, y),
+            Sub { x, y } => xy(0x8005, x, y),
+            ShiftRight { x, y } => xy(0x8006, x, y),
+            SubReverse { x, y } => xy(0x8007, x, y),
+            ShiftLeft { x, y } => xy(0x800E, x, y),
+            SkipNeReg { x, y } => xy(0x9000, x, y),
+            LoadI { nnn } => 0xA000 | nnn,
+            JumpOffset { nnn, .. } => 0xB000 | nnn,
+            Random { x, nn } => xnn(0xC000, x, nn),
+            Draw { x, y, n } => xy(0xD000, x, y) | n as u16,
+            SkipKey { x } => xnn(0xE000, x, 0x9E),
+            SkipNotKey { x } => xnn(0xE000, x, 0xA1),
+            LoadILong => 0xF000,
+            SelectPlanes { n } => fx(0x01, n as usize),
+            LoadAudio => 0xF002,
+            GetDelay { x } => fx(0x07, x),
+            WaitKey { x } => fx(0x0A, x),
+            SetDelay { x } => fx(0x15, x),
+            SetSound { x } => fx(0x18, x),
+            AddI { x } => fx(0x1E, x),
+            Font { x } => fx(0x29, x),
+            BigFont { x } => fx(0x30, x),
+            Bcd { x } => fx(0x33, x),
+            SetPitch { x } => fx(0x3A, x),
+            Store { x } => fx(0x55, x),
+            Load { x } => fx(0x65, x),
+            SaveFlags { x } => fx(0x75, x),
+            LoadFlags { x } => fx(0x85, x),
+            Unknown(op) => op,
+        }
+    }
+
+    /// Opcode pattern with operands as letters, e.g. `8xy4` or `Fx55`
+    ///
+    /// Unknown opcodes keep their last byte (or nibble), so `8AB9` gives
+    /// `8xy9`.
+    pub fn pattern(self) -> String {
+        let op = self.op();
+        let family = op >> 12;
+        let n = op & 0x000F;
+        match family {
+            0 => match op {
+                0x00E0 | 0x00EE | 0x00FB | 0x00FC | 0x00FD | 0x00FE | 0x00FF => {
+                    format!("{:04X}", op)
+                }
+                0x00C0..=0x00CF => "00Cn".to_string(),
+                0x00D0..=0x00DF => "00Dn".to_string(),
+                _ => "0nnn".to_string(),
+            },
+            1 | 2 | 0xA | 0xB => format!("{:X}nnn", family),
+            3 | 4 | 6 | 7 | 0xC => format!("{:X}xnn", family),
+            5 | 9 => format!("{:X}xy{:X}", family, n),
+            8 => format!("8xy{:X}", n),
+            0xD if n == 0 => "Dxy0".to_string(),
+            0xD => "Dxyn".to_string(),
+            0xF if op == 0xF000 || op == 0xF002 => format!("{:04X}", op),
+            _ => format!("{:X}x{:02X}", family, op & 0x00FF),
+        }
+    }
+}
+
+/// Cowgod's mnemonics by default, e.g. `LD VA, 0x2F`, or Octo's with the
+/// alternate flag: `format!("{:#}", instr)` gives `va := 0x2F`
+///
+/// Octo writes skips as the `if ... then` that runs the next instruction,
+/// so `3A12` (skip if VA == 0x12) comes out as `if va != 0x12 then`.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            self.fmt_octo(f)
+        } else {
+            self.fmt_classic(f)
+        }
+    }
+}
+
+impl Instruction {
+    fn fmt_classic(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Instruction::*;
+        match *self {
+            Nop => write!(f, "NOP"),
+            Clear => write!(f, "CLS"),
+            Return => write!(f, "RET"),
+            ScrollDown { n } => write!(f, "SCD {}", n),
+            ScrollUp { n } => write!(f, "SCU {}", n),
+            ScrollRight => write!(f, "SCR"),
+            ScrollLeft => write!(f, "SCL"),
+            Exit => write!(f, "EXIT"),
+            LowRes => write!(f, "LOW"),
+            HighRes => write!(f, "HIGH"),
+            Sys { nnn } => write!(f, "SYS 0x{:03X}", nnn),
+            Jump { nnn } => write!(f, "JP 0x{:03X}", nnn),
+            Call { nnn } => write!(f, "CALL 0x{:03X}", nnn),
+            SkipEqImm { x, nn } => write!(f, "SE V{:X}, 0x{:02X}", x, nn),
+            SkipNeImm { x, nn } => write!(f, "SNE V{:X}, 0x{:02X}", x, nn),
+            SkipEqReg { x, y } => write!(f, "SE V{:X}, V{:X}", x, y),
+            SaveRange { x, y } => write!(f, "SAVE V{:X}, V{:X}", x, y),
+            LoadRange { x, y } => write!(f, "LOAD V{:X}, V{:X}", x, y),
+            LoadImm { x, nn } => write!(f, "LD V{:X}, 0x{:02X}", x, nn),
+            AddImm { x, nn } => write!(f, "ADD V{:X}, 0x{:02X}", x, nn),
+            Move { x, y } => write!(f, "LD V{:X}, V{:X}", x, y),
+            Or { x, y } => write!(f, "OR V{:X}, V{:X}", x, y),
+            And { x, y } => write!(f, "AND V{:X}, V{:X}", x, y),
+            Xor { x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Add { x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Sub { x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
+            ShiftRight { x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
+            SubReverse { x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            ShiftLeft { x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
+            SkipNeReg { x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
+            LoadI { nnn } => write!(f, "LD I, 0x{:03X}", nnn),
+            JumpOffset { nnn, .. } => write!(f, "JP V0, 0x{:03X}", nnn),
+            Random { x, nn } => write!(f, "RND V{:X}, 0x{:02X}", x, nn),
+            Draw { x, y, n } => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            SkipKey { x } => write!(f, "SKP V{:X}", x),
+            SkipNotKey { x } => write!(f, "SKNP V{:X}", x),
+            LoadILong => write!(f, "LD I, LONG"),
+            SelectPlanes { n } => write!(f, "PLANE {}", n),
+            LoadAudio => write!(f, "AUDIO"),
+            GetDelay { x } => write!(f, "LD V{:X}, DT", x),
+            WaitKey { x } => write!(f, "LD V{:X}, K", x),
+            SetDelay { x } => write!(f, "LD DT, V{:X}", x),
+            SetSound { x } => write!(f, "LD ST, V{:X}", x),
+            AddI { x } => write!(f, "ADD I, V{:X}", x),
+            Font { x } => write!(f, "LD F, V{:X}", x),
+            BigFont { x } => write!(f, "LD HF, V{:X}", x),
+            Bcd { x } => write!(f, "LD B, V{:X}", x),
+            SetPitch { x } => write!(f, "PITCH V{:X}", x),
+            Store { x } => write!(f, "LD [I], V{:X}", x),
+            Load { x } => write!(f, "LD V{:X}, [I]", x),
+            SaveFlags { x } => write!(f, "LD R, V{:X}", x),
+            LoadFlags { x } => write!(f, "LD V{:X}, R", x),
+            Unknown(op) => write!(f, "DW 0x{:04X}", op),
+        }
+    }
+
+    fn fmt_octo(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Instruction::*;
+        match *self {
+            Clear => write!(f, "clear"),
+            Return => write!(f, "return"),
+            ScrollDown { n } => write!(f, "scroll-down {}", n),
+            ScrollUp { n } => write!(f, "scroll-up {}", n),
+            ScrollRight => write!(f, "scroll-right"),
+            ScrollLeft => write!(f, "scroll-left"),
+            Exit => write!(f, "exit"),
+            LowRes => write!(f, "lores"),
+            HighRes => write!(f, "hires"),
+            Sys { nnn } => write!(f, "native 0x{:03X}", nnn),
+            Jump { nnn } => write!(f, "jump 0x{:03X}", nnn),
+            Call { nnn } => write!(f, ":call 0x{:03X}", nnn),
+            SkipEqImm { x, nn } => write!(f, "if v{:x} != 0x{:02X} then", x, nn),
+            SkipNeImm { x, nn } => write!(f, "if v{:x} == 0x{:02X} then", x, nn),
+            SkipEqReg { x, y } => write!(f, "if v{:x} != v{:x} then", x, y),
+            SaveRange { x, y } => write!(f, "save v{:x} - v{:x}", x, y),
+            LoadRange { x, y } => write!(f, "load v{:x} - v{:x}", x, y),
+            LoadImm { x, nn } => write!(f, "v{:x} := 0x{:02X}", x, nn),
+            AddImm { x, nn } => write!(f, "v{:x} += 0x{:02X}", x, nn),
+            Move { x, y } => write!(f, "v{:x} := v{:x}", x, y),
+            Or { x, y } => write!(f, "v{:x} |= v{:x}", x, y),
+            And { x, y } => write!(f, "v{:x} &= v{:x}", x, y),
+            Xor { x, y } => write!(f, "v{:x} ^= v{:x}", x, y),
+            Add { x, y } => write!(f, "v{:x} += v{:x}", x, y),
+            Sub { x, y } => write!(f, "v{:x} -= v{:x}", x, y),
+            ShiftRight { x, y } => write!(f, "v{:x} >>= v{:x}", x, y),
+            SubReverse { x, y } => write!(f, "v{:x} =- v{:x}", x, y),
+            ShiftLeft { x, y } => write!(f, "v{:x} <<= v{:x}", x, y),
+            SkipNeReg { x, y } => write!(f, "if v{:x} == v{:x} then", x, y),
+            LoadI { nnn } => write!(f, "i := 0x{:03X}", nnn),
+            JumpOffset { nnn, .. } => write!(f, "jump0 0x{:03X}", nnn),
+            Random { x, nn } => write!(f, "v{:x} := random 0x{:02X}", x, nn),
+            Draw { x, y, n } => write!(f, "sprite v{:x} v{:x} {}", x, y, n),
+            SkipKey { x } => write!(f, "if v{:x} -key then", x),
+            SkipNotKey { x } => write!(f, "if v{:x} key then", x),
+            LoadILong => write!(f, "i := long"),
+            SelectPlanes { n } => write!(f, "plane {}", n),
+            LoadAudio => write!(f, "audio"),
+            GetDelay { x } => write!(f, "v{:x} := delay", x),
+            WaitKey { x } => write!(f, "v{:x} := key", x),
+            SetDelay { x } => write!(f, "delay := v{:x}", x),
+            SetSound { x } => write!(f, "buzzer := v{:x}", x),
+            AddI { x } => write!(f, "i += v{:x}", x),
+            Font { x } => write!(f, "i := hex v{:x}", x),
+            BigFont { x } => write!(f, "i := bighex v{:x}", x),
+            Bcd { x } => write!(f, "bcd v{:x}", x),
+            SetPitch { x } => write!(f, "pitch := v{:x}", x),
+            Store { x } => write!(f, "save v{:x}", x),
+            Load { x } => write!(f, "load v{:x}", x),
+            SaveFlags { x } => write!(f, "saveflags v{:x}", x),
+            LoadFlags { x } => write!(f, "loadflags v{:x}", x),
+            // Octo has no mnemonic for these, write them out as data
+            Nop | Unknown(_) => {
+                let [hi, lo] = self.op().to_be_bytes();
+                write!(f, "0x{:02X} 0x{:02X}", hi, lo)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chip8, Chip8Error};
+
+    #[test]
+    fn decode_operands() {
+        assert_eq!(decode(0xD12F), Instruction::Draw { x: 1, y: 2, n: 0xF });
+        assert_eq!(decode(0x7A05), Instruction::AddImm { x: 0xA, nn: 5 });
+        assert_eq!(decode(0x00C4), Instruction::ScrollDown { n: 4 });
+        assert_eq!(decode(0x0123), Instruction::Sys { nnn: 0x123 });
+        assert_eq!(decode(0xF201), Instruction::SelectPlanes { n: 2 });
+        assert_eq!(decode(0x8AB9), Instruction::Unknown(0x8AB9));
+        assert_eq!(decode(0xF100), Instruction::Unknown(0xF100));
+    }
+
+    #[test]
+    fn patterns() {
+        assert_eq!(decode(0xD12F).pattern(), "Dxyn");
+        assert_eq!(decode(0xD120).pattern(), "Dxy0");
+        assert_eq!(decode(0xF355).pattern(), "Fx55");
+        assert_eq!(decode(0x8AB4).pattern(), "8xy4");
+        assert_eq!(decode(0x00C4).pattern(), "00Cn");
+        assert_eq!(decode(0x8AB9).pattern(), "8xy9");
+    }
+
+    #[test]
+    fn classic_and_octo_syntax() {
+        let instr = decode(0x3A12);
+        assert_eq!(instr.to_string(), "SE VA, 0x12");
+        assert_eq!(format!("{:#}", instr), "if va != 0x12 then");
+        assert_eq!(format!("{:#}", decode(0xF129)), "i := hex v1");
+        assert_eq!(format!("{:#}", decode(0x8AB7)), "va =- vb");
+        assert_eq!(format!("{:#}", decode(0x5AB9)), "0x5A 0xB9");
+    }
+
+    #[test]
+    fn execute_by_hand() {
+        let mut c8 = Chip8::new();
+        c8.set_v(1, 200).unwrap();
+        c8.execute(Instruction::Add { x: 1, y: 1 }).unwrap();
+        assert_eq!((c8.v_reg()[1], c8.v_reg()[0xF]), (144, 1));
+        assert_eq!(c8.instruction_count(), 1);
+        assert_eq!(
+            c8.execute(Instruction::Sys { nnn: 0x123 }),
+            Err(Chip8Error::UnknownOpcode {
+                op: 0x0123,
+                pc: 0x1FE
+            })
+        );
+    }
+
+    #[test]
+    fn every_opcode_encodes_back() {
+        for op in 0..=u16::MAX {
+            assert_eq!(decode(op).op(), op, "{:04X}", op);
+        }
+    }
+}
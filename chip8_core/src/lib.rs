@@ -1,10 +1,121 @@
-use rand::random;
+use input::InputQueue;
+use rng::Rng;
+use screen::Screen;
+use sprites::SpriteLog;
+use std::collections::BTreeSet;
+use std::ops::Range;
+use xochip::PLANE_COUNT;
+
+// First, so its macro is in scope for the modules below
+#[macro_use]
+mod boxed;
+pub mod asm;
+mod audio;
+pub mod calls;
+pub mod condition;
+pub mod config;
+pub mod coverage;
+pub mod custom;
+pub mod debug;
+pub mod disasm;
+pub mod error;
+pub mod events;
+pub mod flags;
+pub mod frame;
+pub mod harness;
+pub mod hash;
+#[cfg(feature = "heatmap")]
+mod heatmap;
+mod history;
+pub mod hooks;
+#[cfg(feature = "serde")]
+pub mod import;
+pub mod input;
+pub mod instruction;
+pub mod mapper;
+#[cfg(feature = "megachip")]
+pub mod megachip;
+pub mod octo;
+pub mod opcodes;
+#[cfg(feature = "serde")]
+pub mod package;
+pub mod persist;
+pub mod pipeline;
+pub mod profile;
+pub mod quirks;
+pub mod replay;
+pub mod rewind;
+pub mod rng;
+#[cfg(feature = "serde")]
+pub mod romdb;
+#[cfg(feature = "serde")]
+pub mod savestate;
+pub mod scheduler;
+mod schip;
+mod screen;
+#[cfg(feature = "settings")]
+pub mod settings;
+pub mod slots;
+pub mod sprites;
+pub mod state;
+pub mod symbols;
+pub mod text;
+pub mod trace;
+mod two_page;
+#[cfg(feature = "unchecked")]
+pub mod unchecked;
+mod xochip;
+
+pub use audio::AUDIO_PATTERN_SIZE;
+pub use calls::CallFrame;
+pub use condition::Condition;
+pub use config::{Config, PcOverflow, Variant};
+pub use coverage::Coverage;
+pub use custom::OpcodeHandler;
+pub use debug::{Access, WatchHit};
+pub use disasm::DisasmLine;
+pub use error::{
+    Chip8Error, ConditionError, ConfigError, DatabaseError, ImportError, LoadError, PackageError,
+    PokeError, ReplayError, SettingsError, StateError, UnknownVariant,
+};
+pub use events::Event;
+pub use flags::{FlagStorage, FLAG_COUNT};
+pub use frame::{Frame, FrameStats};
+pub use harness::TestHarness;
+pub use hooks::ExecHook;
+pub use input::At;
+pub use instruction::{decode, Instruction};
+pub use mapper::{BankedRom, Mapper};
+#[cfg(feature = "megachip")]
+pub use megachip::{Blend, MegaChip, MEGA_HEIGHT, MEGA_WIDTH};
+pub use opcodes::Explanation;
+#[cfg(feature = "serde")]
+pub use package::{Package, RomInfo, RomSettings};
+pub use profile::Profile;
+pub use quirks::Quirks;
+pub use replay::{KeyEvent, Recording};
+pub use rewind::Rewind;
+pub use rng::RandomSource;
+#[cfg(feature = "serde")]
+pub use romdb::{KnownRom, RomDatabase};
+#[cfg(feature = "serde")]
+pub use savestate::SaveState;
+pub use scheduler::Scheduler;
+#[cfg(feature = "settings")]
+pub use settings::Settings;
+pub use slots::{SaveSlot, SaveSlots};
+pub use sprites::{SpriteRef, SpriteSheet};
+pub use state::{Chip8State, State};
+pub use symbols::Symbols;
+pub use text::TextStyle;
+pub use trace::{TraceEntry, TraceFormat};
+pub use two_page::TWO_PAGE_HEIGHT;
 
 const MEM_SIZE: usize = 4096;
 const V_REG_SIZE: usize = 16;
 const STACK_SIZE: usize = 16;
 const KEYPAD_SIZE: usize = 16;
-const START_ADDR: u16 = 0x200; // start address for all chip 8 programs
+const START_ADDR: u16 = 0x200; // default start address for chip 8 programs
 const FONTSET_SIZE: usize = 80;
 
 const FONTSET: [u8; FONTSET_SIZE] = [
@@ -26,92 +137,498 @@ const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
+// SCHIP's 8x10 digits for Fx30, straight after the small font. SCHIP 1.1
+// only had 0-9, the letters are Octo's.
+const BIG_FONT_ADDR: u16 = FONTSET_SIZE as u16;
+const BIG_FONTSET_SIZE: usize = 160;
+const BIG_FONTSET: [u8; BIG_FONTSET_SIZE] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+// Both fonts end here, RAM past this is the interpreter's and program's
+const FONT_END: usize = FONTSET_SIZE + BIG_FONTSET_SIZE;
+
 pub const SCREEN_WIDTH: usize = 64;
 pub const SCREEN_HEIGHT: usize = 32;
+/// SCHIP hi-res mode resolution
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
 
+/// A blob to be placed at `addr`, see `Chip8::load_segments`
+#[derive(Clone, Copy, Debug)]
+pub struct Segment<'a> {
+    pub addr: u16,
+    pub data: &'a [u8],
+}
+
+#[derive(Clone)]
 pub struct Chip8 {
-    pc: u16,                                      // Program Counter
-    ram: [u8; MEM_SIZE],                          // RAM
-    screen: [bool; SCREEN_WIDTH * SCREEN_HEIGHT], // Display Screen
-    v_reg: [u8; V_REG_SIZE],                      // V registers
-    i_reg: u16,                                   // Indexing Register
-    sp: u16,                                      // Stack pointer
-    stack: [u16; STACK_SIZE],                     // CPU stack
-    dt: u8,                                       // delay timer
-    st: u8,                                       // sound timer
-    keys: [bool; KEYPAD_SIZE],                    // Keypad
+    pc: u16,                               // Program Counter
+    ram: Vec<u8>,                          // RAM
+    screen: Screen,                        // Display Screen, bit-packed
+    plane2: Screen,                        // XO-CHIP second display plane
+    hires: bool,                           // SCHIP 128x64 mode
+    two_page: bool,                        // HIRES CHIP-8 64x64 mode
+    v_reg: [u8; V_REG_SIZE],               // V registers
+    i_reg: u16,                            // Indexing Register
+    sp: u16,                               // Stack pointer
+    stack: Vec<u16>,                       // CPU stack
+    call_targets: Vec<Option<u16>>,        // CALL target for each stack slot
+    subroutines: BTreeSet<u16>,            // Every CALL target since reset
+    dt: u8,                                // delay timer
+    st: u8,                                // sound timer
+    keys: [bool; KEYPAD_SIZE],             // Keypad
+    mapper: Option<Box<dyn Mapper>>,       // Memory extension hook
+    handlers: Vec<Box<dyn OpcodeHandler>>, // Custom opcode handlers
+    hooks: Vec<Box<dyn ExecHook>>,         // Called around every instruction
+    persistent: Option<Range<u16>>,        // Game's save data region
+    persistent_dirty: bool,                // Save data written since export
+    sprite_log: Option<SpriteLog>,         // Sprites drawn, when recording
+    events: Option<Vec<Event>>,            // Pending events, when recording
+    recording: Option<Recording>,          // Key changes, when recording
+    blocked: bool,                         // Last op was a halt or key wait
+    state: State,                          // Running, waiting, halted or faulted
+    instructions: u64,                     // Instructions executed since reset
+    frames: u64,                           // tick_timers calls since reset
+    input_queue: InputQueue,               // Key events scheduled for later
+    screen_changed: bool,                  // Screen drawn to this frame
+    frame_changed: bool,                   // Screen drawn to last frame
+    stats: FrameStats,                     // Counts for the frame in progress
+    last_stats: FrameStats,                // Counts for the last whole frame
+    profiler: Option<Profile>,             // Instruction counts, when profiling
+    history: history::History,             // Last few instructions executed
+    #[cfg(feature = "heatmap")]
+    heat: Vec<u32>, // Times each address was executed
+    power_on_seed: Option<u32>,            // Seed for power-on garbage
+    rng_seed: Option<u64>,                 // Seed for RND, if deterministic
+    variant: Variant,                      // Extensions enabled on top of CHIP-8
+    planes: u8,                            // XO-CHIP planes selected for drawing
+    quirks: Quirks,                        // Interpreter differences to emulate
+    pc_overflow: PcOverflow,               // What to do when PC leaves RAM
+    start_addr: u16,                       // Where ROMs load and run from
+    breaks: debug::Breaks,                 // Breakpoints and watches
+    #[cfg(feature = "unchecked")]
+    unchecked: bool, // Inside run_unchecked, bounds checks are skipped
+    rng: Rng,                              // Built-in PRNG for RND
+    random_source: Option<Box<dyn RandomSource>>, // Custom source for RND
+    flags: [u8; FLAG_COUNT],               // SCHIP RPL user flags
+    flag_storage: Option<Box<dyn FlagStorage>>, // Where the flags are kept
+    audio_pattern: [u8; AUDIO_PATTERN_SIZE], // XO-CHIP sound samples
+    pitch: u8,                             // XO-CHIP pattern playback rate
+    audio_pos: f64,                        // Position in the pattern, in samples
+    #[cfg(feature = "megachip")]
+    mega: megachip::MegaChip, // Mega-CHIP mode, palette and screen
+}
+
+impl Default for Chip8 {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Chip8 {
     /// Chip 8 Initialization
     pub fn new() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    /// Chip 8 set up like another interpreter, e.g. `Variant::Chip48`
+    pub fn with_variant(variant: Variant) -> Self {
+        Self::with_config(variant.config())
+    }
+
+    /// Chip 8 whose `RND` gives the same numbers every run, for tests,
+    /// replays and netplay
+    pub fn with_seed(seed: u64) -> Self {
+        Self::with_config(Config {
+            rng_seed: Some(seed),
+            ..Config::default()
+        })
+    }
+
+    /// Chip 8 with non-default memory size or stack depth
+    ///
+    /// Panics if the config is unusable, use `try_with_config` for configs
+    /// that come from users.
+    pub fn with_config(config: Config) -> Self {
+        Self::try_with_config(config).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Chip 8 with non-default memory size or stack depth, or why the
+    /// config can't be used (see `Config` for the limits)
+    pub fn try_with_config(config: Config) -> Result<Self, ConfigError> {
+        config.validate()?;
         let mut new_chip8 = Self {
-            pc: START_ADDR,
-            ram: [0; MEM_SIZE],
-            screen: [false; SCREEN_HEIGHT * SCREEN_WIDTH],
+            pc: config.start_addr,
+            ram: vec![0; config.mem_size],
+            screen: Screen::new(SCREEN_WIDTH, SCREEN_HEIGHT),
+            plane2: Screen::new(SCREEN_WIDTH, SCREEN_HEIGHT),
+            hires: false,
+            two_page: false,
             v_reg: [0; V_REG_SIZE],
             i_reg: 0,
             sp: 0,
-            stack: [0; STACK_SIZE],
+            stack: vec![0; config.stack_size],
+            call_targets: vec![None; config.stack_size],
+            subroutines: BTreeSet::new(),
             keys: [false; KEYPAD_SIZE],
             dt: 0,
             st: 0,
+            mapper: None,
+            handlers: Vec::new(),
+            hooks: Vec::new(),
+            persistent: None,
+            persistent_dirty: false,
+            sprite_log: None,
+            events: None,
+            recording: None,
+            blocked: false,
+            state: State::Running,
+            instructions: 0,
+            frames: 0,
+            input_queue: InputQueue::default(),
+            screen_changed: false,
+            frame_changed: false,
+            stats: FrameStats::default(),
+            last_stats: FrameStats::default(),
+            profiler: None,
+            history: history::History::new(config.history_size),
+            #[cfg(feature = "heatmap")]
+            heat: vec![0; config.mem_size],
+            power_on_seed: config.power_on_seed,
+            rng_seed: config.rng_seed,
+            variant: config.variant,
+            planes: 1,
+            quirks: config.quirks,
+            pc_overflow: config.pc_overflow,
+            start_addr: config.start_addr,
+            breaks: debug::Breaks::default(),
+            #[cfg(feature = "unchecked")]
+            unchecked: false,
+            rng: Rng::from_seed(config.rng_seed),
+            random_source: None,
+            flags: [0; FLAG_COUNT],
+            flag_storage: None,
+            audio_pattern: audio::DEFAULT_PATTERN,
+            pitch: audio::DEFAULT_PITCH,
+            audio_pos: 0.0,
+            #[cfg(feature = "megachip")]
+            mega: megachip::MegaChip::default(),
         };
 
         // important gor fx29 instruction
-        new_chip8.ram[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        new_chip8.load_fonts();
+        new_chip8.power_on();
+
+        Ok(new_chip8)
+    }
+
+    /// Program counter
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    /// Indexing register
+    pub fn i_reg(&self) -> u16 {
+        self.i_reg
+    }
+
+    /// V0 - VF registers
+    pub fn v_reg(&self) -> &[u8] {
+        &self.v_reg
+    }
+
+    /// Stack pointer, the number of return addresses on the stack
+    pub fn sp(&self) -> u16 {
+        self.sp
+    }
+
+    /// Return addresses currently on the stack, innermost call last
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.sp as usize]
+    }
+
+    /// Keypad state, `true` for keys held down
+    pub fn keys(&self) -> &[bool] {
+        &self.keys
+    }
 
-        new_chip8
+    /// Delay timer
+    pub fn dt(&self) -> u8 {
+        self.dt
+    }
+
+    /// Sound timer, the beeper is on while this is non-zero
+    pub fn st(&self) -> u8 {
+        self.st
+    }
+
+    /// What the machine is doing after the last `tick`
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Instruction set the machine was built with
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Where ROMs are loaded and `reset` puts the PC
+    pub fn start_addr(&self) -> u16 {
+        self.start_addr
+    }
+
+    /// Whole of RAM, including the font set and loaded program
+    pub fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    /// Route all memory accesses in `mapper.range()` through `mapper`
+    /// instead of RAM
+    ///
+    /// Only one mapper can be installed; setting another replaces it. The
+    /// mapper survives `reset()` as it's part of the machine's configuration.
+    pub fn set_mapper(&mut self, mapper: Box<dyn Mapper>) {
+        self.mapper = Some(mapper);
+        #[cfg(feature = "unchecked")]
+        {
+            self.unchecked = false;
+        }
+    }
+
+    pub fn clear_mapper(&mut self) {
+        self.mapper = None;
+    }
+
+    /// Address of the instruction being executed (PC has already moved past it)
+    fn op_pc(&self) -> u16 {
+        self.pc.wrapping_sub(2)
+    }
+
+    /// Read a data byte, where watchpoints can see it
+    fn read_mem(&mut self, addr: u16) -> Result<u8, Chip8Error> {
+        let val = self.read_byte(addr)?;
+        self.check_watchpoints(addr, Access::Read);
+        Ok(val)
+    }
+
+    /// Read a byte the way the interpreter sees it, including any mapper
+    ///
+    /// Opcodes and their operands are read with this, so fetching doesn't
+    /// set off read watchpoints.
+    fn read_byte(&mut self, addr: u16) -> Result<u8, Chip8Error> {
+        #[cfg(feature = "unchecked")]
+        if self.unchecked {
+            // SAFETY: only set while RAM covers every u16 address and there's no
+            // mapper, set_mapper and restore clear it
+            return Ok(unsafe { *self.ram.get_unchecked(addr as usize) });
+        }
+        match &mut self.mapper {
+            Some(mapper) if mapper.range().contains(&addr) => Ok(mapper.read(addr)),
+            _ => match self.ram.get(addr as usize) {
+                Some(val) => Ok(*val),
+                None => Err(Chip8Error::AddressOutOfRange {
+                    addr,
+                    pc: self.op_pc(),
+                }),
+            },
+        }
+    }
+
+    fn write_mem(&mut self, addr: u16, val: u8) -> Result<(), Chip8Error> {
+        #[cfg(feature = "unchecked")]
+        if self.unchecked {
+            // SAFETY: as in read_mem
+            unsafe { *self.ram.get_unchecked_mut(addr as usize) = val };
+            self.mark_persistent(addr);
+            self.check_watchpoints(addr, Access::Write);
+            return Ok(());
+        }
+        let pc = self.op_pc();
+        match &mut self.mapper {
+            Some(mapper) if mapper.range().contains(&addr) => mapper.write(addr, val),
+            _ => match self.ram.get_mut(addr as usize) {
+                Some(byte) => *byte = val,
+                None => return Err(Chip8Error::AddressOutOfRange { addr, pc }),
+            },
+        }
+        self.mark_persistent(addr);
+        self.check_watchpoints(addr, Access::Write);
+        Ok(())
     }
 
     /// Push u16 to stack
-    pub fn push(&mut self, val: u16) {
+    pub fn push(&mut self, val: u16) -> Result<(), Chip8Error> {
+        if self.sp as usize >= self.stack.len() {
+            return Err(Chip8Error::StackOverflow { pc: self.op_pc() });
+        }
         self.stack[self.sp as usize] = val;
         self.sp += 1;
+        Ok(())
     }
 
     /// Pop u16 from stack
-    pub fn pop(&mut self) -> u16 {
+    pub fn pop(&mut self) -> Result<u16, Chip8Error> {
+        if self.sp == 0 {
+            return Err(Chip8Error::StackUnderflow { pc: self.op_pc() });
+        }
         self.sp -= 1;
-        self.stack[self.sp as usize]
-        // possible underflow - panics
+        Ok(self.stack[self.sp as usize])
     }
 
     /// Reset chip8
     pub fn reset(&mut self) {
-        self.pc = START_ADDR;
-        self.ram = [0; MEM_SIZE];
-        self.ram[..FONTSET_SIZE].copy_from_slice(&FONTSET);
-        self.screen = [false; SCREEN_HEIGHT * SCREEN_WIDTH];
+        self.pc = self.start_addr;
+        self.ram.fill(0);
+        self.load_fonts();
+        self.set_hires(false);
         self.v_reg = [0; V_REG_SIZE];
         self.i_reg = 0;
         self.sp = 0;
-        self.stack = [0; STACK_SIZE];
+        self.stack.fill(0);
+        self.forget_calls();
+        self.subroutines.clear();
         self.keys = [false; KEYPAD_SIZE];
         self.dt = 0;
         self.st = 0;
+        self.persistent = None;
+        self.persistent_dirty = false;
+        self.blocked = false;
+        self.state = State::Running;
+        self.instructions = 0;
+        self.frames = 0;
+        self.history.clear();
+        #[cfg(feature = "heatmap")]
+        self.clear_heat_map();
+        self.input_queue = InputQueue::default();
+        self.planes = 1;
+        self.audio_pattern = audio::DEFAULT_PATTERN;
+        self.pitch = audio::DEFAULT_PITCH;
+        self.audio_pos = 0.0;
+        #[cfg(feature = "megachip")]
+        {
+            self.mega = megachip::MegaChip::default();
+        }
+        self.rng = Rng::from_seed(self.rng_seed);
+        self.screen_changed = true;
+        self.frame_changed = true;
+        self.stats = FrameStats::default();
+        self.last_stats = FrameStats::default();
+        if let Some(events) = &mut self.events {
+            events.clear();
+        }
+        if let Some(recording) = &mut self.recording {
+            recording.events.clear();
+        }
+        self.power_on();
+    }
+
+    /// Run one instruction, returns the machine's state afterwards
+    ///
+    /// On an error the state is `State::Errored` until the next `tick`.
+    /// Landing on a breakpoint or setting off a watch gives `State::Break`.
+    pub fn tick(&mut self) -> Result<State, Chip8Error> {
+        self.tick_with(|_, _| ())
     }
 
-    pub fn tick(&mut self) {
+    /// `tick`, calling `fetched` with each opcode after it's fetched and
+    /// before it runs
+    pub(crate) fn tick_with(
+        &mut self,
+        fetched: impl FnOnce(&Self, u16),
+    ) -> Result<State, Chip8Error> {
+        if self.state == State::Halted {
+            return Ok(State::Halted);
+        }
+        let result = self.fetch_and_execute(fetched);
+        let hit = self.hit_break();
+        match result {
+            Ok(()) => {
+                if self.state == State::Running && hit {
+                    self.state = State::Break;
+                }
+                Ok(self.state)
+            }
+            Err(e) => {
+                #[cfg(feature = "trace")]
+                tracing::warn!(error = %e, "fault");
+                self.state = State::Errored;
+                Err(e)
+            }
+        }
+    }
+
+    fn fetch_and_execute(&mut self, fetched: impl FnOnce(&Self, u16)) -> Result<(), Chip8Error> {
+        self.state = State::Running;
         // 1. Get value specified at memory address stored in Program Counter
-        let op = self.fetch();
+        let op = match self.fetch() {
+            Ok(op) => op,
+            Err(Chip8Error::AddressOutOfRange { pc, .. })
+                if self.pc_overflow == PcOverflow::Trap && pc == self.pc =>
+            {
+                // Park on the bad address, the PC wasn't moved
+                if !std::mem::replace(&mut self.blocked, true) {
+                    self.emit(Event::Halt { pc });
+                }
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        fetched(self, op);
         // 2. Decode this instruction
         // 3. Execute
-        self.execute(op);
+        self.execute_op(op)
         // 4. Move program counter to next instruction set
     }
 
-    fn fetch(&mut self) -> u16 {
+    /// Pipeline stage 1: read the opcode at PC and advance PC past it
+    ///
+    /// See `decode` and `execute` for the other stages; `tick` runs all three.
+    pub fn fetch(&mut self) -> Result<u16, Chip8Error> {
         // 4 bytes representing the instruction
         // most significant and least significant represnests the op code
-        let higher_byte = self.ram[self.pc as usize] as u16;
-        let lower_byte = self.ram[(self.pc + 1) as usize] as u16;
+        self.pc = self.wrap_pc(self.pc);
+        let pc = self.pc;
+        let out_of_range = |addr| Chip8Error::AddressOutOfRange { addr, pc };
+        let higher_byte = self.read_byte(pc).map_err(|_| out_of_range(pc))? as u16;
+        let lower_addr = self.wrap_pc(pc.wrapping_add(1));
+        let lower_byte = self
+            .read_byte(lower_addr)
+            .map_err(|_| out_of_range(lower_addr))? as u16;
         let op = (higher_byte << 8) | lower_byte;
-        self.pc += 2;
-        op
+        self.pc = pc.wrapping_add(2);
+        Ok(op)
+    }
+
+    /// Bring an address past the end of RAM back to the start, if the
+    /// `PcOverflow::Wrap` policy is on and no mapper claims the address
+    fn wrap_pc(&self, addr: u16) -> u16 {
+        let mapped = matches!(&self.mapper, Some(mapper) if mapper.range().contains(&addr));
+        if self.pc_overflow == PcOverflow::Wrap && !mapped && addr as usize >= self.ram.len() {
+            (addr as usize % self.ram.len()) as u16
+        } else {
+            addr
+        }
     }
 
     pub fn tick_timers(&mut self) {
+        self.end_sprite_frame();
+        self.stats.sound = self.st > 0;
+
         if self.dt > 0 {
             self.dt -= 1;
         }
@@ -119,201 +636,429 @@ impl Chip8 {
         if self.st > 0 {
             if self.st == 1 {
                 // BEEP
+                self.emit(Event::Sound(false));
             }
             self.st -= 1;
         }
+
+        self.frames += 1;
+        self.last_stats = std::mem::take(&mut self.stats);
+        #[cfg(feature = "trace")]
+        tracing::trace!(
+            frame = self.frames,
+            dt = self.dt,
+            st = self.st,
+            "timer tick"
+        );
+        self.frame_changed = std::mem::take(&mut self.screen_changed);
+        self.apply_scheduled_input();
+    }
+
+    /// Row-major pixels, `screen_width() * screen_height()` of them
+    ///
+    /// Unpacked from `get_display_packed` on every call. This is the first
+    /// plane, all there is outside XO-CHIP; see `get_display_colors` for both.
+    pub fn get_display(&self) -> Vec<bool> {
+        self.screen.to_bools()
+    }
+
+    /// Row-major colour indices 0-3, bit 0 from the first plane and bit 1
+    /// from XO-CHIP's second, for frontends to look up in a palette
+    pub fn get_display_colors(&self) -> Vec<u8> {
+        let second = self.plane2.to_bools();
+        self.screen
+            .to_bools()
+            .into_iter()
+            .zip(second)
+            .map(|(first, second)| first as u8 | (second as u8) << 1)
+            .collect()
+    }
+
+    /// The screen as packed rows, `screen_width() / 8` bytes each with the
+    /// leftmost pixel in the high bit
+    pub fn get_display_packed(&self) -> &[u8] {
+        self.screen.bytes()
+    }
+
+    /// Current display width, 64 or 128 in SCHIP hi-res mode
+    pub fn screen_width(&self) -> usize {
+        Self::resolution(self.hires, self.two_page).0
+    }
+
+    /// Current display height, 32, or 64 in SCHIP hi-res or HIRES CHIP-8
+    /// two page mode
+    pub fn screen_height(&self) -> usize {
+        Self::resolution(self.hires, self.two_page).1
+    }
+
+    fn resolution(hires: bool, two_page: bool) -> (usize, usize) {
+        if hires {
+            (HIRES_WIDTH, HIRES_HEIGHT)
+        } else if two_page {
+            (SCREEN_WIDTH, TWO_PAGE_HEIGHT)
+        } else {
+            (SCREEN_WIDTH, SCREEN_HEIGHT)
+        }
+    }
+
+    /// Whether the SCHIP 128x64 mode is on
+    pub fn hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Copy the small and large fonts to the bottom of RAM
+    fn load_fonts(&mut self) {
+        self.ram[..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        self.ram[FONTSET_SIZE..FONT_END].copy_from_slice(&BIG_FONTSET);
     }
 
-    pub fn get_display(&self) -> &[bool] {
-        &self.screen
+    /// Switch resolution, which also clears the screen
+    fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.two_page = false;
+        self.resize_screen();
+    }
+
+    /// Blank screens sized for the current mode
+    fn resize_screen(&mut self) {
+        let (width, height) = (self.screen_width(), self.screen_height());
+        self.screen = Screen::new(width, height);
+        self.plane2 = Screen::new(width, height);
     }
 
     pub fn keypress(&mut self, idx: usize, pressed: bool) {
+        if self.keys[idx] != pressed {
+            self.log_key(idx, pressed);
+        }
         self.keys[idx] = pressed
     }
 
-    pub fn load(&mut self, data: &[u8]) {
-        let start = START_ADDR as usize;
-        let end = data.len() + START_ADDR as usize;
+    /// Copy a ROM to the start address (0x200 unless configured), checking
+    /// it isn't empty and fits in RAM
+    pub fn load(&mut self, data: &[u8]) -> Result<(), LoadError> {
+        if data.is_empty() {
+            return Err(LoadError::Empty);
+        }
+        let start = self.start_addr as usize;
+        let end = data.len() + start;
+        if end > self.ram.len() {
+            return Err(LoadError::TooLarge {
+                size: data.len(),
+                max: self.ram.len() - start,
+            });
+        }
         self.ram[start..end].copy_from_slice(data);
+        Ok(())
+    }
+
+    /// Load several blobs at once, e.g. code at 0x200 plus a level bank at 0xA00
+    ///
+    /// Everything is checked before anything is written, so on error memory is
+    /// left untouched. Segments may go anywhere in RAM but must not overlap.
+    pub fn load_segments(&mut self, segments: &[Segment]) -> Result<(), LoadError> {
+        let mut sorted: Vec<&Segment> = segments.iter().filter(|s| !s.data.is_empty()).collect();
+        sorted.sort_by_key(|s| s.addr);
+
+        for (i, seg) in sorted.iter().enumerate() {
+            if seg.addr as usize + seg.data.len() > self.ram.len() {
+                return Err(LoadError::SegmentOutOfRange {
+                    addr: seg.addr,
+                    size: seg.data.len(),
+                });
+            }
+            if let Some(next) = sorted.get(i + 1) {
+                if seg.addr as usize + seg.data.len() > next.addr as usize {
+                    return Err(LoadError::SegmentOverlap {
+                        first: seg.addr,
+                        second: next.addr,
+                    });
+                }
+            }
+        }
+
+        for seg in sorted {
+            let start = seg.addr as usize;
+            self.ram[start..start + seg.data.len()].copy_from_slice(seg.data);
+        }
+        Ok(())
     }
 
-    fn execute(&mut self, op: u16) {
-        let d1 = (op & 0xF000) >> 12;
-        let d2 = (op & 0x0F00) >> 8;
-        let d3 = (op & 0x00F0) >> 4;
-        let d4 = op & 0x000F;
+    /// Swap in a new program without a full reset
+    ///
+    /// Program memory is cleared and reloaded, apart from a declared
+    /// persistent region which keeps the game's save data. Anything else put
+    /// past the start address, such as `load_segments` data, is cleared too.
+    /// With `keep_registers` the PC, registers, stack, timers and screen carry
+    /// on as they were (for live coding); otherwise they're reset, along with
+    /// the XO-CHIP planes and audio and the frame and instruction counts, as
+    /// if the ROM was freshly loaded. Either way the machine is running again
+    /// afterwards, even if the old program had exited, and any debugging
+    /// state attached to it is kept.
+    pub fn reload_rom(&mut self, data: &[u8], keep_registers: bool) -> Result<(), LoadError> {
+        // Let load() report bad ROMs before anything is cleared
+        if data.is_empty() || self.start_addr as usize + data.len() > self.ram.len() {
+            return self.load(data);
+        }
+        let saved = self.persistent.clone().map(|range| {
+            let bytes = self.ram[range.start as usize..range.end as usize].to_vec();
+            (range, bytes)
+        });
+        self.ram[self.start_addr as usize..].fill(0);
+        self.load(data)?;
+        if let Some((range, bytes)) = saved {
+            self.ram[range.start as usize..range.end as usize].copy_from_slice(&bytes);
+        }
+        self.blocked = false;
+        self.state = State::Running;
+
+        if !keep_registers {
+            self.pc = self.start_addr;
+            self.set_hires(false);
+            self.v_reg = [0; V_REG_SIZE];
+            self.i_reg = 0;
+            self.sp = 0;
+            self.stack.fill(0);
+            self.forget_calls();
+            self.dt = 0;
+            self.st = 0;
+            self.planes = 1;
+            self.audio_pattern = audio::DEFAULT_PATTERN;
+            self.pitch = audio::DEFAULT_PITCH;
+            self.audio_pos = 0.0;
+            #[cfg(feature = "megachip")]
+            {
+                self.mega = megachip::MegaChip::default();
+            }
+            self.instructions = 0;
+            self.frames = 0;
+        }
+        Ok(())
+    }
+
+    fn execute_op(&mut self, op: u16) -> Result<(), Chip8Error> {
+        self.apply_scheduled_input();
+        self.instructions += 1;
+        self.stats.instructions += 1;
+        self.profile_op(op);
+        self.record_history(op);
+        #[cfg(feature = "heatmap")]
+        self.record_heat();
+        #[cfg(feature = "trace")]
+        let _span = tracing::trace_span!(
+            "instruction",
+            pc = format_args!("{:03X}", self.op_pc()),
+            op = format_args!("{:04X}", op)
+        )
+        .entered();
+        self.run_exec_hooks(|hook, c8| hook.before(c8, op));
+        self.dispatch_op(op)?;
+        self.run_exec_hooks(|hook, c8| hook.after(c8, op));
+        Ok(())
+    }
 
-        match (d1, d2, d3, d4) {
-            (0, 0, 0, 0) => return,                                                // NOP
-            (0, 0, 0xE, 0) => self.screen = [false; SCREEN_HEIGHT * SCREEN_WIDTH], // clear screen
-            (0, 0, 0xE, 0xE) => {
+    /// Run `op`, trying the variant's own opcodes before the decoder
+    fn dispatch_op(&mut self, op: u16) -> Result<(), Chip8Error> {
+        #[cfg(feature = "megachip")]
+        if self.variant == Variant::MegaChip && self.execute_megachip(op)? {
+            return Ok(());
+        }
+        if self.variant == Variant::HiresChip8 && self.execute_two_page(op) {
+            return Ok(());
+        }
+        self.execute_instruction(decode(op))
+    }
+
+    /// Run one decoded instruction, without the counting and hooks around
+    /// it (`execute` goes through those)
+    ///
+    /// Extension opcodes the variant doesn't have go to the custom handlers,
+    /// then fail with `UnknownOpcode`.
+    fn execute_instruction(&mut self, instr: Instruction) -> Result<(), Chip8Error> {
+        use Instruction::*;
+        // Halts and key waits re-run the same op, only report the first time
+        let was_blocked = std::mem::replace(&mut self.blocked, false);
+
+        match instr {
+            Nop => (),
+            Clear => {
+                // clear screen, just the selected planes on XO-CHIP
+                for plane in 0..PLANE_COUNT {
+                    if self.planes & (1 << plane) != 0 {
+                        self.plane_mut(plane).clear();
+                    }
+                }
+                self.screen_changed = true;
+                self.emit(Event::Draw);
+            }
+            Return => {
                 // RET
-                let ret_addr = self.pop();
+                let ret_addr = self.pop()?;
                 self.pc = ret_addr;
             }
-            (1, _, _, _) => {
+            LowRes | HighRes if self.variant.has_schip() => {
+                // SCHIP 00FE LOW / 00FF HIGH
+                // Switch to 64x32 or 128x64 mode, clearing the screen
+                self.set_hires(instr == HighRes);
+                self.screen_changed = true;
+                self.emit(Event::Draw);
+            }
+            Jump { nnn } => {
                 //JMP NNN
-                let nnn = op & 0xFFF;
+                if nnn == self.op_pc() {
+                    // Jumping to itself is how most games end
+                    if !was_blocked {
+                        self.emit(Event::Halt { pc: nnn });
+                    }
+                    self.blocked = true;
+                }
                 self.pc = nnn;
             }
-            (2, _, _, _) => {
+            Call { nnn } => {
                 // CALL addr
-                let addr = op & 0xFFF;
-                self.push(self.pc);
-                self.pc = addr;
+                self.push(self.pc)?;
+                self.record_call(nnn);
+                self.pc = nnn;
             }
-            (3, _, _, _) => {
+            SkipEqImm { x, nn } => {
                 // SKIP next if VX == NN
                 // 3XNN
-
-                let x = d2 as usize;
-                let nn = (op & 0xFF) as u8;
                 if self.v_reg[x] == nn {
-                    self.pc += 2
+                    self.skip_next()?;
                 }
             }
-            (4, _, _, _) => {
+            SkipNeImm { x, nn } => {
                 // Skip next if Vx != kk
                 // 4XKK
-                let x = d2 as usize;
-                let nn = (op & 0xFF) as u8;
                 if self.v_reg[x] != nn {
-                    self.pc += 2;
+                    self.skip_next()?;
                 }
             }
-            (5, _, _, 0) => {
+            SkipEqReg { x, y } => {
                 // skip next instruction if Vx = Vy
                 // 5xy0
-                let x = d2 as usize;
-                let y = d3 as usize;
                 if self.v_reg[x] == self.v_reg[y] {
-                    self.pc += 2;
+                    self.skip_next()?;
                 }
             }
-            (6, _, _, _) => {
+            LoadImm { x, nn } => {
                 // set Vx = kk
                 // 6xkk
-                let x = d2 as usize;
-                let kk = (op & 0xFF) as u8;
-                self.v_reg[x] = kk;
+                self.v_reg[x] = nn;
             }
-            (7, _, _, _) => {
+            AddImm { x, nn } => {
                 // set Vx = Vx + kk
                 // 7xkk
-                let x = d2 as usize;
-                let nn = (op & 0xFF) as u8;
                 self.v_reg[x] = self.v_reg[x].wrapping_add(nn);
             }
-            (8, _, _, 0) => {
+            Move { x, y } => {
                 // set Vx = Vy
                 // 8xy0
-                let x = d2 as usize;
-                let y = d3 as usize;
                 self.v_reg[x] = self.v_reg[y];
             }
-            (8, _, _, 1) => {
+            Or { x, y } => {
                 // set Vx = Vx or Vy
                 // 8xy1
-                self.v_reg[d2 as usize] |= self.v_reg[d3 as usize];
+                self.v_reg[x] |= self.v_reg[y];
+                if self.quirks.vf_reset {
+                    self.v_reg[0xF] = 0;
+                }
             }
-            (8, _, _, 2) => {
+            And { x, y } => {
                 // set Vx = Vx and Vy
                 // 8xy2
-                self.v_reg[d2 as usize] &= self.v_reg[d3 as usize];
+                self.v_reg[x] &= self.v_reg[y];
+                if self.quirks.vf_reset {
+                    self.v_reg[0xF] = 0;
+                }
             }
-            (8, _, _, 3) => {
+            Xor { x, y } => {
                 // set Vx = Vx xor Vy
                 // 8xy3
-                self.v_reg[d2 as usize] ^= self.v_reg[d3 as usize];
+                self.v_reg[x] ^= self.v_reg[y];
+                if self.quirks.vf_reset {
+                    self.v_reg[0xF] = 0;
+                }
             }
-            (8, _, _, 4) => {
+            Add { x, y } => {
                 // sets Vx = Vx + Vy, set VF = carry
                 // Values of Vx and Vy are added together.  If reult is greater than 8 bits, VF is set to 1, otherwise 0.  Lowest 8 bits are saved in Vx
                 // 8xy4
-                let x = d2 as usize;
-                let y = d3 as usize;
                 let (new_vx, carry) = self.v_reg[x].overflowing_add(self.v_reg[y]);
                 let new_vf = if carry { 1 } else { 0 };
                 self.v_reg[x] = new_vx;
                 self.v_reg[0xF] = new_vf;
             }
-            (8, _, _, 5) => {
+            Sub { x, y } => {
                 // Set Vx = Vx - Vy, set VF = NOT borrow
                 // if Vx > Vy, then VF is set to 1, otherwise 0.  Then Vy is subtracted from Vx, result is stored in Vx
                 // 8xy5
-                let x = d2 as usize;
-                let y = d3 as usize;
                 let (new_vx, borrow) = self.v_reg[x].overflowing_sub(self.v_reg[y]);
                 let new_vf = if borrow { 0 } else { 1 };
                 self.v_reg[x] = new_vx;
                 self.v_reg[0xF] = new_vf;
             }
-            (8, _, _, 6) => {
+            ShiftRight { x, y } => {
                 // Set Vx = Vx SHR1
                 // if the least-signigicant bit of Vx is 1, then VF is set to 1, otherwise 0.  THen Vx is divided by 2
                 // 8xy6
-                let x = d2 as usize;
+                if self.quirks.shift_vy {
+                    self.v_reg[x] = self.v_reg[y];
+                }
                 let lsb = self.v_reg[x] & 1;
                 self.v_reg[x] >>= 1;
                 self.v_reg[0xF] = lsb;
             }
-            (8, _, _, 7) => {
+            SubReverse { x, y } => {
                 // Set Vx = Vy - Vx, set Vx = NOT borrow
                 // if Vy > Vx, then VF is set to 1 otherwise 0.  Results stored in Vx
                 // 8xy7
-
-                let x = d2 as usize;
-                let y = d3 as usize;
                 let (new_vx, borrow) = self.v_reg[y].overflowing_sub(self.v_reg[x]);
                 let new_vf = if borrow { 0 } else { 1 };
                 self.v_reg[x] = new_vx;
                 self.v_reg[0xF] = new_vf;
             }
-            (8, _, _, 0xE) => {
+            ShiftLeft { x, y } => {
                 // Set Vx = Vx SHL 1.
                 // If the most-significant bit of Vx is 1, then VF is set to 1, otherwise to 0. Then Vx is multiplied by 2.
                 // 8xyE
-                let x = d2 as usize;
+                if self.quirks.shift_vy {
+                    self.v_reg[x] = self.v_reg[y];
+                }
                 let msb = (self.v_reg[x] >> 7) & 1;
                 self.v_reg[x] <<= 1;
                 self.v_reg[0xF] = msb;
             }
-            (9, _, _, 0) => {
+            SkipNeReg { x, y } => {
                 // Skip next instruction if Vx != Vy.
                 // The values of Vx and Vy are compared, and if they are not equal, the program counter is increased by 2
                 // 9xy0
-                let x = d2 as usize;
-                let y = d3 as usize;
                 if self.v_reg[x] != self.v_reg[y] {
-                    self.pc += 2;
+                    self.skip_next()?;
                 }
             }
-            (0xA, _, _, _) => {
+            LoadI { nnn } => {
                 // Set I = nnn.
                 // The value of register I is set to nnn.
                 // Annn
-                let nnn = op & 0xFFF;
                 self.i_reg = nnn;
             }
-            (0xB, _, _, _) => {
+            JumpOffset { x, nnn } => {
                 // Jump to location nnn + V0.
                 // The program counter is set to nnn plus the value of V0.
                 // Bnnn
-                let nnn = op & 0xFFF;
-                self.pc = (self.v_reg[0] as u16) + nnn;
+                // With the jump quirk this is BXNN, using Vx instead of V0
+                let reg = if self.quirks.jump_vx { x } else { 0 };
+                self.pc = (self.v_reg[reg] as u16) + nnn;
             }
-            (0xC, _, _, _) => {
+            Random { x, nn } => {
                 // Set Vx = random byte AND kk.
                 // The interpreter generates a random number from 0 to 255, which is then ANDed with the value kk.
                 // The results are stored in Vx. See instruction 8xy2 for more information on AND.
                 // Cxkk
-                let x = d2 as usize;
-                let nn = (op & 0xFF) as u8;
-                let rng: u8 = random();
+                let rng = self.random_byte();
                 self.v_reg[x] = rng & nn;
             }
-            (0xD, _, _, _) => {
+            Draw { x, y, n } => {
                 // Display n-byte sprite starting at memory location I at (Vx, Vy), set VF = collision.
                 // The interpreter reads n bytes from memory, starting at the address stored in I.
                 // These bytes are then displayed as sprites on screen at coordinates (Vx, Vy).
@@ -322,32 +1067,60 @@ impl Chip8 {
                 // See instruction 8xy3 for more information on XOR, and section 2.4, Display, for more information on the Chip-8 screen and sprites.
                 // Dxyn
 
-                // Get the (x, y) coords for our sprite
-                let x_coord = self.v_reg[d2 as usize] as u16;
-                let y_coord = self.v_reg[d3 as usize] as u16;
-                // The last digit determines how many rows high our sprite is
-                let num_rows = d4;
+                let (width, height) = (self.screen_width(), self.screen_height());
+                // Get the (x, y) coords for our sprite, the start always wraps
+                let x_coord = (self.v_reg[x] as usize % width) as u16;
+                let y_coord = (self.v_reg[y] as usize % height) as u16;
+                // The last digit determines how many rows high our sprite is,
+                // SCHIP's Dxy0 draws a 16x16 sprite with two bytes per row.
+                let (rows, row_bytes) = if n == 0 && self.variant.has_schip() {
+                    (16, 2)
+                } else {
+                    (n as u16, 1)
+                };
+                // With no XO-CHIP plane selected nothing is drawn
+                let num_rows = if self.planes != 0 { rows } else { 0 };
+                if row_bytes == 1 {
+                    self.log_sprite(self.i_reg, num_rows as u8);
+                }
                 // Keep track if any pixels were flipped
                 let mut flipped = false;
-                // Iterate over each row of our sprite
-                for y_line in 0..num_rows {
-                    // Determine which memory address our row's data is stored
-                    let addr = self.i_reg + y_line as u16;
-                    let pixels = self.ram[addr as usize];
-                    // Iterate over each column in our row
-                    for x_line in 0..8 {
-                        // Use a mask to fetch current pixel's bit. Only flip if a 1
-                        if (pixels & (0b1000_0000 >> x_line)) != 0 {
-                            // Sprites should wrap around screen, so apply modulo
-                            let x = (x_coord + x_line) as usize % SCREEN_WIDTH;
-                            let y = (y_coord + y_line) as usize % SCREEN_HEIGHT;
-                            // Get our pixel's index for our 1D screen array
-                            let idx = x + SCREEN_WIDTH * y;
-                            // Check if we're about to flip the pixel and set
-                            flipped |= self.screen[idx];
-                            self.screen[idx] ^= true;
+                let wrap = !self.quirks.clip_sprites;
+                // XO-CHIP draws into each selected plane in turn, the sprite
+                // for the second plane following the first one's in memory
+                let mut sprite_addr = self.i_reg;
+                for plane in 0..PLANE_COUNT {
+                    if self.planes & (1 << plane) == 0 {
+                        continue;
+                    }
+                    // Iterate over each row of our sprite
+                    for y_line in 0..rows {
+                        let y = (y_coord + y_line) as usize;
+                        if !wrap && y >= height {
+                            continue;
+                        }
+                        for byte in 0..row_bytes {
+                            // Determine which memory address this part of the row is stored
+                            let addr = sprite_addr.wrapping_add(y_line * row_bytes + byte);
+                            let pixels = self.read_mem(addr)?;
+                            let x = x_coord as usize + 8 * byte as usize;
+                            if !wrap && x >= width {
+                                continue;
+                            }
+                            // Bits past the right edge are lost when clipping
+                            let visible = if wrap {
+                                pixels
+                            } else {
+                                pixels & !0xFFu8.checked_shr((width - x) as u32).unwrap_or(0)
+                            };
+                            self.stats.pixels_flipped += visible.count_ones();
+                            // XOR the whole byte in, sprites wrap around the screen unless clipped
+                            flipped |=
+                                self.plane_mut(plane)
+                                    .xor_byte(x % width, y % height, pixels, wrap);
                         }
                     }
+                    sprite_addr = sprite_addr.wrapping_add(rows * row_bytes);
                 }
                 // Populate VF register
                 if flipped {
@@ -355,38 +1128,39 @@ impl Chip8 {
                 } else {
                     self.v_reg[0xF] = 0;
                 }
+                self.stats.draws += 1;
+                self.stats.collisions += flipped as u32;
+                self.screen_changed = true;
+                self.emit(Event::Draw);
             }
-            (0xE, _, 9, 0xE) => {
+            SkipKey { x } => {
                 // Ex9E
                 // Skip if keys pressed
-                let x = d2 as usize;
-                let vx = self.v_reg[x];
+                // Only the low nibble selects a key, so odd values can't index past the keypad
+                let vx = self.v_reg[x] & 0xF;
                 let key = self.keys[vx as usize];
                 if key {
-                    self.pc += 2;
+                    self.skip_next()?;
                 }
             }
-            (0xE, _, 0xA, 1) => {
+            SkipNotKey { x } => {
                 //Skip if keys not pressed
                 // ExA1
-                let x = d2 as usize;
-                let vx = self.v_reg[x];
+                let vx = self.v_reg[x] & 0xF;
                 let key = self.keys[vx as usize];
                 if !key {
-                    self.pc += 2;
+                    self.skip_next()?;
                 }
             }
-            (0xF, _, 0, 7) => {
+            GetDelay { x } => {
                 // Fx07
                 // set Vx to delay timer value
-                let x = d2 as usize;
                 self.v_reg[x] = self.dt;
             }
-            (0xF, _, 0, 0xA) => {
+            WaitKey { x } => {
                 // Fx0A
                 // Wait for key press - blocks until a key is prssed
                 // When more than one key prssed, lowest indexed is used.  This key is stored in Vx
-                let x = d2 as usize;
                 let mut pressed = false;
                 for i in 0..self.keys.len() {
                     if self.keys[i] {
@@ -397,42 +1171,46 @@ impl Chip8 {
                 }
                 if !pressed {
                     // Redo opcode
-                    self.pc -= 2;
+                    self.pc = self.op_pc();
+                    if !was_blocked {
+                        self.emit(Event::KeyWait { pc: self.pc });
+                    }
+                    self.blocked = true;
+                    self.state = State::WaitingForKey;
                 }
             }
-            (0xF, _, 1, 5) => {
+            SetDelay { x } => {
                 // Fx15
                 // Dt = Vx
-                let x = d2 as usize;
                 self.dt = self.v_reg[x];
             }
-            (0xF, _, 1, 8) => {
+            SetSound { x } => {
                 // Fx18
                 // St = Vx
-                let x = d2 as usize;
+                let playing = self.st > 0;
                 self.st = self.v_reg[x];
+                if playing != (self.st > 0) {
+                    self.emit(Event::Sound(!playing));
+                }
             }
-            (0xF, _, 1, 0xE) => {
+            AddI { x } => {
                 // Fx1E
                 // I += Vx
                 // if overflow, register should simply roll over to 0.  (rusts wrapping_add)
-                let x = d2 as usize;
                 let vx = self.v_reg[x] as u16;
                 self.i_reg = self.i_reg.wrapping_add(vx);
             }
-            (0xF, _, 2, 9) => {
+            Font { x } => {
                 // Fx29
                 // Set I to Font Address
                 // fonts are stored in the first sections of ram
                 // we are multiplying by 5 since each font is 5 bytes long
-                let x = d2 as usize;
                 let c = self.v_reg[x] as u16;
                 self.i_reg = c * 5;
             }
-            (0xF, _, 3, 3) => {
+            Bcd { x } => {
                 // Fx33
                 // i = BCD of Vx (BCD - binary coded decimal)
-                let x = d2 as usize;
                 let vx = self.v_reg[x] as f32;
                 // Fetch the hundreds digit by dividing by 100 and tossing the decimal
                 let hundreds = (vx / 100.0).floor() as u8;
@@ -440,31 +1218,48 @@ impl Chip8 {
                 let tens = ((vx / 10.0) % 10.0).floor() as u8;
                 // Fetch the ones digit by tossing the hundreds and the tens
                 let ones = (vx % 10.0) as u8;
-                self.ram[self.i_reg as usize] = hundreds;
-                self.ram[(self.i_reg + 1) as usize] = tens;
-                self.ram[(self.i_reg + 2) as usize] = ones;
+                self.write_mem(self.i_reg, hundreds)?;
+                self.write_mem(self.i_reg.wrapping_add(1), tens)?;
+                self.write_mem(self.i_reg.wrapping_add(2), ones)?;
             }
-            (0xF, _, 5, 5) => {
+            Store { x } => {
                 //Store V0 - VX into I
                 // V Registers V0 thru the specified VX (inclusive)
                 // with the same range of values from RAM, beginning with the address in the I Register. This first one stores the
                 // values into RAM, while the next one will load them the opposite way.
-                let x = d2 as usize;
-                let i = self.i_reg as usize;
                 for idx in 0..=x {
-                    self.ram[i + idx] = self.v_reg[idx];
+                    self.write_mem(self.i_reg.wrapping_add(idx as u16), self.v_reg[idx])?;
+                }
+                if self.quirks.memory_increment {
+                    self.i_reg = self.i_reg.wrapping_add(x as u16 + 1);
                 }
             }
-            (0xF, _x, 6, 5) => {
+            Load { x } => {
                 // Load I into V0 - Vx
-                let x = d2 as usize;
-                let i = self.i_reg as usize;
                 for idx in 0..=x {
-                    self.v_reg[idx] = self.ram[i + idx];
+                    self.v_reg[idx] = self.read_mem(self.i_reg.wrapping_add(idx as u16))?;
+                }
+                if self.quirks.memory_increment {
+                    self.i_reg = self.i_reg.wrapping_add(x as u16 + 1);
+                }
+            }
+            _ => {
+                if self.variant.has_schip() && self.execute_schip(instr)? {
+                    return Ok(());
+                }
+                if self.variant == Variant::XoChip && self.execute_xo_chip(instr)? {
+                    return Ok(());
+                }
+                let op = instr.op();
+                if !self.run_opcode_handlers(op) {
+                    return Err(Chip8Error::UnknownOpcode {
+                        op,
+                        pc: self.op_pc(),
+                    });
                 }
             }
-            (_, _, _, _) => unimplemented!("Unimplemented opcode: {}", op),
         }
+        Ok(())
     }
 }
 
@@ -484,7 +1279,7 @@ mod tests {
     fn push_test() {
         let mut c8 = setup();
 
-        c8.push(15);
+        c8.push(15).unwrap();
 
         assert_eq!(c8.sp, 1);
         assert_eq!(c8.stack[0], 15);
@@ -494,22 +1289,207 @@ mod tests {
     fn pop_test() {
         let mut c8 = setup();
 
-        c8.push(15);
-        assert_eq!(c8.pop(), 15);
+        c8.push(15).unwrap();
+        assert_eq!(c8.pop(), Ok(15));
         assert_eq!(c8.sp, 0);
     }
 
+    #[test]
+    fn stack_limits() {
+        let mut c8 = Chip8::with_config(Config {
+            stack_size: 2,
+            ..Config::default()
+        });
+        assert_eq!(c8.pop(), Err(Chip8Error::StackUnderflow { pc: 0x1FE }));
+        c8.push(1).unwrap();
+        c8.push(2).unwrap();
+        assert_eq!(c8.push(3), Err(Chip8Error::StackOverflow { pc: 0x1FE }));
+    }
+
+    #[test]
+    fn stack_faults_through_tick() {
+        let mut c8 = setup();
+        // CALL 0x200 forever
+        c8.load(&[0x22, 0x00]).unwrap();
+        for _ in 0..STACK_SIZE {
+            c8.tick().unwrap();
+        }
+        assert_eq!(c8.tick(), Err(Chip8Error::StackOverflow { pc: 0x200 }));
+
+        let mut c8 = setup();
+        c8.load(&[0x00, 0xEE]).unwrap();
+        assert_eq!(c8.tick(), Err(Chip8Error::StackUnderflow { pc: 0x200 }));
+    }
+
+    #[test]
+    fn introspection() {
+        let mut c8 = setup();
+        // CALL 0x204; JP 0x202; CALL 0x208; JP 0x206; LD V3, 0x42
+        c8.load(&[0x22, 0x04, 0x12, 0x02, 0x22, 0x08, 0x12, 0x06, 0x63, 0x42])
+            .unwrap();
+        c8.keypress(0x5, true);
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        assert_eq!(c8.sp(), 2);
+        assert_eq!(c8.stack(), &[0x202, 0x206]);
+        assert_eq!(c8.pc(), 0x208);
+        assert!(c8.keys()[0x5]);
+        c8.tick().unwrap();
+        assert_eq!(c8.v_reg()[3], 0x42);
+    }
+
+    #[test]
+    fn configurable_memory() {
+        let mut c8 = Chip8::with_config(Config {
+            mem_size: 0x300,
+            ..Config::default()
+        });
+        assert_eq!(c8.ram().len(), 0x300);
+        assert_eq!(
+            c8.load(&[0; 0x101]),
+            Err(LoadError::TooLarge {
+                size: 0x101,
+                max: 0x100
+            })
+        );
+        // LD I, 0x2FF; LD [I], V1 - second byte falls off the end
+        c8.load(&[0xA2, 0xFF, 0xF1, 0x55]).unwrap();
+        c8.tick().unwrap();
+        assert_eq!(
+            c8.tick(),
+            Err(Chip8Error::AddressOutOfRange {
+                addr: 0x300,
+                pc: 0x202
+            })
+        );
+    }
+
+    #[test]
+    fn bad_config_is_an_error() {
+        let config = Config {
+            mem_size: 0x500,
+            ..Variant::Eti660.config()
+        };
+        let err = Chip8::try_with_config(config).err().unwrap();
+        assert_eq!(
+            err,
+            ConfigError::StartAddr {
+                addr: 0x600,
+                max: 0x4FE
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "start_addr must be between 0xF0 and 0x4FE, got 0x600"
+        );
+        let config = Config {
+            mem_size: 0x100,
+            ..Config::default()
+        };
+        assert_eq!(config.validate(), Err(ConfigError::MemSize(0x100)));
+    }
+
+    #[test]
+    fn load_errors() {
+        let mut c8 = setup();
+        assert_eq!(c8.load(&[]), Err(LoadError::Empty));
+        assert_eq!(
+            c8.load(&[0; MEM_SIZE]),
+            Err(LoadError::TooLarge {
+                size: MEM_SIZE,
+                max: MEM_SIZE - START_ADDR as usize
+            })
+        );
+        assert_eq!(
+            LoadError::TooLarge {
+                size: 4000,
+                max: 3584
+            }
+            .to_string(),
+            "ROM is 4000 bytes but at most 3584 bytes fit in memory"
+        );
+
+        c8.load(&[0x12, 0x00]).unwrap();
+        assert_eq!(c8.reload_rom(&[], false), Err(LoadError::Empty));
+        assert_eq!(c8.ram()[0x200], 0x12);
+    }
+
+    #[test]
+    fn pc_off_end_of_memory() {
+        let mut c8 = Chip8::with_config(Config {
+            mem_size: 0x204,
+            ..Config::default()
+        });
+        c8.load(&[0x00, 0xE0, 0x00, 0xE0]).unwrap();
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        assert_eq!(
+            c8.tick(),
+            Err(Chip8Error::AddressOutOfRange {
+                addr: 0x204,
+                pc: 0x204
+            })
+        );
+    }
+
+    #[test]
+    fn key_wait_at_the_top_of_memory() {
+        let mut c8 = Chip8::with_config(Config {
+            mem_size: Config::MAX_MEM_SIZE,
+            ..Config::default()
+        });
+        // LD V0, K in the last word, so the PC wraps to 0 after fetching it
+        c8.ram[0xFFFE] = 0xF0;
+        c8.ram[0xFFFF] = 0x0A;
+        c8.pc = 0xFFFE;
+        assert_eq!(c8.tick(), Ok(State::WaitingForKey));
+        assert_eq!(c8.pc(), 0xFFFE);
+    }
+
+    #[test]
+    fn pc_overflow_policies() {
+        let config = |pc_overflow| Config {
+            mem_size: 0x204,
+            pc_overflow,
+            ..Config::default()
+        };
+        // LD V0, 1; ADD V0, 1 then off the end
+        let rom = [0x60, 0x01, 0x70, 0x01];
+
+        let mut c8 = Chip8::with_config(config(PcOverflow::Wrap));
+        c8.load(&rom).unwrap();
+        c8.ram[0] = 0x12; // JP 0x200 at address 0
+        c8.ram[1] = 0x00;
+        // LD, ADD, then JP from address 0 back to the LD
+        for _ in 0..4 {
+            c8.tick().unwrap();
+        }
+        assert_eq!(c8.v_reg()[0], 1);
+        assert_eq!(c8.pc(), 0x202);
+
+        let mut c8 = Chip8::with_config(config(PcOverflow::Trap));
+        c8.record_events(true);
+        c8.load(&rom).unwrap();
+        for _ in 0..4 {
+            c8.tick().unwrap();
+        }
+        assert_eq!(c8.pc(), 0x204);
+        assert_eq!(c8.v_reg()[0], 2);
+        assert_eq!(c8.take_events(), vec![Event::Halt { pc: 0x204 }]);
+    }
+
     #[test]
     fn reset() {
         let mut c8 = Chip8::new();
         // set random data
         c8.pc += 0x0F;
-        c8.ram = [0xF; MEM_SIZE];
-        c8.screen = [true; SCREEN_HEIGHT * SCREEN_WIDTH];
+        c8.ram = vec![0xF; MEM_SIZE];
+        c8.screen
+            .set_bytes(&[0xFF; SCREEN_HEIGHT * SCREEN_WIDTH / 8]);
         c8.v_reg = [0xF; V_REG_SIZE];
         c8.i_reg = 0xFF;
         c8.sp = 0x1D;
-        c8.stack = [0xF; STACK_SIZE];
+        c8.stack = vec![0xF; STACK_SIZE];
         c8.keys = [true; KEYPAD_SIZE];
         c8.dt = 0x1D;
         c8.st = 0x1D;
@@ -522,6 +1502,176 @@ mod tests {
         assert_eq!(c8.ram, c8_new.ram);
     }
 
+    #[test]
+    fn load_segments_test() {
+        let mut c8 = setup();
+        let code = [0x12, 0x00];
+        let level = [1, 2, 3];
+        c8.load_segments(&[
+            Segment {
+                addr: 0xA00,
+                data: &level,
+            },
+            Segment {
+                addr: START_ADDR,
+                data: &code,
+            },
+        ])
+        .unwrap();
+        assert_eq!(c8.ram[0x200..0x202], code);
+        assert_eq!(c8.ram[0xA00..0xA03], level);
+    }
+
+    #[test]
+    fn load_segments_rejects_overlap() {
+        let mut c8 = setup();
+        let result = c8.load_segments(&[
+            Segment {
+                addr: 0x200,
+                data: &[1; 4],
+            },
+            Segment {
+                addr: 0x203,
+                data: &[2; 2],
+            },
+        ]);
+        assert_eq!(
+            result,
+            Err(LoadError::SegmentOverlap {
+                first: 0x200,
+                second: 0x203
+            })
+        );
+        // nothing was written
+        assert_eq!(c8.ram[0x200], 0);
+
+        let result = c8.load_segments(&[Segment {
+            addr: 0xFFF,
+            data: &[1; 2],
+        }]);
+        assert_eq!(
+            result,
+            Err(LoadError::SegmentOutOfRange {
+                addr: 0xFFF,
+                size: 2
+            })
+        );
+    }
+
+    #[test]
+    fn reload_rom_keeps_registers() {
+        let mut c8 = setup();
+        c8.load(&[0x60, 0x2A, 0x61, 0x07, 0x00, 0xE0]).unwrap();
+        c8.tick().unwrap();
+
+        c8.reload_rom(&[0x62, 0x01], true).unwrap();
+        assert_eq!(c8.pc, START_ADDR + 2);
+        assert_eq!(c8.v_reg[0], 0x2A);
+        // leftovers from the old program are gone
+        assert_eq!(c8.ram[0x202..0x206], [0; 4]);
+        assert_eq!(c8.ram[..FONTSET_SIZE], FONTSET);
+
+        c8.reload_rom(&[0x62, 0x01], false).unwrap();
+        assert_eq!(c8.pc, START_ADDR);
+        assert_eq!(c8.v_reg[0], 0);
+        assert_eq!(c8.ram[0x200..0x202], [0x62, 0x01]);
+    }
+
+    #[test]
+    fn reload_after_exit() {
+        let mut c8 = setup();
+        c8.load(&[0x00, 0xFD]).unwrap();
+        c8.set_persistent_region(0xE00..0xE02).unwrap();
+        c8.ram[0xE00] = 0x99;
+        assert_eq!(c8.tick(), Ok(State::Halted));
+
+        c8.reload_rom(&[0x60, 0x2A], false).unwrap();
+        assert_eq!(c8.tick(), Ok(State::Running));
+        assert_eq!(c8.v_reg[0], 0x2A);
+        assert_eq!(c8.instruction_count(), 1);
+        // the game's save data survives the reload
+        assert_eq!(c8.ram[0xE00], 0x99);
+    }
+
+    #[test]
+    fn hires_mode() {
+        let mut c8 = setup();
+        // HIGH; LD V0, 100; DRW V0, V0, 1; LOW
+        c8.load(&[0x00, 0xFF, 0x60, 0x64, 0xD0, 0x01, 0x00, 0xFE])
+            .unwrap();
+        c8.tick().unwrap();
+        assert!(c8.hires());
+        assert_eq!((c8.screen_width(), c8.screen_height()), (128, 64));
+        assert_eq!(c8.get_display().len(), HIRES_WIDTH * HIRES_HEIGHT);
+
+        // I is 0, so this draws the top row of the "0" glyph at (100, 100 % 64)
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        assert!(c8.get_display()[100 + 36 * HIRES_WIDTH]);
+
+        c8.tick().unwrap();
+        assert_eq!((c8.screen_width(), c8.screen_height()), (64, 32));
+        assert!(c8.get_display().iter().all(|px| !px));
+        c8.set_hires(true);
+        c8.reset();
+        assert!(!c8.hires());
+    }
+
+    #[test]
+    fn eti660_start() {
+        let mut c8 = Chip8::with_variant(Variant::Eti660);
+        // LD V0, 1; JP 0x600
+        c8.load(&[0x60, 0x01, 0x16, 0x00]).unwrap();
+        assert_eq!(c8.ram()[0x600], 0x60);
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        assert_eq!((c8.pc(), c8.v_reg()[0]), (0x600, 1));
+
+        c8.tick().unwrap();
+        c8.reset();
+        assert_eq!(c8.pc(), 0x600);
+    }
+
+    #[test]
+    fn variant_presets() {
+        let c8 = Chip8::with_variant(Variant::XoChip);
+        assert_eq!(c8.ram().len(), Config::MAX_MEM_SIZE);
+        assert!(c8.quirks().memory_increment);
+
+        let c8 = Chip8::with_variant(Variant::SuperChip);
+        assert_eq!(c8.ram().len(), MEM_SIZE);
+        assert!(c8.quirks().jump_vx);
+    }
+
+    #[test]
+    fn no_hires_before_schip() {
+        let mut c8 = Chip8::with_variant(Variant::CosmacVip);
+        c8.load(&[0x00, 0xFF]).unwrap();
+        assert_eq!(
+            c8.tick(),
+            Err(Chip8Error::UnknownOpcode {
+                op: 0x00FF,
+                pc: 0x200
+            })
+        );
+    }
+
+    #[test]
+    fn unknown_opcode_is_an_error() {
+        let mut c8 = setup();
+        c8.load(&[0x60, 0x01, 0x51, 0x27]).unwrap();
+        c8.tick().unwrap();
+        assert_eq!(
+            c8.tick(),
+            Err(Chip8Error::UnknownOpcode {
+                op: 0x5127,
+                pc: 0x202
+            })
+        );
+        // The machine is still usable afterwards
+        assert_eq!(c8.v_reg()[0], 1);
+    }
+
     #[test]
     fn fetch_test() {
         let mut c8 = setup();
@@ -529,7 +1679,7 @@ mod tests {
         c8.ram[(c8.pc + 1) as usize] = 0xA0;
         let before_pc = c8.pc;
 
-        let op = c8.fetch();
+        let op = c8.fetch().unwrap();
         assert_eq!(op, 0x5FA0);
         assert_eq!(c8.pc, before_pc + 2);
     }
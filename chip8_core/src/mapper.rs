@@ -0,0 +1,123 @@
+use std::ops::Range;
+
+/// Hook for memory extensions such as bank switching
+///
+/// Once installed with `Chip8::set_mapper`, every instruction fetch and every
+/// RAM read or write the interpreter makes inside `range` is routed here
+/// instead of to RAM. Addresses are absolute.
+pub trait Mapper: MapperClone {
+    /// Addresses the mapper answers for
+    fn range(&self) -> Range<u16>;
+    fn read(&mut self, addr: u16) -> u8;
+    fn write(&mut self, addr: u16, val: u8);
+}
+
+clone_box!(MapperClone, Mapper);
+
+/// Experimental bank-switched ROM
+///
+/// `data` is split into banks the size of `window`, and the window shows one
+/// bank at a time. The window is read-only; writing any value to an address
+/// inside it selects that bank (e.g. `LD V0, 2; LD I, window; LD [I], V0`).
+/// Bank numbers past the end of `data` read as zero.
+#[derive(Clone)]
+pub struct BankedRom {
+    data: Vec<u8>,
+    window: Range<u16>,
+    bank: usize,
+}
+
+impl BankedRom {
+    /// `None` if the window is empty, as there'd be nothing to show a bank in
+    pub fn new(data: Vec<u8>, window: Range<u16>) -> Option<Self> {
+        if window.is_empty() {
+            return None;
+        }
+        Some(Self {
+            data,
+            window,
+            bank: 0,
+        })
+    }
+
+    pub fn bank(&self) -> usize {
+        self.bank
+    }
+
+    pub fn bank_count(&self) -> usize {
+        self.data.len().div_ceil(self.bank_size())
+    }
+
+    fn bank_size(&self) -> usize {
+        self.window.len()
+    }
+}
+
+impl Mapper for BankedRom {
+    fn range(&self) -> Range<u16> {
+        self.window.clone()
+    }
+
+    fn read(&mut self, addr: u16) -> u8 {
+        let offset = (addr - self.window.start) as usize;
+        let idx = self.bank * self.bank_size() + offset;
+        self.data.get(idx).copied().unwrap_or(0)
+    }
+
+    fn write(&mut self, _addr: u16, val: u8) {
+        self.bank = val as usize;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chip8;
+
+    #[test]
+    fn banked_rom_switches_banks() {
+        // Two 4-byte banks, each holding a different value for V1
+        let mut banks =
+            BankedRom::new(vec![0x61, 0x11, 0, 0, 0x61, 0x22, 0, 0], 0xA00..0xA04).unwrap();
+        assert_eq!(banks.bank_count(), 2);
+        assert_eq!(banks.read(0xA00), 0x61);
+        banks.write(0xA00, 1);
+        assert_eq!(banks.read(0xA01), 0x22);
+        banks.write(0xA00, 5);
+        assert_eq!(banks.read(0xA01), 0);
+    }
+
+    #[test]
+    fn empty_window_is_rejected() {
+        assert!(BankedRom::new(vec![1, 2, 3], 0xA00..0xA00).is_none());
+    }
+
+    #[test]
+    fn chip8_routes_through_mapper() {
+        let mut c8 = Chip8::new();
+        let banks = BankedRom::new(
+            vec![0x61, 0x11, 0x00, 0xEE, 0x61, 0x22, 0x00, 0xEE],
+            0xA00..0xA04,
+        )
+        .unwrap();
+        c8.set_mapper(Box::new(banks));
+        c8.load(&[
+            0x2A, 0x00, // CALL 0xA00
+            0x60, 0x01, // LD V0, 1
+            0xAA, 0x00, // LD I, 0xA00
+            0xF0, 0x55, // LD [I], V0 - select bank 1
+            0x2A, 0x00, // CALL 0xA00
+        ])
+        .unwrap();
+
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        assert_eq!(c8.v_reg()[1], 0x11);
+        for _ in 0..6 {
+            c8.tick().unwrap();
+        }
+        assert_eq!(c8.v_reg()[1], 0x22);
+        // RAM under the window was never touched
+        assert_eq!(c8.ram()[0xA00], 0);
+    }
+}
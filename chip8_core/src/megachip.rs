@@ -0,0 +1,356 @@
+use crate::{Chip8, Chip8Error, Event};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Mega-CHIP screen size, used once `0011` switches mega mode on
+pub const MEGA_WIDTH: usize = 256;
+pub const MEGA_HEIGHT: usize = 192;
+
+/// How Mega-CHIP sprite pixels combine with the screen, set by `080n`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Blend {
+    /// The sprite replaces what's under it
+    #[default]
+    Normal,
+    /// A quarter of the sprite over three quarters of the screen
+    Quarter,
+    /// Half and half
+    Half,
+    /// Channels added, saturating at white
+    Add,
+    /// Channels multiplied, darkening
+    Multiply,
+}
+
+/// Mega-CHIP state, only touched by `Variant::MegaChip`
+///
+/// Part of `Chip8State`, so snapshots and saves taken in mega mode keep the
+/// colour screen and palette.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MegaChip {
+    /// `0011` mega mode, `0010` back to SCHIP
+    pub enabled: bool,
+    /// ARGB, index 0 is transparent. 256 entries in mega mode.
+    pub palette: Vec<u32>,
+    /// `03nn`, 0 means 256
+    pub sprite_width: u8,
+    /// `04nn`, 0 means 256
+    pub sprite_height: u8,
+    /// `05nn` screen alpha, kept for frontends
+    pub alpha: u8,
+    /// `080n`
+    pub blend: Blend,
+    /// `09nn`, drawing over this index sets VF
+    pub collision_color: u8,
+    /// Palette index last drawn to each pixel, `MEGA_WIDTH * MEGA_HEIGHT`
+    /// of them in mega mode
+    pub indices: Vec<u8>,
+    /// ARGB screen, the same size as `indices`
+    pub pixels: Vec<u32>,
+}
+
+impl MegaChip {
+    fn enable(&mut self) {
+        *self = MegaChip {
+            enabled: true,
+            palette: vec![0; 256],
+            indices: vec![0; MEGA_WIDTH * MEGA_HEIGHT],
+            pixels: vec![0; MEGA_WIDTH * MEGA_HEIGHT],
+            ..MegaChip::default()
+        };
+    }
+
+    fn clear(&mut self) {
+        self.indices.fill(0);
+        self.pixels.fill(0);
+    }
+
+    /// Move the screen by `dx`, `dy` pixels, pixels moved off the edge are
+    /// lost and the gap left behind is transparent
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        shift(&mut self.indices, dx, dy);
+        shift(&mut self.pixels, dx, dy);
+    }
+
+    /// Whether the buffers are the sizes drawing relies on
+    pub(crate) fn is_valid(&self) -> bool {
+        let size = MEGA_WIDTH * MEGA_HEIGHT;
+        !self.enabled
+            || (self.palette.len() == 256
+                && self.indices.len() == size
+                && self.pixels.len() == size)
+    }
+}
+
+fn shift<T: Copy + Default>(buf: &mut [T], dx: isize, dy: isize) {
+    let old = buf.to_vec();
+    for (pos, px) in buf.iter_mut().enumerate() {
+        let x = (pos % MEGA_WIDTH) as isize - dx;
+        let y = (pos / MEGA_WIDTH) as isize - dy;
+        let inside =
+            (0..MEGA_WIDTH as isize).contains(&x) && (0..MEGA_HEIGHT as isize).contains(&y);
+        *px = if inside {
+            old[x as usize + y as usize * MEGA_WIDTH]
+        } else {
+            T::default()
+        };
+    }
+}
+
+impl Chip8 {
+    /// Run a Mega-CHIP only opcode, returns false if `op` isn't one
+    ///
+    /// Called before the normal decoder since mega mode changes what `00E0`
+    /// and `Dxyn` do.
+    pub(crate) fn execute_megachip(&mut self, op: u16) -> Result<bool, Chip8Error> {
+        let nn = (op & 0xFF) as u8;
+        match op {
+            0x0010 => {
+                // 0010 back to the SCHIP display
+                self.mega.enabled = false;
+                self.screen_changed = true;
+                self.emit(Event::Draw);
+            }
+            0x0011 => {
+                // 0011 256x192 colour mode
+                self.mega.enable();
+                self.screen_changed = true;
+                self.emit(Event::Draw);
+            }
+            0x0100..=0x01FF => {
+                // 01nn NNNN load I with a 24 bit address, memory stops at 64K here
+                if nn != 0 {
+                    return Err(Chip8Error::UnknownOpcode {
+                        op,
+                        pc: self.op_pc(),
+                    });
+                }
+                let hi = self.read_byte(self.pc)? as u16;
+                let lo = self.read_byte(self.pc.wrapping_add(1))? as u16;
+                self.i_reg = hi << 8 | lo;
+                self.pc = self.pc.wrapping_add(2);
+            }
+            0x0200..=0x02FF => {
+                // 02nn load nn ARGB colours from I into palette entries 1 to nn
+                for idx in 0..nn as u16 {
+                    let mut argb = [0; 4];
+                    for (offset, byte) in argb.iter_mut().enumerate() {
+                        *byte = self.read_mem(self.i_reg.wrapping_add(idx * 4 + offset as u16))?;
+                    }
+                    if let Some(entry) = self.mega.palette.get_mut(idx as usize + 1) {
+                        *entry = u32::from_be_bytes(argb);
+                    }
+                }
+            }
+            0x0300..=0x03FF => self.mega.sprite_width = nn,
+            0x0400..=0x04FF => self.mega.sprite_height = nn,
+            0x0500..=0x05FF => self.mega.alpha = nn,
+            // 060n play and 0700 stop digitised sound, accepted but silent
+            0x0600..=0x060F | 0x0700 => (),
+            0x0800..=0x0804 => {
+                // 080n blend mode
+                self.mega.blend = match nn {
+                    0 => Blend::Normal,
+                    1 => Blend::Quarter,
+                    2 => Blend::Half,
+                    3 => Blend::Add,
+                    _ => Blend::Multiply,
+                };
+            }
+            0x0900..=0x09FF => self.mega.collision_color = nn,
+            0x00B0..=0x00BF | 0x00C0..=0x00CF | 0x00FB | 0x00FC if self.mega.enabled => {
+                // 00Bn up and 00Cn down n pixels, 00FB right and 00FC left 4
+                let n = (op & 0xF) as isize;
+                let (dx, dy) = match op {
+                    0x00FB => (4, 0),
+                    0x00FC => (-4, 0),
+                    _ if op & 0xF0 == 0xB0 => (0, -n),
+                    _ => (0, n),
+                };
+                self.mega.scroll(dx, dy);
+                self.screen_changed = true;
+                self.emit(Event::Draw);
+            }
+            0x00E0 if self.mega.enabled => {
+                self.mega.clear();
+                self.screen_changed = true;
+                self.emit(Event::Draw);
+            }
+            _ if op & 0xF000 == 0xD000 && self.mega.enabled => {
+                let x = self.v_reg[((op & 0x0F00) >> 8) as usize] as usize;
+                let y = self.v_reg[((op & 0x00F0) >> 4) as usize] as usize;
+                let collided = self.draw_mega_sprite(x, y)?;
+                self.v_reg[0xF] = collided as u8;
+                self.screen_changed = true;
+                self.emit(Event::Draw);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Draw the sprite at I, one palette index per byte, clipped at the
+    /// screen edges. Index 0 is transparent. Returns whether any pixel of
+    /// the collision colour was drawn over.
+    fn draw_mega_sprite(&mut self, x: usize, y: usize) -> Result<bool, Chip8Error> {
+        let size = |n: u8| if n == 0 { 256 } else { n as usize };
+        let (width, height) = (size(self.mega.sprite_width), size(self.mega.sprite_height));
+        let mut collided = false;
+        for row in 0..height {
+            for col in 0..width {
+                let addr = self.i_reg.wrapping_add((row * width + col) as u16);
+                let index = self.read_mem(addr)?;
+                let (px, py) = (x + col, y + row);
+                if index == 0 || px >= MEGA_WIDTH || py >= MEGA_HEIGHT {
+                    continue;
+                }
+                let pos = px + py * MEGA_WIDTH;
+                let mega = &mut self.mega;
+                collided |= mega.indices[pos] == mega.collision_color;
+                mega.indices[pos] = index;
+                mega.pixels[pos] =
+                    blend(mega.blend, mega.pixels[pos], mega.palette[index as usize]);
+            }
+        }
+        Ok(collided)
+    }
+
+    /// The Mega-CHIP screen as `MEGA_WIDTH * MEGA_HEIGHT` row-major ARGB
+    /// pixels, `None` unless mega mode is on
+    pub fn mega_display(&self) -> Option<&[u32]> {
+        self.mega.enabled.then_some(&self.mega.pixels[..])
+    }
+
+    /// Screen alpha set by `05nn`, for frontends that fade the display
+    pub fn mega_alpha(&self) -> u8 {
+        self.mega.alpha
+    }
+}
+
+/// Combine a sprite colour with the screen colour under it
+fn blend(mode: Blend, dst: u32, src: u32) -> u32 {
+    let channels = |f: &dyn Fn(u32, u32) -> u32| {
+        (0..4).fold(0, |out, i| {
+            let shift = i * 8;
+            out | f(dst >> shift & 0xFF, src >> shift & 0xFF).min(0xFF) << shift
+        })
+    };
+    match mode {
+        Blend::Normal => src,
+        Blend::Quarter => channels(&|d, s| (d * 3 + s) / 4),
+        Blend::Half => channels(&|d, s| (d + s) / 2),
+        Blend::Add => channels(&|d, s| d + s),
+        Blend::Multiply => channels(&|d, s| d * s / 0xFF),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Variant;
+
+    #[test]
+    fn colour_sprites() {
+        let mut c8 = Chip8::with_variant(Variant::MegaChip);
+        // MEGAON; LDHI I, 0x218; LDPAL 2; SPRW 2; SPRH 1; COL 2; LD V0, 10;
+        // LD I, 0x220; DRW V0, V1, 0 twice; JP 0x216; then two ARGB colours
+        // and a 2x1 sprite
+        let mut rom = vec![
+            0x00, 0x11, 0x01, 0x00, 0x02, 0x18, 0x02, 0x02, 0x03, 0x02, 0x04, 0x01, 0x09, 0x02,
+            0x60, 0x0A, 0xA2, 0x20, 0xD0, 0x10, 0xD0, 0x10, 0x12, 0x16,
+        ];
+        rom.extend([0xFF, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF]);
+        rom.extend([0x01, 0x02]);
+        c8.load(&rom).unwrap();
+        assert!(c8.mega_display().is_none());
+
+        for _ in 0..9 {
+            c8.tick().unwrap();
+        }
+        let screen = c8.mega_display().unwrap();
+        assert_eq!(&screen[10..12], &[0xFFFF_0000, 0xFF00_00FF]);
+        assert_eq!(c8.v_reg()[0xF], 0);
+
+        // Drawing again covers the collision colour
+        c8.tick().unwrap();
+        assert_eq!(c8.v_reg()[0xF], 1);
+    }
+
+    /// Mega mode on with the 2x1 sprite from `colour_sprites` drawn at (10, 0)
+    fn drawn() -> Chip8 {
+        let mut c8 = Chip8::with_variant(Variant::MegaChip);
+        // MEGAON; LDHI I, 0x214; LDPAL 2; SPRW 2; SPRH 1; LD V0, 10;
+        // LD I, 0x21C; DRW V0, V1, 0; JP 0x212; then two ARGB colours and
+        // a 2x1 sprite
+        let mut rom = vec![
+            0x00, 0x11, 0x01, 0x00, 0x02, 0x14, 0x02, 0x02, 0x03, 0x02, 0x04, 0x01, 0x60, 0x0A,
+            0xA2, 0x1C, 0xD0, 0x10, 0x12, 0x12,
+        ];
+        rom.extend([0xFF, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF]);
+        rom.extend([0x01, 0x02]);
+        c8.load(&rom).unwrap();
+        for _ in 0..8 {
+            c8.tick().unwrap();
+        }
+        c8
+    }
+
+    #[test]
+    fn scrolls_the_mega_screen() {
+        let mut c8 = drawn();
+        // SCR (00FB) then SCD 1 (00C1)
+        c8.load(&[0x00, 0xFB, 0x00, 0xC1]).unwrap();
+        c8.set_pc(0x200).unwrap();
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        let screen = c8.mega_display().unwrap();
+        assert_eq!(&screen[10..12], &[0, 0]);
+        let row = MEGA_WIDTH;
+        assert_eq!(&screen[row + 14..row + 16], &[0xFFFF_0000, 0xFF00_00FF]);
+    }
+
+    #[test]
+    fn snapshot_keeps_mega_mode() {
+        let c8 = drawn();
+        let state = c8.snapshot();
+        let mut restored = Chip8::with_variant(Variant::MegaChip);
+        restored.restore(&state).unwrap();
+        assert_eq!(restored.mega_display(), c8.mega_display());
+        assert_eq!(restored.state_hash(), c8.state_hash());
+        assert_eq!(restored.snapshot(), state);
+
+        #[cfg(feature = "serde")]
+        {
+            let json = c8.save_state().to_json();
+            let save = crate::SaveState::from_json(&json).unwrap();
+            let mut loaded = Chip8::with_variant(Variant::MegaChip);
+            loaded.load_state(&save).unwrap();
+            assert_eq!(loaded.snapshot(), state);
+        }
+
+        // Mega mode with the wrong sized screen is refused
+        let mut bad = state.clone();
+        bad.mega.pixels.pop();
+        assert_eq!(
+            restored.restore(&bad),
+            Err(crate::StateError::Mismatch("mega"))
+        );
+        // Taken before mega mode, restoring goes back to the SCHIP screen
+        restored
+            .restore(&Chip8::with_variant(Variant::MegaChip).snapshot())
+            .unwrap();
+        assert!(restored.mega_display().is_none());
+    }
+
+    #[test]
+    fn blend_modes() {
+        assert_eq!(blend(Blend::Half, 0xFF00_0000, 0xFF00_FF00), 0xFF00_7F00);
+        assert_eq!(blend(Blend::Add, 0xFF80_8080, 0xFF80_0000), 0xFFFF_8080);
+        assert_eq!(
+            blend(Blend::Multiply, 0xFF80_FFFF, 0xFFFF_8000),
+            0xFF80_8000
+        );
+    }
+}
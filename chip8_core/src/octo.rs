@@ -0,0 +1,673 @@
+use crate::asm::{AsmError, Assembled};
+use crate::symbols::Symbols;
+use crate::{MEM_SIZE, START_ADDR};
+use std::collections::HashMap;
+
+/// A whitespace separated word of source, `line` and `col` are 1-based
+#[derive(Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+    col: usize,
+}
+
+impl Token<'_> {
+    fn error(&self, message: impl Into<String>) -> AsmError {
+        AsmError {
+            line: self.line,
+            column: self.col,
+            message: message.into(),
+        }
+    }
+}
+
+/// Right hand side of a comparison or assignment
+#[derive(Clone, Copy)]
+enum Operand {
+    Reg(u16),
+    Byte(u16),
+}
+
+/// A condition boiled down to something a skip instruction can test:
+/// either `Vx == rhs` or "key Vx is pressed", being `want`
+struct Test {
+    /// Ops that have to run first, `<` and friends work through VF
+    prelude: Vec<u16>,
+    x: u16,
+    rhs: Option<Operand>,
+    want: bool,
+}
+
+/// An open `if ... begin`, `else` or `loop`, closed by `end` or `again`
+enum Block<'a> {
+    If {
+        jump: usize,
+        token: Token<'a>,
+    },
+    Else {
+        jump: usize,
+        token: Token<'a>,
+    },
+    Loop {
+        start: u16,
+        breaks: Vec<usize>,
+        token: Token<'a>,
+    },
+}
+
+/// A label used before it was defined, patched in at the end
+struct Fixup<'a> {
+    at: usize,
+    name: Token<'a>,
+    long: bool,
+}
+
+/// Compile Octo source into a ROM image that loads at 0x200
+///
+/// Covers the language as most programs use it:
+///
+/// ```text
+/// # comments start with a hash
+/// :alias x v1                 # name a register
+/// :const SPEED 2              # name a number
+/// : main                      # a label
+///   x := 0
+///   loop
+///     x += SPEED
+///     if x == 10 then draw    # calling a label is just its name
+///     while x != 20
+///   again
+/// : draw
+///   i := sprite  sprite x x 1
+///   ;                         # or `return`
+/// : sprite 0xFF 0b10000001    # numbers on their own are data
+/// ```
+///
+/// Comparisons can be `==`, `!=`, `<`, `>`, `<=`, `>=` (the last four
+/// clobber VF like Octo's do), `key` and `-key`. Macros, `:org`,
+/// `:calc` and strings aren't supported.
+pub fn compile(src: &str) -> Result<Assembled, AsmError> {
+    let mut compiler = Compiler {
+        tokens: tokenize(src),
+        pos: 0,
+        rom: Vec::new(),
+        labels: Symbols::new(),
+        consts: HashMap::new(),
+        aliases: HashMap::new(),
+        fixups: Vec::new(),
+        blocks: Vec::new(),
+    };
+    while compiler.pos < compiler.tokens.len() {
+        compiler.statement()?;
+        if compiler.here() as usize > MEM_SIZE {
+            let token = compiler.tokens[compiler.pos - 1];
+            return Err(token.error("program does not fit in memory"));
+        }
+    }
+    compiler.finish()
+}
+
+fn tokenize(src: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    for (i, text) in src.lines().enumerate() {
+        let text = text.split('#').next().unwrap_or("");
+        let mut rest = text;
+        loop {
+            let trimmed = rest.trim_start();
+            if trimmed.is_empty() {
+                break;
+            }
+            let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+            tokens.push(Token {
+                text: &trimmed[..end],
+                line: i + 1,
+                col: text.len() - trimmed.len() + 1,
+            });
+            rest = &trimmed[end..];
+        }
+    }
+    tokens
+}
+
+struct Compiler<'a> {
+    tokens: Vec<Token<'a>>,
+    pos: usize,
+    rom: Vec<u8>,
+    labels: Symbols,
+    consts: HashMap<&'a str, u32>,
+    aliases: HashMap<&'a str, u16>,
+    fixups: Vec<Fixup<'a>>,
+    blocks: Vec<Block<'a>>,
+}
+
+impl<'a> Compiler<'a> {
+    fn next(&mut self) -> Result<Token<'a>, AsmError> {
+        let token = self.tokens.get(self.pos).copied().ok_or_else(|| {
+            let last = self.tokens[self.pos - 1];
+            last.error(format!("unexpected end of file after '{}'", last.text))
+        })?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).map(|t| t.text)
+    }
+
+    /// Consume `text`, which has to come next
+    fn expect(&mut self, text: &str) -> Result<(), AsmError> {
+        let token = self.next()?;
+        if token.text != text {
+            return Err(token.error(format!("expected '{}', found '{}'", text, token.text)));
+        }
+        Ok(())
+    }
+
+    /// Address the next byte will go to
+    fn here(&self) -> u16 {
+        START_ADDR + self.rom.len() as u16
+    }
+
+    fn emit(&mut self, op: u16) {
+        self.rom.extend_from_slice(&op.to_be_bytes());
+    }
+
+    /// Point the jump at `at` to `addr`
+    fn patch(&mut self, at: usize, addr: u16) {
+        self.rom[at..at + 2].copy_from_slice(&(0x1000 | addr).to_be_bytes());
+    }
+
+    fn register(&self, token: Token<'a>) -> Result<u16, AsmError> {
+        if let Some(&reg) = self.aliases.get(token.text) {
+            return Ok(reg);
+        }
+        parse_register(token.text)
+            .ok_or_else(|| token.error(format!("expected a register, found '{}'", token.text)))
+    }
+
+    /// A number or constant no larger than `max`, negative numbers wrap
+    /// around like they do in Octo (`-1` is 0xFF for a byte)
+    fn value(&self, token: Token<'a>, max: u32) -> Result<u16, AsmError> {
+        let n = match self.consts.get(token.text) {
+            Some(&n) => n as i64,
+            None => parse_number(token.text)
+                .ok_or_else(|| token.error(format!("expected a number, found '{}'", token.text)))?,
+        };
+        let n = if n < 0 { n + max as i64 + 1 } else { n };
+        if !(0..=max as i64).contains(&n) {
+            return Err(token.error(format!("value {} is out of range", token.text)));
+        }
+        Ok(n as u16)
+    }
+
+    /// Emit `op` with the address of `token` in its low 12 bits (or the 16
+    /// bits after it when `long`), filled in later for forward references
+    fn emit_addr(&mut self, op: u16, token: Token<'a>, long: bool) -> Result<(), AsmError> {
+        let max = if long { 0xFFFF } else { 0xFFF };
+        let addr = match self.labels.get(token.text) {
+            Some(addr) => addr,
+            None if is_name(token.text) && !self.consts.contains_key(token.text) => {
+                self.fixups.push(Fixup {
+                    at: self.rom.len() + if long { 2 } else { 0 },
+                    name: token,
+                    long,
+                });
+                0
+            }
+            None => self.value(token, max)?,
+        };
+        if long {
+            self.emit(op);
+            self.emit(addr);
+        } else {
+            self.emit(op | addr);
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self) -> Result<(), AsmError> {
+        let token = self.next()?;
+        match token.text {
+            ":" => {
+                let name = self.next()?;
+                if !is_name(name.text) {
+                    return Err(name.error(format!("invalid label '{}'", name.text)));
+                }
+                if !self.labels.insert(name.text, self.here()) {
+                    return Err(name.error(format!("duplicate label '{}'", name.text)));
+                }
+            }
+            ":const" => {
+                let name = self.next()?;
+                let value = self.next()?;
+                let value = self.value(value, 0xFFFF)?;
+                self.consts.insert(name.text, value as u32);
+            }
+            ":alias" => {
+                let name = self.next()?;
+                let reg = self.next()?;
+                let reg = self.register(reg)?;
+                self.aliases.insert(name.text, reg);
+            }
+            ":call" => {
+                let target = self.next()?;
+                self.emit_addr(0x2000, target, false)?;
+            }
+            ":byte" => {
+                let value = self.next()?;
+                let byte = self.value(value, 0xFF)?;
+                self.rom.push(byte as u8);
+            }
+            "clear" => self.emit(0x00E0),
+            "return" | ";" => self.emit(0x00EE),
+            "hires" => self.emit(0x00FF),
+            "lores" => self.emit(0x00FE),
+            "scroll-right" => self.emit(0x00FB),
+            "scroll-left" => self.emit(0x00FC),
+            "exit" => self.emit(0x00FD),
+            "audio" => self.emit(0xF002),
+            "scroll-down" | "scroll-up" | "plane" => {
+                let n = self.next()?;
+                let n = self.value(n, 0xF)?;
+                self.emit(match token.text {
+                    "scroll-down" => 0x00C0 | n,
+                    "scroll-up" => 0x00D0 | n,
+                    _ => 0xF001 | n << 8,
+                });
+            }
+            "jump" | "jump0" | "native" => {
+                let target = self.next()?;
+                let op = match token.text {
+                    "jump" => 0x1000,
+                    "jump0" => 0xB000,
+                    _ => 0x0000,
+                };
+                self.emit_addr(op, target, false)?;
+            }
+            "bcd" | "saveflags" | "loadflags" => {
+                let x = self.next()?;
+                let x = self.register(x)?;
+                self.emit(
+                    match token.text {
+                        "bcd" => 0xF033,
+                        "saveflags" => 0xF075,
+                        _ => 0xF085,
+                    } | x << 8,
+                );
+            }
+            "save" | "load" => {
+                let x = self.next()?;
+                let x = self.register(x)?;
+                let save = token.text == "save";
+                if self.peek() == Some("-") {
+                    // XO-CHIP register range
+                    self.pos += 1;
+                    let y = self.next()?;
+                    let y = self.register(y)?;
+                    self.emit(if save { 0x5002 } else { 0x5003 } | x << 8 | y << 4);
+                } else {
+                    self.emit(if save { 0xF055 } else { 0xF065 } | x << 8);
+                }
+            }
+            "sprite" => {
+                let (x, y, n) = (self.next()?, self.next()?, self.next()?);
+                let (x, y, n) = (self.register(x)?, self.register(y)?, self.value(n, 0xF)?);
+                self.emit(0xD000 | x << 8 | y << 4 | n);
+            }
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.next()?;
+                let x = self.register(x)?;
+                self.emit(
+                    match token.text {
+                        "delay" => 0xF015,
+                        "buzzer" => 0xF018,
+                        _ => 0xF03A,
+                    } | x << 8,
+                );
+            }
+            "i" => self.index_op()?,
+            "if" => {
+                let test = self.condition()?;
+                let then = self.next()?;
+                match then.text {
+                    "then" => self.emit_test(&test, test.want),
+                    "begin" => {
+                        // Skip the jump past the body when the test passes
+                        self.emit_test(&test, !test.want);
+                        self.blocks.push(Block::If {
+                            jump: self.rom.len(),
+                            token,
+                        });
+                        self.emit(0x1000);
+                    }
+                    _ => {
+                        return Err(then
+                            .error(format!("expected 'then' or 'begin', found '{}'", then.text)))
+                    }
+                }
+            }
+            "else" => match self.blocks.pop() {
+                Some(Block::If { jump, .. }) => {
+                    let end_jump = self.rom.len();
+                    self.emit(0x1000);
+                    self.patch(jump, self.here());
+                    self.blocks.push(Block::Else {
+                        jump: end_jump,
+                        token,
+                    });
+                }
+                _ => return Err(token.error("'else' without 'if ... begin'")),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If { jump, .. } | Block::Else { jump, .. }) => {
+                    self.patch(jump, self.here());
+                }
+                _ => return Err(token.error("'end' without 'if ... begin'")),
+            },
+            "loop" => self.blocks.push(Block::Loop {
+                start: self.here(),
+                breaks: Vec::new(),
+                token,
+            }),
+            "while" => {
+                let test = self.condition()?;
+                // Skip the jump out of the loop while the test passes
+                self.emit_test(&test, !test.want);
+                let at = self.rom.len();
+                self.emit(0x1000);
+                match self.blocks.iter_mut().rev().find_map(|b| match b {
+                    Block::Loop { breaks, .. } => Some(breaks),
+                    _ => None,
+                }) {
+                    Some(breaks) => breaks.push(at),
+                    None => return Err(token.error("'while' outside a loop")),
+                }
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, breaks, .. }) => {
+                    self.emit(0x1000 | start);
+                    for at in breaks {
+                        self.patch(at, self.here());
+                    }
+                }
+                _ => return Err(token.error("'again' without 'loop'")),
+            },
+            _ if self.register(token).is_ok() => self.register_op(token)?,
+            _ if is_name(token.text) && !self.consts.contains_key(token.text) => {
+                // A bare label is a subroutine call
+                self.emit_addr(0x2000, token, false)?;
+            }
+            _ => {
+                let byte = self.value(token, 0xFF)?;
+                self.rom.push(byte as u8);
+            }
+        }
+        Ok(())
+    }
+
+    /// `i := ...` and `i += vx`
+    fn index_op(&mut self) -> Result<(), AsmError> {
+        let op = self.next()?;
+        match op.text {
+            ":=" => {
+                let rhs = self.next()?;
+                match rhs.text {
+                    "hex" | "bighex" => {
+                        let x = self.next()?;
+                        let x = self.register(x)?;
+                        self.emit(if rhs.text == "hex" { 0xF029 } else { 0xF030 } | x << 8);
+                    }
+                    "long" => {
+                        let target = self.next()?;
+                        self.emit_addr(0xF000, target, true)?;
+                    }
+                    _ => self.emit_addr(0xA000, rhs, false)?,
+                }
+            }
+            "+=" => {
+                let x = self.next()?;
+                let x = self.register(x)?;
+                self.emit(0xF01E | x << 8);
+            }
+            _ => return Err(op.error(format!("unknown operator '{}' for i", op.text))),
+        }
+        Ok(())
+    }
+
+    /// `vx := ...`, `vx += ...` and the other register operators
+    fn register_op(&mut self, token: Token<'a>) -> Result<(), AsmError> {
+        let x = self.register(token)? << 8;
+        let op = self.next()?;
+        let rhs = self.next()?;
+        let reg = self.register(rhs).ok().map(|y| y << 4);
+        let code = match (op.text, reg) {
+            (":=", Some(y)) => 0x8000 | x | y,
+            (":=", None) => match rhs.text {
+                "random" => {
+                    let mask = self.next()?;
+                    0xC000 | x | self.value(mask, 0xFF)?
+                }
+                "delay" => 0xF007 | x,
+                "key" => 0xF00A | x,
+                _ => 0x6000 | x | self.value(rhs, 0xFF)?,
+            },
+            ("+=", Some(y)) => 0x8004 | x | y,
+            ("+=", None) => 0x7000 | x | self.value(rhs, 0xFF)?,
+            ("-=", Some(y)) => 0x8005 | x | y,
+            ("-=", None) => 0x7000 | x | (0x100 - self.value(rhs, 0xFF)?) & 0xFF,
+            ("=-", Some(y)) => 0x8007 | x | y,
+            ("|=", Some(y)) => 0x8001 | x | y,
+            ("&=", Some(y)) => 0x8002 | x | y,
+            ("^=", Some(y)) => 0x8003 | x | y,
+            (">>=", Some(y)) => 0x8006 | x | y,
+            ("<<=", Some(y)) => 0x800E | x | y,
+            ("=-" | "|=" | "&=" | "^=" | ">>=" | "<<=", None) => {
+                return Err(rhs.error(format!("expected a register, found '{}'", rhs.text)))
+            }
+            _ => return Err(op.error(format!("unknown operator '{}'", op.text))),
+        };
+        self.emit(code);
+        Ok(())
+    }
+
+    /// Parse the condition of an `if` or `while`
+    fn condition(&mut self) -> Result<Test, AsmError> {
+        let lhs = self.next()?;
+        let x = self.register(lhs)?;
+        let op = self.next()?;
+        let key = |want| Test {
+            prelude: Vec::new(),
+            x,
+            rhs: None,
+            want,
+        };
+        match op.text {
+            "key" => return Ok(key(true)),
+            "-key" => return Ok(key(false)),
+            _ => (),
+        }
+        let rhs = self.next()?;
+        let rhs = match self.register(rhs) {
+            Ok(y) => Operand::Reg(y),
+            Err(_) => Operand::Byte(self.value(rhs, 0xFF)?),
+        };
+        let compare = |want| Test {
+            prelude: Vec::new(),
+            x,
+            rhs: Some(rhs),
+            want,
+        };
+        let load_vf = |operand| match operand {
+            Operand::Reg(y) => 0x8F00 | y << 4,
+            Operand::Byte(n) => 0x6F00 | n,
+        };
+        // VF ends up 1 when `a >= b`: VF := b, then VF := a - VF
+        let at_least = |a: u16, b: Operand, want: bool| Test {
+            prelude: vec![load_vf(b), 0x8F07 | a << 4],
+            x: 0xF,
+            rhs: Some(Operand::Byte(want as u16)),
+            want: true,
+        };
+        let above = |n: u16| {
+            if n == 0xFF {
+                Err(op.error("comparison against 255 is always false (or true)"))
+            } else {
+                Ok(Operand::Byte(n + 1))
+            }
+        };
+        Ok(match (op.text, rhs) {
+            ("==", _) => compare(true),
+            ("!=", _) => compare(false),
+            (">=", _) => at_least(x, rhs, true),
+            ("<", _) => at_least(x, rhs, false),
+            // x > y is y < x, and against a number x >= n + 1
+            (">", Operand::Reg(y)) => at_least(y, Operand::Reg(x), false),
+            (">", Operand::Byte(n)) => at_least(x, above(n)?, true),
+            ("<=", Operand::Reg(y)) => at_least(y, Operand::Reg(x), true),
+            ("<=", Operand::Byte(n)) => at_least(x, above(n)?, false),
+            _ => return Err(op.error(format!("unknown comparison '{}'", op.text))),
+        })
+    }
+
+    /// Emit a skip that lets the next instruction run only when `test`
+    /// comes out as `want`
+    fn emit_test(&mut self, test: &Test, want: bool) {
+        for &op in &test.prelude {
+            self.emit(op);
+        }
+        let x = test.x << 8;
+        self.emit(match (test.rhs, want) {
+            (Some(Operand::Byte(n)), true) => 0x4000 | x | n,
+            (Some(Operand::Byte(n)), false) => 0x3000 | x | n,
+            (Some(Operand::Reg(y)), true) => 0x9000 | x | y << 4,
+            (Some(Operand::Reg(y)), false) => 0x5000 | x | y << 4,
+            (None, true) => 0xE0A1 | x,
+            (None, false) => 0xE09E | x,
+        });
+    }
+
+    fn finish(mut self) -> Result<Assembled, AsmError> {
+        if let Some(block) = self.blocks.last() {
+            let (Block::If { token, .. } | Block::Else { token, .. } | Block::Loop { token, .. }) =
+                block;
+            return Err(token.error(format!("'{}' is never closed", token.text)));
+        }
+        for fixup in &self.fixups {
+            let addr = self.labels.get(fixup.name.text).ok_or_else(|| {
+                fixup
+                    .name
+                    .error(format!("unknown label '{}'", fixup.name.text))
+            })?;
+            let word = &mut self.rom[fixup.at..fixup.at + 2];
+            let op = u16::from_be_bytes([word[0], word[1]]);
+            let op = if fixup.long { addr } else { op | addr };
+            word.copy_from_slice(&op.to_be_bytes());
+        }
+        Ok(Assembled {
+            rom: self.rom,
+            symbols: self.labels,
+        })
+    }
+}
+
+fn parse_register(text: &str) -> Option<u16> {
+    let digit = text.strip_prefix(['v', 'V'])?;
+    if digit.len() != 1 {
+        return None;
+    }
+    u16::from_str_radix(digit, 16).ok()
+}
+
+fn parse_number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let n = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(bin) = digits.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2).ok()?
+    } else {
+        digits.parse().ok()?
+    };
+    Some(if negative { -n } else { n })
+}
+
+/// Octo names may contain dashes, e.g. `draw-player`
+fn is_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_flow_and_data() {
+        let src = "\
+:alias x v1
+:const SPEED 2
+: main
+  clear
+  x := 0
+  loop
+    x += SPEED
+    if x == 10 then jump done
+    while x != 20
+  again
+  if v0 key begin
+    v2 := 1
+  else
+    v2 := 2
+  end
+: done
+  i := sprite
+  sprite x x 1
+  draw-thing
+  exit
+: draw-thing
+  return
+: sprite
+  0xFF 0b1
+";
+        let out = compile(src).unwrap();
+        let expected: Vec<u8> = [
+            0x00E0u16, 0x6100, 0x7102, 0x410A, 0x121A, 0x4114, 0x1210, 0x1204, 0xE09E, 0x1218,
+            0x6201, 0x121A, 0x6202, 0xA224, 0xD111, 0x2222, 0x00FD, 0x00EE, 0xFF01,
+        ]
+        .iter()
+        .flat_map(|op| op.to_be_bytes())
+        .collect();
+        assert_eq!(out.rom, expected);
+        assert_eq!(out.symbols.get("done"), Some(0x21A));
+        assert_eq!(out.symbols.get("draw-thing"), Some(0x222));
+    }
+
+    #[test]
+    fn comparisons_use_vf() {
+        let out = compile("if v1 < 5 then v2 := 1\nif v1 > v3 then v2 -= 1").unwrap();
+        assert_eq!(
+            out.rom,
+            vec![
+                0x6F, 0x05, 0x8F, 0x17, 0x4F, 0x00, 0x62, 0x01, 0x8F, 0x10, 0x8F, 0x37, 0x4F, 0x00,
+                0x72, 0xFF
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_have_line_and_column() {
+        let err = compile("clear\n  jump nowhere").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 8));
+        assert_eq!(err.message, "unknown label 'nowhere'");
+
+        let err = compile("loop\n  v0 += 1").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 1));
+
+        let err = compile("v0 := 256").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 7));
+    }
+}
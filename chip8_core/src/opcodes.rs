@@ -0,0 +1,435 @@
+use crate::disasm::disassemble_op;
+use crate::{decode, Chip8, Chip8Error, V_REG_SIZE};
+
+/// Static description of one instruction
+///
+/// In `description`, `reads` and `writes` the operands are written as
+/// placeholders: `Vx`/`Vy` for registers and `{n}`, `{nn}`, `{nnn}` for
+/// immediates. `explain` fills them in for a real opcode.
+#[derive(Clone, Copy, Debug)]
+pub struct OpcodeInfo {
+    /// Pattern as given by `Instruction::pattern`, e.g. `8xy4`
+    pub pattern: &'static str,
+    pub description: &'static str,
+    /// Machine state the instruction looks at
+    pub reads: &'static [&'static str],
+    /// Machine state the instruction may change
+    pub writes: &'static [&'static str],
+}
+
+const fn op(
+    pattern: &'static str,
+    description: &'static str,
+    reads: &'static [&'static str],
+    writes: &'static [&'static str],
+) -> OpcodeInfo {
+    OpcodeInfo {
+        pattern,
+        description,
+        reads,
+        writes,
+    }
+}
+
+/// Every instruction the interpreter implements
+pub const OPCODES: &[OpcodeInfo] = &[
+    op("00E0", "Clear the screen", &[], &["screen"]),
+    op(
+        "00EE",
+        "Return from a subroutine to the address on top of the stack",
+        &["stack", "SP"],
+        &["PC", "SP"],
+    ),
+    op(
+        "00FE",
+        "Switch to the 64x32 low resolution mode and clear the screen",
+        &[],
+        &["screen"],
+    ),
+    op(
+        "00FF",
+        "Switch to the SCHIP 128x64 high resolution mode and clear the screen",
+        &[],
+        &["screen"],
+    ),
+    op(
+        "00Cn",
+        "Scroll the screen down {n} pixels (SCHIP)",
+        &[],
+        &["screen"],
+    ),
+    op(
+        "00FB",
+        "Scroll the screen right 4 pixels (SCHIP)",
+        &[],
+        &["screen"],
+    ),
+    op(
+        "00FC",
+        "Scroll the screen left 4 pixels (SCHIP)",
+        &[],
+        &["screen"],
+    ),
+    op(
+        "00FD",
+        "Exit the interpreter, the machine halts (SCHIP)",
+        &[],
+        &["PC"],
+    ),
+    op(
+        "00Dn",
+        "Scroll the screen up {n} pixels (XO-CHIP)",
+        &[],
+        &["screen"],
+    ),
+    op(
+        "0nnn",
+        "Run the machine code routine at {nnn}; not emulated, only custom handlers can run it",
+        &[],
+        &[],
+    ),
+    op("1nnn", "Jump to {nnn}", &[], &["PC"]),
+    op(
+        "2nnn",
+        "Call the subroutine at {nnn}, pushing the return address on the stack",
+        &["PC", "SP"],
+        &["PC", "SP", "stack"],
+    ),
+    op(
+        "3xnn",
+        "Skip the next instruction if Vx equals {nn}",
+        &["Vx"],
+        &["PC"],
+    ),
+    op(
+        "4xnn",
+        "Skip the next instruction if Vx doesn't equal {nn}",
+        &["Vx"],
+        &["PC"],
+    ),
+    op(
+        "5xy0",
+        "Skip the next instruction if Vx equals Vy",
+        &["Vx", "Vy"],
+        &["PC"],
+    ),
+    op(
+        "5xy2",
+        "Store Vx through Vy in memory starting at I, leaving I alone (XO-CHIP)",
+        &["Vx-Vy", "I"],
+        &["memory"],
+    ),
+    op(
+        "5xy3",
+        "Load Vx through Vy from memory starting at I, leaving I alone (XO-CHIP)",
+        &["I", "memory"],
+        &["Vx-Vy"],
+    ),
+    op("6xnn", "Set Vx to {nn}", &[], &["Vx"]),
+    op(
+        "7xnn",
+        "Add {nn} to Vx, wrapping around without setting VF",
+        &["Vx"],
+        &["Vx"],
+    ),
+    op("8xy0", "Set Vx to the value of Vy", &["Vy"], &["Vx"]),
+    op("8xy1", "Set Vx to Vx OR Vy", &["Vx", "Vy"], &["Vx"]),
+    op("8xy2", "Set Vx to Vx AND Vy", &["Vx", "Vy"], &["Vx"]),
+    op("8xy3", "Set Vx to Vx XOR Vy", &["Vx", "Vy"], &["Vx"]),
+    op(
+        "8xy4",
+        "Add Vy to Vx, setting VF to 1 if it carried and 0 if not",
+        &["Vx", "Vy"],
+        &["Vx", "VF"],
+    ),
+    op(
+        "8xy5",
+        "Subtract Vy from Vx, setting VF to 0 if it borrowed and 1 if not",
+        &["Vx", "Vy"],
+        &["Vx", "VF"],
+    ),
+    op(
+        "8xy6",
+        "Shift Vx right by one bit, putting the bit shifted out in VF",
+        &["Vx"],
+        &["Vx", "VF"],
+    ),
+    op(
+        "8xy7",
+        "Set Vx to Vy minus Vx, setting VF to 0 if it borrowed and 1 if not",
+        &["Vx", "Vy"],
+        &["Vx", "VF"],
+    ),
+    op(
+        "8xyE",
+        "Shift Vx left by one bit, putting the bit shifted out in VF",
+        &["Vx"],
+        &["Vx", "VF"],
+    ),
+    op(
+        "9xy0",
+        "Skip the next instruction if Vx doesn't equal Vy",
+        &["Vx", "Vy"],
+        &["PC"],
+    ),
+    op("Annn", "Set I to {nnn}", &[], &["I"]),
+    op("Bnnn", "Jump to {nnn} plus V0", &["V0"], &["PC"]),
+    op(
+        "Cxnn",
+        "Set Vx to a random number ANDed with {nn}",
+        &[],
+        &["Vx"],
+    ),
+    op(
+        "Dxyn",
+        "Draw the {n} row sprite at I at (Vx, Vy), setting VF to 1 if any lit pixel was erased",
+        &["Vx", "Vy", "I", "memory"],
+        &["screen", "VF"],
+    ),
+    op(
+        "Dxy0",
+        "Draw the 16x16 sprite at I at (Vx, Vy), two bytes per row, setting VF to 1 if any lit pixel was erased (SCHIP)",
+        &["Vx", "Vy", "I", "memory"],
+        &["screen", "VF"],
+    ),
+    op(
+        "Ex9E",
+        "Skip the next instruction if the key in Vx is pressed",
+        &["Vx", "keys"],
+        &["PC"],
+    ),
+    op(
+        "ExA1",
+        "Skip the next instruction if the key in Vx isn't pressed",
+        &["Vx", "keys"],
+        &["PC"],
+    ),
+    op(
+        "F000",
+        "Set I to the 16 bit address in the next two bytes (XO-CHIP)",
+        &["memory"],
+        &["I", "PC"],
+    ),
+    op(
+        "Fx01",
+        "Select the drawing planes in bitmask {x} (XO-CHIP)",
+        &[],
+        &["planes"],
+    ),
+    op(
+        "F002",
+        "Load the 16 byte audio pattern from memory at I (XO-CHIP)",
+        &["I", "memory"],
+        &["audio"],
+    ),
+    op(
+        "Fx3A",
+        "Set the audio pitch register to Vx (XO-CHIP)",
+        &["Vx"],
+        &["pitch"],
+    ),
+    op("Fx07", "Set Vx to the delay timer", &["DT"], &["Vx"]),
+    op(
+        "Fx0A",
+        "Wait for a key press and put the key in Vx",
+        &["keys"],
+        &["Vx"],
+    ),
+    op("Fx15", "Set the delay timer to Vx", &["Vx"], &["DT"]),
+    op("Fx18", "Set the sound timer to Vx", &["Vx"], &["ST"]),
+    op("Fx1E", "Add Vx to I", &["Vx", "I"], &["I"]),
+    op(
+        "Fx29",
+        "Point I at the font sprite for the digit in Vx",
+        &["Vx"],
+        &["I"],
+    ),
+    op(
+        "Fx30",
+        "Point I at the large 8x10 font sprite for the digit in Vx (SCHIP)",
+        &["Vx"],
+        &["I"],
+    ),
+    op(
+        "Fx33",
+        "Store the hundreds, tens and ones digits of Vx at I, I+1 and I+2",
+        &["Vx", "I"],
+        &["memory"],
+    ),
+    op(
+        "Fx55",
+        "Store V0 through Vx in memory starting at I",
+        &["V0-Vx", "I"],
+        &["memory"],
+    ),
+    op(
+        "Fx65",
+        "Load V0 through Vx from memory starting at I",
+        &["I", "memory"],
+        &["V0-Vx"],
+    ),
+    op(
+        "Fx75",
+        "Save V0 through Vx to the RPL user flags (SCHIP)",
+        &["V0-Vx"],
+        &["flags"],
+    ),
+    op(
+        "Fx85",
+        "Load V0 through Vx from the RPL user flags (SCHIP)",
+        &["flags"],
+        &["V0-Vx"],
+    ),
+];
+
+/// Look up the table entry for an opcode, `None` if it isn't an instruction
+pub fn info(op: u16) -> Option<&'static OpcodeInfo> {
+    let pattern = decode(op).pattern();
+    OPCODES.iter().find(|info| info.pattern == pattern)
+}
+
+/// Plain English description of an opcode with its operands filled in,
+/// e.g. `0x8AB4` -> "Add VB to VA, setting VF to 1 if it carried and 0 if not"
+pub fn explain(op: u16) -> String {
+    match info(op) {
+        Some(info) => fill(info.description, op),
+        None => format!("0x{:04X} isn't an instruction, it's probably data", op),
+    }
+}
+
+/// Replace operand placeholders with the values from `op`
+fn fill(template: &str, op: u16) -> String {
+    let x = (op & 0x0F00) >> 8;
+    let y = (op & 0x00F0) >> 4;
+    template
+        .replace("Vx", &format!("V{:X}", x))
+        .replace("Vy", &format!("V{:X}", y))
+        .replace("{x}", &x.to_string())
+        .replace("{nnn}", &format!("0x{:03X}", op & 0x0FFF))
+        .replace("{nn}", &format!("0x{:02X}", op & 0x00FF))
+        .replace("{n}", &(op & 0x000F).to_string())
+}
+
+/// What one executed instruction did, from `Chip8::tick_explained`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Explanation {
+    pub pc: u16,
+    pub op: u16,
+    pub description: String,
+    /// State the instruction read, with operands filled in
+    pub reads: Vec<String>,
+    /// Registers that changed as `(name, before, after)`
+    pub changes: Vec<(String, u16, u16)>,
+}
+
+impl Explanation {
+    /// One line summary, e.g.
+    /// `0x200 LD VA, 0x2F: Set VA to 0x2F. Read nothing. VA: 0x00 -> 0x2F`
+    pub fn summary(&self) -> String {
+        let reads = if self.reads.is_empty() {
+            "nothing".to_string()
+        } else {
+            self.reads.join(", ")
+        };
+        let mut out = format!(
+            "0x{:03X} {}: {}. Read {}.",
+            self.pc,
+            disassemble_op(self.op),
+            self.description,
+            reads
+        );
+        for (name, before, after) in &self.changes {
+            out.push_str(&format!(" {}: 0x{:02X} -> 0x{:02X}", name, before, after));
+        }
+        out
+    }
+}
+
+impl Chip8 {
+    /// Run one instruction and describe what it did, for teaching frontends
+    pub fn tick_explained(&mut self) -> Result<Explanation, Chip8Error> {
+        let pc = self.pc;
+        let before = self.register_values();
+        let op = self.fetch()?;
+        self.execute_op(op)?;
+        let after = self.register_values();
+
+        let mut changes: Vec<(String, u16, u16)> = before
+            .iter()
+            .zip(&after)
+            .filter(|(b, a)| b.1 != a.1)
+            .map(|(b, a)| (b.0.clone(), b.1, a.1))
+            .collect();
+        // Only mention the PC when it didn't simply move on to the next instruction
+        if self.pc != pc.wrapping_add(2) {
+            changes.push(("PC".to_string(), pc, self.pc));
+        }
+
+        let reads = match info(op) {
+            Some(info) => info.reads.iter().map(|r| fill(r, op)).collect(),
+            None => Vec::new(),
+        };
+        Ok(Explanation {
+            pc,
+            op,
+            description: explain(op),
+            reads,
+            changes,
+        })
+    }
+
+    fn register_values(&self) -> Vec<(String, u16)> {
+        let mut regs: Vec<(String, u16)> = (0..V_REG_SIZE)
+            .map(|i| (format!("V{:X}", i), self.v_reg[i] as u16))
+            .collect();
+        regs.push(("I".to_string(), self.i_reg));
+        regs.push(("DT".to_string(), self.dt as u16));
+        regs.push(("ST".to_string(), self.st as u16));
+        regs.push(("SP".to_string(), self.sp));
+        regs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explain_ops() {
+        assert_eq!(explain(0x6A2F), "Set VA to 0x2F");
+        assert_eq!(
+            explain(0xD125),
+            "Draw the 5 row sprite at I at (V1, V2), setting VF to 1 if any lit pixel was erased"
+        );
+        assert_eq!(
+            explain(0xF355),
+            "Store V0 through V3 in memory starting at I"
+        );
+        assert!(explain(0x5121).contains("isn't an instruction"));
+        // Every pattern in the table is reachable
+        for info in OPCODES {
+            assert!(!info.description.is_empty(), "{}", info.pattern);
+        }
+    }
+
+    #[test]
+    fn tick_explained() {
+        let mut c8 = Chip8::new();
+        // LD V1, 0xFF; ADD V1, V1; JP 0x200
+        c8.load(&[0x61, 0xFF, 0x81, 0x14, 0x12, 0x00]).unwrap();
+        c8.tick().unwrap();
+        let e = c8.tick_explained().unwrap();
+        assert_eq!(e.reads, vec!["V1", "V1"]);
+        assert_eq!(
+            e.changes,
+            vec![("V1".to_string(), 0xFF, 0xFE), ("VF".to_string(), 0, 1)]
+        );
+        assert_eq!(
+            e.summary(),
+            "0x202 ADD V1, V1: Add V1 to V1, setting VF to 1 if it carried and 0 if not. \
+             Read V1, V1. V1: 0xFF -> 0xFE VF: 0x00 -> 0x01"
+        );
+        let e = c8.tick_explained().unwrap();
+        assert_eq!(e.changes, vec![("PC".to_string(), 0x204, 0x200)]);
+    }
+}
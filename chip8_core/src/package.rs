@@ -0,0 +1,129 @@
+use crate::error::PackageError;
+use serde::{Deserialize, Serialize};
+
+const FORMAT: &str = "chip8-package";
+const VERSION: u32 = 1;
+
+/// Information about a game carried alongside its ROM
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RomInfo {
+    pub title: String,
+    pub author: String,
+    pub description: String,
+    /// Settings the game was written for, frontends may ignore them
+    pub settings: RomSettings,
+}
+
+impl RomInfo {
+    /// The metadata on its own, as a JSON object
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RomSettings {
+    /// Instructions per 60Hz frame
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ticks_per_frame: Option<u32>,
+    /// RAM range holding save data, see `Chip8::set_persistent_region`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub persistent: Option<(u16, u16)>,
+}
+
+/// A ROM bundled with its metadata, stored as JSON with the ROM in hex
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Package {
+    pub info: RomInfo,
+    pub rom: Vec<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PackageFile {
+    format: String,
+    version: u32,
+    #[serde(flatten)]
+    info: RomInfo,
+    rom: String,
+}
+
+impl Package {
+    pub fn new(rom: Vec<u8>) -> Self {
+        Package {
+            info: RomInfo::default(),
+            rom,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let file = PackageFile {
+            format: FORMAT.to_string(),
+            version: VERSION,
+            info: self.info.clone(),
+            rom: self.rom.iter().map(|b| format!("{:02x}", b)).collect(),
+        };
+        serde_json::to_string_pretty(&file).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, PackageError> {
+        let file: PackageFile =
+            serde_json::from_str(json).map_err(|e| PackageError::Parse(e.to_string()))?;
+        if file.format != FORMAT {
+            return Err(PackageError::Parse(format!("not a {} file", FORMAT)));
+        }
+        if file.version > VERSION {
+            return Err(PackageError::Version(file.version));
+        }
+        Ok(Package {
+            info: file.info,
+            rom: parse_hex(&file.rom)?,
+        })
+    }
+}
+
+fn parse_hex(hex: &str) -> Result<Vec<u8>, PackageError> {
+    let digits: Vec<char> = hex.chars().filter(|c| !c.is_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(PackageError::Parse(
+            "ROM hex has an odd number of digits".to_string(),
+        ));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let byte: String = pair.iter().collect();
+            u8::from_str_radix(&byte, 16)
+                .map_err(|_| PackageError::Parse(format!("bad ROM byte '{}'", byte)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut pkg = Package::new(vec![0x00, 0xE0, 0x12, 0x02]);
+        pkg.info.title = "Blank".to_string();
+        pkg.info.settings.persistent = Some((0xE00, 0xE10));
+        let json = pkg.to_json();
+        assert!(json.contains(r#""rom": "00e01202""#));
+        assert_eq!(Package::from_json(&json).unwrap(), pkg);
+    }
+
+    #[test]
+    fn rejects_bad_files() {
+        assert!(matches!(
+            Package::from_json(r#"{"format":"other","version":1,"rom":""}"#),
+            Err(PackageError::Parse(_))
+        ));
+        assert_eq!(
+            Package::from_json(r#"{"format":"chip8-package","version":9,"rom":""}"#),
+            Err(PackageError::Version(9))
+        );
+        assert!(Package::from_json(r#"{"format":"chip8-package","version":1,"rom":"0"}"#).is_err());
+    }
+}
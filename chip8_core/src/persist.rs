@@ -0,0 +1,117 @@
+use crate::{Chip8, LoadError};
+use std::ops::Range;
+
+// Saved data starts with the region's address and length so a save from a
+// different game (or build) isn't restored into the wrong place
+const HEADER_SIZE: usize = 4;
+
+impl Chip8 {
+    /// Declare a RAM range the game wants kept between sessions (high scores etc.)
+    ///
+    /// Writes the interpreter makes into the range (`Fx33`, `Fx55`) mark it
+    /// dirty. Frontends poll `persistent_dirty`, store `export_persistent` and
+    /// feed it back with `import_persistent` after loading the ROM next time.
+    /// The region is per-game and is cleared by `reset`.
+    pub fn set_persistent_region(&mut self, range: Range<u16>) -> Result<(), LoadError> {
+        if range.end as usize > self.ram.len() || range.is_empty() {
+            return Err(LoadError::SegmentOutOfRange {
+                addr: range.start,
+                size: range.len(),
+            });
+        }
+        self.persistent = Some(range);
+        self.persistent_dirty = false;
+        Ok(())
+    }
+
+    pub fn persistent_region(&self) -> Option<Range<u16>> {
+        self.persistent.clone()
+    }
+
+    /// True if the game wrote to its persistent region since the last export
+    pub fn persistent_dirty(&self) -> bool {
+        self.persistent_dirty
+    }
+
+    /// Serialize just the persistent region and clear the dirty flag
+    pub fn export_persistent(&mut self) -> Option<Vec<u8>> {
+        let range = self.persistent.clone()?;
+        let mut out = Vec::with_capacity(HEADER_SIZE + range.len());
+        out.extend_from_slice(&range.start.to_be_bytes());
+        out.extend_from_slice(&(range.len() as u16).to_be_bytes());
+        out.extend_from_slice(&self.ram[range.start as usize..range.end as usize]);
+        self.persistent_dirty = false;
+        Some(out)
+    }
+
+    /// Restore data produced by `export_persistent` into the declared region
+    pub fn import_persistent(&mut self, data: &[u8]) -> Result<(), LoadError> {
+        let range = self
+            .persistent
+            .clone()
+            .ok_or(LoadError::PersistentMismatch)?;
+        if data.len() < HEADER_SIZE {
+            return Err(LoadError::PersistentMismatch);
+        }
+        let addr = u16::from_be_bytes([data[0], data[1]]);
+        let len = u16::from_be_bytes([data[2], data[3]]) as usize;
+        let bytes = &data[HEADER_SIZE..];
+        if addr != range.start || len != range.len() || bytes.len() != len {
+            return Err(LoadError::PersistentMismatch);
+        }
+        self.ram[range.start as usize..range.end as usize].copy_from_slice(bytes);
+        self.persistent_dirty = false;
+        Ok(())
+    }
+
+    /// Called for every interpreter write to RAM
+    pub(crate) fn mark_persistent(&mut self, addr: u16) {
+        if let Some(range) = &self.persistent {
+            if range.contains(&addr) {
+                self.persistent_dirty = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dirty_tracking_and_round_trip() {
+        let mut c8 = Chip8::new();
+        c8.set_persistent_region(0xE00..0xE04).unwrap();
+        // LD V0, 7; LD I, 0xE01; LD [I], V0
+        c8.load(&[0x60, 0x07, 0xAE, 0x01, 0xF0, 0x55]).unwrap();
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        assert!(!c8.persistent_dirty());
+        c8.tick().unwrap();
+        assert!(c8.persistent_dirty());
+
+        let saved = c8.export_persistent().unwrap();
+        assert_eq!(saved, vec![0x0E, 0x00, 0x00, 0x04, 0, 7, 0, 0]);
+        assert!(!c8.persistent_dirty());
+
+        let mut fresh = Chip8::new();
+        fresh.set_persistent_region(0xE00..0xE04).unwrap();
+        fresh.import_persistent(&saved).unwrap();
+        assert_eq!(fresh.ram()[0xE01], 7);
+    }
+
+    #[test]
+    fn import_rejects_other_regions() {
+        let mut c8 = Chip8::new();
+        assert_eq!(
+            c8.import_persistent(&[0x0E, 0x00, 0x00, 0x01, 9]),
+            Err(LoadError::PersistentMismatch)
+        );
+        c8.set_persistent_region(0xE00..0xE02).unwrap();
+        assert_eq!(
+            c8.import_persistent(&[0x0E, 0x00, 0x00, 0x01, 9]),
+            Err(LoadError::PersistentMismatch)
+        );
+        assert!(c8.set_persistent_region(0xFFF..0x1001).is_err());
+    }
+}
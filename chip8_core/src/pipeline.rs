@@ -0,0 +1,75 @@
+use crate::{Chip8, Chip8Error, Instruction};
+
+impl Chip8 {
+    /// Pipeline stage 2: turn an opcode into an `Instruction`
+    ///
+    /// Decoding is pure, it doesn't look at or change the machine.
+    pub fn decode(op: u16) -> Instruction {
+        crate::decode(op)
+    }
+
+    /// Pipeline stage 3: run an instruction
+    ///
+    /// PC should already point past the instruction (as `fetch` leaves it)
+    /// since jumps, calls and skips are relative to that. This is the same
+    /// path `tick` takes, so the instruction is counted, profiled and seen
+    /// by hooks, and scheduled input is applied first.
+    ///
+    /// Fields that don't fit their opcode, such as a register past VF or a
+    /// 16 bit jump target, fail with `InvalidInstruction` before anything
+    /// runs. So does `Unknown` holding an opcode that decodes to something
+    /// else.
+    pub fn execute(&mut self, instr: Instruction) -> Result<(), Chip8Error> {
+        let op = instr.op();
+        if crate::decode(op) != instr {
+            return Err(Chip8Error::InvalidInstruction {
+                instr,
+                pc: self.op_pc(),
+            });
+        }
+        self.execute_op(op)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_match_tick() {
+        let rom = [0x6A, 0x02, 0x7A, 0x03, 0x22, 0x00];
+        let mut ticked = Chip8::new();
+        let mut staged = Chip8::new();
+        ticked.load(&rom).unwrap();
+        staged.load(&rom).unwrap();
+
+        for _ in 0..3 {
+            ticked.tick().unwrap();
+            let op = staged.fetch().unwrap();
+            let instr = Chip8::decode(op);
+            staged.execute(instr).unwrap();
+        }
+        assert_eq!(staged.v_reg(), ticked.v_reg());
+        assert_eq!(staged.pc(), ticked.pc());
+        assert_eq!(staged.pc(), 0x200);
+        assert_eq!(staged.instruction_count(), ticked.instruction_count());
+    }
+
+    #[test]
+    fn out_of_range_fields() {
+        let mut c8 = Chip8::new();
+        for instr in [
+            Instruction::Jump { nnn: 0x1234 },
+            Instruction::Add { x: 0x14, y: 0 },
+            Instruction::Draw { x: 0, y: 0, n: 16 },
+            Instruction::SelectPlanes { n: 16 },
+            Instruction::Unknown(0x00E0),
+        ] {
+            assert_eq!(
+                c8.execute(instr),
+                Err(Chip8Error::InvalidInstruction { instr, pc: 0x1FE })
+            );
+        }
+        assert_eq!((c8.pc(), c8.instruction_count()), (0x200, 0));
+    }
+}
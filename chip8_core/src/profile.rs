@@ -0,0 +1,121 @@
+use crate::{decode, Chip8};
+use std::collections::BTreeMap;
+
+/// Instruction counts gathered while the profiler is enabled
+#[derive(Clone, Default)]
+pub struct Profile {
+    total: u64,
+    by_addr: BTreeMap<u16, u64>,
+    by_pattern: BTreeMap<String, u64>,
+    // Counts per call stack, each stack given as routine entry addresses
+    by_stack: BTreeMap<Vec<u16>, u64>,
+    call_stack: Vec<u16>,
+}
+
+impl Profile {
+    /// Instructions executed while profiling
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Execution counts per address, busiest first
+    pub fn hot_addresses(&self) -> Vec<(u16, u64)> {
+        sorted(self.by_addr.iter().map(|(addr, count)| (*addr, *count)))
+    }
+
+    /// Execution counts per opcode pattern (e.g. `Dxyn`), busiest first
+    pub fn hot_opcodes(&self) -> Vec<(String, u64)> {
+        sorted(
+            self.by_pattern
+                .iter()
+                .map(|(pat, count)| (pat.clone(), *count)),
+        )
+    }
+
+    /// Counts per call stack in the folded format used by flamegraph tools,
+    /// `root;caller;callee count` per line, with routines named by `name`
+    pub fn folded(&self, name: impl Fn(u16) -> String) -> String {
+        let mut out = String::new();
+        for (stack, count) in &self.by_stack {
+            let frames: Vec<String> = stack.iter().map(|addr| name(*addr)).collect();
+            out.push_str(&format!("{} {}\n", frames.join(";"), count));
+        }
+        out
+    }
+
+    fn record(&mut self, pc: u16, op: u16) {
+        self.total += 1;
+        *self.by_addr.entry(pc).or_default() += 1;
+        *self.by_pattern.entry(decode(op).pattern()).or_default() += 1;
+        *self.by_stack.entry(self.call_stack.clone()).or_default() += 1;
+
+        match op & 0xF000 {
+            0x2000 => self.call_stack.push(op & 0xFFF),
+            // Keep the root even if the game returns from it
+            _ if op == 0x00EE && self.call_stack.len() > 1 => {
+                self.call_stack.pop();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Sort counts busiest first, ties in key order
+fn sorted<K: Ord>(counts: impl Iterator<Item = (K, u64)>) -> Vec<(K, u64)> {
+    let mut counts: Vec<(K, u64)> = counts.collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    counts
+}
+
+impl Chip8 {
+    /// Start or stop counting executed instructions for `profile`
+    ///
+    /// Starting clears any earlier results. Call stacks are rooted at the
+    /// current PC.
+    pub fn enable_profiler(&mut self, enable: bool) {
+        self.profiler = if enable {
+            Some(Profile {
+                call_stack: vec![self.pc],
+                ..Profile::default()
+            })
+        } else {
+            None
+        };
+    }
+
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profiler.as_ref()
+    }
+
+    pub(crate) fn profile_op(&mut self, op: u16) {
+        let pc = self.op_pc();
+        if let Some(profile) = &mut self.profiler {
+            profile.record(pc, op);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_and_stacks() {
+        let mut c8 = Chip8::new();
+        // CALL 0x206; JP 0x200; NOP; ADD V0, 1; RET
+        c8.load(&[0x22, 0x06, 0x12, 0x00, 0x00, 0x00, 0x70, 0x01, 0x00, 0xEE])
+            .unwrap();
+        c8.enable_profiler(true);
+        for _ in 0..8 {
+            c8.tick().unwrap();
+        }
+        let profile = c8.profile().unwrap();
+        assert_eq!(profile.total(), 8);
+        assert_eq!(profile.hot_addresses()[0], (0x200, 2));
+        assert_eq!(profile.hot_opcodes()[0], ("00EE".to_string(), 2));
+        assert_eq!(
+            profile.folded(|addr| format!("{:03X}", addr)),
+            "200 4\n200;206 4\n"
+        );
+    }
+}
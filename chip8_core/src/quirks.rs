@@ -0,0 +1,159 @@
+use crate::Chip8;
+
+/// Behaviours that differ between CHIP-8 interpreters
+///
+/// Games are written against the interpreter of their era, so the wrong
+/// setting can break them. The default has every quirk off, which is how
+/// this interpreter always behaved: shifts work on Vx in place, Fx55/Fx65
+/// leave I alone, Bnnn adds V0, sprites wrap around the screen edges, the
+/// logic ops keep VF and scrolls move the full distance. That mix doesn't
+/// match any one historical interpreter, `Variant::config` has their quirks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Quirks {
+    /// 8xy6/8xyE shift Vy into Vx instead of shifting Vx in place (COSMAC VIP)
+    pub shift_vy: bool,
+    /// Fx55/Fx65 leave I pointing past the last register (COSMAC VIP)
+    pub memory_increment: bool,
+    /// Bnnn jumps to nnn + Vx, with x the top nibble of nnn, instead of
+    /// nnn + V0 (CHIP-48 and SCHIP's BXNN)
+    pub jump_vx: bool,
+    /// Sprites are cut off at the screen edge instead of wrapping around.
+    /// The starting position always wraps.
+    pub clip_sprites: bool,
+    /// 8xy1/8xy2/8xy3 reset VF to 0 (COSMAC VIP)
+    pub vf_reset: bool,
+    /// In lo-res mode, 00Cn/00FB/00FC scroll by half as many pixels, as
+    /// SCHIP 1.1 did by scrolling its 128x64 buffer. Odd amounts round down.
+    pub half_pixel_scroll: bool,
+}
+
+impl Chip8 {
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Change quirks on a running machine, e.g. after identifying the ROM
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Variant};
+
+    fn run(quirks: Quirks, rom: &[u8], ticks: usize) -> Chip8 {
+        let mut c8 = Chip8::with_config(Config {
+            quirks,
+            ..Config::default()
+        });
+        c8.load(rom).unwrap();
+        for _ in 0..ticks {
+            c8.tick().unwrap();
+        }
+        c8
+    }
+
+    #[test]
+    fn shift_vy() {
+        // LD V0, 1; LD V1, 4; SHR V0, V1
+        let rom = [0x60, 0x01, 0x61, 0x04, 0x80, 0x16];
+        let c8 = run(Quirks::default(), &rom, 3);
+        assert_eq!((c8.v_reg()[0], c8.v_reg()[0xF]), (0, 1));
+        let quirks = Quirks {
+            shift_vy: true,
+            ..Quirks::default()
+        };
+        let c8 = run(quirks, &rom, 3);
+        assert_eq!((c8.v_reg()[0], c8.v_reg()[0xF]), (2, 0));
+    }
+
+    #[test]
+    fn memory_increment() {
+        // LD I, 0x300; LD [I], V2
+        let rom = [0xA3, 0x00, 0xF2, 0x55];
+        assert_eq!(run(Quirks::default(), &rom, 2).i_reg(), 0x300);
+        let quirks = Quirks {
+            memory_increment: true,
+            ..Quirks::default()
+        };
+        assert_eq!(run(quirks, &rom, 2).i_reg(), 0x303);
+    }
+
+    #[test]
+    fn jump_vx() {
+        // LD V0, 2; LD V3, 4; JP V0, 0x300
+        let rom = [0x60, 0x02, 0x63, 0x04, 0xB3, 0x00];
+        assert_eq!(run(Quirks::default(), &rom, 3).pc(), 0x302);
+        let quirks = Quirks {
+            jump_vx: true,
+            ..Quirks::default()
+        };
+        assert_eq!(run(quirks, &rom, 3).pc(), 0x304);
+
+        // CHIP-48 and SCHIP presets pick it up
+        for variant in [Variant::Chip48, Variant::SuperChip] {
+            let mut c8 = Chip8::with_variant(variant);
+            c8.load(&rom).unwrap();
+            for _ in 0..3 {
+                c8.tick().unwrap();
+            }
+            assert_eq!(c8.pc(), 0x304);
+        }
+    }
+
+    #[test]
+    fn clip_sprites() {
+        // LD V0, 62; DRW V0, V1, 1 - top of the "0" glyph, 4 pixels wide
+        let rom = [0x60, 0x3E, 0xD0, 0x11];
+        let c8 = run(Quirks::default(), &rom, 2);
+        assert!(c8.get_display()[0]);
+        let quirks = Quirks {
+            clip_sprites: true,
+            ..Quirks::default()
+        };
+        let c8 = run(quirks, &rom, 2);
+        assert!(!c8.get_display()[0]);
+        assert!(c8.get_display()[63]);
+
+        // LD V1, 30; DRW V0, V1, 5 - the bottom rows of the glyph go off
+        // screen, and the start wraps: LD V0, 66 draws from x = 2
+        let rom = [0x61, 0x1E, 0xD0, 0x15, 0x60, 0x42, 0xD0, 0x15];
+        let c8 = run(Quirks::default(), &rom, 2);
+        assert!(c8.get_display()[0]);
+        let mut c8 = run(quirks, &rom, 2);
+        assert!(!c8.get_display()[0]);
+        assert!(c8.get_display()[64 * 31]);
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        assert!(c8.get_display()[5 + 64 * 30]);
+    }
+
+    #[test]
+    fn vf_reset() {
+        let quirks = Quirks {
+            vf_reset: true,
+            ..Quirks::default()
+        };
+        // LD VF, 1; then OR, AND or XOR V0, V1
+        for op in [0x11, 0x12, 0x13] {
+            let rom = [0x6F, 0x01, 0x80, op];
+            assert_eq!(run(Quirks::default(), &rom, 2).v_reg()[0xF], 1);
+            assert_eq!(run(quirks, &rom, 2).v_reg()[0xF], 0);
+        }
+        assert!(Variant::CosmacVip.quirks().vf_reset);
+    }
+
+    #[test]
+    fn set_at_runtime() {
+        let mut c8 = Chip8::new();
+        let quirks = Quirks {
+            jump_vx: true,
+            ..Quirks::default()
+        };
+        c8.set_quirks(quirks);
+        c8.reset();
+        assert_eq!(c8.quirks(), quirks);
+    }
+}
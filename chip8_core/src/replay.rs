@@ -0,0 +1,150 @@
+use crate::{At, Chip8, ReplayError};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A key press or release, stamped with how many instructions had run
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KeyEvent {
+    pub instruction: u64,
+    pub key: u8,
+    pub pressed: bool,
+}
+
+/// Keypad input logged by `record_input`, oldest first
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Recording {
+    pub events: Vec<KeyEvent>,
+}
+
+impl Recording {
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Schedule every event on `chip8`, which should be freshly reset with the
+    /// same ROM and config as the recorded session
+    ///
+    /// Running the same instructions then reproduces the session exactly, as
+    /// long as both use the same `RND` seed (see `Chip8::with_seed`). A
+    /// recording loaded from elsewhere can name keys past 0xF, so nothing is
+    /// scheduled unless every key is on the keypad.
+    pub fn replay(&self, chip8: &mut Chip8) -> Result<(), ReplayError> {
+        if let Some(event) = self.events.iter().find(|event| event.key > 0xF) {
+            return Err(ReplayError {
+                instruction: event.instruction,
+                key: event.key,
+            });
+        }
+        for event in &self.events {
+            chip8.schedule_key(
+                At::Instruction(event.instruction),
+                event.key as usize,
+                event.pressed,
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Chip8 {
+    /// Start or stop logging key changes for `take_recording`
+    pub fn record_input(&mut self, enable: bool) {
+        self.recording = if enable {
+            Some(Recording::default())
+        } else {
+            None
+        };
+    }
+
+    /// Input logged since recording started or was last taken
+    pub fn take_recording(&mut self) -> Recording {
+        match &mut self.recording {
+            Some(recording) => std::mem::take(recording),
+            None => Recording::default(),
+        }
+    }
+
+    pub(crate) fn log_key(&mut self, idx: usize, pressed: bool) {
+        if let Some(recording) = &mut self.recording {
+            recording.events.push(KeyEvent {
+                instruction: self.instructions,
+                key: idx as u8,
+                pressed,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // LD V1, K; ADD V2, V1; LD V1, K; ADD V2, V1; JP 0x208
+    const ROM: [u8; 10] = [0xF1, 0x0A, 0x82, 0x14, 0xF1, 0x0A, 0x82, 0x14, 0x12, 0x08];
+
+    #[test]
+    fn replay_reproduces_session() {
+        let mut c8 = Chip8::new();
+        c8.load(&ROM).unwrap();
+        c8.record_input(true);
+        for i in 0..40 {
+            match i {
+                5 => c8.keypress(0x3, true),
+                6 => c8.keypress(0x3, false),
+                20 => c8.keypress(0xC, true),
+                _ => c8.keypress(0xC, i > 20),
+            }
+            c8.tick().unwrap();
+        }
+        let recording = c8.take_recording();
+        // Repeats of an unchanged key aren't logged
+        assert_eq!(recording.len(), 3);
+        assert_eq!(c8.v_reg()[2], 0xF);
+
+        let mut replay = Chip8::new();
+        replay.load(&ROM).unwrap();
+        recording.replay(&mut replay).unwrap();
+        for _ in 0..40 {
+            replay.tick().unwrap();
+        }
+        assert!(replay.snapshot() == c8.snapshot());
+    }
+
+    #[test]
+    fn replay_rejects_bad_keys() {
+        let mut recording = Recording::default();
+        for key in [0x3, 0x10] {
+            recording.events.push(KeyEvent {
+                instruction: 4,
+                key,
+                pressed: true,
+            });
+        }
+        let mut c8 = Chip8::new();
+        assert_eq!(
+            recording.replay(&mut c8),
+            Err(ReplayError {
+                instruction: 4,
+                key: 0x10
+            })
+        );
+        assert_eq!(c8.scheduled_keys(), 0);
+    }
+
+    #[test]
+    fn not_recording_by_default() {
+        let mut c8 = Chip8::new();
+        c8.keypress(0x1, true);
+        assert!(c8.take_recording().is_empty());
+        c8.record_input(true);
+        c8.keypress(0x1, false);
+        c8.reset();
+        assert!(c8.take_recording().is_empty());
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{Chip8, Chip8State};
+use std::collections::VecDeque;
+
+/// Ring buffer of recent states for rewinding gameplay
+///
+/// Call `record` once per frame (after `tick_timers`); every `interval`
+/// frames a snapshot is kept, and once `capacity` of them are held the
+/// oldest is dropped. At 60 frames a second, `Rewind::new(600, 6)` keeps
+/// the last minute at a tenth of a second resolution.
+#[derive(Clone, Debug)]
+pub struct Rewind {
+    states: VecDeque<Chip8State>,
+    capacity: usize,
+    interval: u32,
+    since_last: u32,
+}
+
+impl Rewind {
+    pub fn new(capacity: usize, interval: u32) -> Self {
+        Rewind {
+            states: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+            interval: interval.max(1),
+            since_last: 0,
+        }
+    }
+
+    /// Note that a frame went by, snapshotting the machine if one is due
+    pub fn record(&mut self, chip8: &Chip8) {
+        if self.since_last > 0 {
+            self.since_last -= 1;
+            return;
+        }
+        if self.states.len() == self.capacity {
+            self.states.pop_front();
+        }
+        self.states.push_back(chip8.snapshot());
+        self.since_last = self.interval - 1;
+    }
+
+    /// Go back at least `frames` frames, or as far as the buffer reaches
+    ///
+    /// Snapshots newer than the one restored are discarded. Returns false
+    /// if there was nothing to go back to, or the snapshots came from a
+    /// machine with a different memory or stack size.
+    pub fn rewind(&mut self, frames: u64, chip8: &mut Chip8) -> bool {
+        let target = chip8.frame_count().saturating_sub(frames);
+        while self.states.len() > 1 && self.states.back().is_some_and(|s| s.frames > target) {
+            self.states.pop_back();
+        }
+        match self.states.back() {
+            Some(state) if chip8.restore(state).is_ok() => {
+                self.since_last = self.interval - 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Snapshots held
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// Forget everything, e.g. after loading another ROM
+    pub fn clear(&mut self) {
+        self.states.clear();
+        self.since_last = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counter() -> Chip8 {
+        let mut c8 = Chip8::new();
+        // ADD V0, 1; JP 0x200
+        c8.load(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+        c8
+    }
+
+    #[test]
+    fn rewinds_to_earlier_frame() {
+        let mut c8 = counter();
+        let mut rewind = Rewind::new(100, 2);
+        for _ in 0..10 {
+            c8.run_frame(2).unwrap();
+            rewind.record(&c8);
+        }
+        // Frames 1, 3, 5, 7 and 9 were kept
+        assert_eq!(rewind.len(), 5);
+        assert_eq!(c8.frame_count(), 10);
+
+        assert!(rewind.rewind(4, &mut c8));
+        assert_eq!(c8.frame_count(), 5);
+        assert_eq!(c8.v_reg()[0], 5);
+        assert_eq!(rewind.len(), 3);
+    }
+
+    #[test]
+    fn capacity_drops_oldest() {
+        let mut c8 = counter();
+        let mut rewind = Rewind::new(3, 1);
+        assert!(!rewind.rewind(1, &mut c8));
+        for _ in 0..10 {
+            c8.run_frame(2).unwrap();
+            rewind.record(&c8);
+        }
+        assert_eq!(rewind.len(), 3);
+        // Asking for more than is held goes back to the oldest
+        assert!(rewind.rewind(100, &mut c8));
+        assert_eq!(c8.frame_count(), 8);
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{Chip8, FONT_END};
+
+/// Where `RND` gets its numbers from, see `Chip8::set_random_source`
+///
+/// Implemented for any cloneable `FnMut() -> u8` closure, so a counter or a
+/// fixed sequence is one line in a test.
+pub trait RandomSource: RandomClone {
+    fn next_byte(&mut self) -> u8;
+}
+
+clone_box!(RandomClone, RandomSource);
+
+impl<F: FnMut() -> u8 + Clone + 'static> RandomSource for F {
+    fn next_byte(&mut self) -> u8 {
+        self()
+    }
+}
+
+/// xorshift32, good enough for games and much smaller than pulling in rand
+#[derive(Clone, Debug)]
+pub(crate) struct Rng(u32);
+
+impl Default for Rng {
+    fn default() -> Self {
+        Rng::new(0x2545_F491)
+    }
+}
+
+impl Rng {
+    pub(crate) fn new(seed: u32) -> Self {
+        // Zero would only ever produce zero
+        Rng(if seed == 0 { 0x2545_F491 } else { seed })
+    }
+
+    /// Generator for `Config::rng_seed`, the default seed if there's none
+    pub(crate) fn from_seed(seed: Option<u64>) -> Self {
+        match seed {
+            Some(seed) => Rng::new((seed ^ seed >> 32) as u32),
+            None => Rng::default(),
+        }
+    }
+
+    pub(crate) fn state(&self) -> u32 {
+        self.0
+    }
+
+    fn next(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+}
+
+impl Chip8 {
+    /// Fill RAM (past the fonts), V registers and the screen with garbage when
+    /// a power-on seed is configured. The same seed gives the same garbage.
+    pub(crate) fn power_on(&mut self) {
+        let mut rng = match self.power_on_seed {
+            Some(seed) => Rng::new(seed),
+            None => return,
+        };
+        for byte in &mut self.ram[FONT_END..] {
+            *byte = (rng.next() >> 24) as u8;
+        }
+        for reg in &mut self.v_reg {
+            *reg = (rng.next() >> 24) as u8;
+        }
+        for y in 0..self.screen.height() {
+            for x in 0..self.screen.width() {
+                self.screen.set(x, y, rng.next() & 0x8000_0000 != 0);
+            }
+        }
+    }
+
+    /// Reseed `RND`, or go back to the rand crate with `None`
+    ///
+    /// Also sets the seed `reset` starts from again.
+    pub fn set_rng_seed(&mut self, seed: Option<u64>) {
+        self.rng_seed = seed;
+        self.rng = Rng::from_seed(seed);
+    }
+
+    /// Take `RND` numbers from `source` instead of the seed or rand crate
+    ///
+    /// The source stays installed across `reset`; its state isn't part of
+    /// `snapshot`.
+    pub fn set_random_source(&mut self, source: Box<dyn RandomSource>) {
+        self.random_source = Some(source);
+    }
+
+    /// Go back to the seeded generator or the rand crate
+    pub fn clear_random_source(&mut self) {
+        self.random_source = None;
+    }
+
+    /// Random byte for RND
+    pub(crate) fn random_byte(&mut self) -> u8 {
+        if let Some(source) = &mut self.random_source {
+            return source.next_byte();
+        }
+        #[cfg(feature = "rand")]
+        if self.rng_seed.is_none() {
+            return rand::random();
+        }
+        // The high bits of xorshift are the better mixed ones
+        (self.rng.next() >> 24) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    #[test]
+    fn power_on_garbage() {
+        let config = Config {
+            power_on_seed: Some(7),
+            ..Config::default()
+        };
+        let mut c8 = Chip8::with_config(config);
+        assert_eq!(&c8.ram()[..crate::FONTSET_SIZE], &crate::FONTSET[..]);
+        assert!(c8.ram()[FONT_END..].iter().any(|b| *b != 0));
+        assert!(c8.get_display().iter().any(|px| *px));
+
+        // Same seed, same garbage, and reset brings it back
+        let snapshot = c8.ram().to_vec();
+        assert_eq!(Chip8::with_config(config).ram(), &snapshot[..]);
+        c8.load(&[0x12, 0x00]).unwrap();
+        c8.reset();
+        assert_eq!(c8.ram(), &snapshot[..]);
+
+        assert!(Chip8::new().ram()[FONT_END..].iter().all(|b| *b == 0));
+    }
+
+    #[cfg(not(feature = "rand"))]
+    #[test]
+    fn builtin_rng_varies() {
+        let mut c8 = Chip8::new();
+        let bytes: Vec<u8> = (0..16).map(|_| c8.random_byte()).collect();
+        assert!(bytes.iter().any(|b| *b != bytes[0]));
+    }
+
+    #[test]
+    fn custom_random_source() {
+        let mut c8 = Chip8::new();
+        // RND V0, 0xFF; RND V1, 0x0F; RND V2, 0xFF
+        c8.load(&[0xC0, 0xFF, 0xC1, 0x0F, 0xC2, 0xFF]).unwrap();
+        let mut n = 0x40u8;
+        c8.set_random_source(Box::new(move || {
+            n += 1;
+            n
+        }));
+        for _ in 0..3 {
+            c8.tick().unwrap();
+        }
+        assert_eq!(&c8.v_reg()[..3], &[0x41, 0x02, 0x43]);
+
+        c8.clear_random_source();
+        assert!(c8.random_source.is_none());
+    }
+
+    #[test]
+    fn seeded_rnd_repeats() {
+        // RND V0, 0xFF; JP 0x200
+        let rom = [0xC0, 0xFF, 0x12, 0x00];
+        let run = |c8: &mut Chip8| -> Vec<u8> {
+            (0..8)
+                .map(|_| {
+                    c8.tick().unwrap();
+                    c8.tick().unwrap();
+                    c8.v_reg()[0]
+                })
+                .collect()
+        };
+        let mut a = Chip8::with_seed(42);
+        a.load(&rom).unwrap();
+        let first = run(&mut a);
+        let mut b = Chip8::with_seed(42);
+        b.load(&rom).unwrap();
+        assert_eq!(run(&mut b), first);
+
+        // Reset starts the sequence over
+        a.reset();
+        a.load(&rom).unwrap();
+        assert_eq!(run(&mut a), first);
+
+        b.set_rng_seed(Some(43));
+        b.reset();
+        b.load(&rom).unwrap();
+        assert_ne!(run(&mut b), first);
+    }
+}
@@ -0,0 +1,258 @@
+use crate::error::{DatabaseError, LoadError};
+use crate::hash::sha1_hex;
+use crate::{Chip8, Config, Quirks, Variant};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// ROMs recognised by hash, read from the CHIP-8 community database's
+/// `programs.json`
+///
+/// Each ROM there lists the platforms it runs on, best first, the quirks it
+/// needs where they differ from the platform's, and how many instructions
+/// to run per frame. Use `lookup` to identify a ROM, or `Chip8::load_known`
+/// to build a machine set up for it. ROMs that only run on platforms this
+/// interpreter lacks (CHIP-8X) are left out.
+#[derive(Clone, Debug, Default)]
+pub struct RomDatabase {
+    roms: HashMap<String, KnownRom>,
+}
+
+/// What the database says about one ROM
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KnownRom {
+    pub title: String,
+    /// The database's name for the platform picked, e.g. `superchip`
+    pub platform: String,
+    pub variant: Variant,
+    /// The variant's quirks with the ROM's own exceptions applied
+    pub quirks: Quirks,
+    /// Instructions per 60Hz frame, when the ROM needs a particular speed
+    pub ticks_per_frame: Option<u32>,
+}
+
+impl KnownRom {
+    /// The variant's config with the ROM's quirks
+    pub fn config(&self) -> Config {
+        Config {
+            quirks: self.quirks,
+            ..self.variant.config()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Program {
+    title: String,
+    #[serde(default)]
+    roms: BTreeMap<String, Rom>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Rom {
+    #[serde(default)]
+    platforms: Vec<String>,
+    tickrate: Option<u32>,
+    #[serde(default)]
+    quirky_platforms: BTreeMap<String, DbQuirks>,
+}
+
+/// The database's quirk names, each one `true` where the ROM needs the
+/// behaviour the name describes
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DbQuirks {
+    /// 8xy6/8xyE shift Vx in place
+    shift: Option<bool>,
+    /// Fx55/Fx65 add x to I (CHIP-48), treated like leaving I alone
+    memory_increment_by_x: Option<bool>,
+    memory_leave_i_unchanged: Option<bool>,
+    /// Sprites wrap around the screen edge
+    wrap: Option<bool>,
+    /// Bxnn jumps to xnn + Vx
+    jump: Option<bool>,
+    /// 8xy1/8xy2/8xy3 reset VF
+    logic: Option<bool>,
+}
+
+impl DbQuirks {
+    fn apply(self, quirks: &mut Quirks) {
+        if let Some(shift) = self.shift {
+            quirks.shift_vy = !shift;
+        }
+        if let Some(leave) = self.memory_leave_i_unchanged {
+            quirks.memory_increment = !leave;
+        }
+        if self.memory_increment_by_x == Some(true) {
+            quirks.memory_increment = false;
+        }
+        if let Some(wrap) = self.wrap {
+            quirks.clip_sprites = !wrap;
+        }
+        if let Some(jump) = self.jump {
+            quirks.jump_vx = jump;
+        }
+        if let Some(logic) = self.logic {
+            quirks.vf_reset = logic;
+        }
+    }
+}
+
+/// The variant for one of the database's platform names, with the quirks
+/// the database assumes for it
+fn platform(name: &str) -> Option<(Variant, Quirks)> {
+    let variant = match name {
+        "originalChip8" | "hybridVIP" => Variant::CosmacVip,
+        // Modern CHIP-8 is the VIP's behaviour without the VF reset
+        "modernChip8" => {
+            let quirks = Quirks {
+                vf_reset: false,
+                ..Variant::CosmacVip.quirks()
+            };
+            return Some((Variant::Chip8, quirks));
+        }
+        "chip48" => Variant::Chip48,
+        "superchip1" | "superchip" => Variant::SuperChip,
+        "xochip" => Variant::XoChip,
+        #[cfg(feature = "megachip")]
+        "megachip8" => Variant::MegaChip,
+        _ => return None,
+    };
+    Some((variant, variant.quirks()))
+}
+
+impl RomDatabase {
+    /// Read the database's `programs.json`
+    pub fn from_json(programs: &str) -> Result<Self, DatabaseError> {
+        let programs: Vec<Program> =
+            serde_json::from_str(programs).map_err(|e| DatabaseError::Parse(e.to_string()))?;
+        let mut roms = HashMap::new();
+        for program in programs {
+            for (hash, rom) in program.roms {
+                let Some((name, variant, mut quirks)) = rom
+                    .platforms
+                    .iter()
+                    .find_map(|name| platform(name).map(|(v, q)| (name, v, q)))
+                else {
+                    continue;
+                };
+                if let Some(exceptions) = rom.quirky_platforms.get(name) {
+                    exceptions.apply(&mut quirks);
+                }
+                let known = KnownRom {
+                    title: program.title.clone(),
+                    platform: name.clone(),
+                    variant,
+                    quirks,
+                    ticks_per_frame: rom.tickrate,
+                };
+                roms.insert(hash.to_ascii_lowercase(), known);
+            }
+        }
+        Ok(RomDatabase { roms })
+    }
+
+    /// The entry for `rom`, matched by SHA-1
+    pub fn lookup(&self, rom: &[u8]) -> Option<&KnownRom> {
+        self.roms.get(&sha1_hex(rom))
+    }
+
+    /// Number of ROMs this interpreter can run
+    pub fn len(&self) -> usize {
+        self.roms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roms.is_empty()
+    }
+}
+
+impl Chip8 {
+    /// A machine with `rom` loaded, set up the way `db` says it should be,
+    /// or with `config` if `db` doesn't know it
+    ///
+    /// Frontends can show the title and pick the speed from the entry
+    /// returned alongside.
+    pub fn load_known<'a>(
+        rom: &[u8],
+        db: &'a RomDatabase,
+        config: Config,
+    ) -> Result<(Chip8, Option<&'a KnownRom>), LoadError> {
+        let known = db.lookup(rom);
+        let mut chip8 = Chip8::try_with_config(known.map_or(config, KnownRom::config))?;
+        chip8.load(rom)?;
+        Ok((chip8, known))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROM: [u8; 2] = [0x12, 0x00];
+
+    fn database() -> String {
+        format!(
+            r#"[
+                {{
+                    "title": "Spinner",
+                    "authors": ["someone"],
+                    "roms": {{
+                        "{}": {{
+                            "file": "spinner.ch8",
+                            "platforms": ["chip8x", "superchip", "xochip"],
+                            "tickrate": 30,
+                            "quirkyPlatforms": {{
+                                "superchip": {{ "shift": false, "vblank": true }}
+                            }}
+                        }}
+                    }}
+                }},
+                {{
+                    "title": "Only on CHIP-8X",
+                    "roms": {{ "0000": {{ "platforms": ["chip8x"] }} }}
+                }}
+            ]"#,
+            sha1_hex(&ROM).to_ascii_uppercase()
+        )
+    }
+
+    #[test]
+    fn recognises_roms() {
+        let db = RomDatabase::from_json(&database()).unwrap();
+        assert_eq!(db.len(), 1);
+        let known = db.lookup(&ROM).unwrap();
+        assert_eq!(known.title, "Spinner");
+        assert_eq!(known.platform, "superchip");
+        assert_eq!(known.variant, Variant::SuperChip);
+        assert_eq!(
+            known.quirks,
+            Quirks {
+                shift_vy: true,
+                ..Variant::SuperChip.quirks()
+            }
+        );
+        assert_eq!(known.ticks_per_frame, Some(30));
+        assert_eq!(db.lookup(&[0x00, 0xE0]), None);
+
+        let (c8, known) = Chip8::load_known(&ROM, &db, Config::default()).unwrap();
+        assert_eq!(c8.variant(), Variant::SuperChip);
+        assert!(c8.quirks().shift_vy && known.is_some());
+        let (c8, known) = Chip8::load_known(&[0x00, 0xE0], &db, Config::default()).unwrap();
+        assert_eq!(c8.variant(), Variant::Chip8);
+        assert!(known.is_none());
+    }
+
+    #[test]
+    fn platform_defaults() {
+        let (variant, quirks) = platform("modernChip8").unwrap();
+        assert_eq!(variant, Variant::Chip8);
+        assert!(quirks.shift_vy && quirks.memory_increment && !quirks.vf_reset);
+        assert_eq!(platform("hybridVIP").unwrap().0, Variant::CosmacVip);
+        assert_eq!(platform("chip8x"), None);
+        assert!(matches!(
+            RomDatabase::from_json("{}"),
+            Err(DatabaseError::Parse(_))
+        ));
+    }
+}
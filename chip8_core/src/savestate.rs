@@ -0,0 +1,113 @@
+use crate::error::StateError;
+use crate::{Chip8, Chip8State};
+use serde::{Deserialize, Serialize};
+
+const FORMAT: &str = "chip8-state";
+const VERSION: u32 = 1;
+
+/// A save file: the machine state with a format tag and version, so saves
+/// from other programs or newer versions are detected instead of misread
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveState {
+    pub format: String,
+    pub version: u32,
+    pub state: Chip8State,
+}
+
+impl SaveState {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, StateError> {
+        let save: SaveState =
+            serde_json::from_str(json).map_err(|e| StateError::Parse(e.to_string()))?;
+        if save.format != FORMAT {
+            return Err(StateError::Parse(format!("not a {} file", FORMAT)));
+        }
+        if save.version > VERSION {
+            return Err(StateError::Version(save.version));
+        }
+        Ok(save)
+    }
+}
+
+impl Chip8 {
+    pub fn save_state(&self) -> SaveState {
+        SaveState {
+            format: FORMAT.to_string(),
+            version: VERSION,
+            state: self.snapshot(),
+        }
+    }
+
+    /// Resume from a save, which must come from a machine with the same
+    /// memory and stack size. Nothing is changed if the save doesn't fit.
+    pub fn load_state(&mut self, save: &SaveState) -> Result<(), StateError> {
+        self.restore(&save.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    #[test]
+    fn round_trip() {
+        let mut c8 = Chip8::new();
+        // LD V3, 7; CALL 0x206; HIGH; DRW V0, V0, 5
+        c8.load(&[0x63, 0x07, 0x22, 0x06, 0x00, 0x00, 0x00, 0xFF, 0xD0, 0x05])
+            .unwrap();
+        for _ in 0..4 {
+            c8.tick().unwrap();
+        }
+        c8.keypress(4, true);
+        let json = c8.save_state().to_json();
+
+        let mut restored = Chip8::new();
+        restored
+            .load_state(&SaveState::from_json(&json).unwrap())
+            .unwrap();
+        assert_eq!(restored.save_state(), c8.save_state());
+        assert_eq!(restored.screen_width(), 128);
+        assert_eq!(restored.pop(), Ok(0x204));
+    }
+
+    #[test]
+    fn unpacked_screen_loads() {
+        let mut c8 = Chip8::new();
+        // DRW V0, V0, 5
+        c8.load(&[0xD0, 0x05]).unwrap();
+        c8.tick().unwrap();
+        // Older saves stored one bool per pixel
+        let mut json: serde_json::Value = serde_json::from_str(&c8.save_state().to_json()).unwrap();
+        json["state"]["screen"] = serde_json::json!(c8.get_display());
+
+        let save = SaveState::from_json(&json.to_string()).unwrap();
+        assert_eq!(save, c8.save_state());
+    }
+
+    #[test]
+    fn rejects_other_saves() {
+        let mut save = Chip8::new().save_state();
+        save.version = VERSION + 1;
+        assert_eq!(
+            SaveState::from_json(&save.to_json()),
+            Err(StateError::Version(VERSION + 1))
+        );
+        assert!(matches!(
+            SaveState::from_json(r#"{"format":"chip8-package","version":1}"#),
+            Err(StateError::Parse(_))
+        ));
+
+        let mut small = Chip8::with_config(Config {
+            mem_size: 0x800,
+            ..Config::default()
+        });
+        assert_eq!(
+            small.load_state(&Chip8::new().save_state()),
+            Err(StateError::Mismatch("ram"))
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{Chip8, Chip8Error, State};
+use std::time::Duration;
+
+const FRAME: Duration = Duration::from_nanos(1_000_000_000 / 60);
+// After a long stall (a breakpoint, a background tab) don't try to catch up
+// more than this, or the game would fast-forward
+const MAX_FRAMES: u32 = 15;
+
+/// Turns elapsed host time into instructions and 60Hz timer ticks
+///
+/// Frontends measure time their own way (`Instant`, `performance.now()`,
+/// vsync) and hand the difference to `run_for`. Time that doesn't add up to
+/// a whole frame, and fractions of an instruction, carry over to the next call.
+#[derive(Clone, Debug)]
+pub struct Scheduler {
+    ips: u32,
+    pending: Duration, // time not yet run
+    carry: u32,        // instructions owed, in 60ths
+}
+
+impl Scheduler {
+    /// A scheduler running `ips` instructions per second
+    pub fn new(ips: u32) -> Self {
+        Scheduler {
+            ips,
+            pending: Duration::ZERO,
+            carry: 0,
+        }
+    }
+
+    pub fn ips(&self) -> u32 {
+        self.ips
+    }
+
+    pub fn set_ips(&mut self, ips: u32) {
+        self.ips = ips;
+    }
+
+    /// Run `chip8` for `elapsed` of host time, returns how many frames ran
+    ///
+    /// Each frame is `ips / 60` instructions followed by a timer tick. At a
+    /// breakpoint it stops there, without the timer tick, and drops the time
+    /// still owed so resuming doesn't fast-forward.
+    pub fn run_for(&mut self, chip8: &mut Chip8, elapsed: Duration) -> Result<u32, Chip8Error> {
+        self.pending += elapsed;
+        let mut frames = 0;
+        while self.pending >= FRAME {
+            self.pending -= FRAME;
+            if frames == MAX_FRAMES {
+                continue;
+            }
+            self.carry += self.ips;
+            chip8.tick_many(self.carry / 60)?;
+            self.carry %= 60;
+            if chip8.state() == State::Break {
+                self.pending = Duration::ZERO;
+                break;
+            }
+            chip8.tick_timers();
+            frames += 1;
+        }
+        Ok(frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_by_wall_clock() {
+        let mut c8 = Chip8::new();
+        // JP 0x200
+        c8.load(&[0x12, 0x00]).unwrap();
+        let mut scheduler = Scheduler::new(700);
+
+        assert_eq!(scheduler.run_for(&mut c8, FRAME / 2), Ok(0));
+        assert_eq!(scheduler.run_for(&mut c8, FRAME / 2), Ok(1));
+        // 700 / 60 is 11.67, the fractions add up
+        assert_eq!(c8.instruction_count(), 11);
+        assert_eq!(scheduler.run_for(&mut c8, FRAME * 2), Ok(2));
+        assert_eq!(c8.instruction_count(), 35);
+        assert_eq!(c8.frame_count(), 3);
+
+        assert_eq!(
+            scheduler.run_for(&mut c8, Duration::from_secs(10)),
+            Ok(MAX_FRAMES)
+        );
+    }
+
+    #[test]
+    fn stops_at_breakpoint() {
+        let mut c8 = Chip8::new();
+        // ADD V0, 1; JP 0x200
+        c8.load(&[0x70, 0x01, 0x12, 0x00]).unwrap();
+        c8.add_breakpoint(0x202);
+        let mut scheduler = Scheduler::new(700);
+        assert_eq!(
+            scheduler.run_for(&mut c8, Duration::from_millis(100)),
+            Ok(0)
+        );
+        assert_eq!(c8.state(), State::Break);
+        assert_eq!((c8.v_reg()[0], c8.frame_count()), (1, 0));
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{Chip8, Chip8Error, Event, Instruction, State, BIG_FONT_ADDR};
+
+impl Chip8 {
+    /// Run a SCHIP only instruction, returns false if `instr` isn't one
+    pub(crate) fn execute_schip(&mut self, instr: Instruction) -> Result<bool, Chip8Error> {
+        match instr {
+            Instruction::ScrollDown { n } => {
+                // 00Cn scroll down n pixels
+                self.schip_scroll(0, n as isize);
+            }
+            Instruction::ScrollRight => {
+                // 00FB scroll right 4 pixels
+                self.schip_scroll(4, 0);
+            }
+            Instruction::ScrollLeft => {
+                // 00FC scroll left 4 pixels
+                self.schip_scroll(-4, 0);
+            }
+            Instruction::Exit => {
+                // 00FD exit the interpreter, park on the exit
+                self.pc = self.op_pc();
+                self.state = State::Halted;
+                self.emit(Event::Halt { pc: self.pc });
+            }
+            Instruction::BigFont { x } => {
+                // Fx30 point I at the large font sprite for the digit in Vx
+                let digit = self.v_reg[x] & 0xF;
+                self.i_reg = BIG_FONT_ADDR + digit as u16 * 10;
+            }
+            Instruction::SaveFlags { x } => {
+                // Fx75 save V0 - Vx to the RPL flags
+                self.save_flags(x);
+            }
+            Instruction::LoadFlags { x } => {
+                // Fx85 load V0 - Vx from the RPL flags
+                self.load_flags(x);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Scroll by an amount given in pixels of the current mode, or in hi-res
+    /// pixels with the half pixel quirk (rounding odd lo-res amounts down)
+    fn schip_scroll(&mut self, dx: isize, dy: isize) {
+        let (dx, dy) = if !self.hires && self.quirks.half_pixel_scroll {
+            (dx / 2, dy / 2)
+        } else {
+            (dx, dy)
+        };
+        self.scroll(dx, dy);
+        self.screen_changed = true;
+        self.emit(Event::Draw);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Config, Quirks, Variant};
+
+    // DRW V0, V0, 1 (top row of "0", 4 pixels at the top left), then `op`
+    fn scrolled(config: Config, hires: bool, op: u16) -> Chip8 {
+        let mut c8 = Chip8::with_config(config);
+        let [hi, lo] = op.to_be_bytes();
+        let mode = if hires { 0xFF } else { 0xFE };
+        c8.load(&[0x00, mode, 0xD0, 0x01, hi, lo]).unwrap();
+        for _ in 0..3 {
+            c8.tick().unwrap();
+        }
+        c8
+    }
+
+    fn lit(c8: &Chip8) -> Vec<(usize, usize)> {
+        let width = c8.screen_width();
+        let display = c8.get_display();
+        (0..display.len())
+            .filter(|i| display[*i])
+            .map(|i| (i % width, i / width))
+            .collect()
+    }
+
+    #[test]
+    fn scroll_opcodes() {
+        for hires in [false, true] {
+            let c8 = scrolled(Config::default(), hires, 0x00C3);
+            assert_eq!(lit(&c8), vec![(0, 3), (1, 3), (2, 3), (3, 3)]);
+            let c8 = scrolled(Config::default(), hires, 0x00FB);
+            assert_eq!(lit(&c8), vec![(4, 0), (5, 0), (6, 0), (7, 0)]);
+            // Pixels scrolled off the edge are lost
+            let c8 = scrolled(Config::default(), hires, 0x00FC);
+            assert!(lit(&c8).is_empty());
+        }
+    }
+
+    #[test]
+    fn half_pixel_scroll() {
+        let config = Config {
+            quirks: Quirks {
+                half_pixel_scroll: true,
+                ..Quirks::default()
+            },
+            ..Config::default()
+        };
+        let c8 = scrolled(config, false, 0x00C3);
+        assert_eq!(lit(&c8)[0], (0, 1));
+        let c8 = scrolled(config, false, 0x00FB);
+        assert_eq!(lit(&c8)[0], (2, 0));
+        // Hi-res is unaffected
+        let c8 = scrolled(config, true, 0x00C3);
+        assert_eq!(lit(&c8)[0], (0, 3));
+    }
+
+    #[test]
+    fn large_font() {
+        let mut c8 = Chip8::new();
+        // LD V3, 8; LD HF, V3
+        c8.load(&[0x63, 0x08, 0xF3, 0x30]).unwrap();
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        let i = c8.i_reg() as usize;
+        assert_eq!(
+            &c8.ram()[i..i + 10],
+            &[0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF]
+        );
+    }
+
+    #[test]
+    fn big_sprite() {
+        let mut c8 = Chip8::new();
+        // HIGH; LD I, 0x20C; LD V0, 120; DRW V0, V1, 0 twice; JP 0x20A;
+        // then a 16x16 sprite with only its top row and right column lit
+        let mut rom = vec![
+            0x00, 0xFF, 0xA2, 0x0C, 0x60, 0x78, 0xD0, 0x10, 0xD0, 0x10, 0x12, 0x0A,
+        ];
+        rom.extend([0xFF, 0xFF]);
+        for _ in 1..16 {
+            rom.extend([0x00, 0x01]);
+        }
+        c8.load(&rom).unwrap();
+        for _ in 0..4 {
+            c8.tick().unwrap();
+        }
+        assert_eq!(c8.v_reg()[0xF], 0);
+        // The right half wraps around to the left edge
+        let pixels = lit(&c8);
+        assert_eq!(pixels.len(), 16 + 15);
+        assert!(pixels.contains(&(127, 0)) && pixels.contains(&(0, 0)));
+        assert!(pixels.contains(&(7, 15)) && !pixels.contains(&(8, 0)));
+
+        // Drawing it again erases it and reports the collision
+        c8.tick().unwrap();
+        assert_eq!(c8.v_reg()[0xF], 1);
+        assert!(lit(&c8).is_empty());
+    }
+
+    #[test]
+    fn big_sprite_clipped() {
+        let mut c8 = Chip8::with_variant(Variant::SuperChip);
+        // LD I, 0x206; DRW V0, V0, 0; JP 0x204; then a filled 16x16 sprite
+        let mut rom = vec![0xA2, 0x06, 0xD0, 0x00, 0x12, 0x04];
+        rom.extend([0xFF; 32]);
+        c8.load(&rom).unwrap();
+        c8.set_v(0, 56).unwrap();
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        // Lo-res still draws 16x16, cut off at the right and bottom edges
+        assert_eq!(lit(&c8).len(), 8 * 8);
+    }
+
+    #[test]
+    fn exit() {
+        let mut c8 = Chip8::new();
+        c8.record_events(true);
+        // LD V0, 1; EXIT; LD V0, 2
+        c8.load(&[0x60, 0x01, 0x00, 0xFD, 0x60, 0x02]).unwrap();
+        for _ in 0..4 {
+            c8.tick().unwrap();
+        }
+        assert_eq!(c8.state(), State::Halted);
+        assert_eq!((c8.pc(), c8.v_reg()[0]), (0x202, 1));
+        assert_eq!(c8.instruction_count(), 2);
+        assert_eq!(c8.take_events(), vec![Event::Halt { pc: 0x202 }]);
+
+        c8.reset();
+        assert_eq!(c8.state(), State::Running);
+    }
+
+    #[test]
+    fn not_on_chip48() {
+        let mut c8 = Chip8::with_variant(Variant::Chip48);
+        c8.load(&[0x00, 0xFB]).unwrap();
+        assert_eq!(
+            c8.tick(),
+            Err(Chip8Error::UnknownOpcode {
+                op: 0x00FB,
+                pc: 0x200
+            })
+        );
+    }
+}
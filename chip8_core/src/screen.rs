@@ -0,0 +1,138 @@
+/// Bit-packed display, `width / 8` bytes per row with the leftmost pixel of
+/// each byte in its high bit, the same layout as sprite data
+///
+/// Sprite rows are XORed in a byte at a time rather than pixel by pixel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Screen {
+    width: usize,
+    height: usize,
+    bytes: Vec<u8>,
+}
+
+impl Screen {
+    /// A blank screen, `width` must be a multiple of 8
+    pub fn new(width: usize, height: usize) -> Self {
+        debug_assert!(width.is_multiple_of(8));
+        Screen {
+            width,
+            height,
+            bytes: vec![0; width / 8 * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Replace the contents with packed rows of the same size
+    pub fn set_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.copy_from_slice(bytes);
+    }
+
+    pub fn clear(&mut self) {
+        self.bytes.fill(0);
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        let i = x + y * self.width;
+        self.bytes[i / 8] & (0x80 >> (i % 8)) != 0
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, on: bool) {
+        let i = x + y * self.width;
+        let mask = 0x80 >> (i % 8);
+        if on {
+            self.bytes[i / 8] |= mask;
+        } else {
+            self.bytes[i / 8] &= !mask;
+        }
+    }
+
+    /// One bool per pixel, row-major
+    pub fn to_bools(&self) -> Vec<bool> {
+        (0..self.width * self.height)
+            .map(|i| self.bytes[i / 8] & (0x80 >> (i % 8)) != 0)
+            .collect()
+    }
+
+    /// XOR 8 pixels of sprite data into row `y` from column `x` (which must
+    /// be on screen), wrapping past the right edge or clipping there.
+    /// Returns whether any lit pixel was turned off.
+    pub fn xor_byte(&mut self, x: usize, y: usize, bits: u8, wrap: bool) -> bool {
+        let stride = self.width / 8;
+        let row = &mut self.bytes[y * stride..(y + 1) * stride];
+        let (idx, shift) = (x / 8, x % 8);
+        let mut collided = row[idx] & (bits >> shift) != 0;
+        row[idx] ^= bits >> shift;
+        if shift > 0 {
+            // The rest of the sprite spills into the next byte
+            let spill = bits << (8 - shift);
+            let next = if idx + 1 < stride {
+                Some(idx + 1)
+            } else if wrap {
+                Some(0)
+            } else {
+                None
+            };
+            if let Some(next) = next {
+                collided |= row[next] & spill != 0;
+                row[next] ^= spill;
+            }
+        }
+        collided
+    }
+
+    /// Move the contents by (dx, dy) pixels, filling with blank pixels
+    pub fn scroll(&mut self, dx: isize, dy: isize) {
+        let old = self.clone();
+        let (width, height) = (self.width as isize, self.height as isize);
+        for y in 0..height {
+            for x in 0..width {
+                let (src_x, src_y) = (x - dx, y - dy);
+                let inside = (0..width).contains(&src_x) && (0..height).contains(&src_y);
+                let on = inside && old.get(src_x as usize, src_y as usize);
+                self.set(x as usize, y as usize, on);
+            }
+        }
+    }
+}
+
+/// Pack one bool per pixel into bytes, high bit first
+#[cfg(any(feature = "serde", test))]
+pub(crate) fn pack(pixels: &[bool]) -> Vec<u8> {
+    pixels
+        .chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0, |byte, (i, on)| byte | (*on as u8) << (7 - i))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xor_wraps_and_clips() {
+        let mut screen = Screen::new(16, 2);
+        assert!(!screen.xor_byte(12, 0, 0xFF, true));
+        assert_eq!(screen.bytes(), &[0xF0, 0x0F, 0, 0]);
+        assert!(screen.xor_byte(8, 0, 0xFF, true));
+        assert_eq!(screen.bytes(), &[0xF0, 0xF0, 0, 0]);
+        screen.xor_byte(12, 1, 0xFF, false);
+        assert_eq!(&screen.bytes()[2..], &[0, 0x0F]);
+        assert!(screen.get(15, 1) && !screen.get(0, 1));
+        assert_eq!(pack(&screen.to_bools()), screen.bytes());
+    }
+}
@@ -0,0 +1,292 @@
+use crate::error::SettingsError;
+use crate::hash::{crc32, sha1_hex};
+use crate::{Config, Quirks, Variant, KEYPAD_SIZE};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Frontend preferences read from a TOML file, every entry optional
+///
+/// ```text
+/// variant = "schip"
+/// ips = 900
+///
+/// [quirks]
+/// clip_sprites = false
+///
+/// [colors]
+/// background = "#1D2021"
+/// plane1 = "#FBF1C7"
+///
+/// [keys]
+/// Up = 0x5
+///
+/// [audio]
+/// volume = 0.5
+///
+/// [games.d40abc54374e4343639f993e897e00904ddf85d9]
+/// title = "Blinky"
+/// quirks = { shift_vy = true, memory_increment = true }
+/// ```
+///
+/// `keys` bind host keys on top of the frontend's defaults, named however
+/// that frontend names them. `games` are per-ROM overrides keyed by the
+/// ROM's SHA-1 or CRC-32 in hex, see `for_rom`. Frontends apply what they
+/// support and let their own command line or API calls override it.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub variant: Option<Variant>,
+    /// Instructions per second
+    pub ips: Option<u32>,
+    pub quirks: QuirkOverrides,
+    pub colors: Colors,
+    pub keys: BTreeMap<String, u8>,
+    pub audio: Audio,
+    pub games: BTreeMap<String, GameSettings>,
+}
+
+/// Settings for one ROM, on top of the rest of the file
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameSettings {
+    /// For whoever edits the file, the wasm page also shows it as its title
+    pub title: Option<String>,
+    pub variant: Option<Variant>,
+    pub ips: Option<u32>,
+    pub quirks: QuirkOverrides,
+}
+
+/// Quirks to switch on or off on top of the variant's
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuirkOverrides {
+    pub shift_vy: Option<bool>,
+    pub jump_vx: Option<bool>,
+    pub clip_sprites: Option<bool>,
+    pub vf_reset: Option<bool>,
+    pub memory_increment: Option<bool>,
+    pub half_pixel_scroll: Option<bool>,
+}
+
+/// Colours for the background and XO-CHIP's plane 1, plane 2 and both
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    pub background: Option<Rgb>,
+    pub plane1: Option<Rgb>,
+    pub plane2: Option<Rgb>,
+    pub both: Option<Rgb>,
+}
+
+/// A colour written `#RRGGBB`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Audio {
+    pub enabled: Option<bool>,
+    /// 0-1
+    pub volume: Option<f32>,
+}
+
+impl Settings {
+    pub fn from_toml(text: &str) -> Result<Self, SettingsError> {
+        let settings: Settings =
+            toml::from_str(text).map_err(|e| SettingsError::Parse(e.to_string()))?;
+        if let Some((key, &button)) = settings
+            .keys
+            .iter()
+            .find(|(_, &button)| button as usize >= KEYPAD_SIZE)
+        {
+            return Err(SettingsError::Key {
+                key: key.clone(),
+                button,
+            });
+        }
+        Ok(settings)
+    }
+
+    /// The `games` entry for `rom`, if there is one
+    pub fn game(&self, rom: &[u8]) -> Option<&GameSettings> {
+        let (sha1, crc) = (sha1_hex(rom), format!("{:08x}", crc32(rom)));
+        self.games.iter().find_map(|(hash, game)| {
+            let hash = hash.to_ascii_lowercase();
+            (hash == sha1 || hash == crc).then_some(game)
+        })
+    }
+
+    /// These settings with `rom`'s `games` entry applied over them
+    ///
+    /// Frontends use this when loading a ROM, so games that need a
+    /// particular speed or quirks get them without anyone remembering.
+    pub fn for_rom(&self, rom: &[u8]) -> Settings {
+        let mut settings = self.clone();
+        if let Some(game) = self.game(rom) {
+            settings.variant = game.variant.or(self.variant);
+            settings.ips = game.ips.or(self.ips);
+            settings.quirks = game.quirks.or(self.quirks);
+        }
+        settings
+    }
+
+    /// The variant's config, the default one without a variant, with the
+    /// quirk overrides applied
+    pub fn config(&self) -> Config {
+        let mut config = self.variant.unwrap_or_default().config();
+        self.quirks.apply(&mut config.quirks);
+        config
+    }
+}
+
+impl QuirkOverrides {
+    /// These overrides, falling back to `other` for quirks they leave alone
+    pub fn or(self, other: QuirkOverrides) -> QuirkOverrides {
+        QuirkOverrides {
+            shift_vy: self.shift_vy.or(other.shift_vy),
+            jump_vx: self.jump_vx.or(other.jump_vx),
+            clip_sprites: self.clip_sprites.or(other.clip_sprites),
+            vf_reset: self.vf_reset.or(other.vf_reset),
+            memory_increment: self.memory_increment.or(other.memory_increment),
+            half_pixel_scroll: self.half_pixel_scroll.or(other.half_pixel_scroll),
+        }
+    }
+
+    pub fn apply(&self, quirks: &mut Quirks) {
+        let overrides = [
+            (self.shift_vy, &mut quirks.shift_vy),
+            (self.jump_vx, &mut quirks.jump_vx),
+            (self.clip_sprites, &mut quirks.clip_sprites),
+            (self.vf_reset, &mut quirks.vf_reset),
+            (self.memory_increment, &mut quirks.memory_increment),
+            (self.half_pixel_scroll, &mut quirks.half_pixel_scroll),
+        ];
+        for (value, quirk) in overrides {
+            if let Some(on) = value {
+                *quirk = on;
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for Rgb {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        let hex = s.strip_prefix('#').unwrap_or(&s);
+        match u32::from_str_radix(hex, 16) {
+            Ok(rgb) if hex.len() == 6 => Ok(Rgb {
+                r: (rgb >> 16) as u8,
+                g: (rgb >> 8) as u8,
+                b: rgb as u8,
+            }),
+            _ => Err(format!("invalid colour '{}', use #RRGGBB", s)),
+        }
+    }
+}
+
+impl fmt::Display for Rgb {
+    /// `#RRGGBB`, which CSS understands too
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_toml() {
+        let settings = Settings::from_toml(
+            r##"
+            variant = "schip"
+            ips = 900
+
+            [quirks]
+            clip_sprites = false
+
+            [colors]
+            plane1 = "#FBF1C7"
+
+            [keys]
+            Up = 0x5
+            "##,
+        )
+        .unwrap();
+        assert_eq!(settings.variant, Some(Variant::SuperChip));
+        assert_eq!(settings.ips, Some(900));
+        assert_eq!(settings.keys["Up"], 5);
+        assert_eq!(settings.colors.plane1.unwrap().to_string(), "#FBF1C7");
+        assert_eq!(settings.colors.background, None);
+        assert_eq!(settings.audio, Audio::default());
+
+        let config = settings.config();
+        assert!(!config.quirks.clip_sprites);
+        assert_eq!(
+            config.quirks.jump_vx,
+            Variant::SuperChip.config().quirks.jump_vx
+        );
+        assert_eq!(Settings::from_toml("").unwrap().config(), Config::default());
+    }
+
+    #[test]
+    fn per_game() {
+        let rom = [0x00, 0xE0, 0x12, 0x00];
+        let text = format!(
+            r#"
+            ips = 600
+            [quirks]
+            vf_reset = true
+            clip_sprites = true
+
+            [games.{}]
+            ips = 1200
+            quirks = {{ clip_sprites = false }}
+
+            [games.{:08X}]
+            variant = "cosmac-vip"
+            "#,
+            sha1_hex(&rom),
+            crc32(b"another game")
+        );
+        let settings = Settings::from_toml(&text).unwrap();
+
+        let game = settings.for_rom(&rom);
+        assert_eq!(game.ips, Some(1200));
+        assert_eq!(game.quirks.clip_sprites, Some(false));
+        assert_eq!(game.quirks.vf_reset, Some(true));
+        assert_eq!(game.variant, None);
+
+        let other = settings.for_rom(b"another game");
+        assert_eq!(other.variant, Some(Variant::CosmacVip));
+        assert_eq!(other.ips, Some(600));
+        assert!(settings.game(b"unknown").is_none());
+        assert_eq!(settings.for_rom(b"unknown"), settings);
+    }
+
+    #[test]
+    fn bad_settings() {
+        assert!(matches!(
+            Settings::from_toml("speed = 5"),
+            Err(SettingsError::Parse(_))
+        ));
+        assert!(matches!(
+            Settings::from_toml("[colors]\nplane1 = \"white\""),
+            Err(SettingsError::Parse(_))
+        ));
+        assert_eq!(
+            Settings::from_toml("[keys]\nx = 16"),
+            Err(SettingsError::Key {
+                key: "x".to_string(),
+                button: 16
+            })
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{Chip8, Chip8State, StateError};
+use std::collections::BTreeMap;
+
+/// A single named save
+#[derive(Clone)]
+pub struct SaveSlot {
+    name: String,
+    timestamp: u64,
+    thumbnail: Vec<bool>,
+    state: Chip8State,
+}
+
+impl SaveSlot {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Time the slot was written, as supplied by the frontend (e.g. unix seconds)
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Copy of the screen at save time, same layout as `Chip8::get_display`
+    pub fn thumbnail(&self) -> &[bool] {
+        &self.thumbnail
+    }
+
+    pub fn thumbnail_size(&self) -> (usize, usize) {
+        self.state.screen_size()
+    }
+}
+
+/// Named save-state slots shared by all frontends
+///
+/// The core has no clock of its own (wasm has no `SystemTime`), so callers
+/// pass the timestamp in when saving.
+#[derive(Clone, Default)]
+pub struct SaveSlots {
+    slots: BTreeMap<String, SaveSlot>,
+}
+
+impl SaveSlots {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Save the machine into `name`, overwriting any existing slot with that name
+    pub fn save(&mut self, name: &str, chip8: &Chip8, timestamp: u64) {
+        let slot = SaveSlot {
+            name: name.to_string(),
+            timestamp,
+            thumbnail: chip8.get_display().to_vec(),
+            state: chip8.snapshot(),
+        };
+        self.slots.insert(name.to_string(), slot);
+    }
+
+    /// Restore `name` into the machine, returns false if there is no such
+    /// slot or an error if it was saved from a differently sized machine
+    pub fn load(&self, name: &str, chip8: &mut Chip8) -> Result<bool, StateError> {
+        match self.slots.get(name) {
+            Some(slot) => {
+                chip8.restore(&slot.state)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Remove a slot, returns false if there is no such slot
+    pub fn delete(&mut self, name: &str) -> bool {
+        self.slots.remove(name).is_some()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SaveSlot> {
+        self.slots.get(name)
+    }
+
+    /// All slots, ordered by name
+    pub fn list(&self) -> impl Iterator<Item = &SaveSlot> {
+        self.slots.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+    #[test]
+    fn save_and_load() {
+        let mut c8 = Chip8::new();
+        let mut slots = SaveSlots::new();
+        c8.load(&[0x60, 0x2A]).unwrap(); // V0 = 0x2A
+        c8.tick().unwrap();
+        slots.save("level 1", &c8, 100);
+
+        c8.reset();
+        assert_eq!(slots.load("level 1", &mut c8), Ok(true));
+        assert_eq!(c8.v_reg()[0], 0x2A);
+        assert_eq!(c8.pc(), 0x202);
+        assert_eq!(slots.load("missing", &mut c8), Ok(false));
+    }
+
+    #[test]
+    fn list_and_delete() {
+        let c8 = Chip8::new();
+        let mut slots = SaveSlots::new();
+        slots.save("b", &c8, 2);
+        slots.save("a", &c8, 1);
+        slots.save("b", &c8, 3);
+
+        let names: Vec<_> = slots.list().map(|s| (s.name(), s.timestamp())).collect();
+        assert_eq!(names, vec![("a", 1), ("b", 3)]);
+
+        assert!(slots.delete("a"));
+        assert!(!slots.delete("a"));
+        assert_eq!(slots.len(), 1);
+    }
+
+    #[test]
+    fn thumbnail_copies_screen() {
+        let mut c8 = Chip8::new();
+        // draw font glyph 0 at (0, 0)
+        c8.load(&[0xD0, 0x05]).unwrap();
+        c8.tick().unwrap();
+        let mut slots = SaveSlots::new();
+        slots.save("pic", &c8, 0);
+
+        let slot = slots.get("pic").unwrap();
+        assert_eq!(slot.thumbnail(), c8.get_display());
+        assert_eq!(slot.thumbnail_size(), (SCREEN_WIDTH, SCREEN_HEIGHT));
+        assert!(slot.thumbnail()[0]);
+    }
+}
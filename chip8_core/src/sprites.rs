@@ -0,0 +1,191 @@
+use crate::Chip8;
+use std::collections::BTreeSet;
+
+const SPRITE_WIDTH: usize = 8;
+const MAX_SPRITE_HEIGHT: usize = 15;
+// Blank pixels between cells in a sheet
+const SPACING: usize = 1;
+
+/// A sprite in memory: `height` rows of 8 pixels starting at `addr`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SpriteRef {
+    pub addr: u16,
+    pub height: u8,
+}
+
+/// Scan a ROM loaded at `base` for sprites it draws
+///
+/// This is a linear heuristic: each `DRW` is assumed to draw from the most
+/// recent `LD I, nnn` before it. Good enough for most games, which set I just
+/// before drawing, but indexed sprite tables (`ADD I, Vx`) aren't followed.
+pub fn find_sprites(rom: &[u8], base: u16) -> Vec<SpriteRef> {
+    let mut found = BTreeSet::new();
+    let mut last_i = None;
+    for word in rom.chunks_exact(2) {
+        let op = u16::from_be_bytes([word[0], word[1]]);
+        match op & 0xF000 {
+            0xA000 => last_i = Some(op & 0xFFF),
+            // A jump or call ends the straight line code, I may come from anywhere
+            0x1000 | 0x2000 | 0xB000 => last_i = None,
+            0xD000 => {
+                if let Some(addr) = last_i {
+                    let height = (op & 0xF) as u8;
+                    let in_rom = addr >= base && ((addr - base) as usize) < rom.len();
+                    if height > 0 && in_rom {
+                        found.insert(SpriteRef { addr, height });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    found.into_iter().collect()
+}
+
+/// Sprites laid out on a grid, one monochrome pixel per `bool`
+///
+/// Each cell is 8x15, the largest sprite `DRW` can draw, so sprites of
+/// different heights still line up.
+pub struct SpriteSheet {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<bool>,
+}
+
+impl SpriteSheet {
+    /// Lay out `sprites` read from `mem`, `columns` per row (a strip if it's
+    /// at least the number of sprites)
+    pub fn new(mem: &[u8], sprites: &[SpriteRef], columns: usize) -> Self {
+        let columns = columns.clamp(1, sprites.len().max(1));
+        let rows = sprites.len().div_ceil(columns);
+        let width = columns * (SPRITE_WIDTH + SPACING) - SPACING;
+        let height = (rows * (MAX_SPRITE_HEIGHT + SPACING)).saturating_sub(SPACING);
+        let mut pixels = vec![false; width * height];
+
+        for (idx, sprite) in sprites.iter().enumerate() {
+            let left = (idx % columns) * (SPRITE_WIDTH + SPACING);
+            let top = (idx / columns) * (MAX_SPRITE_HEIGHT + SPACING);
+            for row in 0..(sprite.height as usize).min(MAX_SPRITE_HEIGHT) {
+                // Sprites running off the end of memory are cut short
+                let byte = match mem.get(sprite.addr as usize + row) {
+                    Some(byte) => *byte,
+                    None => break,
+                };
+                for col in 0..SPRITE_WIDTH {
+                    pixels[(top + row) * width + left + col] = byte & (0x80 >> col) != 0;
+                }
+            }
+        }
+
+        SpriteSheet {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// Sprites drawn in the frame in progress and the last complete one
+#[derive(Clone, Default)]
+pub(crate) struct SpriteLog {
+    current: BTreeSet<SpriteRef>,
+    last: Vec<SpriteRef>,
+}
+
+impl Chip8 {
+    /// Start or stop recording which sprites are drawn each frame
+    ///
+    /// Meant for debugger "sprites in use" panels. A frame ends at each
+    /// `tick_timers` call, after which `frame_sprites` returns what was drawn.
+    pub fn record_sprites(&mut self, enable: bool) {
+        self.sprite_log = if enable {
+            Some(SpriteLog::default())
+        } else {
+            None
+        };
+    }
+
+    /// Distinct sprites drawn during the last complete frame, in address order
+    pub fn frame_sprites(&self) -> &[SpriteRef] {
+        match &self.sprite_log {
+            Some(log) => &log.last,
+            None => &[],
+        }
+    }
+
+    pub(crate) fn log_sprite(&mut self, addr: u16, height: u8) {
+        if let Some(log) = &mut self.sprite_log {
+            if height > 0 {
+                log.current.insert(SpriteRef { addr, height });
+            }
+        }
+    }
+
+    pub(crate) fn end_sprite_frame(&mut self) {
+        if let Some(log) = &mut self.sprite_log {
+            log.last = std::mem::take(&mut log.current).into_iter().collect();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn static_scan() {
+        // LD I, 0x208; DRW V0, V1, 1; LD I, 0x209; JP 0x206; DRW V0, V1, 1
+        let rom = [0xA2, 0x08, 0xD0, 0x11, 0xA2, 0x09, 0x12, 0x06, 0xD0, 0x11];
+        // The second draw follows a jump, so I isn't known
+        assert_eq!(
+            find_sprites(&rom, 0x200),
+            vec![SpriteRef {
+                addr: 0x208,
+                height: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn sheet_layout() {
+        let mem = [0x80, 0x01, 0xFF];
+        let sprites = [
+            SpriteRef { addr: 0, height: 2 },
+            SpriteRef { addr: 2, height: 1 },
+        ];
+        let sheet = SpriteSheet::new(&mem, &sprites, 8);
+        assert_eq!((sheet.width, sheet.height), (17, 15));
+        assert!(sheet.pixels[0]);
+        assert!(sheet.pixels[sheet.width + 7]);
+        assert!(!sheet.pixels[8]);
+        assert!(sheet.pixels[9..17].iter().all(|p| *p));
+
+        let sheet = SpriteSheet::new(&mem, &sprites, 1);
+        assert_eq!((sheet.width, sheet.height), (8, 31));
+    }
+
+    #[test]
+    fn records_sprites_per_frame() {
+        let mut c8 = Chip8::new();
+        // LD I, 0x050; DRW V0, V0, 5; DRW V0, V0, 5; LD I, 0x055; DRW V0, V0, 5
+        c8.load(&[0xA0, 0x50, 0xD0, 0x05, 0xD0, 0x05, 0xA0, 0x55, 0xD0, 0x05])
+            .unwrap();
+        c8.record_sprites(true);
+        for _ in 0..3 {
+            c8.tick().unwrap();
+        }
+        assert!(c8.frame_sprites().is_empty());
+        c8.tick_timers();
+        assert_eq!(
+            c8.frame_sprites(),
+            &[SpriteRef {
+                addr: 0x50,
+                height: 5
+            }]
+        );
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        c8.tick_timers();
+        assert_eq!(c8.frame_sprites()[0].addr, 0x55);
+    }
+}
@@ -0,0 +1,358 @@
+use crate::rng::Rng;
+use crate::screen::Screen;
+use crate::{Chip8, StateError, AUDIO_PATTERN_SIZE, KEYPAD_SIZE, V_REG_SIZE};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// A copy of the machine itself: memory, registers, timers, stack, screen
+/// (Mega-CHIP's too) and keys, plus the instruction and frame counters and
+/// the seeded `RND` generator
+///
+/// Debugging and recording state (events, profiler, sprite log, scheduled
+/// input) and the machine's configuration aren't part of it. A whole state
+/// is a few KB, so taking one per frame is fine.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Chip8State {
+    pub pc: u16,
+    pub ram: Vec<u8>,
+    pub v_reg: [u8; V_REG_SIZE],
+    pub i_reg: u16,
+    pub sp: u16,
+    pub stack: Vec<u16>,
+    pub dt: u8,
+    pub st: u8,
+    pub keys: [bool; KEYPAD_SIZE],
+    pub hires: bool,
+    /// HIRES CHIP-8 64x64 mode, missing from older saves
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub two_page: bool,
+    /// Packed rows, see `Chip8::get_display_packed`
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "packed_screen"))]
+    pub screen: Vec<u8>,
+    pub planes: u8,
+    /// XO-CHIP's second plane, packed like `screen`, empty in older saves
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub plane2: Vec<u8>,
+    // Saves written before the counters were added still load
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub instructions: u64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub frames: u64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rng: u32,
+    #[cfg_attr(feature = "serde", serde(default = "default_pattern"))]
+    pub audio_pattern: [u8; AUDIO_PATTERN_SIZE],
+    #[cfg_attr(feature = "serde", serde(default = "default_pitch"))]
+    pub pitch: u8,
+    /// Mega-CHIP mode, palette and screen, missing from saves without it
+    #[cfg(feature = "megachip")]
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub mega: crate::megachip::MegaChip,
+}
+
+#[cfg(feature = "serde")]
+fn default_pattern() -> [u8; AUDIO_PATTERN_SIZE] {
+    crate::audio::DEFAULT_PATTERN
+}
+
+#[cfg(feature = "serde")]
+fn default_pitch() -> u8 {
+    crate::audio::DEFAULT_PITCH
+}
+
+/// What the machine is doing, see `Chip8::state`
+///
+/// Lets a frontend tell a blocked or finished program from one that's just
+/// not moving the PC.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    Running,
+    /// Blocked on `Fx0A` until a key is pressed
+    WaitingForKey,
+    /// The program exited with SCHIP's `00FD`, `tick` does nothing more
+    /// until `reset`
+    Halted,
+    /// The last instruction faulted, `tick` returned the error
+    Errored,
+    /// The PC reached a breakpoint, the next `tick` runs the instruction there
+    Break,
+}
+
+/// Saves from before the screen was bit-packed have one bool per pixel
+#[cfg(feature = "serde")]
+fn packed_screen<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<u8>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Packed(Vec<u8>),
+        Pixels(Vec<bool>),
+    }
+    Ok(match Stored::deserialize(d)? {
+        Stored::Packed(bytes) => bytes,
+        Stored::Pixels(pixels) => crate::screen::pack(&pixels),
+    })
+}
+
+impl Chip8State {
+    /// Screen size in this state, `(width, height)`
+    pub fn screen_size(&self) -> (usize, usize) {
+        Chip8::resolution(self.hires, self.two_page)
+    }
+}
+
+impl Chip8 {
+    pub fn snapshot(&self) -> Chip8State {
+        Chip8State {
+            pc: self.pc,
+            ram: self.ram.clone(),
+            v_reg: self.v_reg,
+            i_reg: self.i_reg,
+            sp: self.sp,
+            stack: self.stack.clone(),
+            dt: self.dt,
+            st: self.st,
+            keys: self.keys,
+            hires: self.hires,
+            two_page: self.two_page,
+            screen: self.screen.bytes().to_vec(),
+            planes: self.planes,
+            plane2: self.plane2.bytes().to_vec(),
+            instructions: self.instructions,
+            frames: self.frames,
+            rng: self.rng.state(),
+            audio_pattern: self.audio_pattern,
+            pitch: self.pitch,
+            #[cfg(feature = "megachip")]
+            mega: self.mega.clone(),
+        }
+    }
+
+    /// Put the machine back the way it was when `state` was taken
+    ///
+    /// The state must come from a machine with the same memory and stack
+    /// size. Nothing is changed if it doesn't fit.
+    pub fn restore(&mut self, state: &Chip8State) -> Result<(), StateError> {
+        self.check_fits(state)?;
+        self.pc = state.pc;
+        self.ram.clone_from(&state.ram);
+        #[cfg(feature = "unchecked")]
+        {
+            self.unchecked = false;
+        }
+        self.v_reg = state.v_reg;
+        self.i_reg = state.i_reg;
+        self.sp = state.sp;
+        self.stack.clone_from(&state.stack);
+        self.forget_calls();
+        self.dt = state.dt;
+        self.st = state.st;
+        self.keys = state.keys;
+        self.hires = state.hires;
+        self.two_page = state.two_page;
+        let (width, height) = state.screen_size();
+        self.screen = Screen::new(width, height);
+        self.screen.set_bytes(&state.screen);
+        self.plane2 = Screen::new(width, height);
+        if !state.plane2.is_empty() {
+            self.plane2.set_bytes(&state.plane2);
+        }
+        self.planes = state.planes;
+        self.instructions = state.instructions;
+        self.frames = state.frames;
+        self.rng = Rng::new(state.rng);
+        self.audio_pattern = state.audio_pattern;
+        self.pitch = state.pitch;
+        #[cfg(feature = "megachip")]
+        self.mega.clone_from(&state.mega);
+        self.blocked = false;
+        self.state = State::Running;
+        self.screen_changed = true;
+        Ok(())
+    }
+
+    /// Whether `state` can be restored into this machine, checking every
+    /// size `restore` relies on
+    fn check_fits(&self, state: &Chip8State) -> Result<(), StateError> {
+        let (width, height) = state.screen_size();
+        if state.ram.len() != self.ram.len() {
+            return Err(StateError::Mismatch("ram"));
+        }
+        if state.stack.len() != self.stack.len() || state.sp as usize > state.stack.len() {
+            return Err(StateError::Mismatch("stack"));
+        }
+        if state.screen.len() != width * height / 8 {
+            return Err(StateError::Mismatch("screen"));
+        }
+        if !state.plane2.is_empty() && state.plane2.len() != state.screen.len() {
+            return Err(StateError::Mismatch("plane2"));
+        }
+        #[cfg(feature = "megachip")]
+        if !state.mega.is_valid() {
+            return Err(StateError::Mismatch("mega"));
+        }
+        Ok(())
+    }
+
+    /// A 64-bit hash of everything `snapshot` stores except the counters
+    ///
+    /// So `restore(snapshot())` always hashes the same. The RPL flags aren't
+    /// part of a snapshot (they outlive the machine, like on the HP48) and
+    /// aren't hashed either.
+    ///
+    /// FNV-1a over little-endian bytes, so the same state hashes the same on
+    /// every platform and build. With a seeded `RND`, comparing hashes each
+    /// frame is a cheap check that two runs haven't diverged.
+    pub fn state_hash(&self) -> u64 {
+        let mut h = Fnv::new();
+        h.write(&self.pc.to_le_bytes());
+        h.write(&self.ram);
+        h.write(&self.v_reg);
+        h.write(&self.i_reg.to_le_bytes());
+        h.write(&self.sp.to_le_bytes());
+        for slot in &self.stack {
+            h.write(&slot.to_le_bytes());
+        }
+        h.write(&[self.dt, self.st]);
+        for &key in &self.keys {
+            h.write(&[key as u8]);
+        }
+        h.write(&[self.hires as u8, self.two_page as u8, self.planes]);
+        h.write(self.screen.bytes());
+        h.write(self.plane2.bytes());
+        h.write(&self.rng.state().to_le_bytes());
+        h.write(&self.audio_pattern);
+        h.write(&[self.pitch]);
+        #[cfg(feature = "megachip")]
+        {
+            let mega = &self.mega;
+            h.write(&[
+                mega.enabled as u8,
+                mega.sprite_width,
+                mega.sprite_height,
+                mega.alpha,
+                mega.blend as u8,
+                mega.collision_color,
+            ]);
+            for colour in mega.palette.iter().chain(&mega.pixels) {
+                h.write(&colour.to_le_bytes());
+            }
+            h.write(&mega.indices);
+        }
+        h.0
+    }
+}
+
+/// 64-bit FNV-1a, `DefaultHasher` isn't guaranteed stable between releases
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(0xCBF2_9CE4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0100_0000_01B3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_and_restore() {
+        let mut c8 = Chip8::new();
+        // LD V0, 1; ADD V0, 1; DRW V0, V0, 5
+        c8.load(&[0x60, 0x01, 0x70, 0x01, 0xD0, 0x05]).unwrap();
+        c8.tick().unwrap();
+        let state = c8.snapshot();
+
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        assert_ne!(c8.snapshot(), state);
+
+        c8.restore(&state).unwrap();
+        assert_eq!(c8.snapshot(), state);
+        assert_eq!(c8.v_reg()[0], 1);
+        // Replays the same way from there
+        c8.tick().unwrap();
+        assert_eq!(c8.v_reg()[0], 2);
+        assert_eq!(state.screen_size(), (64, 32));
+    }
+
+    #[test]
+    fn restore_checks_sizes() {
+        let mut c8 = Chip8::new();
+        c8.load(&[0x12, 0x00]).unwrap();
+        let good = c8.snapshot();
+
+        let mut state = good.clone();
+        state.screen.pop();
+        assert_eq!(c8.restore(&state), Err(StateError::Mismatch("screen")));
+        let mut state = good.clone();
+        state.sp = 17;
+        assert_eq!(c8.restore(&state), Err(StateError::Mismatch("stack")));
+        let mut state = good.clone();
+        state.ram.truncate(0x300);
+        state.pc = 0x250;
+        assert_eq!(c8.restore(&state), Err(StateError::Mismatch("ram")));
+        // Nothing was changed by the failed restores
+        assert_eq!(c8.snapshot(), good);
+    }
+
+    #[test]
+    fn state_hash() {
+        let rom = [0xC0, 0xFF, 0xA2, 0x00, 0xD0, 0x05, 0x12, 0x00]; // RND V0; LD I; DRW; JP
+        let run = |seed| {
+            let mut c8 = Chip8::with_seed(seed);
+            c8.load(&rom).unwrap();
+            for _ in 0..40 {
+                c8.tick().unwrap();
+            }
+            c8
+        };
+        let (a, b) = (run(7), run(7));
+        assert_eq!(a.state_hash(), b.state_hash());
+        assert_ne!(a.state_hash(), run(8).state_hash());
+
+        let mut c = Chip8::new();
+        c.restore(&a.snapshot()).unwrap();
+        assert_eq!(c.state_hash(), a.state_hash());
+        c.keypress(3, true);
+        assert_ne!(c.state_hash(), a.state_hash());
+    }
+
+    #[test]
+    fn restored_state_hashes_the_same() {
+        let mut c8 = Chip8::new();
+        // LD V0, K then blocked waiting for a key, with a flag saved
+        c8.load(&[0xF0, 0x0A]).unwrap();
+        c8.set_flags(&[1, 2, 3]);
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+
+        let mut fresh = Chip8::new();
+        fresh.restore(&c8.snapshot()).unwrap();
+        assert_eq!(fresh.state_hash(), c8.state_hash());
+    }
+
+    #[test]
+    fn run_state() {
+        let mut c8 = Chip8::new();
+        // LD V0, K; EXIT; then RET with an empty stack at 0x204
+        c8.load(&[0xF0, 0x0A, 0x00, 0xFD, 0x00, 0xEE]).unwrap();
+        assert_eq!(c8.tick(), Ok(State::WaitingForKey));
+        assert_eq!(c8.state(), State::WaitingForKey);
+        c8.keypress(5, true);
+        assert_eq!(c8.tick(), Ok(State::Running));
+        assert_eq!(c8.tick(), Ok(State::Halted));
+        assert_eq!(c8.tick(), Ok(State::Halted));
+
+        c8.set_pc(0x204).unwrap();
+        assert!(c8.tick().is_err());
+        assert_eq!(c8.state(), State::Errored);
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+
+/// Label names and the addresses they point to
+///
+/// The on-disk format is one `ADDR NAME` pair per line with the address in
+/// hex, e.g. `0x206 draw_paddle`. Lines starting with `;` are comments.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Symbols {
+    by_name: BTreeMap<String, u16>,
+}
+
+impl Symbols {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a symbol, returns false if the name was already taken
+    pub fn insert(&mut self, name: &str, addr: u16) -> bool {
+        if self.by_name.contains_key(name) {
+            return false;
+        }
+        self.by_name.insert(name.to_string(), addr);
+        true
+    }
+
+    pub fn get(&self, name: &str) -> Option<u16> {
+        self.by_name.get(name).copied()
+    }
+
+    /// First symbol (by name) pointing at `addr`
+    pub fn name_at(&self, addr: u16) -> Option<&str> {
+        self.by_name
+            .iter()
+            .find(|(_, &a)| a == addr)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// All symbols ordered by address
+    pub fn by_addr(&self) -> Vec<(u16, &str)> {
+        let mut syms: Vec<_> = self
+            .by_name
+            .iter()
+            .map(|(name, &addr)| (addr, name.as_str()))
+            .collect();
+        syms.sort();
+        syms
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_name.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_name.is_empty()
+    }
+
+    /// Serialize into the symbol file format
+    pub fn to_file_string(&self) -> String {
+        self.by_addr()
+            .iter()
+            .map(|(addr, name)| format!("0x{:03X} {}\n", addr, name))
+            .collect()
+    }
+
+    /// Parse a symbol file, the error names the offending line
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut syms = Self::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let (Some(addr), Some(name), None) = (parts.next(), parts.next(), parts.next()) else {
+                return Err(format!("line {}: expected 'ADDR NAME'", i + 1));
+            };
+            let digits = addr.trim_start_matches("0x").trim_start_matches("0X");
+            let addr = u16::from_str_radix(digits, 16)
+                .map_err(|_| format!("line {}: invalid address '{}'", i + 1, addr))?;
+            if !syms.insert(name, addr) {
+                return Err(format!("line {}: duplicate symbol '{}'", i + 1, name));
+            }
+        }
+        Ok(syms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_round_trip() {
+        let mut syms = Symbols::new();
+        syms.insert("loop", 0x20A);
+        syms.insert("start", 0x200);
+        assert!(!syms.insert("loop", 0x300));
+
+        let text = syms.to_file_string();
+        assert_eq!(text, "0x200 start\n0x20A loop\n");
+        assert_eq!(Symbols::parse(&text).unwrap(), syms);
+        assert_eq!(syms.name_at(0x20A), Some("loop"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(Symbols::parse("0x200").is_err());
+        assert!(Symbols::parse("zz start").is_err());
+        assert!(Symbols::parse("0x200 a\n0x202 a").is_err());
+    }
+}
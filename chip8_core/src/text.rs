@@ -0,0 +1,90 @@
+use crate::{Chip8, Frame};
+
+/// How `render_text` packs pixels into characters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextStyle {
+    /// `▀`, `▄` and `█`, one character per 1x2 pixels, so 64x32 takes 16 lines
+    HalfBlock,
+    /// Braille dots, one character per 2x4 pixels, so 64x32 takes 32x8
+    Braille,
+}
+
+// Braille dot bits for a 2x4 cell, by row then column
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+impl Frame<'_> {
+    /// The screen as lines of Unicode characters, each ending in a newline
+    ///
+    /// A pixel lit on either XO-CHIP plane counts as lit. Handy over SSH,
+    /// in test output, and anywhere else without a window.
+    pub fn render_text(&self, style: TextStyle) -> String {
+        let lit = |x: usize, y: usize| x < self.width && y < self.height && self.color(x, y) != 0;
+        let (cell_w, cell_h) = match style {
+            TextStyle::HalfBlock => (1, 2),
+            TextStyle::Braille => (2, 4),
+        };
+        let mut out = String::new();
+        for y in (0..self.height).step_by(cell_h) {
+            for x in (0..self.width).step_by(cell_w) {
+                out.push(match style {
+                    TextStyle::HalfBlock => match (lit(x, y), lit(x, y + 1)) {
+                        (true, true) => '█',
+                        (true, false) => '▀',
+                        (false, true) => '▄',
+                        (false, false) => ' ',
+                    },
+                    TextStyle::Braille => {
+                        let mut bits = 0;
+                        for (dy, row) in BRAILLE_DOTS.iter().enumerate() {
+                            for (dx, bit) in row.iter().enumerate() {
+                                if lit(x + dx, y + dy) {
+                                    bits |= bit;
+                                }
+                            }
+                        }
+                        char::from_u32(0x2800 + bits).unwrap()
+                    }
+                });
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl Chip8 {
+    /// The screen as it is now, see `Frame::render_text`
+    pub fn render_text(&self, style: TextStyle) -> String {
+        self.latest_frame().render_text(style)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn half_block_and_braille() {
+        let mut c8 = Chip8::new();
+        // LD V0, 0; LD F, V0; DRW V0, V0, 5 draws the "0" glyph in the corner
+        c8.load(&[0x60, 0x00, 0xF0, 0x29, 0xD0, 0x05]).unwrap();
+        for _ in 0..3 {
+            c8.tick().unwrap();
+        }
+
+        let text = c8.render_text(TextStyle::HalfBlock);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 16);
+        assert!(lines.iter().all(|line| line.chars().count() == 64));
+        assert_eq!(lines[0].trim_end(), "█▀▀█");
+        assert_eq!(lines[1].trim_end(), "█  █");
+        assert_eq!(lines[2].trim_end(), "▀▀▀▀");
+
+        let text = c8.render_text(TextStyle::Braille);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert!(lines.iter().all(|line| line.chars().count() == 32));
+        assert!(lines[0].starts_with("⡏⢹\u{2800}"));
+        assert!(lines[1].starts_with("⠉⠉\u{2800}"));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{Chip8, Chip8Error, V_REG_SIZE};
+use std::fmt::Write;
+
+/// Machine state just before an instruction ran
+///
+/// Produced by `Chip8::tick_traced`. Traces can be written as JSON Lines
+/// (`to_jsonl`) for tooling, or as plain text (`to_text`) in the
+/// `PC OP registers` layout most emulator trace logs use, so the same ROM can
+/// be diffed instruction by instruction against another emulator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub pc: u16,
+    pub op: u16,
+    pub v_reg: [u8; V_REG_SIZE],
+    pub i_reg: u16,
+    pub sp: u16,
+    pub dt: u8,
+    pub st: u8,
+}
+
+/// Output format for a trace
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceFormat {
+    JsonLines,
+    Text,
+}
+
+impl TraceEntry {
+    /// Format as one line in the given format, `step` is the instruction number
+    pub fn format(&self, format: TraceFormat, step: u64) -> String {
+        match format {
+            TraceFormat::JsonLines => self.to_jsonl(step),
+            TraceFormat::Text => self.to_text(),
+        }
+    }
+
+    /// One JSON object, without a trailing newline
+    ///
+    /// `{"step":0,"pc":512,"op":27138,"v":[0,...],"i":0,"sp":0,"dt":0,"st":0}`
+    pub fn to_jsonl(&self, step: u64) -> String {
+        let v: Vec<String> = self.v_reg.iter().map(|v| v.to_string()).collect();
+        format!(
+            r#"{{"step":{},"pc":{},"op":{},"v":[{}],"i":{},"sp":{},"dt":{},"st":{}}}"#,
+            step,
+            self.pc,
+            self.op,
+            v.join(","),
+            self.i_reg,
+            self.sp,
+            self.dt,
+            self.st
+        )
+    }
+
+    /// `0200 6A02 V0:00 ... VF:00 I:0000 SP:0 DT:00 ST:00`
+    pub fn to_text(&self) -> String {
+        let mut line = format!("{:04X} {:04X}", self.pc, self.op);
+        for (i, v) in self.v_reg.iter().enumerate() {
+            let _ = write!(line, " V{:X}:{:02X}", i, v);
+        }
+        let _ = write!(
+            line,
+            " I:{:04X} SP:{:X} DT:{:02X} ST:{:02X}",
+            self.i_reg, self.sp, self.dt, self.st
+        );
+        line
+    }
+}
+
+impl Chip8 {
+    /// Same as `tick`, but also returns the state the instruction started from
+    ///
+    /// `None` when no instruction ran, because the program has halted or the
+    /// PC is parked past the end of RAM (`PcOverflow::Trap`).
+    pub fn tick_traced(&mut self) -> Result<Option<TraceEntry>, Chip8Error> {
+        let mut entry = None;
+        self.tick_with(|c8, op| {
+            entry = Some(TraceEntry {
+                pc: c8.op_pc(),
+                op,
+                v_reg: c8.v_reg,
+                i_reg: c8.i_reg,
+                sp: c8.sp,
+                dt: c8.dt,
+                st: c8.st,
+            })
+        })?;
+        Ok(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::State;
+
+    fn trace() -> Vec<TraceEntry> {
+        let mut c8 = Chip8::new();
+        c8.load(&[0x6A, 0x02, 0xA1, 0x23]).unwrap();
+        vec![
+            c8.tick_traced().unwrap().unwrap(),
+            c8.tick_traced().unwrap().unwrap(),
+        ]
+    }
+
+    #[test]
+    fn records_state_before_execution() {
+        let t = trace();
+        assert_eq!((t[0].pc, t[0].op), (0x200, 0x6A02));
+        assert_eq!(t[0].v_reg[0xA], 0);
+        assert_eq!((t[1].pc, t[1].op), (0x202, 0xA123));
+        assert_eq!(t[1].v_reg[0xA], 2);
+        assert_eq!(t[1].i_reg, 0);
+    }
+
+    #[test]
+    fn same_run_as_tick() {
+        let mut c8 = Chip8::new();
+        // LD V0, 1; EXIT; with a breakpoint on the EXIT
+        c8.load(&[0x60, 0x01, 0x00, 0xFD]).unwrap();
+        c8.add_breakpoint(0x202);
+        assert!(c8.tick_traced().unwrap().is_some());
+        assert_eq!(c8.state(), State::Break);
+        assert!(c8.tick_traced().unwrap().is_some());
+        assert_eq!(c8.state(), State::Halted);
+        assert_eq!(c8.tick_traced(), Ok(None));
+
+        // RET with an empty stack
+        let mut c8 = Chip8::new();
+        c8.load(&[0x00, 0xEE]).unwrap();
+        assert!(c8.tick_traced().is_err());
+        assert_eq!(c8.state(), State::Errored);
+    }
+
+    #[test]
+    fn formats() {
+        let t = trace();
+        assert_eq!(
+            t[1].to_jsonl(1),
+            r#"{"step":1,"pc":514,"op":41251,"v":[0,0,0,0,0,0,0,0,0,0,2,0,0,0,0,0],"i":0,"sp":0,"dt":0,"st":0}"#
+        );
+        assert_eq!(
+            t[1].to_text(),
+            "0202 A123 V0:00 V1:00 V2:00 V3:00 V4:00 V5:00 V6:00 V7:00 V8:00 V9:00 \
+             VA:02 VB:00 VC:00 VD:00 VE:00 VF:00 I:0000 SP:0 DT:00 ST:00"
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{Chip8, Event};
+
+/// HIRES CHIP-8 programs start with a jump over the patched interpreter
+/// that lives at 0x200 to 0x2BF on the VIP
+const ENTRY_OP: u16 = 0x1260;
+/// Where the program itself starts once the patch is skipped
+const PROGRAM_ADDR: u16 = 0x2C0;
+/// Two page display height, the width stays at 64
+pub const TWO_PAGE_HEIGHT: usize = 64;
+
+impl Chip8 {
+    /// Run a HIRES CHIP-8 only opcode, returns false if `op` isn't one
+    pub(crate) fn execute_two_page(&mut self, op: u16) -> bool {
+        match op {
+            ENTRY_OP if self.op_pc() == self.start_addr => {
+                // 1260 as the first instruction switches to 64x64 and skips
+                // the interpreter patch, which we don't need
+                self.two_page = true;
+                self.resize_screen();
+                self.pc = PROGRAM_ADDR;
+                self.screen_changed = true;
+                self.emit(Event::Draw);
+            }
+            0x0230 => {
+                // 0230 clear both pages
+                self.screen.clear();
+                self.screen_changed = true;
+                self.emit(Event::Draw);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Whether a HIRES CHIP-8 program switched on the 64x64 display
+    pub fn two_page(&self) -> bool {
+        self.two_page
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Variant;
+
+    #[test]
+    fn entry_sequence() {
+        let mut c8 = Chip8::with_variant(Variant::HiresChip8);
+        // JP 0x260, then at 0x2C0: LD V0, 60; LD F, V1; DRW V0, V0, 5;
+        // CLS (0230)
+        let mut rom = vec![0x12, 0x60];
+        rom.resize((PROGRAM_ADDR - 0x200) as usize, 0);
+        rom.extend([0x60, 0x3C, 0xF1, 0x29, 0xD0, 0x05, 0x02, 0x30]);
+        c8.load(&rom).unwrap();
+        c8.tick().unwrap();
+        assert!(c8.two_page());
+        assert_eq!((c8.screen_width(), c8.screen_height()), (64, 64));
+        assert_eq!(c8.pc(), PROGRAM_ADDR);
+
+        for _ in 0..3 {
+            c8.tick().unwrap();
+        }
+        // Drawn on the second page, below where the 64x32 screen ends
+        assert!(c8.get_display()[60 + 64 * 64 - 64]);
+        c8.tick().unwrap();
+        assert!(!c8.get_display().contains(&true));
+
+        c8.reset();
+        assert_eq!(c8.screen_height(), 32);
+    }
+
+    #[test]
+    fn only_at_start() {
+        let mut c8 = Chip8::with_variant(Variant::HiresChip8);
+        // JP 0x202; JP 0x260
+        c8.load(&[0x12, 0x02, 0x12, 0x60]).unwrap();
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        assert!(!c8.two_page());
+        assert_eq!(c8.pc(), 0x260);
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{Chip8, Chip8Error, Config};
+
+impl Chip8 {
+    /// Whether `run_unchecked` can skip bounds checks on this machine
+    ///
+    /// Every address the interpreter forms is a `u16` (offsets from I and the
+    /// PC wrap), so once RAM covers all 64K of them no access can be out of
+    /// range. A mapper could claim any address, so it must not be installed.
+    ///
+    /// Only 64K machines qualify, which in practice means XO-CHIP and
+    /// Mega-CHIP. Which addresses a ROM touches depends on what it computes
+    /// at run time, so smaller machines can't be proven safe up front.
+    pub fn can_run_unchecked(&self) -> bool {
+        self.ram.len() == Config::MAX_MEM_SIZE && self.mapper.is_none()
+    }
+
+    /// Run `ticks` instructions, skipping memory bounds checks when that has
+    /// been proven safe by `can_run_unchecked`
+    ///
+    /// For fast-forwarding, fuzzing and other bulk runs. Falls back to normal
+    /// checked ticks otherwise, so the result is always the same as `tick`.
+    /// Hooks and opcode handlers can change the machine between
+    /// instructions, so the check is made again before each one.
+    pub fn run_unchecked(&mut self, ticks: u64) -> Result<(), Chip8Error> {
+        let result = (0..ticks).try_for_each(|_| {
+            self.unchecked = self.can_run_unchecked();
+            self.tick().map(drop)
+        });
+        self.unchecked = false;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BankedRom, Chip8State, ExecHook, StateError};
+
+    /// Tries to swap in a 4K machine's state, then maps the top of 4K, just
+    /// before the first store runs
+    #[derive(Clone)]
+    struct Shrink(Option<Chip8State>);
+
+    impl ExecHook for Shrink {
+        fn before(&mut self, chip8: &mut Chip8, op: u16) {
+            if op & 0xF0FF != 0xF055 {
+                return;
+            }
+            if let Some(state) = self.0.take() {
+                assert_eq!(chip8.restore(&state), Err(StateError::Mismatch("ram")));
+                let banks = BankedRom::new(vec![0; 0x100], 0xF00..0x1000).unwrap();
+                chip8.set_mapper(Box::new(banks));
+            }
+        }
+    }
+
+    #[test]
+    fn matches_checked_run() {
+        let config = Config {
+            mem_size: Config::MAX_MEM_SIZE,
+            ..Config::default()
+        };
+        // LD I, 0xFFE; LD V2, 9; LD [I], V2; LD V0, [I]; ADD I, V2; JP 0x206
+        let rom = [
+            0xAF, 0xFE, 0x62, 0x09, 0xF2, 0x55, 0xF0, 0x65, 0xF2, 0x1E, 0x12, 0x06,
+        ];
+        let mut checked = Chip8::with_config(config);
+        let mut fast = Chip8::with_config(config);
+        checked.load(&rom).unwrap();
+        fast.load(&rom).unwrap();
+        assert!(fast.can_run_unchecked());
+
+        for _ in 0..100 {
+            checked.tick().unwrap();
+        }
+        fast.run_unchecked(100).unwrap();
+        assert_eq!(checked.ram(), fast.ram());
+        assert_eq!(checked.v_reg(), fast.v_reg());
+        assert_eq!(checked.i_reg(), fast.i_reg());
+    }
+
+    #[test]
+    fn small_ram_stays_checked() {
+        let mut c8 = Chip8::new();
+        assert!(!c8.can_run_unchecked());
+        // LD I, 0xFFF; LD [I], V1
+        c8.load(&[0xAF, 0xFF, 0xF1, 0x55]).unwrap();
+        assert_eq!(
+            c8.run_unchecked(2),
+            Err(Chip8Error::AddressOutOfRange {
+                addr: 0x1000,
+                pc: 0x202
+            })
+        );
+    }
+
+    #[test]
+    fn hooks_changing_memory_are_checked() {
+        // LD V0, 3; LD I, 0xFFF; LD [I], V0
+        let rom = [0x60, 0x03, 0xAF, 0xFF, 0xF0, 0x55];
+        let mut c8 = Chip8::with_config(Config {
+            mem_size: Config::MAX_MEM_SIZE,
+            ..Config::default()
+        });
+        c8.load(&rom).unwrap();
+        c8.add_exec_hook(Box::new(Shrink(Some(Chip8::new().snapshot()))));
+        assert!(c8.can_run_unchecked());
+        c8.run_unchecked(3).unwrap();
+        assert!(!c8.can_run_unchecked());
+        // The store went to the mapper, not straight to RAM
+        assert_eq!(c8.ram()[0xFFF], 0);
+    }
+}
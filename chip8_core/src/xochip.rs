@@ -0,0 +1,192 @@
+use crate::screen::Screen;
+use crate::{Chip8, Chip8Error, Event, Instruction, Variant, AUDIO_PATTERN_SIZE};
+
+/// XO-CHIP display planes, each pixel's colour takes a bit from both
+pub(crate) const PLANE_COUNT: usize = 2;
+
+impl Chip8 {
+    /// Run an XO-CHIP only instruction, returns false if `instr` isn't one
+    pub(crate) fn execute_xo_chip(&mut self, instr: Instruction) -> Result<bool, Chip8Error> {
+        match instr {
+            Instruction::SaveRange { x, y } => {
+                // 5xy2 save Vx - Vy to I, either direction, I is left alone
+                for (offset, reg) in reg_range(x, y).enumerate() {
+                    self.write_mem(self.i_reg.wrapping_add(offset as u16), self.v_reg[reg])?;
+                }
+            }
+            Instruction::LoadRange { x, y } => {
+                // 5xy3 load Vx - Vy from I
+                for (offset, reg) in reg_range(x, y).enumerate() {
+                    self.v_reg[reg] = self.read_mem(self.i_reg.wrapping_add(offset as u16))?;
+                }
+            }
+            Instruction::ScrollUp { n } => {
+                // 00Dn scroll up n pixels
+                self.scroll(0, -(n as isize));
+                self.screen_changed = true;
+                self.emit(Event::Draw);
+            }
+            Instruction::LoadILong => {
+                // F000 NNNN load I with the 16 bit address in the next word
+                let hi = self.read_byte(self.pc)? as u16;
+                let lo = self.read_byte(self.pc.wrapping_add(1))? as u16;
+                self.i_reg = hi << 8 | lo;
+                self.pc = self.pc.wrapping_add(2);
+            }
+            Instruction::SelectPlanes { n } => {
+                // Fn01 select the planes in bitmask n for drawing
+                self.planes = n;
+            }
+            Instruction::LoadAudio => {
+                // F002 load the 16 byte audio pattern from I
+                for idx in 0..AUDIO_PATTERN_SIZE {
+                    self.audio_pattern[idx] = self.read_mem(self.i_reg.wrapping_add(idx as u16))?;
+                }
+            }
+            Instruction::SetPitch { x } => {
+                // Fx3A set the pitch register to Vx
+                self.pitch = self.v_reg[x];
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Skip the next instruction, which on XO-CHIP may be the 4 byte F000 NNNN
+    /// (or Mega-CHIP's 01nn NNNN)
+    pub(crate) fn skip_next(&mut self) -> Result<(), Chip8Error> {
+        let long = match self.variant {
+            Variant::XoChip => {
+                self.read_byte(self.pc)? == 0xF0 && self.read_byte(self.pc.wrapping_add(1))? == 0x00
+            }
+            #[cfg(feature = "megachip")]
+            Variant::MegaChip => self.read_byte(self.pc)? == 0x01,
+            _ => false,
+        };
+        if long {
+            self.pc = self.pc.wrapping_add(2);
+        }
+        self.pc = self.pc.wrapping_add(2);
+        Ok(())
+    }
+
+    /// Move the selected planes by (dx, dy) pixels, filling with blank pixels
+    pub(crate) fn scroll(&mut self, dx: isize, dy: isize) {
+        for plane in 0..PLANE_COUNT {
+            if self.planes & (1 << plane) != 0 {
+                self.plane_mut(plane).scroll(dx, dy);
+            }
+        }
+    }
+
+    /// Display plane `plane`, 0 or 1
+    pub(crate) fn plane_mut(&mut self, plane: usize) -> &mut Screen {
+        if plane == 0 {
+            &mut self.screen
+        } else {
+            &mut self.plane2
+        }
+    }
+
+    /// Planes selected for drawing by Fn01, bit 0 is the first plane
+    pub fn planes(&self) -> u8 {
+        self.planes
+    }
+}
+
+/// Register indexes from `x` to `y` inclusive, counting down if `y < x`
+fn reg_range(x: usize, y: usize) -> Box<dyn Iterator<Item = usize>> {
+    if x <= y {
+        Box::new(x..=y)
+    } else {
+        Box::new((y..=x).rev())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Chip8, Config, Variant};
+
+    fn xo_chip(rom: &[u8]) -> Chip8 {
+        let mut c8 = Chip8::with_config(Config {
+            variant: Variant::XoChip,
+            ..Config::default()
+        });
+        c8.load(rom).unwrap();
+        c8
+    }
+
+    #[test]
+    fn long_load_and_skip() {
+        // SE V0, 0; LD I, LONG 0x1234; LD I, LONG 0xABCD
+        let mut c8 = xo_chip(&[0x30, 0x00, 0xF0, 0x00, 0x12, 0x34, 0xF0, 0x00, 0xAB, 0xCD]);
+        c8.tick().unwrap();
+        // The skip steps over all 4 bytes of the long load
+        assert_eq!(c8.pc(), 0x206);
+        c8.tick().unwrap();
+        assert_eq!(c8.i_reg(), 0xABCD);
+        assert_eq!(c8.pc(), 0x20A);
+    }
+
+    #[test]
+    fn save_and_load_ranges() {
+        // LD V1, 1; LD V2, 2; LD V3, 3; LD I, 0x300; SAVE V3, V1; LOAD V4, V6
+        let mut c8 = xo_chip(&[
+            0x61, 0x01, 0x62, 0x02, 0x63, 0x03, 0xA3, 0x00, 0x53, 0x12, 0x54, 0x63,
+        ]);
+        for _ in 0..6 {
+            c8.tick().unwrap();
+        }
+        assert_eq!(c8.ram()[0x300..0x303], [3, 2, 1]);
+        assert_eq!(c8.i_reg(), 0x300);
+        assert_eq!(c8.v_reg()[4..7], [3, 2, 1]);
+    }
+
+    #[test]
+    fn planes_and_scroll() {
+        // DRW V0, V0, 1 (top of the "0" glyph); SCU 1; PLANE 2; CLS
+        let mut c8 = xo_chip(&[0xD0, 0x01, 0x00, 0xD1, 0xF2, 0x01, 0x00, 0xE0]);
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        assert!(c8.get_display().iter().all(|px| !px));
+
+        let mut c8 = xo_chip(&[0xD0, 0x01, 0xF2, 0x01, 0x00, 0xE0]);
+        for _ in 0..3 {
+            c8.tick().unwrap();
+        }
+        assert_eq!(c8.planes(), 2);
+        // The clear only hit plane 2, so plane 1 keeps its pixels
+        assert!(c8.get_display()[0]);
+    }
+
+    #[test]
+    fn four_colors() {
+        // PLANE 3; LD I, 0x20A; DRW V0, V0, 1; PLANE 2; DRW V0, V0, 1;
+        // then the sprite rows for plane 1 and plane 2
+        let mut c8 = xo_chip(&[
+            0xF3, 0x01, 0xA2, 0x0A, 0xD0, 0x01, 0xF2, 0x01, 0xD0, 0x01, 0xF0, 0x3C,
+        ]);
+        for _ in 0..3 {
+            c8.tick().unwrap();
+        }
+        assert_eq!(c8.get_display_colors()[..8], [1, 1, 3, 3, 2, 2, 0, 0]);
+        assert_eq!(c8.v_reg()[0xF], 0);
+        // Plane 2 alone takes the first sprite, plane 1 is left alone
+        c8.tick().unwrap();
+        c8.tick().unwrap();
+        assert_eq!(c8.get_display_colors()[..8], [3, 3, 1, 1, 2, 2, 0, 0]);
+        assert_eq!(c8.v_reg()[0xF], 1);
+        assert!(c8.get_display()[..4].iter().all(|px| *px));
+
+        let frame = c8.latest_frame();
+        assert_eq!((frame.color(0, 0), frame.color(4, 0)), (3, 2));
+    }
+
+    #[test]
+    fn plain_chip8_ignores_xo_opcodes() {
+        let mut c8 = Chip8::new();
+        c8.load(&[0x30, 0x00, 0xF0, 0x00]).unwrap();
+        c8.tick().unwrap();
+        assert_eq!(c8.pc(), 0x204);
+    }
+}
@@ -0,0 +1,77 @@
+; Register loads, logic, arithmetic with VF, shifts and RND
+; Harness opcodes: DW 0x0100 = PASS, DW 0x0101 = FAIL, DW 0x02XY = ASSERT Vx == Vy
+; Shifts use the same register for Vx and Vy so they pass with either shift quirk
+
+    LD V0, 0x0F
+    LD V1, 0xF0
+    LD V2, V0
+    OR V2, V1
+    LD V3, 0xFF
+    DW 0x0223
+    LD V2, V0
+    AND V2, V1
+    LD V3, 0
+    DW 0x0223
+    LD V2, V0
+    XOR V2, V1
+    LD V3, 0xFF
+    DW 0x0223
+
+    LD V2, 0xFE         ; 7xkk wraps
+    ADD V2, 3
+    LD V3, 1
+    DW 0x0223
+
+    LD V2, 0xFF         ; 8xy4 carry
+    LD V4, 2
+    ADD V2, V4
+    LD V3, 1
+    DW 0x0223
+    DW 0x02F3
+    LD V2, 1            ; 8xy4 no carry
+    ADD V2, V4
+    LD V3, 3
+    DW 0x0223
+    LD V3, 0
+    DW 0x02F3
+
+    LD V2, 5            ; 8xy5 no borrow
+    LD V4, 3
+    SUB V2, V4
+    LD V3, 2
+    DW 0x0223
+    LD V3, 1
+    DW 0x02F3
+    LD V2, 3            ; 8xy5 borrow
+    LD V4, 5
+    SUB V2, V4
+    LD V3, 0xFE
+    DW 0x0223
+    LD V3, 0
+    DW 0x02F3
+
+    LD V2, 3            ; 8xy7 no borrow
+    LD V4, 5
+    SUBN V2, V4
+    LD V3, 2
+    DW 0x0223
+    LD V3, 1
+    DW 0x02F3
+
+    LD V2, 0x05         ; 8xy6
+    SHR V2, V2
+    LD V3, 0x02
+    DW 0x0223
+    LD V3, 1
+    DW 0x02F3
+    LD V2, 0x81         ; 8xyE
+    SHL V2, V2
+    LD V3, 0x02
+    DW 0x0223
+    LD V3, 1
+    DW 0x02F3
+
+    RND V2, 0           ; Cxkk masks with kk
+    LD V3, 0
+    DW 0x0223
+    DW 0x0100
@@ -0,0 +1,22 @@
+; Drawing, collision in VF and clearing the screen
+; Harness opcodes: DW 0x0100 = PASS, DW 0x0101 = FAIL, DW 0x02XY = ASSERT Vx == Vy
+
+    CLS
+    LD V0, 0
+    LD V1, 0
+    LD I, dot
+    DRW V0, V1, 1       ; draws, no collision
+    LD V2, 0
+    DW 0x02F2
+    DRW V0, V1, 1       ; erases, collision
+    LD V2, 1
+    DW 0x02F2
+    DRW V0, V1, 1
+    CLS
+    DRW V0, V1, 1       ; nothing left to collide with
+    LD V2, 0
+    DW 0x02F2
+    DW 0x0100
+
+dot:
+    DB 0x80
@@ -0,0 +1,37 @@
+; Jumps, calls, returns and skips
+; Harness opcodes: DW 0x0100 = PASS, DW 0x0101 = FAIL, DW 0x02XY = ASSERT Vx == Vy
+
+    LD V0, 0
+    LD V1, 1
+    SE V0, 0            ; 3xnn taken
+    DW 0x0101
+    SNE V0, 1           ; 4xnn taken
+    DW 0x0101
+    SE V0, 2            ; 3xnn not taken
+    JP se_ok
+    DW 0x0101
+se_ok:
+    SE V0, V1           ; 5xy0 not taken
+    JP sev_ok
+    DW 0x0101
+sev_ok:
+    SNE V0, V1          ; 9xy0 taken
+    DW 0x0101
+    CALL set_v3
+    LD V2, 7
+    DW 0x0223
+    LD V0, 4
+    LD V2, 4            ; BXNN reads V2 here (table is in 0x2xx), so either jump quirk passes
+    JP V0, table        ; Bnnn lands on table + 4
+    DW 0x0101
+table:
+    DW 0x0101
+    DW 0x0101
+    DW 0x0100
+
+set_v3:
+    CALL inner          ; nested call and return
+    LD V3, 7
+    RET
+inner:
+    RET
@@ -0,0 +1,11 @@
+; Key skips with nothing pressed
+; Harness opcodes: DW 0x0100 = PASS, DW 0x0101 = FAIL, DW 0x02XY = ASSERT Vx == Vy
+
+    LD V0, 5
+    SKP V0              ; not pressed, not taken
+    JP skp_ok
+    DW 0x0101
+skp_ok:
+    SKNP V0             ; not pressed, taken
+    DW 0x0101
+    DW 0x0100
@@ -0,0 +1,49 @@
+; I register, register store/load, BCD and font lookup
+; Harness opcodes: DW 0x0100 = PASS, DW 0x0101 = FAIL, DW 0x02XY = ASSERT Vx == Vy
+; I is reloaded after Fx55/Fx65 so either increment quirk passes
+
+    LD I, buf
+    LD V0, 1
+    LD V1, 2
+    LD V2, 3
+    LD [I], V2
+    LD V0, 0
+    LD V1, 0
+    LD V2, 0
+    LD I, buf
+    LD V2, [I]
+    LD V3, 1
+    DW 0x0203
+    LD V3, 2
+    DW 0x0213
+    LD V3, 3
+    DW 0x0223
+
+    LD I, buf           ; Fx33
+    LD V0, 234
+    LD B, V0
+    LD I, buf
+    LD V2, [I]
+    LD V3, 2
+    DW 0x0203
+    LD V3, 3
+    DW 0x0213
+    LD V3, 4
+    DW 0x0223
+
+    LD I, buf           ; Fx1E
+    LD V0, 2
+    ADD I, V0
+    LD V0, [I]
+    LD V3, 4
+    DW 0x0203
+
+    LD V0, 0xA          ; Fx29, first row of "A" is 0xF0
+    LD F, V0
+    LD V0, [I]
+    LD V3, 0xF0
+    DW 0x0203
+    DW 0x0100
+
+buf:
+    DB 0, 0, 0, 0
@@ -0,0 +1,13 @@
+; Delay timer load, read and countdown
+; Harness opcodes: DW 0x0100 = PASS, DW 0x0101 = FAIL, DW 0x02XY = ASSERT Vx == Vy
+
+    LD V0, 3
+    LD DT, V0
+    LD V1, DT
+    DW 0x0201
+    LD ST, V0
+wait:                   ; times out if DT never reaches 0
+    LD V1, DT
+    SE V1, 0
+    JP wait
+    DW 0x0100
@@ -0,0 +1,57 @@
+use chip8_core::asm::{assemble, AsmError, Assembled};
+use chip8_core::octo;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+pub fn run(source: &Path, output: &Path, symbols: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let src = fs::read_to_string(source)
+        .map_err(|e| format!("unable to read {}: {}", source.display(), e))?;
+
+    // Prefix errors with the file name so editors can jump to file:line:col
+    let out = build(source, &src)
+        .map_err(|e| format!("{}:{}\n{}", source.display(), e, excerpt(&src, &e)))?;
+
+    fs::write(output, &out.rom)
+        .map_err(|e| format!("unable to write {}: {}", output.display(), e))?;
+    println!("Wrote {} bytes to {}", out.rom.len(), output.display());
+
+    if let Some(path) = symbols {
+        fs::write(path, out.symbols.to_file_string())
+            .map_err(|e| format!("unable to write {}: {}", path.display(), e))?;
+        println!("Wrote {} symbols to {}", out.symbols.len(), path.display());
+    }
+    Ok(())
+}
+
+/// The line an error points at with a caret under its column, like
+///
+/// ```text
+/// 3 |   JP nowhere
+///   |      ^
+/// ```
+fn excerpt(src: &str, err: &AsmError) -> String {
+    let Some(text) = src.lines().nth(err.line.saturating_sub(1)) else {
+        return String::new();
+    };
+    // Keep tabs so the caret lines up however wide the terminal draws them
+    let pad: String = text
+        .chars()
+        .take(err.column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let gutter = " ".repeat(err.line.to_string().len());
+    format!("{} | {}\n{} | {}^", err.line, text, gutter, pad)
+}
+
+/// Assemble `src`, or compile it as Octo if `path` ends in .8o
+pub fn build(path: &Path, src: &str) -> Result<Assembled, AsmError> {
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("8o"))
+    {
+        octo::compile(src)
+    } else {
+        assemble(src)
+    }
+}
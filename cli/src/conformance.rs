@@ -0,0 +1,169 @@
+use crate::harness::{report, result_name, run_headless};
+use chip8_core::asm::assemble;
+use chip8_core::{Chip8, Config, TestHarness, Variant};
+use clap::ValueEnum;
+use std::error::Error;
+
+const MAX_FRAMES: u32 = 600;
+
+/// Bundled test ROMs, one per opcode group, assembled at startup
+const SUITES: [(&str, &str); 6] = [
+    ("flow", include_str!("../conformance/flow.asm")),
+    ("arith", include_str!("../conformance/arith.asm")),
+    ("memory", include_str!("../conformance/memory.asm")),
+    ("timers", include_str!("../conformance/timers.asm")),
+    ("display", include_str!("../conformance/display.asm")),
+    ("input", include_str!("../conformance/input.asm")),
+];
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ReportFormat {
+    Text,
+    Json,
+    Junit,
+}
+
+/// Machine configurations every suite is run under
+fn profiles() -> Vec<(&'static str, Config)> {
+    vec![("default", Config::default())]
+        .into_iter()
+        .chain(
+            [
+                Variant::CosmacVip,
+                Variant::Chip48,
+                Variant::SuperChip,
+                Variant::XoChip,
+            ]
+            .map(|variant| (variant.name(), variant.config())),
+        )
+        .collect()
+}
+
+struct CaseResult {
+    profile: &'static str,
+    suite: &'static str,
+    result: &'static str,
+    assertions: u32,
+    failures: usize,
+    detail: String,
+}
+
+/// Run every suite under every profile and print a report
+///
+/// Returns whether everything passed so the caller can set the exit code.
+pub fn run(format: ReportFormat) -> Result<bool, Box<dyn Error>> {
+    let mut results = Vec::new();
+    for (profile, config) in profiles() {
+        for (suite, source) in SUITES {
+            let rom = assemble(source).map_err(|e| format!("{}.asm:{}", suite, e))?;
+            let mut chip8 = Chip8::try_with_config(config)?;
+            chip8.register_opcodes(Box::new(TestHarness::new()));
+            chip8.load(&rom.rom)?;
+
+            let (_, fault) = run_headless(&mut chip8, MAX_FRAMES);
+            let report = report(&chip8);
+            let mut detail: Vec<String> = report
+                .failures
+                .iter()
+                .map(|f| {
+                    format!(
+                        "0x{:03X}: V{:X} ({:#04X}) != V{:X} ({:#04X})",
+                        f.pc, f.x, f.vx, f.y, f.vy
+                    )
+                })
+                .collect();
+            if let Some(e) = fault {
+                detail.push(e.to_string());
+            }
+            results.push(CaseResult {
+                profile,
+                suite,
+                result: result_name(report, fault),
+                assertions: report.assertions,
+                failures: report.failures.len(),
+                detail: detail.join("; "),
+            });
+        }
+    }
+
+    match format {
+        ReportFormat::Text => print_text(&results),
+        ReportFormat::Json => println!("{}", to_json(&results)),
+        ReportFormat::Junit => println!("{}", to_junit(&results)),
+    }
+    Ok(results.iter().all(|r| r.result == "pass"))
+}
+
+fn print_text(results: &[CaseResult]) {
+    for r in results {
+        println!(
+            "{:<10} {:<10} {:<8} {} assertions, {} failed",
+            r.profile, r.suite, r.result, r.assertions, r.failures
+        );
+        if !r.detail.is_empty() {
+            println!("    {}", r.detail);
+        }
+    }
+    let passed = results.iter().filter(|r| r.result == "pass").count();
+    println!("{} of {} passed", passed, results.len());
+}
+
+fn to_json(results: &[CaseResult]) -> String {
+    let cases: Vec<String> = results
+        .iter()
+        .map(|r| {
+            format!(
+                r#"{{"profile":{:?},"suite":{:?},"result":"{}","assertions":{},"failures":{},"detail":{:?}}}"#,
+                r.profile, r.suite, r.result, r.assertions, r.failures, r.detail
+            )
+        })
+        .collect();
+    let passed = results.iter().filter(|r| r.result == "pass").count();
+    format!(
+        r#"{{"passed":{},"failed":{},"results":[{}]}}"#,
+        passed,
+        results.len() - passed,
+        cases.join(",")
+    )
+}
+
+fn to_junit(results: &[CaseResult]) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"chip8 conformance\">\n",
+    );
+    for (profile, _) in profiles() {
+        let cases: Vec<&CaseResult> = results.iter().filter(|r| r.profile == profile).collect();
+        let failed = cases.iter().filter(|r| r.result != "pass").count();
+        out.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n",
+            profile,
+            cases.len(),
+            failed
+        ));
+        for r in cases {
+            out.push_str(&format!(
+                "    <testcase classname=\"conformance.{}\" name=\"{}\"",
+                r.profile, r.suite
+            ));
+            if r.result == "pass" {
+                out.push_str("/>\n");
+            } else {
+                out.push_str(&format!(
+                    ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>\n",
+                    r.result,
+                    escape_xml(&r.detail)
+                ));
+            }
+        }
+        out.push_str("  </testsuite>\n");
+    }
+    out.push_str("</testsuites>");
+    out
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,83 @@
+use crate::read_rom;
+use chip8_core::Package;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// Octocarts hold Octo source and options rather than a compiled program, so
+// there's nothing to convert to or from without an Octo compiler
+const OCTOCART_UNSUPPORTED: &str = "Octocart GIFs contain Octo source code, which this toolkit \
+     can't compile or produce; use Octo to export a .ch8 instead";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Container {
+    Raw,
+    Package,
+    Octocart,
+}
+
+/// Metadata to store when writing a package
+pub struct Info {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Convert between raw ROMs and packages, guessing formats from file names
+pub fn run(input: &Path, output: &Path, info: Info) -> Result<(), Box<dyn Error>> {
+    let data = read_rom(input)?;
+    let mut package = match sniff(&data, input) {
+        Container::Raw => Package::new(data),
+        Container::Package => {
+            let json = String::from_utf8(data).map_err(|_| "package isn't valid UTF-8")?;
+            Package::from_json(&json)?
+        }
+        Container::Octocart => return Err(OCTOCART_UNSUPPORTED.into()),
+    };
+
+    if let Some(title) = info.title {
+        package.info.title = title;
+    }
+    if let Some(author) = info.author {
+        package.info.author = author;
+    }
+    if let Some(description) = info.description {
+        package.info.description = description;
+    }
+
+    let bytes = match container_for(output) {
+        Container::Raw => package.rom,
+        Container::Package => package.to_json().into_bytes(),
+        Container::Octocart => return Err(OCTOCART_UNSUPPORTED.into()),
+    };
+    fs::write(output, bytes).map_err(|e| format!("unable to write {}: {}", output.display(), e))?;
+    println!("Wrote {}", output.display());
+    Ok(())
+}
+
+/// Work out an input's format from its contents, falling back to its name
+fn sniff(data: &[u8], path: &Path) -> Container {
+    if data.starts_with(b"GIF8") {
+        Container::Octocart
+    } else if data.first() == Some(&b'{') && !has_extension(path, "ch8") {
+        // `{` is also the start of ADD VB, so a .ch8 is always taken as raw
+        Container::Package
+    } else {
+        Container::Raw
+    }
+}
+
+fn container_for(path: &Path) -> Container {
+    if has_extension(path, "gif") {
+        Container::Octocart
+    } else if has_extension(path, "c8p") || has_extension(path, "json") {
+        Container::Package
+    } else {
+        Container::Raw
+    }
+}
+
+fn has_extension(path: &Path, ext: &str) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case(ext))
+}
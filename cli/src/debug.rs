@@ -0,0 +1,414 @@
+use crate::{parse_addr, read_rom, read_symbols};
+use chip8_core::disasm::disassemble_op;
+use chip8_core::{Access, Chip8, Condition, Config, State, Symbols};
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::Path;
+
+const TICKS_PER_FRAME: u64 = 10;
+// `continue` gives up after this many instructions so a ROM that never hits a
+// breakpoint doesn't hang the prompt
+const CONTINUE_LIMIT: u64 = 10_000_000;
+
+const HELP: &str = "\
+Addresses are hexadecimal (0x prefix optional) or symbol names, counts are decimal.
+  break|b [ADDR [if COND]] set a breakpoint, or list them with no argument
+  delete|del ADDR         remove a breakpoint
+  watch|w [COND]          stop when COND becomes true, or list watches
+  unwatch COND            remove a watch
+  watchmem|wm ADDR [LEN] [r|w|rw]
+                          stop when LEN bytes at ADDR (default 1) are read or
+                          written (default rw), or list watchpoints
+  step|s [N]              execute N instructions (default 1)
+  continue|c              run until a breakpoint is hit, the program exits or it
+                          waits for a key
+  key|k K [up]            hold key K (hex 0-F) down, or release it with up
+  regs|r                  print registers
+  backtrace|bt            print the subroutine calls in progress
+  history|hist [N]        print the last N instructions executed (default 10)
+  set REG VALUE           set V0-VF, DT, ST (hex byte), PC or I (address)
+  x ADDR [LEN]            examine LEN bytes of memory (default 16)
+  disas|d [ADDR] [COUNT]  disassemble COUNT instructions (default PC, 10)
+  help|h                  show this message
+  quit|q                  exit";
+
+pub fn run(rom: &Path, symbols: Option<&Path>, config: Config) -> Result<(), Box<dyn Error>> {
+    let data = read_rom(rom)?;
+    let symbols = read_symbols(symbols)?;
+    let mut chip8 = Chip8::try_with_config(config)?;
+    chip8.load(&data)?;
+
+    let mut repl = Repl {
+        chip8,
+        symbols,
+        ticks: 0,
+    };
+
+    println!(
+        "Loaded {} ({} bytes). Type 'help' for commands.",
+        rom.display(),
+        data.len()
+    );
+    repl.print_current();
+
+    let stdin = io::stdin();
+    let mut line = String::new();
+    loop {
+        print!("(chip8) ");
+        io::stdout().flush()?;
+
+        line.clear();
+        if stdin.read_line(&mut line)? == 0 {
+            break;
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((cmd, args)) = words.split_first() else {
+            continue;
+        };
+        match repl.command(cmd, args) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(msg) => println!("{}", msg),
+        }
+    }
+
+    Ok(())
+}
+
+struct Repl {
+    chip8: Chip8,
+    symbols: Symbols,
+    ticks: u64,
+}
+
+impl Repl {
+    /// Run a single command, returns false when the session should end
+    fn command(&mut self, cmd: &str, args: &[&str]) -> Result<bool, String> {
+        match cmd {
+            "break" | "b" => match args {
+                [arg] => {
+                    let addr = self.parse_addr(arg)?;
+                    self.chip8.add_breakpoint(addr);
+                    println!("Breakpoint at 0x{:03X}", addr);
+                }
+                [arg, "if", cond @ ..] => {
+                    let addr = self.parse_addr(arg)?;
+                    let cond = parse_condition(cond)?;
+                    self.chip8.add_conditional_breakpoint(addr, cond);
+                    println!("Breakpoint at 0x{:03X} if {}", addr, cond);
+                }
+                [] if self.chip8.breakpoints().next().is_none() => println!("No breakpoints"),
+                [] => {
+                    for (addr, cond) in self.chip8.breakpoints() {
+                        match cond {
+                            Some(cond) => println!("  0x{:03X} if {}", addr, cond),
+                            None => println!("  0x{:03X}", addr),
+                        }
+                    }
+                }
+                _ => return Err("usage: break ADDR [if COND]".to_string()),
+            },
+            "watch" | "w" if args.is_empty() => {
+                if self.chip8.watches().next().is_none() {
+                    println!("No watches");
+                }
+                for cond in self.chip8.watches() {
+                    println!("  {}", cond);
+                }
+            }
+            "watch" | "w" => {
+                let cond = parse_condition(args)?;
+                self.chip8.add_watch(cond);
+                println!("Watching {}", cond);
+            }
+            "watchmem" | "wm" => match args {
+                [] => {
+                    if self.chip8.watchpoints().next().is_none() {
+                        println!("No watchpoints");
+                    }
+                    for (range, access) in self.chip8.watchpoints() {
+                        println!(
+                            "  0x{:03X}-0x{:03X} {:?}",
+                            range.start,
+                            range.end - 1,
+                            access
+                        );
+                    }
+                }
+                [addr, rest @ ..] if rest.len() <= 2 => {
+                    let start = self.parse_addr(addr)?;
+                    let len = parse_count(rest.first(), 1)? as u16;
+                    let access = match rest.get(1).copied().unwrap_or("rw") {
+                        "r" => Access::Read,
+                        "w" => Access::Write,
+                        "rw" => Access::ReadWrite,
+                        other => return Err(format!("Unknown access '{}', use r, w or rw", other)),
+                    };
+                    let end = start.saturating_add(len.max(1));
+                    self.chip8.add_watchpoint(start..end, access);
+                    println!("Watching 0x{:03X}-0x{:03X} {:?}", start, end - 1, access);
+                }
+                _ => return Err("usage: watchmem ADDR [LEN] [r|w|rw]".to_string()),
+            },
+            "unwatch" => {
+                let cond = parse_condition(args)?;
+                if !self.chip8.remove_watch(&cond) {
+                    return Err(format!("Not watching {}", cond));
+                }
+            }
+            "delete" | "del" => {
+                let addr = self.parse_addr(args.first().ok_or("delete needs an address")?)?;
+                if !self.chip8.remove_breakpoint(addr) {
+                    return Err(format!("No breakpoint at 0x{:03X}", addr));
+                }
+            }
+            "step" | "s" => {
+                let n = parse_count(args.first(), 1)?;
+                for _ in 0..n {
+                    self.step()?;
+                }
+                self.print_current();
+            }
+            "continue" | "c" => {
+                let mut ran = 0;
+                let hit = loop {
+                    // run up to the next timer tick, so time passes as it does for `step`
+                    let budget = TICKS_PER_FRAME - self.ticks % TICKS_PER_FRAME;
+                    let before = self.chip8.instruction_count();
+                    let hit = self
+                        .chip8
+                        .continue_until_break(budget)
+                        .map_err(|e| format!("Emulation fault: {}", e))?;
+                    let n = match hit {
+                        Some(n) => n,
+                        None if self.chip8.state() == State::Running => budget,
+                        None => self.chip8.instruction_count() - before,
+                    };
+                    self.ticks += n;
+                    ran += n;
+                    if n == budget {
+                        self.chip8.tick_timers();
+                    }
+                    if hit.is_some()
+                        || self.chip8.state() != State::Running
+                        || ran >= CONTINUE_LIMIT
+                    {
+                        break hit;
+                    }
+                };
+                if let Some(hit) = self.chip8.watch_hit() {
+                    println!(
+                        "{:?} of 0x{:03X} by the instruction at 0x{:03X}",
+                        hit.access, hit.addr, hit.pc
+                    );
+                } else if hit.is_some() {
+                    println!("Stopped at 0x{:03X}", self.chip8.pc());
+                } else {
+                    match self.chip8.state() {
+                        State::Halted => println!("Program exited at 0x{:03X}", self.chip8.pc()),
+                        State::WaitingForKey => {
+                            println!(
+                                "Waiting for a key at 0x{:03X}, press one with 'key'",
+                                self.chip8.pc()
+                            )
+                        }
+                        _ => println!("Stopped after {} instructions", ran),
+                    }
+                }
+                self.print_current();
+            }
+            "key" | "k" => {
+                let (key, pressed) = match args {
+                    [key] => (key, true),
+                    [key, "up"] => (key, false),
+                    _ => return Err("key needs a key 0-F and optionally 'up'".to_string()),
+                };
+                let idx = usize::from_str_radix(key, 16)
+                    .ok()
+                    .filter(|&idx| idx < 16)
+                    .ok_or_else(|| format!("Invalid key '{}'", key))?;
+                self.chip8.keypress(idx, pressed);
+            }
+            "regs" | "r" => self.print_regs(),
+            "backtrace" | "bt" => self.print_backtrace(),
+            "history" | "hist" => {
+                let count = parse_count(args.first(), 10)? as usize;
+                let recent: Vec<_> = self.chip8.recent_instructions().collect();
+                for (addr, op) in &recent[recent.len().saturating_sub(count)..] {
+                    println!("   {:03X}: {:04X}  {}", addr, op, disassemble_op(*op));
+                }
+            }
+            "set" => {
+                let [reg, value] = args else {
+                    return Err("set needs a register and a value".to_string());
+                };
+                self.set(reg, value)?;
+                self.print_regs();
+            }
+            "x" => {
+                let addr = self.parse_addr(args.first().ok_or("x needs an address")?)? as usize;
+                let len = parse_count(args.get(1), 16)? as usize;
+                self.print_memory(addr, len);
+            }
+            "disas" | "d" => {
+                let addr = match args.first() {
+                    Some(arg) => self.parse_addr(arg)?,
+                    None => self.chip8.pc(),
+                };
+                let count = parse_count(args.get(1), 10)?;
+                self.print_disassembly(addr, count);
+            }
+            "help" | "h" => println!("{}", HELP),
+            "quit" | "q" => return Ok(false),
+            _ => return Err(format!("Unknown command '{}', try 'help'", cmd)),
+        }
+        Ok(true)
+    }
+
+    /// Execute one instruction, ticking the timers at the same rate as the desktop frontend
+    fn step(&mut self) -> Result<(), String> {
+        self.chip8
+            .tick()
+            .map_err(|e| format!("Emulation fault: {}", e))?;
+        self.ticks += 1;
+        if self.ticks.is_multiple_of(TICKS_PER_FRAME) {
+            self.chip8.tick_timers();
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, reg: &str, value: &str) -> Result<(), String> {
+        let byte = || {
+            let digits = value
+                .strip_prefix("0x")
+                .or_else(|| value.strip_prefix("0X"))
+                .unwrap_or(value);
+            u8::from_str_radix(digits, 16).map_err(|_| format!("Invalid byte '{}'", value))
+        };
+        let c8 = &mut self.chip8;
+        let result = match reg.to_ascii_uppercase().as_str() {
+            "PC" => c8.set_pc(parse_addr(value, &self.symbols)?),
+            "I" => c8.set_i(parse_addr(value, &self.symbols)?),
+            "DT" => {
+                c8.set_dt(byte()?);
+                Ok(())
+            }
+            "ST" => {
+                c8.set_st(byte()?);
+                Ok(())
+            }
+            name => match name
+                .strip_prefix('V')
+                .and_then(|n| usize::from_str_radix(n, 16).ok())
+            {
+                Some(idx) => c8.set_v(idx, byte()?),
+                None => return Err(format!("Unknown register '{}'", reg)),
+            },
+        };
+        result.map_err(|e| e.to_string())
+    }
+
+    fn parse_addr(&self, arg: &str) -> Result<u16, String> {
+        parse_addr(arg, &self.symbols)
+    }
+
+    fn print_current(&self) {
+        self.print_disassembly(self.chip8.pc(), 1);
+    }
+
+    fn print_regs(&self) {
+        let c8 = &self.chip8;
+        for (i, v) in c8.v_reg().iter().enumerate() {
+            print!("V{:X}={:02X}{}", i, v, if i % 8 == 7 { "\n" } else { " " });
+        }
+        println!(
+            "PC={:03X} I={:03X} DT={:02X} ST={:02X} ticks={}",
+            c8.pc(),
+            c8.i_reg(),
+            c8.dt(),
+            c8.st(),
+            self.ticks
+        );
+        let stack: Vec<String> = c8.stack().iter().map(|a| format!("{:03X}", a)).collect();
+        println!("SP={} stack=[{}]", c8.sp(), stack.join(" "));
+    }
+
+    /// Innermost call first, like gdb
+    fn print_backtrace(&self) {
+        let frames = self.chip8.call_frames();
+        if frames.is_empty() {
+            println!("No calls in progress");
+        }
+        for (depth, frame) in frames.iter().rev().enumerate() {
+            let entry = match frame.entry {
+                Some(addr) => match self.symbols.name_at(addr) {
+                    Some(name) => format!("{} (0x{:03X})", name, addr),
+                    None => format!("0x{:03X}", addr),
+                },
+                None => "unknown".to_string(),
+            };
+            println!(
+                "#{} {}, returns to 0x{:03X}",
+                depth, entry, frame.return_addr
+            );
+        }
+    }
+
+    fn print_memory(&self, addr: usize, len: usize) {
+        let ram = self.chip8.ram();
+        let end = (addr + len).min(ram.len());
+        for (row, chunk) in ram[addr.min(end)..end].chunks(16).enumerate() {
+            let bytes: Vec<String> = chunk.iter().map(|b| format!("{:02X}", b)).collect();
+            println!("{:03X}: {}", addr + row * 16, bytes.join(" "));
+        }
+    }
+
+    fn print_disassembly(&self, addr: u16, count: u64) {
+        let ram = self.chip8.ram();
+        let mut addr = addr as usize;
+        for _ in 0..count {
+            if addr + 1 >= ram.len() {
+                break;
+            }
+            if let Some(name) = self.symbols.name_at(addr as u16) {
+                println!("{}:", name);
+            }
+            let op = (ram[addr] as u16) << 8 | ram[addr + 1] as u16;
+            let marker = if addr == self.chip8.pc() as usize {
+                "=>"
+            } else {
+                "  "
+            };
+            let bp = if self.chip8.breakpoints().any(|(bp, _)| bp == addr as u16) {
+                "*"
+            } else {
+                " "
+            };
+            println!(
+                "{}{} {:03X}: {:04X}  {}",
+                marker,
+                bp,
+                addr,
+                op,
+                disassemble_op(op)
+            );
+            addr += 2;
+        }
+    }
+}
+
+/// Conditions arrive split on whitespace, e.g. `["V3", "==", "0x1F"]`
+fn parse_condition(words: &[&str]) -> Result<Condition, String> {
+    words
+        .join(" ")
+        .parse::<Condition>()
+        .map_err(|e| e.to_string())
+}
+
+fn parse_count(arg: Option<&&str>, default: u64) -> Result<u64, String> {
+    match arg {
+        Some(arg) => arg.parse().map_err(|_| format!("Invalid count '{}'", arg)),
+        None => Ok(default),
+    }
+}
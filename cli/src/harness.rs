@@ -0,0 +1,157 @@
+use crate::read_rom;
+use chip8_core::harness::{Outcome, TestReport};
+use chip8_core::{Chip8, Chip8Error, Config, Coverage, State, TestHarness};
+use std::error::Error;
+use std::path::Path;
+
+const TICKS_PER_FRAME: u32 = 10;
+
+/// Run a self-checking test ROM headless and report the result
+///
+/// Returns whether the ROM passed so the caller can set the exit code.
+pub fn run(
+    rom: &Path,
+    max_frames: u32,
+    json: bool,
+    coverage: bool,
+    config: Config,
+) -> Result<bool, Box<dyn Error>> {
+    let data = read_rom(rom)?;
+    let mut chip8 = Chip8::try_with_config(config)?;
+    chip8.register_opcodes(Box::new(TestHarness::new()));
+    if coverage {
+        chip8.add_exec_hook(Box::new(Coverage::new()));
+    }
+    chip8.load(&data)?;
+
+    let (frames, fault) = run_headless(&mut chip8, max_frames);
+
+    let report = report(&chip8);
+    let coverage = chip8.exec_hook::<Coverage>();
+    let name = rom.display().to_string();
+    if json {
+        println!("{}", to_json(&name, frames, report, fault, coverage));
+    } else {
+        print_summary(&name, frames, report, fault);
+        if let Some(coverage) = coverage {
+            print_coverage(coverage);
+        }
+    }
+    Ok(fault.is_none() && report.passed())
+}
+
+/// Run until the ROM passes or fails, faults, or `max_frames` go by
+///
+/// Returns the frames run and the fault, if any.
+pub fn run_headless(chip8: &mut Chip8, max_frames: u32) -> (u32, Option<Chip8Error>) {
+    let mut frames = 0;
+    while frames < max_frames && !report(chip8).finished() && chip8.state() != State::Halted {
+        for _ in 0..TICKS_PER_FRAME {
+            if let Err(e) = chip8.tick() {
+                return (frames, Some(e));
+            }
+        }
+        chip8.tick_timers();
+        frames += 1;
+    }
+    (frames, None)
+}
+
+pub fn report(chip8: &Chip8) -> &TestReport {
+    chip8.opcode_handler::<TestHarness>().unwrap().report()
+}
+
+pub fn result_name(report: &TestReport, fault: Option<Chip8Error>) -> &'static str {
+    match report.outcome {
+        _ if fault.is_some() => "error",
+        _ if report.passed() => "pass",
+        Some(Outcome::Pass) | Some(Outcome::Fail) => "fail",
+        None => "timeout",
+    }
+}
+
+fn print_summary(name: &str, frames: u32, report: &TestReport, fault: Option<Chip8Error>) {
+    for entry in &report.log {
+        println!(
+            "log  {:03X}: V{:X} = {:#04X}",
+            entry.pc, entry.x, entry.value
+        );
+    }
+    for f in &report.failures {
+        println!(
+            "FAIL {:03X}: V{:X} ({:#04X}) != V{:X} ({:#04X})",
+            f.pc, f.x, f.vx, f.y, f.vy
+        );
+    }
+    if let Some(e) = fault {
+        println!("ERROR {}", e);
+    }
+    println!(
+        "{}: {} after {} frames, {} assertions, {} failed",
+        name,
+        result_name(report, fault),
+        frames,
+        report.assertions,
+        report.failures.len()
+    );
+}
+
+fn print_coverage(coverage: &Coverage) {
+    let executed: Vec<String> = coverage
+        .executed()
+        .iter()
+        .map(|(pattern, count)| format!("{} x{}", pattern, count))
+        .collect();
+    println!("executed: {}", executed.join(", "));
+    println!("never executed: {}", coverage.missed().join(", "));
+}
+
+fn to_json(
+    name: &str,
+    frames: u32,
+    report: &TestReport,
+    fault: Option<Chip8Error>,
+    coverage: Option<&Coverage>,
+) -> String {
+    let failures: Vec<String> = report
+        .failures
+        .iter()
+        .map(|f| {
+            format!(
+                r#"{{"pc":{},"x":{},"y":{},"vx":{},"vy":{}}}"#,
+                f.pc, f.x, f.y, f.vx, f.vy
+            )
+        })
+        .collect();
+    let log: Vec<String> = report
+        .log
+        .iter()
+        .map(|e| format!(r#"{{"pc":{},"x":{},"value":{}}}"#, e.pc, e.x, e.value))
+        .collect();
+    let error = match fault {
+        Some(e) => format!("{:?}", e.to_string()),
+        None => "null".to_string(),
+    };
+    let coverage = match coverage {
+        Some(coverage) => {
+            let counts: Vec<String> = coverage
+                .executed()
+                .iter()
+                .map(|(pattern, count)| format!("{:?}:{}", pattern, count))
+                .collect();
+            format!(r#","coverage":{{{}}}"#, counts.join(","))
+        }
+        None => String::new(),
+    };
+    format!(
+        r#"{{"rom":{:?},"result":"{}","error":{},"frames":{},"assertions":{},"failures":[{}],"log":[{}]{}}}"#,
+        name,
+        result_name(report, fault),
+        error,
+        frames,
+        report.assertions,
+        failures.join(","),
+        log.join(","),
+        coverage
+    )
+}
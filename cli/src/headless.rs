@@ -0,0 +1,121 @@
+use crate::read_rom;
+use chip8_core::{Chip8, Config, State, TextStyle};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+const TICKS_PER_FRAME: u32 = 10;
+// Grey levels for colour indices 0-3, so XO-CHIP's planes stay apart in PNGs
+const GREYS: [u8; 4] = [0x00, 0xFF, 0xAA, 0x55];
+
+/// Run a ROM for `frames` frames without any UI and save the screen
+///
+/// The final screen goes to `dump`, or is printed as text without one. With
+/// `every`, the screen is also saved every that many frames, numbered like
+/// `out-000060.pbm`.
+pub fn run(
+    rom: &Path,
+    frames: u32,
+    dump: Option<&Path>,
+    every: Option<u32>,
+    config: Config,
+) -> Result<(), Box<dyn Error>> {
+    // Catch a bad extension now rather than after the whole run
+    let format = dump.map(ImageFormat::from_path).transpose()?;
+    let data = read_rom(rom)?;
+    let mut chip8 = Chip8::try_with_config(config)?;
+    chip8.load(&data)?;
+
+    let mut ran = 0;
+    while ran < frames && chip8.state() != State::Halted {
+        // A crash still leaves the screen as it was, which is worth seeing
+        if let Err(e) = chip8.run_frame(TICKS_PER_FRAME) {
+            eprintln!("warning: stopped at frame {}: {}", ran, e);
+            break;
+        }
+        ran += 1;
+        if let (Some(path), Some(format), Some(every)) = (dump, format, every) {
+            if every > 0 && ran.is_multiple_of(every) {
+                format.write(&chip8, &numbered(path, ran))?;
+            }
+        }
+    }
+
+    match dump.zip(format) {
+        Some((path, format)) => {
+            format.write(&chip8, path)?;
+            println!(
+                "Wrote the screen after {} frames to {}",
+                ran,
+                path.display()
+            );
+        }
+        None => print!("{}", chip8.render_text(TextStyle::HalfBlock)),
+    }
+    Ok(())
+}
+
+/// `out.pbm` becomes `out-000060.pbm` for frame 60
+fn numbered(path: &Path, frame: u32) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{}-{:06}", stem, frame);
+    if let Some(ext) = path.extension() {
+        name = format!("{}.{}", name, ext.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
+
+#[derive(Clone, Copy)]
+enum ImageFormat {
+    Pbm,
+    Png,
+}
+
+impl ImageFormat {
+    /// The format for `path`'s extension
+    fn from_path(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let ext = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("pbm") => Ok(ImageFormat::Pbm),
+            Some("png") => Ok(ImageFormat::Png),
+            _ => Err(format!("{}: unknown image format, use .pbm or .png", path.display()).into()),
+        }
+    }
+
+    /// Save the screen at one image pixel per CHIP-8 pixel
+    fn write(self, chip8: &Chip8, path: &Path) -> Result<(), Box<dyn Error>> {
+        match self {
+            ImageFormat::Pbm => write_pbm(chip8, path),
+            ImageFormat::Png => write_png(chip8, path),
+        }
+    }
+}
+
+/// Binary PBM, whose packed rows are the screen's own with black and white
+/// swapped (PBM's 1 is black)
+fn write_pbm(chip8: &Chip8, path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut out = format!("P4\n{} {}\n", chip8.screen_width(), chip8.screen_height()).into_bytes();
+    out.extend(chip8.get_display_packed().iter().map(|b| !b));
+    fs::write(path, out).map_err(|e| format!("unable to write {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+fn write_png(chip8: &Chip8, path: &Path) -> Result<(), Box<dyn Error>> {
+    let file =
+        File::create(path).map_err(|e| format!("unable to create {}: {}", path.display(), e))?;
+    let (width, height) = (chip8.screen_width(), chip8.screen_height());
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let image: Vec<u8> = chip8
+        .get_display_colors()
+        .iter()
+        .map(|&color| GREYS[color as usize])
+        .collect();
+    encoder.write_header()?.write_image_data(&image)?;
+    Ok(())
+}
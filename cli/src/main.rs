@@ -0,0 +1,454 @@
+use chip8_core::disasm::{disassemble, disassemble_labeled, infer_labels};
+use chip8_core::{Config, Quirks, Symbols, TraceFormat, Variant};
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+mod asm;
+mod conformance;
+mod convert;
+mod debug;
+mod harness;
+mod headless;
+mod profile;
+mod run;
+mod sprites;
+mod trace;
+
+#[derive(Parser)]
+#[command(name = "chip8", version, about = "Chip 8 emulator toolkit")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a ROM under an interactive debugger prompt
+    Debug {
+        /// Path to the ROM
+        rom: PathBuf,
+        /// Symbol file written by `chip8 asm --symbols`
+        #[arg(long)]
+        symbols: Option<PathBuf>,
+        #[command(flatten)]
+        machine: MachineArgs,
+    },
+    /// Print a listing of a ROM, with sprite data shown as bit patterns
+    Disasm {
+        /// Path to the ROM
+        rom: PathBuf,
+        /// Name jump and call targets, e.g. `sub_2D4`
+        #[arg(long)]
+        labels: bool,
+        /// Symbol file written by `chip8 asm --symbols`, to name addresses
+        #[arg(long)]
+        symbols: Option<PathBuf>,
+    },
+    /// Assemble a source file into a ROM
+    Asm {
+        /// Assembly source, or Octo source if it ends in .8o
+        source: PathBuf,
+        /// Output ROM, defaults to the source name with a .ch8 extension
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Also write a symbol file for `chip8 debug --symbols`
+        #[arg(long)]
+        symbols: Option<PathBuf>,
+    },
+    /// Run a ROM or assembly source in the terminal
+    Run {
+        /// ROM (.ch8) or assembly/Octo (.8o) source, which is assembled first
+        path: PathBuf,
+        /// Reassemble and reload whenever the file changes
+        #[arg(long)]
+        watch: bool,
+        /// Keep registers, timers and the screen when reloading
+        #[arg(long)]
+        keep_state: bool,
+        /// Pause when the PC reaches this address or label (repeatable)
+        #[arg(long = "break", value_name = "ADDR")]
+        breakpoints: Vec<String>,
+        #[command(flatten)]
+        machine: MachineArgs,
+    },
+    /// Run the bundled conformance ROMs and report pass/fail per opcode group
+    Conformance {
+        /// Report format
+        #[arg(long, value_enum, default_value_t = conformance::ReportFormat::Text)]
+        format: conformance::ReportFormat,
+    },
+    /// Convert a ROM between raw .ch8 and .c8p packages with metadata
+    Convert {
+        /// Input file, a raw ROM or a package
+        input: PathBuf,
+        /// Output file, the format is chosen by extension (.ch8, .c8p)
+        output: PathBuf,
+        /// Game title to store in the package
+        #[arg(long)]
+        title: Option<String>,
+        /// Author to store in the package
+        #[arg(long)]
+        author: Option<String>,
+        /// Description to store in the package
+        #[arg(long)]
+        description: Option<String>,
+    },
+    /// Export the sprites a ROM draws as a PNG sheet
+    Sprites {
+        /// Path to the ROM
+        rom: PathBuf,
+        /// Output PNG, defaults to the ROM name with a .png extension
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Also run the ROM for this many frames to catch sprites set up at runtime
+        #[arg(long, default_value_t = 600)]
+        frames: u32,
+        /// Sprites per row, use 1 for a vertical strip
+        #[arg(long, default_value_t = 16)]
+        columns: usize,
+        #[command(flatten)]
+        machine: MachineArgs,
+    },
+    /// Run a self-checking test ROM headless and report pass/fail
+    Test {
+        /// Path to the ROM
+        rom: PathBuf,
+        /// Give up if the ROM hasn't passed or failed after this many frames
+        #[arg(long, default_value_t = 600)]
+        max_frames: u32,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+        /// Also report which opcode patterns ran and how often
+        #[arg(long)]
+        coverage: bool,
+        #[command(flatten)]
+        machine: MachineArgs,
+    },
+    /// Run a ROM without any UI and save the screen as an image
+    Headless {
+        /// Path to the ROM
+        rom: PathBuf,
+        /// Number of 60Hz frames to run
+        #[arg(long, default_value_t = 600)]
+        frames: u32,
+        /// Write the final screen here as .pbm or .png, instead of printing it
+        #[arg(long, value_name = "PATH")]
+        dump_screen: Option<PathBuf>,
+        /// Also write the screen every N frames, numbered after the frame
+        #[arg(long, value_name = "N", requires = "dump_screen")]
+        every: Option<u32>,
+        #[command(flatten)]
+        machine: MachineArgs,
+    },
+    /// Run a ROM headless and report where it spends its time
+    Profile {
+        /// Path to the ROM
+        rom: PathBuf,
+        /// Number of instructions to run
+        #[arg(long, default_value_t = 100_000)]
+        ticks: u64,
+        /// How many of the busiest addresses and opcodes to list
+        #[arg(long, default_value_t = 20)]
+        top: usize,
+        /// Print folded call stacks for flamegraph tools instead of a report
+        #[arg(long)]
+        folded: bool,
+        /// Symbol file written by `chip8 asm --symbols`, to name routines
+        #[arg(long)]
+        symbols: Option<PathBuf>,
+        #[command(flatten)]
+        machine: MachineArgs,
+    },
+    /// Run a ROM headless and write an execution trace
+    Trace {
+        /// Path to the ROM
+        rom: PathBuf,
+        /// Number of instructions to trace
+        #[arg(long, default_value_t = 1000)]
+        ticks: u64,
+        /// Trace line format
+        #[arg(long, value_enum, default_value_t = Format::Jsonl)]
+        format: Format,
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        machine: MachineArgs,
+    },
+}
+
+/// Options that set up the machine, shared by the commands that run a ROM
+#[derive(Args)]
+struct MachineArgs {
+    /// Start with RAM, registers and screen full of garbage from this
+    /// seed instead of zeros, to catch reads of uninitialised state
+    #[arg(long, value_name = "SEED")]
+    power_on_seed: Option<u32>,
+    /// Interpreter to behave like (quirks, memory size and opcodes)
+    #[arg(long, default_value = "chip8", value_parser = variant_parser())]
+    variant: Variant,
+    /// Bytes of RAM instead of the variant's, e.g. 65536 or 0x10000 for 64K
+    #[arg(long, value_name = "BYTES", value_parser = parse_mem_size)]
+    mem_size: Option<usize>,
+    /// Turn a quirk on, on top of the variant's (repeatable)
+    #[arg(long = "quirk", value_enum, value_name = "QUIRK")]
+    quirks: Vec<Quirk>,
+    /// Turn a quirk off, even if the variant has it (repeatable)
+    #[arg(long = "no-quirk", value_enum, value_name = "QUIRK")]
+    no_quirks: Vec<Quirk>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// JSON Lines, one object per instruction
+    Jsonl,
+    /// Plain "PC OP registers" text, as used by other emulators' trace logs
+    Text,
+}
+
+impl From<Format> for TraceFormat {
+    fn from(f: Format) -> Self {
+        match f {
+            Format::Jsonl => TraceFormat::JsonLines,
+            Format::Text => TraceFormat::Text,
+        }
+    }
+}
+
+/// `--variant`, one of `Variant::NAMES`
+fn variant_parser() -> impl TypedValueParser<Value = Variant> {
+    PossibleValuesParser::new(Variant::NAMES).try_map(|name| name.parse::<Variant>())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Quirk {
+    /// 8xy6/8xyE shift Vy into Vx instead of shifting Vx (COSMAC VIP)
+    ShiftVy,
+    /// Bnnn jumps to xnn + Vx instead of nnn + V0 (CHIP-48, SCHIP)
+    JumpVx,
+    /// Sprites are cut off at the screen edges instead of wrapping around
+    ClipSprites,
+    /// 8xy1/8xy2/8xy3 reset VF to 0 (COSMAC VIP)
+    VfReset,
+    /// Fx55/Fx65 leave I pointing past the last register (COSMAC VIP)
+    MemoryIncrement,
+    /// SCHIP scrolls move half as far in lo-res mode (SCHIP 1.1)
+    HalfPixelScroll,
+}
+
+impl Quirk {
+    fn set(self, quirks: &mut Quirks, on: bool) {
+        match self {
+            Quirk::ShiftVy => quirks.shift_vy = on,
+            Quirk::JumpVx => quirks.jump_vx = on,
+            Quirk::ClipSprites => quirks.clip_sprites = on,
+            Quirk::VfReset => quirks.vf_reset = on,
+            Quirk::MemoryIncrement => quirks.memory_increment = on,
+            Quirk::HalfPixelScroll => quirks.half_pixel_scroll = on,
+        }
+    }
+}
+
+impl MachineArgs {
+    /// The variant's config with quirks switched on or off from the command
+    /// line
+    fn config(&self) -> Config {
+        let mut config = self.variant.config();
+        for quirk in &self.quirks {
+            quirk.set(&mut config.quirks, true);
+        }
+        for quirk in &self.no_quirks {
+            quirk.set(&mut config.quirks, false);
+        }
+        Config {
+            mem_size: self.mem_size.unwrap_or(config.mem_size),
+            power_on_seed: self.power_on_seed,
+            ..config
+        }
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Debug {
+            rom,
+            symbols,
+            machine,
+        } => debug::run(&rom, symbols.as_deref(), machine.config()),
+        Command::Disasm {
+            rom,
+            labels,
+            symbols,
+        } => disasm(&rom, labels, symbols.as_deref()),
+        Command::Asm {
+            source,
+            output,
+            symbols,
+        } => {
+            let output = output.unwrap_or_else(|| source.with_extension("ch8"));
+            asm::run(&source, &output, symbols.as_deref())
+        }
+        Command::Run {
+            path,
+            watch,
+            keep_state,
+            breakpoints,
+            machine,
+        } => run::run(&path, watch, keep_state, &breakpoints, machine.config()),
+        Command::Conformance { format } => conformance::run(format).map(|passed| {
+            if !passed {
+                process::exit(1);
+            }
+        }),
+        Command::Convert {
+            input,
+            output,
+            title,
+            author,
+            description,
+        } => convert::run(
+            &input,
+            &output,
+            convert::Info {
+                title,
+                author,
+                description,
+            },
+        ),
+        Command::Sprites {
+            rom,
+            output,
+            frames,
+            columns,
+            machine,
+        } => {
+            let output = output.unwrap_or_else(|| rom.with_extension("png"));
+            sprites::run(&rom, &output, frames, columns, machine.config())
+        }
+        Command::Test {
+            rom,
+            max_frames,
+            json,
+            coverage,
+            machine,
+        } => {
+            let config = machine.config();
+            harness::run(&rom, max_frames, json, coverage, config).map(|passed| {
+                if !passed {
+                    process::exit(1);
+                }
+            })
+        }
+        Command::Headless {
+            rom,
+            frames,
+            dump_screen,
+            every,
+            machine,
+        } => headless::run(
+            &rom,
+            frames,
+            dump_screen.as_deref(),
+            every,
+            machine.config(),
+        ),
+        Command::Profile {
+            rom,
+            ticks,
+            top,
+            folded,
+            symbols,
+            machine,
+        } => profile::run(
+            &rom,
+            ticks,
+            top,
+            folded,
+            symbols.as_deref(),
+            machine.config(),
+        ),
+        Command::Trace {
+            rom,
+            ticks,
+            format,
+            output,
+            machine,
+        } => trace::run(
+            &rom,
+            ticks,
+            format.into(),
+            output.as_deref(),
+            machine.config(),
+        ),
+    };
+
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+pub fn read_rom(path: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+    fs::read(path).map_err(|e| format!("unable to read {}: {}", path.display(), e).into())
+}
+
+fn disasm(rom: &Path, labels: bool, symbols: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let rom = read_rom(rom)?;
+    let mut symbols = read_symbols(symbols)?;
+    if labels {
+        for (addr, name) in infer_labels(&disassemble(&rom, 0x200)).by_addr() {
+            // Names from the symbol file win over guessed ones
+            if symbols.name_at(addr).is_none() {
+                symbols.insert(name, addr);
+            }
+        }
+    }
+    print!("{}", disassemble_labeled(&rom, 0x200, &symbols));
+    Ok(())
+}
+
+/// Read a symbol file written by `chip8 asm --symbols`, if one was given
+pub fn read_symbols(path: Option<&Path>) -> Result<Symbols, Box<dyn Error>> {
+    let path = match path {
+        Some(path) => path,
+        None => return Ok(Symbols::new()),
+    };
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+    Ok(Symbols::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?)
+}
+
+/// RAM size in decimal, or hex with a 0x prefix, within what `Config` accepts
+fn parse_mem_size(arg: &str) -> Result<usize, String> {
+    let size = match arg.strip_prefix("0x").or_else(|| arg.strip_prefix("0X")) {
+        Some(digits) => usize::from_str_radix(digits, 16),
+        None => arg.parse(),
+    }
+    .map_err(|_| format!("invalid size '{}'", arg))?;
+    if !(Config::MIN_MEM_SIZE..=Config::MAX_MEM_SIZE).contains(&size) {
+        return Err(format!(
+            "must be between 0x{:X} and 0x{:X}",
+            Config::MIN_MEM_SIZE,
+            Config::MAX_MEM_SIZE
+        ));
+    }
+    Ok(size)
+}
+
+/// Parse a hex address (0x prefix optional) or look up a symbol name
+pub fn parse_addr(arg: &str, symbols: &Symbols) -> Result<u16, String> {
+    if let Some(addr) = symbols.get(arg) {
+        return Ok(addr);
+    }
+    let digits = arg
+        .strip_prefix("0x")
+        .or_else(|| arg.strip_prefix("0X"))
+        .unwrap_or(arg);
+    u16::from_str_radix(digits, 16).map_err(|_| format!("Invalid address '{}'", arg))
+}
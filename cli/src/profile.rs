@@ -0,0 +1,69 @@
+use crate::{read_rom, read_symbols};
+use chip8_core::disasm::disassemble_op;
+use chip8_core::{Chip8, Config, Symbols};
+use std::error::Error;
+use std::path::Path;
+
+const TICKS_PER_FRAME: u64 = 10;
+
+/// Run a ROM headless for `ticks` instructions with the profiler on
+pub fn run(
+    rom: &Path,
+    ticks: u64,
+    top: usize,
+    folded: bool,
+    symbols: Option<&Path>,
+    config: Config,
+) -> Result<(), Box<dyn Error>> {
+    let data = read_rom(rom)?;
+    let symbols = read_symbols(symbols)?;
+    let mut chip8 = Chip8::try_with_config(config)?;
+    chip8.load(&data)?;
+    chip8.enable_profiler(true);
+
+    for step in 0..ticks {
+        // Report what ran up to a fault rather than nothing
+        if let Err(e) = chip8.tick() {
+            eprintln!("warning: stopped after {} instructions: {}", step, e);
+            break;
+        }
+        if (step + 1).is_multiple_of(TICKS_PER_FRAME) {
+            chip8.tick_timers();
+        }
+    }
+
+    let profile = chip8.profile().unwrap();
+    if folded {
+        print!("{}", profile.folded(|addr| name(&symbols, addr)));
+        return Ok(());
+    }
+
+    let percent = |count: u64| count as f64 * 100.0 / profile.total().max(1) as f64;
+    println!("{} instructions\n", profile.total());
+    println!("Hottest addresses:");
+    for (addr, count) in profile.hot_addresses().into_iter().take(top) {
+        let ram = chip8.ram();
+        let byte = |addr: usize| ram.get(addr).copied().unwrap_or(0);
+        let op = u16::from_be_bytes([byte(addr as usize), byte(addr as usize + 1)]);
+        println!(
+            "  {:>10} {:>5.1}%  {:<16} {}",
+            count,
+            percent(count),
+            name(&symbols, addr),
+            disassemble_op(op)
+        );
+    }
+    println!("\nHottest opcodes:");
+    for (pattern, count) in profile.hot_opcodes().into_iter().take(top) {
+        println!("  {:>10} {:>5.1}%  {}", count, percent(count), pattern);
+    }
+    Ok(())
+}
+
+/// Label an address with its symbol, falling back to hex
+fn name(symbols: &Symbols, addr: u16) -> String {
+    match symbols.name_at(addr) {
+        Some(name) => name.to_string(),
+        None => format!("0x{:03X}", addr),
+    }
+}
@@ -0,0 +1,257 @@
+use crate::{asm, parse_addr, read_rom};
+use chip8_core::{Chip8, Config, State, Symbols, TextStyle};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, execute, queue, style, terminal};
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+const TICKS_PER_FRAME: usize = 10;
+const FRAME_TIME: Duration = Duration::from_micros(16_667);
+// Terminals only report key presses, so a key counts as held for this many
+// frames after its last press (auto-repeat keeps it held)
+const KEY_HOLD_FRAMES: u32 = 6;
+// How often to check the watched file for changes
+const WATCH_INTERVAL: u32 = 15;
+
+/// Assemble or read the program at `path`
+fn build(path: &Path) -> Result<(Vec<u8>, Symbols), String> {
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("ch8"))
+    {
+        let rom = read_rom(path).map_err(|e| e.to_string())?;
+        return Ok((rom, Symbols::new()));
+    }
+    let src = fs::read_to_string(path)
+        .map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+    let out = asm::build(path, &src).map_err(|e| format!("{}:{}", path.display(), e))?;
+    Ok((out.rom, out.symbols))
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Restores the terminal even if the run loop bails out early
+struct RawTerminal;
+
+impl RawTerminal {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(RawTerminal)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+struct Runner {
+    chip8: Chip8,
+    // Breakpoints are kept as written so labels follow the code across reloads
+    break_specs: Vec<String>,
+    paused: bool,
+    held: [u32; 16],
+    status: String,
+}
+
+impl Runner {
+    fn load(&mut self, rom: &[u8], symbols: &Symbols, keep_state: bool) -> Result<(), String> {
+        let breakpoints = self
+            .break_specs
+            .iter()
+            .map(|spec| parse_addr(spec, symbols))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.chip8
+            .reload_rom(rom, keep_state)
+            .map_err(|e| e.to_string())?;
+        self.chip8.clear_breakpoints();
+        for addr in breakpoints {
+            self.chip8.add_breakpoint(addr);
+        }
+        Ok(())
+    }
+
+    /// Handle pending key events, returns false when the user quits
+    fn input(&mut self) -> io::Result<bool> {
+        while event::poll(Duration::ZERO)? {
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind == KeyEventKind::Release {
+                continue;
+            }
+            match key.code {
+                KeyCode::Esc => return Ok(false),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(false)
+                }
+                KeyCode::Char(' ') => self.paused = !self.paused,
+                KeyCode::Char('.') if self.paused => {
+                    self.tick();
+                }
+                KeyCode::Char(c) => {
+                    if let Some(k) = key2btn(c.to_ascii_lowercase()) {
+                        self.held[k] = KEY_HOLD_FRAMES;
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(true)
+    }
+
+    fn frame(&mut self) {
+        for (k, held) in self.held.iter_mut().enumerate() {
+            self.chip8.keypress(k, *held > 0);
+            *held = held.saturating_sub(1);
+        }
+
+        if self.paused {
+            return;
+        }
+        for _ in 0..TICKS_PER_FRAME {
+            if !self.tick() {
+                return;
+            }
+        }
+        self.chip8.tick_timers();
+    }
+
+    /// Run one instruction, pausing with the fault in the status line if it fails
+    fn tick(&mut self) -> bool {
+        match self.chip8.tick() {
+            Ok(State::Halted) => {
+                self.paused = true;
+                self.status = "program exited".to_string();
+                false
+            }
+            Ok(State::Break) => {
+                self.paused = true;
+                self.status = format!("breakpoint at 0x{:03X}", self.chip8.pc());
+                false
+            }
+            Ok(_) => true,
+            Err(e) => {
+                self.paused = true;
+                self.status = format!("emulation fault: {}", e);
+                false
+            }
+        }
+    }
+
+    fn draw(&self, out: &mut impl Write, name: &str) -> io::Result<()> {
+        queue!(out, cursor::MoveTo(0, 0))?;
+        for row in self.chip8.render_text(TextStyle::HalfBlock).lines() {
+            // Clear the rest of the line in case the screen just got narrower
+            queue!(
+                out,
+                style::Print(row),
+                terminal::Clear(terminal::ClearType::UntilNewLine),
+                cursor::MoveToNextLine(1)
+            )?;
+        }
+
+        let state = if self.paused {
+            "paused (space: resume, .: step)"
+        } else {
+            "running (space: pause)"
+        };
+        queue!(
+            out,
+            terminal::Clear(terminal::ClearType::FromCursorDown),
+            style::Print(format!("{} | PC {:03X} | {}", name, self.chip8.pc(), state)),
+            cursor::MoveToNextLine(1),
+            style::Print(&self.status),
+        )?;
+        out.flush()
+    }
+}
+
+pub fn run(
+    path: &Path,
+    watch: bool,
+    keep_state: bool,
+    breakpoints: &[String],
+    config: Config,
+) -> Result<(), Box<dyn Error>> {
+    let (rom, symbols) = build(path)?;
+    let mut runner = Runner {
+        chip8: Chip8::try_with_config(config)?,
+        break_specs: breakpoints.to_vec(),
+        paused: false,
+        held: [0; 16],
+        status: String::from("Esc to quit"),
+    };
+    runner.load(&rom, &symbols, false)?;
+
+    let name = path.display().to_string();
+    let mut last_modified = modified(path);
+    let mut frames: u32 = 0;
+
+    let _term = RawTerminal::enter()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
+
+    loop {
+        let start = Instant::now();
+        if !runner.input()? {
+            break;
+        }
+
+        frames = frames.wrapping_add(1);
+        if watch && frames.is_multiple_of(WATCH_INTERVAL) {
+            let now = modified(path);
+            if now != last_modified {
+                last_modified = now;
+                // Keep running the old program if the new one doesn't build
+                runner.status = match build(path)
+                    .and_then(|(rom, symbols)| runner.load(&rom, &symbols, keep_state))
+                {
+                    Ok(()) => format!("reloaded {}", name),
+                    Err(e) => e,
+                };
+            }
+        }
+
+        runner.frame();
+        runner.draw(&mut stdout, &name)?;
+
+        if let Some(rest) = FRAME_TIME.checked_sub(start.elapsed()) {
+            thread::sleep(rest);
+        }
+    }
+
+    Ok(())
+}
+
+fn key2btn(key: char) -> Option<usize> {
+    match key {
+        '1' => Some(0x1),
+        '2' => Some(0x2),
+        '3' => Some(0x3),
+        '4' => Some(0xC),
+        'q' => Some(0x4),
+        'w' => Some(0x5),
+        'e' => Some(0x6),
+        'r' => Some(0xD),
+        'a' => Some(0x7),
+        's' => Some(0x8),
+        'd' => Some(0x9),
+        'f' => Some(0xE),
+        'z' => Some(0xA),
+        'x' => Some(0x0),
+        'c' => Some(0xB),
+        'v' => Some(0xF),
+        _ => None,
+    }
+}
@@ -0,0 +1,72 @@
+use crate::read_rom;
+use chip8_core::sprites::find_sprites;
+use chip8_core::{Chip8, Config, SpriteRef, SpriteSheet};
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+const TICKS_PER_FRAME: u32 = 10;
+// Each sheet pixel becomes a SCALE x SCALE block so the PNG is viewable
+const SCALE: usize = 4;
+
+/// Find the sprites a ROM draws and write them out as a PNG sheet
+///
+/// Sprites are found statically, and by running the ROM headless for
+/// `frames` frames and noting I and the height of every draw.
+pub fn run(
+    rom: &Path,
+    output: &Path,
+    frames: u32,
+    columns: usize,
+    config: Config,
+) -> Result<(), Box<dyn Error>> {
+    let data = read_rom(rom)?;
+    let mut chip8 = Chip8::try_with_config(config)?;
+    chip8.load(&data)?;
+
+    let mut sprites: BTreeSet<SpriteRef> = find_sprites(&data, chip8.start_addr())
+        .into_iter()
+        .collect();
+    chip8.record_sprites(true);
+    for _ in 0..frames {
+        let result = (0..TICKS_PER_FRAME).try_for_each(|_| chip8.tick().map(drop));
+        chip8.tick_timers();
+        sprites.extend(chip8.frame_sprites());
+        // A crash still leaves whatever was found up to that point
+        if let Err(e) = result {
+            eprintln!("warning: stopped early: {}", e);
+            break;
+        }
+    }
+
+    if sprites.is_empty() {
+        return Err("no sprites found".into());
+    }
+    // Sprites the game built in RAM are exported as they were at the end of the run
+    let sprites: Vec<SpriteRef> = sprites.into_iter().collect();
+    let sheet = SpriteSheet::new(chip8.ram(), &sprites, columns);
+    write_png(&sheet, output)?;
+    println!("Wrote {} sprites to {}", sprites.len(), output.display());
+    Ok(())
+}
+
+fn write_png(sheet: &SpriteSheet, path: &Path) -> Result<(), Box<dyn Error>> {
+    let file =
+        File::create(path).map_err(|e| format!("unable to create {}: {}", path.display(), e))?;
+    let (width, height) = (sheet.width * SCALE, sheet.height * SCALE);
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut image = vec![0u8; width * height];
+    for (idx, pixel) in image.iter_mut().enumerate() {
+        let (x, y) = (idx % width / SCALE, idx / width / SCALE);
+        if sheet.pixels[y * sheet.width + x] {
+            *pixel = 0xFF;
+        }
+    }
+    encoder.write_header()?.write_image_data(&image)?;
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use crate::read_rom;
+use chip8_core::{Chip8, Config, TraceFormat};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const TICKS_PER_FRAME: u64 = 10;
+
+/// Run a ROM headless for `ticks` instructions, writing one trace line each
+pub fn run(
+    rom: &Path,
+    ticks: u64,
+    format: TraceFormat,
+    output: Option<&Path>,
+    config: Config,
+) -> Result<(), Box<dyn Error>> {
+    let data = read_rom(rom)?;
+    let mut chip8 = Chip8::try_with_config(config)?;
+    chip8.load(&data)?;
+
+    let mut out: Box<dyn Write> = match output {
+        Some(path) => {
+            Box::new(BufWriter::new(File::create(path).map_err(|e| {
+                format!("unable to create {}: {}", path.display(), e)
+            })?))
+        }
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    for step in 0..ticks {
+        // Nothing more runs once the program has halted
+        let Some(entry) = chip8.tick_traced()? else {
+            break;
+        };
+        writeln!(out, "{}", entry.format(format, step))?;
+        if (step + 1).is_multiple_of(TICKS_PER_FRAME) {
+            chip8.tick_timers();
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
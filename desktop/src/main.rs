@@ -1,32 +1,173 @@
 use chip8_core::*;
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{Parser, ValueEnum};
+use sdl2::audio::{AudioQueue, AudioSpecDesired};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
 use sdl2::render::Canvas;
 use sdl2::video::Window;
-use std::env;
-use std::fs::File;
-use std::io::Read;
+use sdl2::Sdl;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
 
-const SCALE: u32 = 15;
-const WINDOW_WIDTH: u32 = SCREEN_WIDTH as u32 * SCALE;
-const WINDOW_HEIGHT: u32 = SCREEN_HEIGHT as u32 * SCALE;
-const TICKS_PER_FRAME: usize = 10;
+mod settings;
+
+// Background, then the colours for XO-CHIP's planes 1, 2 and both
+const PALETTE: [Color; 4] = [
+    Color::RGB(0, 0, 0),
+    Color::RGB(255, 255, 255),
+    Color::RGB(255, 102, 0),
+    Color::RGB(153, 102, 0),
+];
+// 10 instructions per 60Hz frame
+const INSTRUCTIONS_PER_SECOND: u32 = 600;
+const SAMPLE_RATE: i32 = 44100;
+// Keep about 50ms of sound queued, enough to ride out a slow frame without
+// the beep lagging behind the sound timer
+const AUDIO_LATENCY: usize = SAMPLE_RATE as usize / 20;
+const VOLUME: f32 = 0.2;
+
+#[derive(Parser)]
+#[command(name = "chip8_sdl", version, about = "Play a Chip 8 ROM in a window")]
+struct Args {
+    /// Path to the ROM
+    rom: PathBuf,
+    /// Window pixels per low resolution pixel, the window can be resized after
+    #[arg(long, default_value_t = 15)]
+    scale: u32,
+    /// Settings file with keys, colours, speed, quirks and audio, defaults
+    /// to chip8.toml if there is one. Command line options win over it.
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// The CHIP-8 community database's programs.json, to pick the variant,
+    /// quirks and speed for ROMs it knows. The settings file's entry for
+    /// the ROM and the command line win over it.
+    #[arg(long, value_name = "PATH")]
+    database: Option<PathBuf>,
+    /// Instructions per second, the default is 10 per 60Hz frame
+    #[arg(long)]
+    ips: Option<u32>,
+    /// Interpreter to behave like (quirks, memory size and opcodes)
+    #[arg(long, value_parser = variant_parser())]
+    variant: Option<Variant>,
+    /// Turn a quirk on, on top of the variant's (repeatable)
+    #[arg(long = "quirk", value_enum, value_name = "QUIRK")]
+    quirks: Vec<Quirk>,
+    /// Turn a quirk off, even if the variant has it (repeatable)
+    #[arg(long = "no-quirk", value_enum, value_name = "QUIRK")]
+    no_quirks: Vec<Quirk>,
+    /// Seed `RND` so every run plays out the same
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Background and foreground colours as hex, e.g. 000000,FFFFFF, then
+    /// optionally XO-CHIP's second plane and both planes
+    #[arg(long, value_parser = parse_palette)]
+    palette: Option<[Option<Color>; 4]>,
+    /// Beep volume from 0 to 1, 0 turns sound off
+    #[arg(long)]
+    volume: Option<f32>,
+}
+
+/// `--variant`, one of `Variant::NAMES`
+fn variant_parser() -> impl TypedValueParser<Value = Variant> {
+    PossibleValuesParser::new(Variant::NAMES).try_map(|name| name.parse::<Variant>())
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Quirk {
+    /// 8xy6/8xyE shift Vy into Vx instead of shifting Vx (COSMAC VIP)
+    ShiftVy,
+    /// Bnnn jumps to xnn + Vx instead of nnn + V0 (CHIP-48, SCHIP)
+    JumpVx,
+    /// Sprites are cut off at the screen edges instead of wrapping around
+    ClipSprites,
+    /// 8xy1/8xy2/8xy3 reset VF to 0 (COSMAC VIP)
+    VfReset,
+    /// Fx55/Fx65 leave I pointing past the last register (COSMAC VIP)
+    MemoryIncrement,
+    /// SCHIP scrolls move half as far in lo-res mode (SCHIP 1.1)
+    HalfPixelScroll,
+}
+
+impl Quirk {
+    fn set(self, quirks: &mut Quirks, on: bool) {
+        match self {
+            Quirk::ShiftVy => quirks.shift_vy = on,
+            Quirk::JumpVx => quirks.jump_vx = on,
+            Quirk::ClipSprites => quirks.clip_sprites = on,
+            Quirk::VfReset => quirks.vf_reset = on,
+            Quirk::MemoryIncrement => quirks.memory_increment = on,
+            Quirk::HalfPixelScroll => quirks.half_pixel_scroll = on,
+        }
+    }
+}
+
+/// Two to four comma separated `RRGGBB` colours, the rest are left alone
+fn parse_palette(arg: &str) -> Result<[Option<Color>; 4], String> {
+    let colors: Vec<&str> = arg.split(',').collect();
+    if !(2..=4).contains(&colors.len()) {
+        return Err("expected 2 to 4 colours".to_string());
+    }
+    let mut palette = [None; 4];
+    for (slot, hex) in palette.iter_mut().zip(colors) {
+        let hex = hex.trim().trim_start_matches('#');
+        let rgb = match u32::from_str_radix(hex, 16) {
+            Ok(rgb) if hex.len() == 6 => rgb,
+            _ => return Err(format!("invalid colour '{}', use RRGGBB", hex)),
+        };
+        *slot = Some(Color::RGB((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8));
+    }
+    Ok(palette)
+}
 
 fn main() {
-    let args: Vec<_> = env::args().collect();
-    if args.len() != 2 {
-        println!("Usage: cargo run path/to/game");
-        return;
+    let args = Args::parse();
+    let settings = match settings::load(args.config.as_deref()) {
+        Ok(settings) => settings,
+        Err(e) => {
+            println!("{}", e);
+            return;
+        }
+    };
+    let mut palette = settings::palette(&settings, PALETTE);
+    for (slot, color) in palette.iter_mut().zip(args.palette.unwrap_or_default()) {
+        if let Some(color) = color {
+            *slot = color;
+        }
+    }
+    let database = match &args.database {
+        Some(path) => match settings::database(path) {
+            Ok(db) => Some(db),
+            Err(e) => {
+                println!("{}", e);
+                return;
+            }
+        },
+        None => None,
+    };
+    let keymap = settings::keymap(&settings);
+    let volume = match args.volume {
+        Some(volume) => volume,
+        None if settings.audio.enabled == Some(false) => 0.0,
+        None => settings.audio.volume.unwrap_or(VOLUME),
     }
+    .clamp(0.0, 1.0);
+    let scale = args.scale.max(1);
 
     // setup SDL
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
     let window = video_subsystem
-        .window("Chip-8 Emulator", WINDOW_WIDTH, WINDOW_HEIGHT)
+        .window(
+            "Chip-8 Emulator",
+            SCREEN_WIDTH as u32 * scale,
+            SCREEN_HEIGHT as u32 * scale,
+        )
         .position_centered()
+        .resizable()
         .opengl()
         .build()
         .unwrap();
@@ -35,15 +176,75 @@ fn main() {
     canvas.clear();
     canvas.present();
 
+    let audio = if volume > 0.0 {
+        open_audio(&sdl_context)
+    } else {
+        None
+    };
+
     let mut event_pump = sdl_context.event_pump().unwrap();
 
-    let mut chip8 = Chip8::new();
+    let name = args.rom.display();
+    let buffer = match fs::read(&args.rom) {
+        Ok(buffer) => buffer,
+        Err(e) => {
+            println!("Unable to read {}: {}", name, e);
+            return;
+        }
+    };
 
-    let mut rom = File::open(&args[1]).expect("Unable to open file"); // see if we can use somethine else other than expect
-    let mut buffer = Vec::new();
+    // The database, then the file's entry for this ROM, then the command line
+    let known = database.as_ref().and_then(|db| db.lookup(&buffer));
+    if let Some(known) = known {
+        println!("Recognised {} ({})", known.title, known.platform);
+        let _ = canvas.window_mut().set_title(&known.title);
+    }
+    let game = settings.game(&buffer).cloned();
+    let settings = settings.for_rom(&buffer);
+    let mut config = match known {
+        Some(known) if game.is_none() => known.config(),
+        _ => settings.config(),
+    };
+    if let Some(variant) = args.variant {
+        config = variant.config();
+        settings.quirks.apply(&mut config.quirks);
+    }
+    for quirk in &args.quirks {
+        quirk.set(&mut config.quirks, true);
+    }
+    for quirk in &args.no_quirks {
+        quirk.set(&mut config.quirks, false);
+    }
+    let mut chip8 = match Chip8::try_with_config(config) {
+        Ok(chip8) => chip8,
+        Err(e) => {
+            println!("Unable to set up the machine: {}", e);
+            return;
+        }
+    };
+    if args.seed.is_some() {
+        chip8.set_rng_seed(args.seed);
+    }
+    if let Err(e) = chip8.load(&buffer) {
+        println!("Unable to load {}: {}", name, e);
+        return;
+    }
+    // High scores saved with the SCHIP flags go next to the ROM
+    let flags = FileFlags(PathBuf::from(format!("{}.flags", name)));
+    chip8.set_flag_storage(Box::new(flags));
 
-    rom.read_to_end(&mut buffer).unwrap();
-    chip8.load(&buffer);
+    // vsync paces the loop, but monitors aren't all 60Hz, so run the
+    // machine by the clock rather than one frame per loop
+    let ips = args
+        .ips
+        .or(game.and_then(|game| game.ips))
+        .or(known
+            .and_then(|known| known.ticks_per_frame)
+            .map(|ticks| ticks * 60))
+        .or(settings.ips)
+        .unwrap_or(INSTRUCTIONS_PER_SECOND);
+    let mut scheduler = Scheduler::new(ips);
+    let mut last = Instant::now();
 
     'gameloop: loop {
         for evt in event_pump.poll_iter() {
@@ -58,14 +259,14 @@ fn main() {
                 Event::KeyDown {
                     keycode: Some(key), ..
                 } => {
-                    if let Some(k) = key2btn(key) {
+                    if let Some(&k) = keymap.get(&key) {
                         chip8.keypress(k, true);
                     }
                 }
                 Event::KeyUp {
                     keycode: Some(key), ..
                 } => {
-                    if let Some(k) = key2btn(key) {
+                    if let Some(&k) = keymap.get(&key) {
                         chip8.keypress(k, false);
                     }
                 }
@@ -73,54 +274,110 @@ fn main() {
             }
         }
 
-        for _ in 0..TICKS_PER_FRAME {
-            chip8.tick();
+        let now = Instant::now();
+        let elapsed = now - last;
+        last = now;
+        match scheduler.run_for(&mut chip8, elapsed) {
+            Ok(_) if chip8.state() == State::Halted => {
+                println!("Program exited");
+                break 'gameloop;
+            }
+            Ok(_) => {
+                if let Some(queue) = &audio {
+                    queue_audio(&mut chip8, queue, volume);
+                }
+                draw_screen(&chip8.latest_frame(), &palette, &mut canvas);
+            }
+            Err(e) => {
+                println!("Emulation stopped: {}", e);
+                break 'gameloop;
+            }
         }
-        chip8.tick_timers();
-        draw_screen(&chip8, &mut canvas);
     }
 }
 
-fn draw_screen(chip8: &Chip8, canvas: &mut Canvas<Window>) {
-    // Clear canvas as black
-    canvas.set_draw_color(Color::RGB(0, 0, 0));
+fn draw_screen(frame: &Frame, palette: &[Color; 4], canvas: &mut Canvas<Window>) {
+    // Clear canvas to the background colour
+    canvas.set_draw_color(palette[0]);
     canvas.clear();
 
-    let screen_buf = chip8.get_display();
-    // Now set draw color to white, iterate through each point and see if it should be drawn
-    canvas.set_draw_color(Color::RGB(255, 255, 255));
-    for (i, pixel) in screen_buf.iter().enumerate() {
-        if *pixel {
-            // Convert our 1D array's index into a 2D (x,y) position
-            let x = (i % SCREEN_WIDTH) as u32;
-            let y = (i / SCREEN_WIDTH) as u32;
-
-            // Draw a rectangle at (x,y), scaled up by our SCALE value
-            let rect = Rect::new((x * SCALE) as i32, (y * SCALE) as i32, SCALE, SCALE);
+    // Scale to fit the window as it's been resized, centred with black bars.
+    // Hi-res modes have more pixels in the same window, so they're drawn smaller
+    let (width, height) = canvas.output_size().unwrap();
+    let scale = (width / frame.width as u32)
+        .min(height / frame.height as u32)
+        .max(1);
+    let left = (width.saturating_sub(frame.width as u32 * scale) / 2) as i32;
+    let top = (height.saturating_sub(frame.height as u32 * scale) / 2) as i32;
+
+    // Iterate through each point and draw it in its colour, if it's lit
+    for y in 0..frame.height {
+        for x in 0..frame.width {
+            let color = frame.color(x, y) as usize;
+            if color == 0 {
+                continue;
+            }
+            let (x, y) = (x as u32, y as u32);
+
+            // Draw a rectangle at (x,y), scaled up to fill the window
+            canvas.set_draw_color(palette[color]);
+            let rect = Rect::new(
+                left + (x * scale) as i32,
+                top + (y * scale) as i32,
+                scale,
+                scale,
+            );
             canvas.fill_rect(rect).unwrap();
         }
     }
     canvas.present();
 }
 
-fn key2btn(key: Keycode) -> Option<usize> {
-    match key {
-        Keycode::Num1 => Some(0x1),
-        Keycode::Num2 => Some(0x2),
-        Keycode::Num3 => Some(0x3),
-        Keycode::Num4 => Some(0xC),
-        Keycode::Q => Some(0x4),
-        Keycode::W => Some(0x5),
-        Keycode::E => Some(0x6),
-        Keycode::R => Some(0xD),
-        Keycode::A => Some(0x7),
-        Keycode::S => Some(0x8),
-        Keycode::D => Some(0x9),
-        Keycode::F => Some(0xE),
-        Keycode::Z => Some(0xA),
-        Keycode::X => Some(0x0),
-        Keycode::C => Some(0xB),
-        Keycode::V => Some(0xF),
-        _ => None,
+/// A running queue for the beep, carrying on without sound if there's no
+/// audio device
+fn open_audio(sdl_context: &Sdl) -> Option<AudioQueue<f32>> {
+    let spec = AudioSpecDesired {
+        freq: Some(SAMPLE_RATE),
+        channels: Some(1),
+        samples: None,
+    };
+    let queue = sdl_context
+        .audio()
+        .and_then(|audio| audio.open_queue::<f32, _>(None, &spec))
+        .map_err(|e| println!("No sound: {}", e))
+        .ok()?;
+    queue.resume();
+    Some(queue)
+}
+
+/// Top the queue back up with whatever the machine is playing now
+fn queue_audio(chip8: &mut Chip8, queue: &AudioQueue<f32>, volume: f32) {
+    let queued = queue.size() as usize / std::mem::size_of::<f32>();
+    let mut buf = vec![0.0; AUDIO_LATENCY.saturating_sub(queued)];
+    if buf.is_empty() {
+        return;
+    }
+    chip8.fill_audio_buffer(queue.spec().freq as u32, &mut buf);
+    for sample in &mut buf {
+        *sample *= volume;
+    }
+    if let Err(e) = queue.queue_audio(&buf) {
+        println!("Unable to play sound: {}", e);
+    }
+}
+
+/// RPL user flags kept in a file
+#[derive(Clone)]
+struct FileFlags(PathBuf);
+
+impl FlagStorage for FileFlags {
+    fn load(&mut self) -> Option<Vec<u8>> {
+        std::fs::read(&self.0).ok()
+    }
+
+    fn save(&mut self, flags: &[u8]) {
+        if let Err(e) = std::fs::write(&self.0, flags) {
+            println!("Unable to save flags to {}: {}", self.0.display(), e);
+        }
     }
 }
@@ -0,0 +1,81 @@
+use chip8_core::settings::Rgb;
+use chip8_core::{RomDatabase, Settings};
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Read from the working directory when `--config` isn't given
+const DEFAULT_FILE: &str = "chip8.toml";
+
+/// The same bindings as the wasm frontend's default keymap, the left four
+/// columns of a QWERTY keyboard
+const DEFAULT_KEYS: [(Keycode, usize); 16] = [
+    (Keycode::Num1, 0x1),
+    (Keycode::Num2, 0x2),
+    (Keycode::Num3, 0x3),
+    (Keycode::Num4, 0xC),
+    (Keycode::Q, 0x4),
+    (Keycode::W, 0x5),
+    (Keycode::E, 0x6),
+    (Keycode::R, 0xD),
+    (Keycode::A, 0x7),
+    (Keycode::S, 0x8),
+    (Keycode::D, 0x9),
+    (Keycode::F, 0xE),
+    (Keycode::Z, 0xA),
+    (Keycode::X, 0x0),
+    (Keycode::C, 0xB),
+    (Keycode::V, 0xF),
+];
+
+/// Read the settings file at `path`, or `chip8.toml` if there is one
+pub fn load(path: Option<&Path>) -> Result<Settings, String> {
+    let path = match path {
+        Some(path) => path,
+        None if Path::new(DEFAULT_FILE).exists() => Path::new(DEFAULT_FILE),
+        None => return Ok(Settings::default()),
+    };
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+    Settings::from_toml(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// The ROM database at `path`
+pub fn database(path: &Path) -> Result<RomDatabase, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Unable to read {}: {}", path.display(), e))?;
+    RomDatabase::from_json(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// The default palette with the file's colours swapped in
+pub fn palette(settings: &Settings, mut palette: [Color; 4]) -> [Color; 4] {
+    let colors = &settings.colors;
+    let rgb = |c: Rgb| Color::RGB(c.r, c.g, c.b);
+    for (slot, color) in
+        palette
+            .iter_mut()
+            .zip([colors.background, colors.plane1, colors.plane2, colors.both])
+    {
+        if let Some(color) = color {
+            *slot = rgb(color);
+        }
+    }
+    palette
+}
+
+/// Default bindings plus the file's, which use SDL key names like `Up` or
+/// `Keypad 5`
+pub fn keymap(settings: &Settings) -> HashMap<Keycode, usize> {
+    let mut keys: HashMap<Keycode, usize> = DEFAULT_KEYS.into_iter().collect();
+    for (name, &button) in &settings.keys {
+        match Keycode::from_name(name) {
+            Some(key) => {
+                keys.insert(key, button as usize);
+            }
+            None => println!("Ignoring unknown key '{}' in the settings", name),
+        }
+    }
+    keys
+}
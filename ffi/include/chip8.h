@@ -0,0 +1,110 @@
+/*
+ * C API for the chip8 emulator core, implemented by libchip8_ffi.
+ *
+ *     chip8 *c8 = chip8_new(CHIP8_VARIANT_CHIP8);
+ *     if (chip8_load(c8, rom, rom_len) != 0)
+ *         fprintf(stderr, "%s\n", chip8_last_error(c8));
+ *     while (running) {
+ *         chip8_set_key(c8, 0x5, up_pressed);
+ *         if (chip8_run_frame(c8, 10) == CHIP8_ERROR)
+ *             break;
+ *         chip8_framebuffer_rgba(c8, 0xFFFFFFFF, 0x000000FF, pixels, sizeof pixels);
+ *     }
+ *     chip8_free(c8);
+ *
+ * A machine must only be used from one thread at a time.
+ */
+#ifndef CHIP8_H
+#define CHIP8_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct chip8 chip8;
+
+/* Interpreters to behave like, each with its own quirks, memory size and opcodes */
+enum chip8_variant {
+    /* CHIP-8 plus the SCHIP opcodes, no quirks */
+    CHIP8_VARIANT_CHIP8 = 0,
+    CHIP8_VARIANT_COSMAC_VIP = 1,
+    CHIP8_VARIANT_CHIP48 = 2,
+    CHIP8_VARIANT_SCHIP = 3,
+    CHIP8_VARIANT_XO_CHIP = 4,
+    CHIP8_VARIANT_HIRES_CHIP8 = 5,
+    CHIP8_VARIANT_ETI_660 = 6,
+    /* Only when the core is built with Mega-CHIP, chip8_new gives NULL otherwise */
+    CHIP8_VARIANT_MEGA_CHIP = 7,
+};
+
+/* What chip8_tick and chip8_run_frame return when they succeed */
+enum chip8_state {
+    CHIP8_RUNNING = 0,
+    /* Blocked on Fx0A until a key is pressed */
+    CHIP8_WAITING_FOR_KEY = 1,
+    /* The program exited with 00FD, nothing runs until chip8_reset */
+    CHIP8_HALTED = 2,
+    CHIP8_ERRORED = 3,
+    /* The PC reached a breakpoint */
+    CHIP8_BREAK = 4,
+};
+
+/* Returned when a call fails, chip8_last_error says why */
+#define CHIP8_ERROR (-1)
+
+/* A new machine, NULL for an unknown variant. Free it with chip8_free. */
+chip8 *chip8_new(int variant);
+void chip8_free(chip8 *c8);
+
+/* Why the last call on c8 that can return CHIP8_ERROR did, "" if it
+ * succeeded. Valid until the next call on c8. */
+const char *chip8_last_error(const chip8 *c8);
+
+/* Copy a ROM into memory at the start address, 0 or CHIP8_ERROR */
+int chip8_load(chip8 *c8, const uint8_t *rom, size_t len);
+/* Back to power-on with RAM cleared, load the ROM again after */
+void chip8_reset(chip8 *c8);
+
+/* Run one instruction, a chip8_state or CHIP8_ERROR */
+int chip8_tick(chip8 *c8);
+/* Run `ticks` instructions and a 60Hz timer tick, a chip8_state or CHIP8_ERROR */
+int chip8_run_frame(chip8 *c8, uint32_t ticks);
+/* Count the delay and sound timers down, call at 60Hz when using chip8_tick */
+void chip8_tick_timers(chip8 *c8);
+
+/* Press or release key 0x0-0xF, other keys are ignored */
+void chip8_set_key(chip8 *c8, uint8_t key, bool pressed);
+
+/* Current screen size in pixels, it changes with SCHIP's hi-res mode */
+size_t chip8_screen_width(const chip8 *c8);
+size_t chip8_screen_height(const chip8 *c8);
+/* The screen as packed rows, width / 8 bytes each with the leftmost pixel
+ * in the high bit. Stores the length in `len` unless it's NULL. Valid
+ * until the next call that runs the machine. */
+const uint8_t *chip8_framebuffer(const chip8 *c8, size_t *len);
+/* Render the screen into `buf` as 4 bytes per pixel in RGBA order, colours
+ * given as 0xRRGGBBAA. `len` must be width * height * 4, 0 or CHIP8_ERROR. */
+int chip8_framebuffer_rgba(chip8 *c8, uint32_t fg, uint32_t bg, uint8_t *buf, size_t len);
+
+/* Whether the sound timer is running */
+bool chip8_sound_active(const chip8 *c8);
+/* Fill `buf` with `len` mono samples from -1 to 1 at `rate` Hz, silence
+ * when the sound timer isn't running */
+void chip8_fill_audio(chip8 *c8, uint32_t rate, float *buf, size_t len);
+
+/* The whole machine as a JSON string, free it with chip8_free_string */
+char *chip8_save_state(const chip8 *c8);
+/* Resume from chip8_save_state's JSON, 0 or CHIP8_ERROR. The save must come
+ * from a machine with the same memory and stack size. */
+int chip8_load_state(chip8 *c8, const char *json);
+void chip8_free_string(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CHIP8_H */
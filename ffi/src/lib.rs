@@ -0,0 +1,252 @@
+use chip8_core::*;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::ptr;
+use std::slice;
+
+/// Returned in place of a state or 0 when a call fails, see `chip8_last_error`
+const CHIP8_ERROR: c_int = -1;
+
+/// The machine behind the C API's `chip8` pointer
+pub struct Handle {
+    chip8: Chip8,
+    // Why the last call failed, empty after one that didn't
+    error: CString,
+}
+
+impl Handle {
+    fn result<T, E: std::fmt::Display>(&mut self, result: Result<T, E>) -> Option<T> {
+        match result {
+            Ok(val) => {
+                self.error = CString::default();
+                Some(val)
+            }
+            Err(e) => {
+                self.error = CString::new(e.to_string()).unwrap_or_default();
+                None
+            }
+        }
+    }
+
+    /// `result`'s state as a `chip8_state`, or `CHIP8_ERROR`
+    fn state(&mut self, result: Result<State, Chip8Error>) -> c_int {
+        match self.result(result) {
+            Some(state) => state_code(state),
+            None => CHIP8_ERROR,
+        }
+    }
+}
+
+fn state_code(state: State) -> c_int {
+    match state {
+        State::Running => 0,
+        State::WaitingForKey => 1,
+        State::Halted => 2,
+        State::Errored => 3,
+        State::Break => 4,
+    }
+}
+
+/// `chip8_variant` codes are indices into `Variant::ALL`
+fn variant(code: c_int) -> Option<Variant> {
+    let idx = usize::try_from(code).ok()?;
+    Variant::ALL.get(idx).copied()
+}
+
+/// Callers promise the pointer came from `chip8_new` and isn't freed
+unsafe fn handle<'a>(c8: *mut Handle) -> &'a mut Handle {
+    c8.as_mut().expect("null chip8 pointer")
+}
+
+unsafe fn handle_ref<'a>(c8: *const Handle) -> &'a Handle {
+    c8.as_ref().expect("null chip8 pointer")
+}
+
+/// A new machine for one of the `chip8_variant`s, null for an unknown one
+#[no_mangle]
+pub extern "C" fn chip8_new(variant_code: c_int) -> *mut Handle {
+    let chip8 =
+        variant(variant_code).and_then(|variant| Chip8::try_with_config(variant.config()).ok());
+    match chip8 {
+        Some(chip8) => Box::into_raw(Box::new(Handle {
+            chip8,
+            error: CString::default(),
+        })),
+        None => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// `c8` must come from `chip8_new` and not be used again, null is ignored
+#[no_mangle]
+pub unsafe extern "C" fn chip8_free(c8: *mut Handle) {
+    if !c8.is_null() {
+        drop(Box::from_raw(c8));
+    }
+}
+
+/// # Safety
+/// `c8` must come from `chip8_new`
+#[no_mangle]
+pub unsafe extern "C" fn chip8_last_error(c8: *const Handle) -> *const c_char {
+    handle_ref(c8).error.as_ptr()
+}
+
+/// # Safety
+/// `c8` must come from `chip8_new` and `rom` point to `len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load(c8: *mut Handle, rom: *const u8, len: usize) -> c_int {
+    let c8 = handle(c8);
+    let rom = if rom.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(rom, len)
+    };
+    let result = c8.chip8.load(rom);
+    match c8.result(result) {
+        Some(()) => 0,
+        None => CHIP8_ERROR,
+    }
+}
+
+/// # Safety
+/// `c8` must come from `chip8_new`
+#[no_mangle]
+pub unsafe extern "C" fn chip8_reset(c8: *mut Handle) {
+    handle(c8).chip8.reset();
+}
+
+/// # Safety
+/// `c8` must come from `chip8_new`
+#[no_mangle]
+pub unsafe extern "C" fn chip8_tick(c8: *mut Handle) -> c_int {
+    let c8 = handle(c8);
+    let result = c8.chip8.tick();
+    c8.state(result)
+}
+
+/// # Safety
+/// `c8` must come from `chip8_new`
+#[no_mangle]
+pub unsafe extern "C" fn chip8_run_frame(c8: *mut Handle, ticks: u32) -> c_int {
+    let c8 = handle(c8);
+    let ran = c8.chip8.run_frame(ticks).map(|_| ());
+    let result = ran.map(|()| c8.chip8.state());
+    c8.state(result)
+}
+
+/// # Safety
+/// `c8` must come from `chip8_new`
+#[no_mangle]
+pub unsafe extern "C" fn chip8_tick_timers(c8: *mut Handle) {
+    handle(c8).chip8.tick_timers();
+}
+
+/// # Safety
+/// `c8` must come from `chip8_new`
+#[no_mangle]
+pub unsafe extern "C" fn chip8_set_key(c8: *mut Handle, key: u8, pressed: bool) {
+    let c8 = handle(c8);
+    if (key as usize) < c8.chip8.keys().len() {
+        c8.chip8.keypress(key as usize, pressed);
+    }
+}
+
+/// # Safety
+/// `c8` must come from `chip8_new`
+#[no_mangle]
+pub unsafe extern "C" fn chip8_screen_width(c8: *const Handle) -> usize {
+    handle_ref(c8).chip8.screen_width()
+}
+
+/// # Safety
+/// `c8` must come from `chip8_new`
+#[no_mangle]
+pub unsafe extern "C" fn chip8_screen_height(c8: *const Handle) -> usize {
+    handle_ref(c8).chip8.screen_height()
+}
+
+/// # Safety
+/// `c8` must come from `chip8_new`, `len` may be null
+#[no_mangle]
+pub unsafe extern "C" fn chip8_framebuffer(c8: *const Handle, len: *mut usize) -> *const u8 {
+    let packed = handle_ref(c8).chip8.get_display_packed();
+    if let Some(len) = len.as_mut() {
+        *len = packed.len();
+    }
+    packed.as_ptr()
+}
+
+/// # Safety
+/// `c8` must come from `chip8_new` and `buf` point to `len` writable bytes
+#[no_mangle]
+pub unsafe extern "C" fn chip8_framebuffer_rgba(
+    c8: *mut Handle,
+    fg: u32,
+    bg: u32,
+    buf: *mut u8,
+    len: usize,
+) -> c_int {
+    let c8 = handle(c8);
+    let size = c8.chip8.screen_width() * c8.chip8.screen_height() * 4;
+    if buf.is_null() || len != size {
+        c8.error = CString::new(format!("RGBA buffer must be {} bytes", size)).unwrap();
+        return CHIP8_ERROR;
+    }
+    c8.chip8
+        .get_display_rgba(fg, bg, slice::from_raw_parts_mut(buf, len));
+    c8.error = CString::default();
+    0
+}
+
+/// # Safety
+/// `c8` must come from `chip8_new`
+#[no_mangle]
+pub unsafe extern "C" fn chip8_sound_active(c8: *const Handle) -> bool {
+    handle_ref(c8).chip8.st() > 0
+}
+
+/// # Safety
+/// `c8` must come from `chip8_new` and `buf` point to `len` floats
+#[no_mangle]
+pub unsafe extern "C" fn chip8_fill_audio(c8: *mut Handle, rate: u32, buf: *mut f32, len: usize) {
+    if !buf.is_null() {
+        handle(c8)
+            .chip8
+            .fill_audio_buffer(rate, slice::from_raw_parts_mut(buf, len));
+    }
+}
+
+/// # Safety
+/// `c8` must come from `chip8_new`
+#[no_mangle]
+pub unsafe extern "C" fn chip8_save_state(c8: *const Handle) -> *mut c_char {
+    let json = handle_ref(c8).chip8.save_state().to_json();
+    CString::new(json).unwrap().into_raw()
+}
+
+/// # Safety
+/// `c8` must come from `chip8_new` and `json` be a NUL terminated string
+#[no_mangle]
+pub unsafe extern "C" fn chip8_load_state(c8: *mut Handle, json: *const c_char) -> c_int {
+    let c8 = handle(c8);
+    if json.is_null() {
+        c8.error = CString::new("no save state given").unwrap();
+        return CHIP8_ERROR;
+    }
+    let json = CStr::from_ptr(json).to_string_lossy();
+    let result = SaveState::from_json(&json).and_then(|save| c8.chip8.load_state(&save));
+    match c8.result(result) {
+        Some(()) => 0,
+        None => CHIP8_ERROR,
+    }
+}
+
+/// # Safety
+/// `s` must come from `chip8_save_state` and not be used again, null is
+/// ignored
+#[no_mangle]
+pub unsafe extern "C" fn chip8_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
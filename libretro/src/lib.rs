@@ -0,0 +1,480 @@
+use chip8_core::*;
+use ffi::*;
+use std::cell::RefCell;
+use std::ffi::{c_char, c_uint, c_void, CStr, CString};
+use std::ptr;
+use std::slice;
+use std::sync::OnceLock;
+
+mod ffi;
+
+const FPS: f64 = 60.0;
+const SAMPLE_RATE: u32 = 44100;
+const SAMPLES_PER_FRAME: usize = SAMPLE_RATE as usize / 60;
+const VOLUME: f32 = 0.2;
+// Background, then the colours for XO-CHIP's planes 1, 2 and both
+const PALETTE: [u32; 4] = [0x000000, 0xFFFFFF, 0xFF6600, 0x996600];
+const TICKS_PER_FRAME: u32 = 10;
+// How long messages stay on screen, in frames
+const MESSAGE_FRAMES: c_uint = 180;
+
+// Core options, shown in RetroArch's Quick Menu. The first value is the default.
+const VARIANT_OPTION: &CStr = c"chip8_variant";
+const TICKS_OPTION: &CStr = c"chip8_ticks";
+fn options() -> [(&'static CStr, &'static CStr); 2] {
+    static VARIANTS: OnceLock<CString> = OnceLock::new();
+    let variants = VARIANTS.get_or_init(|| {
+        let names = Variant::NAMES.join("|");
+        CString::new(format!("Interpreter (restart to apply); {}", names)).unwrap()
+    });
+    [
+        (VARIANT_OPTION, variants),
+        (
+            TICKS_OPTION,
+            c"Instructions per frame; 10|15|20|30|50|100|200|500|1000",
+        ),
+    ]
+}
+
+// The CHIP-8 key each keyboard key presses, keys 0-F in order. The same
+// layout as the other frontends, the left of a QWERTY keyboard. RETROK_*
+// codes are the lowercase ASCII characters.
+const KEYBOARD: [u8; 16] = *b"x123qweasdzc4rfv";
+// RetroPad buttons: the d-pad is 5/7/8/9 (WASD in the keyboard layout) with
+// 4 and 6 (Q and E) beside it on B and A, then 1, 2, 3 and C above them
+const JOYPAD: [(c_uint, usize); 10] = [
+    (RETRO_DEVICE_ID_JOYPAD_UP, 0x5),
+    (RETRO_DEVICE_ID_JOYPAD_LEFT, 0x7),
+    (RETRO_DEVICE_ID_JOYPAD_DOWN, 0x8),
+    (RETRO_DEVICE_ID_JOYPAD_RIGHT, 0x9),
+    (RETRO_DEVICE_ID_JOYPAD_B, 0x4),
+    (RETRO_DEVICE_ID_JOYPAD_A, 0x6),
+    (RETRO_DEVICE_ID_JOYPAD_Y, 0x1),
+    (RETRO_DEVICE_ID_JOYPAD_X, 0x2),
+    (RETRO_DEVICE_ID_JOYPAD_SELECT, 0x3),
+    (RETRO_DEVICE_ID_JOYPAD_START, 0xC),
+];
+
+/// Everything the frontend handed over, plus the loaded game
+struct Retro {
+    environment: Option<RetroEnvironment>,
+    video_refresh: Option<RetroVideoRefresh>,
+    audio_batch: Option<RetroAudioSampleBatch>,
+    input_poll: Option<RetroInputPoll>,
+    input_state: Option<RetroInputState>,
+    core: Option<Core>,
+}
+
+struct Core {
+    chip8: Chip8,
+    rom: Vec<u8>,
+    ticks: u32,
+    framebuffer: Vec<u32>,
+    audio: Vec<f32>,
+    samples: Vec<i16>,
+    state_size: usize,
+    // Set after an emulation fault, the last frame stays up
+    stopped: bool,
+}
+
+// libretro calls a core from one thread
+thread_local! {
+    static RETRO: RefCell<Retro> = const {
+        RefCell::new(Retro {
+            environment: None,
+            video_refresh: None,
+            audio_batch: None,
+            input_poll: None,
+            input_state: None,
+            core: None,
+        })
+    };
+}
+
+fn with_retro<R>(f: impl FnOnce(&mut Retro) -> R) -> R {
+    RETRO.with_borrow_mut(f)
+}
+
+impl Retro {
+    fn environment<T>(&self, cmd: c_uint, data: &mut T) -> bool {
+        self.environment
+            .is_some_and(|env| env(cmd, data as *mut T as *mut c_void))
+    }
+
+    fn variable(&self, key: &CStr) -> Option<String> {
+        let mut var = RetroVariable {
+            key: key.as_ptr(),
+            value: ptr::null(),
+        };
+        if !self.environment(RETRO_ENVIRONMENT_GET_VARIABLE, &mut var) || var.value.is_null() {
+            return None;
+        }
+        let value = unsafe { CStr::from_ptr(var.value) };
+        Some(value.to_string_lossy().into_owned())
+    }
+
+    fn options_changed(&self) -> bool {
+        let mut changed = false;
+        self.environment(RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE, &mut changed) && changed
+    }
+
+    fn variant(&self) -> Variant {
+        self.variable(VARIANT_OPTION)
+            .and_then(|name| name.parse().ok())
+            .unwrap_or_default()
+    }
+
+    fn ticks(&self) -> u32 {
+        self.variable(TICKS_OPTION)
+            .and_then(|ticks| ticks.parse().ok())
+            .unwrap_or(TICKS_PER_FRAME)
+    }
+
+    fn message(&self, msg: &str) {
+        let msg = CString::new(msg).unwrap_or_default();
+        let mut message = RetroMessage {
+            msg: msg.as_ptr(),
+            frames: MESSAGE_FRAMES,
+        };
+        self.environment(RETRO_ENVIRONMENT_SET_MESSAGE, &mut message);
+    }
+
+    fn pressed(&self, device: c_uint, id: c_uint) -> bool {
+        self.input_state
+            .is_some_and(|state| state(0, device, 0, id) != 0)
+    }
+
+    /// A machine for `rom` set up by the core options
+    fn start(&self, rom: Vec<u8>) -> Result<Core, LoadError> {
+        let mut chip8 = Chip8::try_with_config(self.variant().config())?;
+        chip8.load(&rom)?;
+        Ok(Core {
+            state_size: max_state_size(&chip8),
+            chip8,
+            rom,
+            ticks: self.ticks(),
+            framebuffer: Vec::with_capacity(HIRES_WIDTH * HIRES_HEIGHT),
+            audio: vec![0.0; SAMPLES_PER_FRAME],
+            samples: vec![0; SAMPLES_PER_FRAME * 2],
+            stopped: false,
+        })
+    }
+}
+
+impl Core {
+    fn input(&mut self, retro: &Retro) {
+        for (k, &key) in KEYBOARD.iter().enumerate() {
+            let pressed = retro.pressed(RETRO_DEVICE_KEYBOARD, key as c_uint)
+                || JOYPAD
+                    .iter()
+                    .any(|&(id, btn)| btn == k && retro.pressed(RETRO_DEVICE_JOYPAD, id));
+            self.chip8.keypress(k, pressed);
+        }
+    }
+
+    fn run(&mut self, retro: &Retro) {
+        if !self.stopped {
+            if let Err(e) = self.chip8.run_frame(self.ticks) {
+                self.stopped = true;
+                retro.message(&format!("Emulation stopped: {}", e));
+            }
+        }
+
+        let frame = self.chip8.latest_frame();
+        let (width, height) = (frame.width, frame.height);
+        self.framebuffer.clear();
+        for y in 0..height {
+            for x in 0..width {
+                self.framebuffer.push(PALETTE[frame.color(x, y) as usize]);
+            }
+        }
+        if let Some(video_refresh) = retro.video_refresh {
+            video_refresh(
+                self.framebuffer.as_ptr() as *const c_void,
+                width as c_uint,
+                height as c_uint,
+                width * 4,
+            );
+        }
+
+        if self.stopped {
+            self.audio.fill(0.0);
+        } else {
+            self.chip8.fill_audio_buffer(SAMPLE_RATE, &mut self.audio);
+        }
+        for (pair, sample) in self.samples.chunks_exact_mut(2).zip(&self.audio) {
+            pair.fill((sample * VOLUME * i16::MAX as f32) as i16);
+        }
+        if let Some(audio_batch) = retro.audio_batch {
+            audio_batch(self.samples.as_ptr(), SAMPLES_PER_FRAME);
+        }
+    }
+}
+
+/// Length of the longest save state JSON `chip8` can produce
+///
+/// libretro wants the same size for every save, so shorter ones are padded
+/// with zeros, which JSON never contains.
+fn max_state_size(chip8: &Chip8) -> usize {
+    let mut save = chip8.save_state();
+    let state = &mut save.state;
+    let screen = HIRES_WIDTH * HIRES_HEIGHT / 8;
+    state.pc = u16::MAX;
+    state.i_reg = u16::MAX;
+    state.sp = u16::MAX;
+    state.ram.fill(u8::MAX);
+    state.v_reg.fill(u8::MAX);
+    state.stack.fill(u16::MAX);
+    state.dt = u8::MAX;
+    state.st = u8::MAX;
+    state.keys.fill(false);
+    state.hires = false;
+    state.two_page = false;
+    state.screen = vec![u8::MAX; screen];
+    state.planes = u8::MAX;
+    state.plane2 = vec![u8::MAX; screen];
+    state.instructions = u64::MAX;
+    state.frames = u64::MAX;
+    state.rng = u32::MAX;
+    state.audio_pattern.fill(u8::MAX);
+    state.pitch = u8::MAX;
+    save.to_json().len()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(cb: RetroEnvironment) {
+    with_retro(|retro| {
+        retro.environment = Some(cb);
+        let mut options: Vec<RetroVariable> = options()
+            .iter()
+            .map(|(key, value)| RetroVariable {
+                key: key.as_ptr(),
+                value: value.as_ptr(),
+            })
+            .collect();
+        options.push(RetroVariable {
+            key: ptr::null(),
+            value: ptr::null(),
+        });
+        retro.environment(RETRO_ENVIRONMENT_SET_VARIABLES, &mut options[0]);
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(cb: RetroVideoRefresh) {
+    with_retro(|retro| retro.video_refresh = Some(cb));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_cb: RetroAudioSample) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(cb: RetroAudioSampleBatch) {
+    with_retro(|retro| retro.audio_batch = Some(cb));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(cb: RetroInputPoll) {
+    with_retro(|retro| retro.input_poll = Some(cb));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(cb: RetroInputState) {
+    with_retro(|retro| retro.input_state = Some(cb));
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    with_retro(|retro| retro.core = None);
+}
+
+/// # Safety
+/// `info` must point to a `retro_system_info`
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut RetroSystemInfo) {
+    *info = RetroSystemInfo {
+        library_name: c"chip8".as_ptr(),
+        library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+        valid_extensions: c"ch8|c8|sc8|xo8|rom".as_ptr(),
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+/// # Safety
+/// `info` must point to a `retro_system_av_info`
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut RetroSystemAvInfo) {
+    *info = RetroSystemAvInfo {
+        geometry: RetroGameGeometry {
+            base_width: SCREEN_WIDTH as c_uint,
+            base_height: SCREEN_HEIGHT as c_uint,
+            max_width: HIRES_WIDTH as c_uint,
+            max_height: HIRES_HEIGHT as c_uint,
+            aspect_ratio: 2.0,
+        },
+        timing: RetroSystemTiming {
+            fps: FPS,
+            sample_rate: SAMPLE_RATE as f64,
+        },
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+/// Power cycle, picking up a changed interpreter option
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    with_retro(|retro| {
+        let Some(rom) = retro.core.take().map(|core| core.rom) else {
+            return;
+        };
+        match retro.start(rom) {
+            Ok(core) => retro.core = Some(core),
+            Err(e) => retro.message(&format!("Unable to load the game: {}", e)),
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    with_retro(|retro| {
+        let Some(mut core) = retro.core.take() else {
+            return;
+        };
+        if let Some(input_poll) = retro.input_poll {
+            input_poll();
+        }
+        if retro.options_changed() {
+            core.ticks = retro.ticks();
+        }
+        core.input(retro);
+        core.run(retro);
+        retro.core = Some(core);
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    with_retro(|retro| retro.core.as_ref().map_or(0, |core| core.state_size))
+}
+
+/// # Safety
+/// `data` must point to `size` writable bytes
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+    with_retro(|retro| {
+        let Some(core) = &retro.core else {
+            return false;
+        };
+        let json = core.chip8.save_state().to_json();
+        if data.is_null() || json.len() > size {
+            return false;
+        }
+        let buf = slice::from_raw_parts_mut(data as *mut u8, size);
+        let (head, rest) = buf.split_at_mut(json.len());
+        head.copy_from_slice(json.as_bytes());
+        rest.fill(0);
+        true
+    })
+}
+
+/// # Safety
+/// `data` must point to `size` readable bytes
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+    with_retro(|retro| {
+        let Some(core) = &mut retro.core else {
+            return false;
+        };
+        if data.is_null() {
+            return false;
+        }
+        let buf = slice::from_raw_parts(data as *const u8, size);
+        let len = buf.iter().position(|&b| b == 0).unwrap_or(size);
+        let Ok(json) = std::str::from_utf8(&buf[..len]) else {
+            return false;
+        };
+        match SaveState::from_json(json).and_then(|save| core.chip8.load_state(&save)) {
+            Ok(()) => {
+                core.stopped = false;
+                true
+            }
+            Err(_) => false,
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+/// # Safety
+/// `game` must be null or point to a `retro_game_info` with `size` bytes of
+/// ROM at `data`
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const RetroGameInfo) -> bool {
+    with_retro(|retro| {
+        let Some(game) = game.as_ref() else {
+            return false;
+        };
+        if game.data.is_null() {
+            return false;
+        }
+        let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+        if !retro.environment(RETRO_ENVIRONMENT_SET_PIXEL_FORMAT, &mut format) {
+            return false;
+        }
+        let rom = slice::from_raw_parts(game.data as *const u8, game.size).to_vec();
+        match retro.start(rom) {
+            Ok(core) => {
+                retro.core = Some(core);
+                true
+            }
+            Err(e) => {
+                retro.message(&format!("Unable to load the game: {}", e));
+                false
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(
+    _game_type: c_uint,
+    _info: *const RetroGameInfo,
+    _num_info: usize,
+) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    with_retro(|retro| retro.core = None);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+    ptr::null_mut()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+    0
+}
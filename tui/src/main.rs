@@ -0,0 +1,370 @@
+use chip8_core::disasm::disassemble_op;
+use chip8_core::{Chip8, State, TextStyle};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::env;
+use std::fs;
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TICKS_PER_FRAME: usize = 10;
+const FRAME_TIME: Duration = Duration::from_micros(16_667);
+// Terminals only report key presses, so a key counts as held for this many
+// frames after its last press (auto-repeat keeps it held)
+const KEY_HOLD_FRAMES: u32 = 6;
+const MEM_ROW: usize = 8;
+const HELP: &str =
+    "F5 run/pause  F10 step  F9 breakpoint  Up/Down move  PgUp/PgDn memory  Home reset view  Esc quit";
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    if args.len() != 2 {
+        println!("Usage: chip8_tui path/to/game");
+        return;
+    }
+
+    let rom = match fs::read(&args[1]) {
+        Ok(rom) => rom,
+        Err(e) => {
+            println!("Unable to read {}: {}", args[1], e);
+            return;
+        }
+    };
+    let mut chip8 = Chip8::new();
+    if let Err(e) = chip8.load(&rom) {
+        println!("Unable to load {}: {}", args[1], e);
+        return;
+    }
+
+    let mut app = App {
+        chip8,
+        paused: true,
+        held: [0; 16],
+        cursor: None,
+        mem_addr: None,
+        status: HELP.to_string(),
+    };
+    let terminal = ratatui::init();
+    let result = app.run(terminal);
+    ratatui::restore();
+    if let Err(e) = result {
+        println!("{}", e);
+    }
+}
+
+struct App {
+    chip8: Chip8,
+    paused: bool,
+    held: [u32; 16],
+    // Disassembly line picked with the arrow keys, the PC when `None`
+    cursor: Option<u16>,
+    // First address in the memory pane, follows I when `None`
+    mem_addr: Option<usize>,
+    status: String,
+}
+
+impl App {
+    fn run(&mut self, mut terminal: DefaultTerminal) -> io::Result<()> {
+        loop {
+            let start = Instant::now();
+            if !self.input()? {
+                return Ok(());
+            }
+            self.frame();
+            terminal.draw(|f| self.draw(f))?;
+
+            if let Some(rest) = FRAME_TIME.checked_sub(start.elapsed()) {
+                thread::sleep(rest);
+            }
+        }
+    }
+
+    /// Handle pending key events, returns false when the user quits
+    fn input(&mut self) -> io::Result<bool> {
+        while event::poll(Duration::ZERO)? {
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind == KeyEventKind::Release {
+                continue;
+            }
+            match key.code {
+                KeyCode::Esc => return Ok(false),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(false)
+                }
+                KeyCode::F(5) => {
+                    self.paused = !self.paused;
+                    self.status = HELP.to_string();
+                }
+                KeyCode::F(10) if self.paused => {
+                    self.tick();
+                }
+                KeyCode::F(9) => self.toggle_breakpoint(),
+                KeyCode::Up => self.move_cursor(-2),
+                KeyCode::Down => self.move_cursor(2),
+                KeyCode::PageUp => self.scroll_memory(-1),
+                KeyCode::PageDown => self.scroll_memory(1),
+                KeyCode::Home => {
+                    self.cursor = None;
+                    self.mem_addr = None;
+                }
+                KeyCode::Char(c) => {
+                    if let Some(k) = key2btn(c.to_ascii_lowercase()) {
+                        self.held[k] = KEY_HOLD_FRAMES;
+                    }
+                }
+                _ => (),
+            }
+        }
+        Ok(true)
+    }
+
+    fn frame(&mut self) {
+        for (k, held) in self.held.iter_mut().enumerate() {
+            self.chip8.keypress(k, *held > 0);
+            *held = held.saturating_sub(1);
+        }
+
+        if self.paused {
+            return;
+        }
+        for _ in 0..TICKS_PER_FRAME {
+            if !self.tick() {
+                return;
+            }
+        }
+        self.chip8.tick_timers();
+    }
+
+    /// Run one instruction, pausing with the fault in the status line if it fails
+    fn tick(&mut self) -> bool {
+        match self.chip8.tick() {
+            Ok(State::Halted) => {
+                self.paused = true;
+                self.status = "program exited".to_string();
+                false
+            }
+            Ok(State::Break) => {
+                self.paused = true;
+                self.status = format!("breakpoint at 0x{:03X}", self.chip8.pc());
+                false
+            }
+            Ok(_) => true,
+            Err(e) => {
+                self.paused = true;
+                self.status = format!("emulation fault: {}", e);
+                false
+            }
+        }
+    }
+
+    fn toggle_breakpoint(&mut self) {
+        let addr = self.cursor.unwrap_or(self.chip8.pc());
+        if self.chip8.remove_breakpoint(addr) {
+            self.status = format!("removed breakpoint at 0x{:03X}", addr);
+        } else {
+            self.chip8.add_breakpoint(addr);
+            self.status = format!("breakpoint at 0x{:03X}", addr);
+        }
+    }
+
+    fn move_cursor(&mut self, by: i32) {
+        let addr = self.cursor.unwrap_or(self.chip8.pc()) as i32 + by;
+        let last = self.chip8.ram().len() as i32 - 2;
+        self.cursor = Some(addr.clamp(0, last) as u16);
+    }
+
+    fn scroll_memory(&mut self, pages: isize) {
+        let start = self.mem_start() as isize + pages * (MEM_ROW * 8) as isize;
+        let last = self.chip8.ram().len().saturating_sub(MEM_ROW) as isize;
+        self.mem_addr = Some(start.clamp(0, last) as usize);
+    }
+
+    fn mem_start(&self) -> usize {
+        self.mem_addr
+            .unwrap_or(self.chip8.i_reg() as usize / MEM_ROW * MEM_ROW)
+    }
+
+    fn draw(&self, f: &mut Frame) {
+        let (width, height) = (self.chip8.screen_width(), self.chip8.screen_height());
+        let [main, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(f.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Length(width as u16 + 2), Constraint::Min(0)])
+                .areas(main);
+        // Each character cell covers two pixel rows
+        let [screen, regs, stack] = Layout::vertical([
+            Constraint::Length(height as u16 / 2 + 2),
+            Constraint::Length(5),
+            Constraint::Min(0),
+        ])
+        .areas(left);
+        let [disasm, memory] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(10)]).areas(right);
+
+        self.draw_screen(f, screen);
+        self.draw_regs(f, regs);
+        self.draw_stack(f, stack);
+        self.draw_disasm(f, disasm);
+        self.draw_memory(f, memory);
+        f.render_widget(Line::raw(self.status.as_str()), status);
+    }
+
+    fn draw_screen(&self, f: &mut Frame, area: Rect) {
+        let rows = self.chip8.render_text(TextStyle::HalfBlock);
+        let title = if self.paused {
+            "Screen (paused)"
+        } else {
+            "Screen"
+        };
+        f.render_widget(
+            Paragraph::new(rows).block(Block::bordered().title(title)),
+            area,
+        );
+    }
+
+    fn draw_regs(&self, f: &mut Frame, area: Rect) {
+        let c8 = &self.chip8;
+        let mut lines: Vec<Line> = c8
+            .v_reg()
+            .chunks(8)
+            .enumerate()
+            .map(|(row, regs)| {
+                let regs: Vec<String> = regs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| format!("V{:X} {:02X}", row * 8 + i, v))
+                    .collect();
+                Line::raw(regs.join("  "))
+            })
+            .collect();
+        lines.push(Line::raw(format!(
+            "PC {:03X}  I {:03X}  DT {:02X}  ST {:02X}  SP {}",
+            c8.pc(),
+            c8.i_reg(),
+            c8.dt(),
+            c8.st(),
+            c8.sp()
+        )));
+        f.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Registers")),
+            area,
+        );
+    }
+
+    /// Innermost call first, like a backtrace
+    fn draw_stack(&self, f: &mut Frame, area: Rect) {
+        let lines: Vec<Line> = self
+            .chip8
+            .call_frames()
+            .iter()
+            .rev()
+            .map(|frame| match frame.entry {
+                Some(entry) => Line::raw(format!(
+                    "{:03X} returns to {:03X}",
+                    entry, frame.return_addr
+                )),
+                None => Line::raw(format!("??? returns to {:03X}", frame.return_addr)),
+            })
+            .collect();
+        f.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Stack")),
+            area,
+        );
+    }
+
+    /// Instructions around the cursor, which is the PC unless moved
+    fn draw_disasm(&self, f: &mut Frame, area: Rect) {
+        let ram = self.chip8.ram();
+        let pc = self.chip8.pc() as usize;
+        let center = self.cursor.map_or(pc, |addr| addr as usize);
+        let rows = area.height.saturating_sub(2) as usize;
+        let mut addr = center.saturating_sub(rows / 2 * 2);
+        let mut lines = Vec::with_capacity(rows);
+        while lines.len() < rows && addr + 1 < ram.len() {
+            let op = (ram[addr] as u16) << 8 | ram[addr + 1] as u16;
+            let marker = if addr == pc { "=>" } else { "  " };
+            let bp = if self.chip8.breakpoints().any(|(bp, _)| bp as usize == addr) {
+                "*"
+            } else {
+                " "
+            };
+            let text = format!(
+                "{}{} {:03X}: {:04X}  {}",
+                marker,
+                bp,
+                addr,
+                op,
+                disassemble_op(op)
+            );
+            let style = if self.cursor == Some(addr as u16) {
+                Style::new().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::new()
+            };
+            lines.push(Line::styled(text, style));
+            addr += 2;
+        }
+        f.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Disassembly")),
+            area,
+        );
+    }
+
+    fn draw_memory(&self, f: &mut Frame, area: Rect) {
+        let ram = self.chip8.ram();
+        let start = self.mem_start().min(ram.len());
+        let rows = area.height.saturating_sub(2) as usize;
+        let end = (start + rows * MEM_ROW).min(ram.len());
+        let lines: Vec<Line> = ram[start..end]
+            .chunks(MEM_ROW)
+            .enumerate()
+            .map(|(row, chunk)| {
+                let bytes: Vec<String> = chunk.iter().map(|b| format!("{:02X}", b)).collect();
+                Line::raw(format!(
+                    "{:03X}: {}",
+                    start + row * MEM_ROW,
+                    bytes.join(" ")
+                ))
+            })
+            .collect();
+        let title = if self.mem_addr.is_some() {
+            "Memory"
+        } else {
+            "Memory (at I)"
+        };
+        f.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            area,
+        );
+    }
+}
+
+/// Same layout as the other frontends, the left of a QWERTY keyboard
+fn key2btn(key: char) -> Option<usize> {
+    match key {
+        '1' => Some(0x1),
+        '2' => Some(0x2),
+        '3' => Some(0x3),
+        '4' => Some(0xC),
+        'q' => Some(0x4),
+        'w' => Some(0x5),
+        'e' => Some(0x6),
+        'r' => Some(0xD),
+        'a' => Some(0x7),
+        's' => Some(0x8),
+        'd' => Some(0x9),
+        'f' => Some(0xE),
+        'z' => Some(0xA),
+        'x' => Some(0x0),
+        'c' => Some(0xB),
+        'v' => Some(0xF),
+        _ => None,
+    }
+}
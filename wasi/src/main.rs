@@ -0,0 +1,149 @@
+use chip8_core::{Chip8, State, TextStyle, UnknownVariant, Variant};
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::iter;
+use std::process;
+
+const USAGE: &str = "\
+Usage: chip8_wasi [options] path/to/game > screen.pgm
+
+Runs a ROM without any UI and writes the final screen as a PGM image to
+stdout. The path can be - to read the ROM from stdin.
+
+Options:
+  --frames N     60Hz frames to run (default 600)
+  --ticks N      instructions per frame (default 10)
+  --variant V    {variants}
+  --seed N       seed for RND
+  --scale N      image pixels per CHIP-8 pixel (default 1)
+  --text         print the screen as text instead of an image";
+
+/// `USAGE` with the variant names filled in
+fn usage() -> String {
+    USAGE.replace("{variants}", &Variant::NAMES.join(", "))
+}
+
+// Grey levels for colour indices 0-3, the same as the cli's PNGs
+const GREYS: [u8; 4] = [0x00, 0xFF, 0xAA, 0x55];
+
+struct Options {
+    rom: String,
+    frames: u32,
+    ticks: u32,
+    variant: Variant,
+    seed: Option<u64>,
+    scale: usize,
+    text: bool,
+}
+
+fn main() {
+    let opts = match parse_args(env::args().skip(1)) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, usage());
+            process::exit(2);
+        }
+    };
+    if let Err(e) = run(&opts) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut opts = Options {
+        rom: String::new(),
+        frames: 600,
+        ticks: 10,
+        variant: Variant::Chip8,
+        seed: None,
+        scale: 1,
+        text: false,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--frames" => opts.frames = number(&value()?)?,
+            "--ticks" => opts.ticks = number(&value()?)?,
+            "--variant" => {
+                opts.variant = value()?
+                    .parse()
+                    .map_err(|e: UnknownVariant| e.to_string())?
+            }
+            "--seed" => opts.seed = Some(number(&value()?)?),
+            "--scale" => opts.scale = number::<usize>(&value()?)?.max(1),
+            "--text" => opts.text = true,
+            "-h" | "--help" => {
+                println!("{}", usage());
+                process::exit(0);
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ if opts.rom.is_empty() => opts.rom = arg,
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+    if opts.rom.is_empty() {
+        return Err("no ROM given".to_string());
+    }
+    Ok(opts)
+}
+
+fn number<T: std::str::FromStr>(arg: &str) -> Result<T, String> {
+    arg.parse()
+        .map_err(|_| format!("'{}' is not a number", arg))
+}
+
+fn run(opts: &Options) -> Result<(), String> {
+    let rom = if opts.rom == "-" {
+        let mut rom = Vec::new();
+        io::stdin()
+            .read_to_end(&mut rom)
+            .map_err(|e| format!("unable to read stdin: {}", e))?;
+        rom
+    } else {
+        fs::read(&opts.rom).map_err(|e| format!("unable to read {}: {}", opts.rom, e))?
+    };
+    let mut chip8 = Chip8::try_with_config(opts.variant.config()).map_err(|e| e.to_string())?;
+    if opts.seed.is_some() {
+        chip8.set_rng_seed(opts.seed);
+    }
+    chip8.load(&rom).map_err(|e| e.to_string())?;
+
+    for frame in 0..opts.frames {
+        if chip8.state() == State::Halted {
+            break;
+        }
+        // A crash still leaves the screen as it was, which is worth seeing
+        if let Err(e) = chip8.run_frame(opts.ticks) {
+            eprintln!("warning: stopped at frame {}: {}", frame, e);
+            break;
+        }
+    }
+
+    let out = if opts.text {
+        chip8.render_text(TextStyle::HalfBlock).into_bytes()
+    } else {
+        pgm(&chip8, opts.scale)
+    };
+    io::stdout()
+        .write_all(&out)
+        .map_err(|e| format!("unable to write the screen: {}", e))
+}
+
+/// Binary PGM, `scale` by `scale` pixels for each CHIP-8 pixel
+fn pgm(chip8: &Chip8, scale: usize) -> Vec<u8> {
+    let (width, height) = (chip8.screen_width(), chip8.screen_height());
+    let mut out = format!("P5\n{} {}\n255\n", width * scale, height * scale).into_bytes();
+    let colors = chip8.get_display_colors();
+    for row in colors.chunks(width) {
+        let line: Vec<u8> = row
+            .iter()
+            .flat_map(|&color| iter::repeat_n(GREYS[color as usize], scale))
+            .collect();
+        for _ in 0..scale {
+            out.extend(&line);
+        }
+    }
+    out
+}
@@ -0,0 +1,72 @@
+use crate::Chip8Wasm;
+use chip8_core::{Chip8, Event};
+use js_sys::Function;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+const EVENT_NAMES: [&str; 5] = ["draw", "sound", "halt", "keywait", "error"];
+
+/// JS callbacks registered with `on`, by event name
+#[derive(Default)]
+pub struct Listeners(HashMap<String, Function>);
+
+impl Listeners {
+    /// Pass the core's pending events on to their listeners
+    pub fn dispatch(&self, chip8: &mut Chip8) {
+        for event in chip8.take_events() {
+            match event {
+                Event::Draw => self.emit("draw", &JsValue::UNDEFINED),
+                Event::Sound(on) => self.emit("sound", &JsValue::from_bool(on)),
+                Event::Halt { pc } => self.emit("halt", &JsValue::from(pc)),
+                Event::KeyWait { pc } => self.emit("keywait", &JsValue::from(pc)),
+            }
+        }
+    }
+
+    pub fn emit(&self, event: &str, arg: &JsValue) {
+        if let Some(callback) = self.0.get(event) {
+            // A throwing listener shouldn't stop the emulator
+            if let Err(e) = callback.call1(&JsValue::NULL, arg) {
+                web_sys::console::error_1(&e);
+            }
+        }
+    }
+}
+
+// `on` and `off` are typed here so each event's callback gets its argument
+#[wasm_bindgen(typescript_custom_section)]
+const TYPES: &str = r#"
+export interface Chip8Events {
+  draw: () => void;
+  sound: (on: boolean) => void;
+  halt: (pc: number) => void;
+  keywait: (pc: number) => void;
+  error: (message: string) => void;
+}
+
+export interface Chip8Wasm {
+  on<K extends keyof Chip8Events>(event: K, callback: Chip8Events[K]): void;
+  off(event: keyof Chip8Events): void;
+}
+"#;
+
+#[wasm_bindgen]
+impl Chip8Wasm {
+    /// Call `callback` when `event` happens, replacing any earlier callback
+    ///
+    /// Events are "draw", "sound" (called with true/false), "halt" and
+    /// "keywait" (called with the PC) and "error" (called with the message).
+    #[wasm_bindgen(skip_typescript)]
+    pub fn on(&mut self, event: &str, callback: Function) -> Result<(), JsValue> {
+        if !EVENT_NAMES.contains(&event) {
+            return Err(JsValue::from_str(&format!("unknown event '{}'", event)));
+        }
+        self.listeners.0.insert(event.to_string(), callback);
+        Ok(())
+    }
+
+    #[wasm_bindgen(skip_typescript)]
+    pub fn off(&mut self, event: &str) {
+        self.listeners.0.remove(event);
+    }
+}
@@ -0,0 +1,47 @@
+use crate::Chip8Wasm;
+use chip8_core::FlagStorage;
+use wasm_bindgen::prelude::*;
+
+/// RPL user flags kept in localStorage as a hex string under `key`
+#[derive(Clone)]
+struct LocalStorageFlags {
+    key: String,
+}
+
+impl LocalStorageFlags {
+    fn storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+}
+
+impl FlagStorage for LocalStorageFlags {
+    fn load(&mut self) -> Option<Vec<u8>> {
+        let hex = Self::storage()?.get_item(&self.key).ok()??;
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+            .collect()
+    }
+
+    fn save(&mut self, flags: &[u8]) {
+        let hex: String = flags.iter().map(|b| format!("{:02x}", b)).collect();
+        // Private browsing can refuse the write, the game still runs
+        if let Some(storage) = Self::storage() {
+            let _ = storage.set_item(&self.key, &hex);
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Chip8Wasm {
+    /// Keep the SCHIP RPL flags (high scores) in localStorage under `key`,
+    /// loading any saved there before. Call after loading the game.
+    #[wasm_bindgen]
+    pub fn persist_flags(&mut self, key: &str) {
+        // Don't carry the last game's flags over if this one has none saved
+        self.chip8.set_flags(&[]);
+        self.chip8.set_flag_storage(Box::new(LocalStorageFlags {
+            key: key.to_string(),
+        }));
+    }
+}
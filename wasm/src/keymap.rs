@@ -0,0 +1,89 @@
+use crate::Chip8Wasm;
+use js_sys::{Object, Reflect};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TYPES: &str = r#"
+/** Host key (`KeyboardEvent.key`) to CHIP-8 key (0-15) */
+export type Keymap = Record<string, number>;
+"#;
+
+/// The usual QWERTY layout, the left four columns of keys standing in for
+/// the COSMAC VIP's 4x4 hex keypad
+const DEFAULT_KEYMAP: [(&str, usize); 16] = [
+    ("1", 0x1),
+    ("2", 0x2),
+    ("3", 0x3),
+    ("4", 0xC),
+    ("q", 0x4),
+    ("w", 0x5),
+    ("e", 0x6),
+    ("r", 0xD),
+    ("a", 0x7),
+    ("s", 0x8),
+    ("d", 0x9),
+    ("f", 0xE),
+    ("z", 0xA),
+    ("x", 0x0),
+    ("c", 0xB),
+    ("v", 0xF),
+];
+
+/// Host key (`KeyboardEvent.key`) to CHIP-8 key bindings
+pub struct Keymap(BTreeMap<String, usize>);
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap(
+            DEFAULT_KEYMAP
+                .iter()
+                .map(|(key, button)| (key.to_string(), *button))
+                .collect(),
+        )
+    }
+}
+
+impl Keymap {
+    pub fn get(&self, key: &str) -> Option<usize> {
+        self.0.get(key).copied()
+    }
+}
+
+#[wasm_bindgen]
+impl Chip8Wasm {
+    /// The active bindings as an object of host key -> CHIP-8 key (0-15)
+    #[wasm_bindgen(unchecked_return_type = "Keymap")]
+    pub fn get_keymap(&self) -> Result<JsValue, JsValue> {
+        let map = Object::new();
+        for (key, button) in &self.keymap.0 {
+            Reflect::set(
+                &map,
+                &JsValue::from_str(key),
+                &JsValue::from(*button as u32),
+            )?;
+        }
+        Ok(map.into())
+    }
+
+    /// Bind a host key to a CHIP-8 key, on top of any existing bindings
+    #[wasm_bindgen]
+    pub fn bind_key(&mut self, key: &str, button: usize) -> Result<(), JsValue> {
+        if button > 0xF {
+            return Err(JsValue::from_str(&format!("no CHIP-8 key {}", button)));
+        }
+        self.keymap.0.insert(key.to_string(), button);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn unbind_key(&mut self, key: &str) {
+        self.keymap.0.remove(key);
+    }
+
+    /// Go back to the default bindings
+    #[wasm_bindgen]
+    pub fn reset_keymap(&mut self) {
+        self.keymap = Keymap::default();
+    }
+}
@@ -1,75 +1,375 @@
 use chip8_core::*;
 use js_sys::Uint8Array;
 use wasm_bindgen::prelude::*;
-use web_sys::KeyboardEvent;
+use wasm_bindgen::JsCast;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, KeyboardEvent};
+
+#[cfg(feature = "events")]
+mod events;
+mod flags;
+mod keymap;
+mod machine;
+#[cfg(feature = "debug")]
+mod overlay;
+#[cfg(feature = "package")]
+mod package;
+mod render;
+mod rewind;
+#[cfg(feature = "savestate")]
+mod savestate;
+#[cfg(feature = "settings")]
+mod settings;
 
 #[wasm_bindgen]
 pub struct Chip8Wasm {
     chip8: Chip8,
+    // None when running headless
+    ctx: Option<CanvasRenderingContext2d>,
+    keymap: keymap::Keymap,
+    renderer: render::Renderer,
+    rewind: Rewind,
+    #[cfg(feature = "debug")]
+    overlay: overlay::Overlay,
+    #[cfg(feature = "events")]
+    listeners: events::Listeners,
+    #[cfg(feature = "package")]
+    rom_info: Option<RomInfo>,
+    #[cfg(feature = "settings")]
+    settings: settings::Loaded,
 }
 
+#[wasm_bindgen(typescript_custom_section)]
+const TYPES: &str = r#"
+/** What the machine is doing, see `Chip8Wasm.state` */
+export type Chip8State = "running" | "waiting_for_key" | "halted" | "errored" | "break";
+"#;
+
 #[wasm_bindgen]
 impl Chip8Wasm {
+    /// A CHIP-8 machine drawing to `canvas`, or the page's `#canvas`
+    ///
+    /// Without either it runs headless: `draw_screen` does nothing and the
+    /// screen is read with `display_rgba` or `display_colors` instead.
     #[wasm_bindgen(constructor)]
-    pub fn new() -> Chip8Wasm {
-        Chip8Wasm {
-            chip8: Chip8::new(),
+    pub fn new(canvas: Option<HtmlCanvasElement>) -> Result<Chip8Wasm, JsValue> {
+        let canvas = match canvas {
+            Some(canvas) => Some(canvas),
+            None => web_sys::window()
+                .and_then(|window| window.document())
+                .and_then(|document| document.get_element_by_id("canvas"))
+                .map(|el| el.dyn_into::<HtmlCanvasElement>())
+                .transpose()?,
+        };
+        let ctx = match canvas {
+            Some(canvas) => Some(
+                canvas
+                    .get_context("2d")?
+                    .ok_or("2d canvas context unavailable")?
+                    .dyn_into::<CanvasRenderingContext2d>()?,
+            ),
+            None => None,
+        };
+
+        Ok(Chip8Wasm {
+            chip8: machine::new(Variant::Chip8)?,
+            ctx,
+            keymap: keymap::Keymap::default(),
+            renderer: render::Renderer::default(),
+            rewind: rewind::new(),
+            #[cfg(feature = "debug")]
+            overlay: overlay::Overlay::new(),
+            #[cfg(feature = "events")]
+            listeners: events::Listeners::default(),
+            #[cfg(feature = "package")]
+            rom_info: None,
+            #[cfg(feature = "settings")]
+            settings: settings::Loaded::default(),
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn tick(&mut self) -> Result<(), JsValue> {
+        let result = self.chip8.tick();
+        #[cfg(feature = "events")]
+        self.listeners.dispatch(&mut self.chip8);
+        if let Err(e) = result {
+            let msg = JsValue::from_str(&e.to_string());
+            #[cfg(feature = "events")]
+            self.listeners.emit("error", &msg);
+            return Err(msg);
         }
+        #[cfg(feature = "debug")]
+        self.overlay.tick(1);
+        Ok(())
     }
 
+    /// Run `ticks` instructions then tick the timers, one call per 60Hz
+    /// frame instead of one per instruction
     #[wasm_bindgen]
-    pub fn tick(&mut self) {
-        self.chip8.tick();
+    pub fn run_frame(&mut self, ticks: u32) -> Result<(), JsValue> {
+        let result = self.chip8.tick_many(ticks);
+        #[cfg(feature = "events")]
+        self.listeners.dispatch(&mut self.chip8);
+        #[allow(unused_variables)]
+        let ran = result.map_err(|e| {
+            let msg = JsValue::from_str(&e.to_string());
+            #[cfg(feature = "events")]
+            self.listeners.emit("error", &msg);
+            msg
+        })?;
+        #[cfg(feature = "debug")]
+        self.overlay.tick(ran);
+        self.tick_timers();
+        Ok(())
+    }
+
+    /// Run until the PC reaches `addr` ("run to cursor"), at most `max_ticks`
+    /// instructions. Returns whether it got there.
+    #[wasm_bindgen]
+    pub fn run_to(&mut self, addr: u16, max_ticks: u32) -> Result<bool, JsValue> {
+        let result = self.chip8.run_to(addr, max_ticks as u64);
+        #[cfg(feature = "events")]
+        self.listeners.dispatch(&mut self.chip8);
+        result
+            .map(|ticks| ticks.is_some())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Stop `run_frame` when the PC reaches `addr`, `state` is then "break"
+    #[wasm_bindgen]
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.chip8.add_breakpoint(addr);
+    }
+
+    /// Breakpoint that only stops when `cond` holds, e.g. "V3 == 0x1F"
+    #[wasm_bindgen]
+    pub fn add_conditional_breakpoint(&mut self, addr: u16, cond: &str) -> Result<(), JsValue> {
+        let cond = cond
+            .parse()
+            .map_err(|e: ConditionError| JsValue::from_str(&e.to_string()))?;
+        self.chip8.add_conditional_breakpoint(addr, cond);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool {
+        self.chip8.remove_breakpoint(addr)
+    }
+
+    /// Stop `run_frame` when `cond` becomes true, e.g. "I >= 0x300"
+    #[wasm_bindgen]
+    pub fn add_watch(&mut self, cond: &str) -> Result<(), JsValue> {
+        let cond = cond
+            .parse()
+            .map_err(|e: ConditionError| JsValue::from_str(&e.to_string()))?;
+        self.chip8.add_watch(cond);
+        Ok(())
+    }
+
+    /// Returns false if `cond` wasn't watched or doesn't parse
+    #[wasm_bindgen]
+    pub fn remove_watch(&mut self, cond: &str) -> bool {
+        cond.parse()
+            .is_ok_and(|cond: Condition| self.chip8.remove_watch(&cond))
+    }
+
+    /// Stop `run_frame` when `len` bytes from `addr` are accessed, `access`
+    /// is "read", "write" or "readwrite"
+    #[wasm_bindgen]
+    pub fn add_watchpoint(&mut self, addr: u16, len: u16, access: &str) -> Result<(), JsValue> {
+        let access = match access {
+            "read" => Access::Read,
+            "write" => Access::Write,
+            "readwrite" => Access::ReadWrite,
+            _ => return Err(JsValue::from_str(&format!("unknown access '{}'", access))),
+        };
+        self.chip8
+            .add_watchpoint(addr..addr.saturating_add(len), access);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn remove_watchpoint(&mut self, addr: u16, len: u16) -> bool {
+        self.chip8.remove_watchpoint(addr..addr.saturating_add(len))
+    }
+
+    /// What the watchpoint that stopped the last instruction saw, e.g.
+    /// "write 0x301 at 0x204"
+    #[wasm_bindgen]
+    pub fn watch_hit(&self) -> Option<String> {
+        self.chip8.watch_hit().map(|hit| {
+            let access = match hit.access {
+                Access::Write => "write",
+                _ => "read",
+            };
+            format!("{} 0x{:03X} at 0x{:03X}", access, hit.addr, hit.pc)
+        })
+    }
+
+    /// Run until a breakpoint, at most `max_ticks` instructions. Returns
+    /// whether one was hit.
+    #[wasm_bindgen]
+    pub fn continue_until_break(&mut self, max_ticks: u32) -> Result<bool, JsValue> {
+        let result = self.chip8.continue_until_break(max_ticks as u64);
+        #[cfg(feature = "events")]
+        self.listeners.dispatch(&mut self.chip8);
+        result
+            .map(|ticks| ticks.is_some())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Like `tick`, but returns a plain English summary of what the
+    /// instruction did and which registers it read and wrote
+    #[wasm_bindgen]
+    pub fn tick_explained(&mut self) -> Result<String, JsValue> {
+        let result = self.chip8.tick_explained();
+        #[cfg(feature = "events")]
+        self.listeners.dispatch(&mut self.chip8);
+        result
+            .map(|e| e.summary())
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Plain English description of an opcode
+    #[wasm_bindgen]
+    pub fn explain(op: u16) -> String {
+        opcodes::explain(op)
     }
 
     #[wasm_bindgen]
     pub fn tick_timers(&mut self) {
         self.chip8.tick_timers();
+        self.rewind.record(&self.chip8);
+        #[cfg(feature = "events")]
+        self.listeners.dispatch(&mut self.chip8);
+        #[cfg(feature = "debug")]
+        self.overlay.frame();
+    }
+
+    /// True once the program has exited with `00FD`, until `reset`
+    #[wasm_bindgen]
+    pub fn halted(&self) -> bool {
+        self.chip8.state() == State::Halted
+    }
+
+    /// "running", "waiting_for_key", "halted", "errored" or "break"
+    #[wasm_bindgen(unchecked_return_type = "Chip8State")]
+    pub fn state(&self) -> String {
+        match self.chip8.state() {
+            State::Running => "running",
+            State::WaitingForKey => "waiting_for_key",
+            State::Halted => "halted",
+            State::Errored => "errored",
+            State::Break => "break",
+        }
+        .to_string()
     }
 
     #[wasm_bindgen]
     pub fn reset(&mut self) {
         self.chip8.reset();
+        self.rewind.clear();
+        #[cfg(feature = "debug")]
+        self.overlay.reset();
+    }
+
+    /// Current resolution, 64x32, 128x64 in SCHIP hi-res mode or 64x64 in
+    /// HIRES CHIP-8 mode
+    #[wasm_bindgen]
+    pub fn screen_width(&self) -> usize {
+        self.chip8.screen_width()
+    }
+
+    #[wasm_bindgen]
+    pub fn screen_height(&self) -> usize {
+        self.chip8.screen_height()
+    }
+
+    /// Switch a quirk on or off by name: "shift_vy", "jump_vx",
+    /// "clip_sprites", "vf_reset", "memory_increment" or "half_pixel_scroll"
+    #[wasm_bindgen]
+    pub fn set_quirk(
+        &mut self,
+        #[wasm_bindgen(unchecked_param_type = "QuirkName")] name: &str,
+        on: bool,
+    ) -> Result<(), JsValue> {
+        let mut quirks = self.chip8.quirks();
+        let quirk = machine::quirk(&mut quirks, name)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown quirk '{}'", name)))?;
+        *quirk = on;
+        self.chip8.set_quirks(quirks);
+        Ok(())
+    }
+
+    /// Fill a `Float32Array` with `rate` Hz samples of the current sound,
+    /// e.g. from an `AudioWorklet` or `ScriptProcessorNode`
+    #[wasm_bindgen]
+    pub fn fill_audio_buffer(&mut self, rate: u32, buf: &mut [f32]) {
+        self.chip8.fill_audio_buffer(rate, buf);
+    }
+
+    /// Screen as RGBA bytes for `new ImageData(...)`, colours are `0xRRGGBBAA`
+    #[wasm_bindgen]
+    pub fn display_rgba(&self, fg: u32, bg: u32) -> Vec<u8> {
+        self.chip8.display_rgba(fg, bg)
+    }
+
+    /// Screen as one byte per pixel, row by row: 0 unlit, 1 and 2 lit in
+    /// XO-CHIP plane 1 or 2, 3 lit in both
+    #[wasm_bindgen]
+    pub fn display_colors(&self) -> Vec<u8> {
+        self.chip8.get_display_colors()
     }
 
     #[wasm_bindgen]
     pub fn keypress(&mut self, evt: KeyboardEvent, pressed: bool) {
         let key = evt.key();
-        if let Some(k) = key2btn(&key) {
+        if let Some(k) = self.keymap.get(&key) {
             self.chip8.keypress(k, pressed);
         }
     }
 
+    /// Press or release CHIP-8 key `key` (0-15) directly, for on-screen
+    /// keypads and gamepads
     #[wasm_bindgen]
-    pub fn load_game(&mut self, data: Uint8Array) {
-        self.chip8.load(&data.to_vec());
+    pub fn set_key(&mut self, key: usize, pressed: bool) -> Result<(), JsValue> {
+        if key > 0xF {
+            return Err(JsValue::from_str(&format!("no CHIP-8 key {}", key)));
+        }
+        self.chip8.keypress(key, pressed);
+        Ok(())
     }
 
     #[wasm_bindgen]
-    pub fn draw_screen(&mut self, scale: usize) {
-        // TODO
+    pub fn load_game(&mut self, data: Uint8Array) -> Result<(), JsValue> {
+        #[cfg(feature = "package")]
+        {
+            self.rom_info = None;
+        }
+        self.rewind.clear();
+        let rom = data.to_vec();
+        self.chip8
+            .load(&rom)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        #[cfg(feature = "settings")]
+        self.apply_game_settings(&rom);
+        Ok(())
     }
-}
 
-fn key2btn(key: &str) -> Option<usize> {
-    match key {
-        "1" => Some(0x1),
-        "2" => Some(0x2),
-        "3" => Some(0x3),
-        "4" => Some(0xC),
-        "q" => Some(0x4),
-        "w" => Some(0x5),
-        "e" => Some(0x6),
-        "r" => Some(0xD),
-        "a" => Some(0x7),
-        "s" => Some(0x8),
-        "d" => Some(0x9),
-        "f" => Some(0xE),
-        "z" => Some(0xA),
-        "x" => Some(0x0),
-        "c" => Some(0xB),
-        "v" => Some(0xF),
-        _ => None,
+    /// Draw the screen with `scale` canvas pixels per low resolution pixel,
+    /// so the canvas keeps its size when a game switches to hi-res mode
+    #[wasm_bindgen]
+    pub fn draw_screen(&mut self, scale: usize) {
+        let Some(ctx) = &self.ctx else {
+            return;
+        };
+        let cols = self.chip8.screen_width();
+        let scale = (scale * SCREEN_WIDTH) as f64 / cols as f64;
+        self.renderer
+            .draw(ctx, &self.chip8.get_display_colors(), cols, scale);
+
+        #[cfg(feature = "debug")]
+        self.overlay.draw(ctx, &self.chip8);
     }
 }
 
@@ -0,0 +1,104 @@
+use crate::Chip8Wasm;
+use chip8_core::{Chip8, Quirks, UnknownVariant, Variant};
+use js_sys::{Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TYPES: &str = r#"
+/** Interpreters to behave like, `Variant::NAMES` in chip8_core */
+export type Variant = "chip8" | "cosmac-vip" | "chip48" | "schip" | "xo-chip" | "hires-chip8" | "eti660";
+export type QuirkName = "shift_vy" | "jump_vx" | "clip_sprites" | "vf_reset" | "memory_increment" | "half_pixel_scroll";
+export type Quirks = Record<QuirkName, boolean>;
+"#;
+
+const QUIRK_NAMES: [&str; 6] = [
+    "shift_vy",
+    "jump_vx",
+    "clip_sprites",
+    "vf_reset",
+    "memory_increment",
+    "half_pixel_scroll",
+];
+
+/// A machine for `variant`, set up the way the rest of the crate expects
+pub fn new(variant: Variant) -> Result<Chip8, JsValue> {
+    #[allow(unused_mut)]
+    let mut chip8 =
+        Chip8::try_with_config(variant.config()).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    #[cfg(feature = "events")]
+    chip8.record_events(true);
+    Ok(chip8)
+}
+
+pub fn quirk<'a>(quirks: &'a mut Quirks, name: &str) -> Option<&'a mut bool> {
+    Some(match name {
+        "shift_vy" => &mut quirks.shift_vy,
+        "jump_vx" => &mut quirks.jump_vx,
+        "clip_sprites" => &mut quirks.clip_sprites,
+        "vf_reset" => &mut quirks.vf_reset,
+        "memory_increment" => &mut quirks.memory_increment,
+        "half_pixel_scroll" => &mut quirks.half_pixel_scroll,
+        _ => return None,
+    })
+}
+
+#[wasm_bindgen]
+impl Chip8Wasm {
+    #[wasm_bindgen(unchecked_return_type = "Variant")]
+    pub fn variant(&self) -> String {
+        self.chip8.variant().name().to_string()
+    }
+
+    /// Switch interpreter, which gives a fresh machine with the variant's
+    /// quirks, memory size and opcodes. Load the ROM (and call
+    /// `persist_flags`) again after.
+    #[wasm_bindgen]
+    pub fn set_variant(
+        &mut self,
+        #[wasm_bindgen(unchecked_param_type = "Variant")] name: &str,
+    ) -> Result<(), JsValue> {
+        let variant: Variant = name
+            .parse()
+            .map_err(|e: UnknownVariant| JsValue::from_str(&e.to_string()))?;
+        self.chip8 = new(variant)?;
+        self.reset();
+        #[cfg(feature = "package")]
+        {
+            self.rom_info = None;
+        }
+        Ok(())
+    }
+
+    /// Every quirk and whether it's on
+    #[wasm_bindgen(unchecked_return_type = "Quirks")]
+    pub fn quirks(&self) -> Result<JsValue, JsValue> {
+        let mut quirks = self.chip8.quirks();
+        let obj = Object::new();
+        for name in QUIRK_NAMES {
+            let on = *quirk(&mut quirks, name).unwrap();
+            Reflect::set(&obj, &name.into(), &on.into())?;
+        }
+        Ok(obj.into())
+    }
+
+    /// Switch several quirks at once, ones missing from `quirks` are left
+    /// alone
+    #[wasm_bindgen]
+    pub fn set_quirks(
+        &mut self,
+        #[wasm_bindgen(unchecked_param_type = "Partial<Quirks>")] quirks: &Object,
+    ) -> Result<(), JsValue> {
+        let mut set = self.chip8.quirks();
+        for key in Object::keys(quirks) {
+            let name = key.as_string().unwrap_or_default();
+            let on = Reflect::get(quirks, &key)?;
+            let quirk = quirk(&mut set, &name)
+                .ok_or_else(|| JsValue::from_str(&format!("unknown quirk '{}'", name)))?;
+            *quirk = on
+                .as_bool()
+                .ok_or_else(|| JsValue::from_str(&format!("quirk '{}' must be a boolean", name)))?;
+        }
+        self.chip8.set_quirks(set);
+        Ok(())
+    }
+}
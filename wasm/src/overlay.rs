@@ -0,0 +1,133 @@
+use crate::Chip8Wasm;
+use chip8_core::Chip8;
+use js_sys::Date;
+use wasm_bindgen::prelude::*;
+use web_sys::CanvasRenderingContext2d;
+
+const OVERLAY_FONT: &str = "14px monospace";
+const OVERLAY_LINE_HEIGHT: f64 = 16.0;
+
+/// Counters backing the debug overlay
+#[derive(Default)]
+struct Stats {
+    frames: u64,       // emulated 60Hz frames since reset
+    ticks: u64,        // instructions since the last sample
+    draws: u64,        // draw_screen calls since the last sample
+    sample_start: f64, // time of the last sample in ms
+    fps: f64,          // draw_screen calls per second
+    ips: f64,          // instructions per second
+}
+
+impl Stats {
+    /// Recompute FPS and IPS roughly once a second
+    fn sample(&mut self, now: f64) {
+        let elapsed = now - self.sample_start;
+        if elapsed >= 1000.0 {
+            self.fps = self.draws as f64 * 1000.0 / elapsed;
+            self.ips = self.ticks as f64 * 1000.0 / elapsed;
+            self.draws = 0;
+            self.ticks = 0;
+            self.sample_start = now;
+        }
+    }
+}
+
+/// Debug overlay state, drawn over the game when enabled
+pub struct Overlay {
+    enabled: bool,
+    stats: Stats,
+}
+
+impl Overlay {
+    pub fn new() -> Self {
+        Overlay {
+            enabled: false,
+            stats: Stats {
+                sample_start: Date::now(),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn tick(&mut self, n: u32) {
+        self.stats.ticks += n as u64;
+    }
+
+    pub fn frame(&mut self) {
+        self.stats.frames += 1;
+    }
+
+    pub fn reset(&mut self) {
+        self.stats = Stats {
+            sample_start: Date::now(),
+            ..Default::default()
+        };
+    }
+
+    /// Draw FPS, IPS, frame count, registers, sound state and the last
+    /// frame's stats on top of the screen
+    pub fn draw(&mut self, ctx: &CanvasRenderingContext2d, chip8: &Chip8) {
+        self.stats.draws += 1;
+        self.stats.sample(Date::now());
+        if !self.enabled {
+            return;
+        }
+
+        let v = chip8.v_reg();
+        let stats = chip8.latest_frame().stats;
+        let reg_line = |start: usize| {
+            (start..start + 8)
+                .map(|i| format!("V{:X}:{:02X}", i, v[i]))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let lines = [
+            format!(
+                "FPS: {:.1}  IPS: {:.0}  Frame: {}",
+                self.stats.fps, self.stats.ips, self.stats.frames
+            ),
+            format!(
+                "PC:{:04X} I:{:04X} DT:{:02X} ST:{:02X} Sound: {}",
+                chip8.pc(),
+                chip8.i_reg(),
+                chip8.dt(),
+                chip8.st(),
+                if chip8.st() > 0 { "on" } else { "off" }
+            ),
+            reg_line(0),
+            reg_line(8),
+            format!(
+                "Last frame: {} ops  {} draws  {} px  {} hits",
+                stats.instructions, stats.draws, stats.pixels_flipped, stats.collisions
+            ),
+        ];
+
+        // Translucent backdrop so the text is readable over any game
+        let width = ctx.canvas().map_or(0.0, |c| c.width() as f64);
+        let height = OVERLAY_LINE_HEIGHT * lines.len() as f64 + 8.0;
+        ctx.save();
+        ctx.set_fill_style_str("rgba(0, 0, 0, 0.6)");
+        ctx.fill_rect(0.0, 0.0, width, height);
+        ctx.set_fill_style_str("#00ff00");
+        ctx.set_font(OVERLAY_FONT);
+        for (i, line) in lines.iter().enumerate() {
+            let y = OVERLAY_LINE_HEIGHT * (i + 1) as f64;
+            let _ = ctx.fill_text(line, 4.0, y);
+        }
+        ctx.restore();
+    }
+}
+
+#[wasm_bindgen]
+impl Chip8Wasm {
+    /// Show or hide the debug overlay
+    #[wasm_bindgen]
+    pub fn set_overlay(&mut self, enabled: bool) {
+        self.overlay.enabled = enabled;
+    }
+
+    #[wasm_bindgen]
+    pub fn toggle_overlay(&mut self) {
+        self.overlay.enabled = !self.overlay.enabled;
+    }
+}
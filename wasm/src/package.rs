@@ -0,0 +1,56 @@
+use crate::Chip8Wasm;
+use chip8_core::Package;
+use js_sys::{Uint8Array, JSON};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TYPES: &str = r#"
+/** Metadata carried by a .c8p package */
+export interface RomInfo {
+  title: string;
+  author: string;
+  description: string;
+  settings: {
+    /** Instructions per 60Hz frame */
+    ticks_per_frame?: number;
+    /** RAM range holding save data, start and end */
+    persistent?: [number, number];
+  };
+}
+"#;
+
+#[wasm_bindgen]
+impl Chip8Wasm {
+    /// Load a .c8p package, keeping its metadata for `rom_info`
+    ///
+    /// A persistent save region declared by the package is set up too.
+    #[wasm_bindgen]
+    pub fn load_package(&mut self, data: Uint8Array) -> Result<(), JsValue> {
+        let text = String::from_utf8(data.to_vec())
+            .map_err(|_| JsValue::from_str("package isn't valid UTF-8"))?;
+        let package = Package::from_json(&text).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+        self.chip8
+            .load(&package.rom)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        if let Some((start, end)) = package.info.settings.persistent {
+            self.chip8
+                .set_persistent_region(start..end)
+                .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        }
+        #[cfg(feature = "settings")]
+        self.apply_game_settings(&package.rom);
+        self.rom_info = Some(package.info);
+        Ok(())
+    }
+
+    /// Title, author, description and recommended settings of the loaded
+    /// package, or null for a raw ROM
+    #[wasm_bindgen(unchecked_return_type = "RomInfo | null")]
+    pub fn rom_info(&self) -> Result<JsValue, JsValue> {
+        match &self.rom_info {
+            Some(info) => JSON::parse(&info.to_json()),
+            None => Ok(JsValue::NULL),
+        }
+    }
+}
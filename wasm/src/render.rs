@@ -0,0 +1,142 @@
+use crate::Chip8Wasm;
+use std::f64::consts::PI;
+use wasm_bindgen::prelude::*;
+use web_sys::CanvasRenderingContext2d;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Style {
+    /// Plain filled squares
+    Solid,
+    /// Squares with dark lines between every pixel
+    Grid,
+    /// Round LEDs, unlit ones faintly visible
+    Dots,
+    /// Dark line along the bottom of every pixel row, like a CRT
+    Scanlines,
+}
+
+/// How `draw_screen` turns pixels into canvas shapes
+pub struct Renderer {
+    style: Style,
+    gap: f64,             // canvas pixels between cells, or scanline height
+    intensity: f64,       // 0-1, strength of the grid, scanlines or unlit dots
+    palette: [String; 3], // CSS colours for colour indices 1-3
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Renderer {
+            style: Style::Solid,
+            gap: 1.0,
+            intensity: 0.5,
+            palette: ["white", "#ff6600", "#996600"].map(String::from),
+        }
+    }
+}
+
+impl Renderer {
+    /// Replace the CSS colour for colour index `idx + 1`
+    #[cfg(feature = "settings")]
+    pub fn set_color(&mut self, idx: usize, css: String) {
+        self.palette[idx] = css;
+    }
+
+    /// Draw lit pixels in their palette colours, then any effect on top
+    ///
+    /// `colors` are the colour indices from `get_display_colors`, `cols` is
+    /// the number of pixels per row, `scale` the canvas size of each.
+    pub fn draw(&self, ctx: &CanvasRenderingContext2d, colors: &[u8], cols: usize, scale: f64) {
+        let rows = colors.len() / cols;
+        let (width, height) = (cols as f64 * scale, rows as f64 * scale);
+        let cell = |i: usize| ((i % cols) as f64 * scale, (i / cols) as f64 * scale);
+
+        ctx.save();
+        match self.style {
+            Style::Solid | Style::Grid | Style::Scanlines => {
+                for (color, fill) in (1..).zip(&self.palette) {
+                    ctx.set_fill_style_str(fill);
+                    for (i, _) in colors.iter().enumerate().filter(|(_, c)| **c == color) {
+                        let (x, y) = cell(i);
+                        ctx.fill_rect(x, y, scale, scale);
+                    }
+                }
+            }
+            Style::Dots => {
+                let radius = ((scale - self.gap) / 2.0).max(0.5);
+                for (i, color) in colors.iter().enumerate() {
+                    let on = *color != 0;
+                    if !on && self.intensity <= 0.0 {
+                        continue;
+                    }
+                    let (x, y) = cell(i);
+                    // Unlit dots are a faint version of the first colour
+                    ctx.set_fill_style_str(&self.palette[(*color).max(1) as usize - 1]);
+                    ctx.set_global_alpha(if on { 1.0 } else { self.intensity * 0.25 });
+                    ctx.begin_path();
+                    let _ = ctx.arc(x + scale / 2.0, y + scale / 2.0, radius, 0.0, 2.0 * PI);
+                    ctx.fill();
+                }
+            }
+        }
+        ctx.restore();
+
+        ctx.save();
+        ctx.set_fill_style_str(&format!("rgba(0, 0, 0, {})", self.intensity));
+        match self.style {
+            Style::Grid => {
+                for col in 1..cols {
+                    ctx.fill_rect(col as f64 * scale - self.gap / 2.0, 0.0, self.gap, height);
+                }
+                for row in 1..rows {
+                    ctx.fill_rect(0.0, row as f64 * scale - self.gap / 2.0, width, self.gap);
+                }
+            }
+            Style::Scanlines => {
+                for row in 0..rows {
+                    ctx.fill_rect(0.0, (row + 1) as f64 * scale - self.gap, width, self.gap);
+                }
+            }
+            Style::Solid | Style::Dots => {}
+        }
+        ctx.restore();
+    }
+}
+
+#[wasm_bindgen]
+impl Chip8Wasm {
+    /// Pick how pixels are drawn: "solid", "grid", "dots" or "scanlines"
+    ///
+    /// `gap` is in canvas pixels. `intensity` (0-1) is how dark the grid or
+    /// scanlines are, or how visible unlit dots are.
+    #[wasm_bindgen]
+    pub fn set_render_style(
+        &mut self,
+        style: &str,
+        gap: f64,
+        intensity: f64,
+    ) -> Result<(), JsValue> {
+        let style = match style {
+            "solid" => Style::Solid,
+            "grid" => Style::Grid,
+            "dots" => Style::Dots,
+            "scanlines" => Style::Scanlines,
+            _ => {
+                return Err(JsValue::from_str(&format!(
+                    "unknown render style '{}'",
+                    style
+                )))
+            }
+        };
+        self.renderer.style = style;
+        self.renderer.gap = gap.max(0.0);
+        self.renderer.intensity = intensity.clamp(0.0, 1.0);
+        Ok(())
+    }
+
+    /// CSS colours for pixels lit in XO-CHIP plane 1, plane 2 and both.
+    /// Plain CHIP-8 games only use the first.
+    #[wasm_bindgen]
+    pub fn set_palette(&mut self, plane1: &str, plane2: &str, both: &str) {
+        self.renderer.palette = [plane1, plane2, both].map(String::from);
+    }
+}
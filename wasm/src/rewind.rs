@@ -0,0 +1,21 @@
+use crate::Chip8Wasm;
+use chip8_core::Rewind;
+use wasm_bindgen::prelude::*;
+
+// 30 seconds at 60 frames a second, a snapshot every 5 frames
+const SNAPSHOTS: usize = 360;
+const INTERVAL: u32 = 5;
+
+pub fn new() -> Rewind {
+    Rewind::new(SNAPSHOTS, INTERVAL)
+}
+
+#[wasm_bindgen]
+impl Chip8Wasm {
+    /// Go back `frames` frames (up to 30 seconds), returns false if there's
+    /// no history yet
+    #[wasm_bindgen]
+    pub fn rewind(&mut self, frames: u32) -> bool {
+        self.rewind.rewind(frames as u64, &mut self.chip8)
+    }
+}
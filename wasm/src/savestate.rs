@@ -0,0 +1,21 @@
+use crate::Chip8Wasm;
+use chip8_core::SaveState;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+impl Chip8Wasm {
+    /// The whole machine as a JSON string, e.g. for localStorage
+    #[wasm_bindgen]
+    pub fn save_state(&self) -> String {
+        self.chip8.save_state().to_json()
+    }
+
+    /// Resume from a string written by `save_state`
+    #[wasm_bindgen]
+    pub fn load_state(&mut self, json: &str) -> Result<(), JsValue> {
+        let save = SaveState::from_json(json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.chip8
+            .load_state(&save)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
@@ -0,0 +1,115 @@
+use crate::Chip8Wasm;
+use chip8_core::settings::GameSettings;
+use chip8_core::{Settings, Variant};
+use js_sys::{Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+/// The last settings file and its entry for the loaded ROM
+#[derive(Default)]
+pub struct Loaded {
+    file: Settings,
+    game: Option<GameSettings>,
+}
+
+#[wasm_bindgen(typescript_custom_section)]
+const TYPES: &str = r#"
+/** What a settings file leaves to the page, see `Chip8Wasm.load_settings` */
+export interface PageSettings {
+  /** Instructions per second */
+  ips?: number;
+  /** CSS colour */
+  background?: string;
+  volume?: number;
+  sound?: boolean;
+}
+
+export interface GameSettings {
+  title?: string;
+  ips?: number;
+}
+"#;
+
+#[wasm_bindgen]
+impl Chip8Wasm {
+    /// Apply a TOML settings file's key bindings, colours and quirks
+    ///
+    /// Keys are `KeyboardEvent.key` names. A `variant` sets that variant's
+    /// quirks, its memory size and opcodes need a reload to change. Returns
+    /// `{ ips, background, volume, sound }` with whichever the file sets,
+    /// for the page to apply. Later `bind_key`, `set_palette` and
+    /// `set_quirk` calls override the file. Its `games` entries apply as
+    /// matching ROMs load, see `game_settings`.
+    #[wasm_bindgen(unchecked_return_type = "PageSettings")]
+    pub fn load_settings(&mut self, text: &str) -> Result<JsValue, JsValue> {
+        let settings = Settings::from_toml(text).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+        for (key, &button) in &settings.keys {
+            self.bind_key(key, button as usize)?;
+        }
+        let colors = &settings.colors;
+        for (idx, color) in [colors.plane1, colors.plane2, colors.both]
+            .into_iter()
+            .enumerate()
+        {
+            if let Some(color) = color {
+                self.renderer.set_color(idx, color.to_string());
+            }
+        }
+        let mut quirks = settings
+            .variant
+            .map_or(self.chip8.quirks(), Variant::quirks);
+        settings.quirks.apply(&mut quirks);
+        self.chip8.set_quirks(quirks);
+
+        let page = Object::new();
+        let set = |name: &str, value: JsValue| Reflect::set(&page, &name.into(), &value);
+        if let Some(ips) = settings.ips {
+            set("ips", ips.into())?;
+        }
+        if let Some(color) = colors.background {
+            set("background", color.to_string().into())?;
+        }
+        if let Some(volume) = settings.audio.volume {
+            set("volume", volume.into())?;
+        }
+        if let Some(enabled) = settings.audio.enabled {
+            set("sound", enabled.into())?;
+        }
+        self.settings.file = settings;
+        Ok(page.into())
+    }
+
+    /// `{ title, ips }` from the settings file's entry for the loaded ROM,
+    /// with whichever it sets, or null if it has none
+    #[wasm_bindgen(unchecked_return_type = "GameSettings | null")]
+    pub fn game_settings(&self) -> Result<JsValue, JsValue> {
+        let Some(game) = &self.settings.game else {
+            return Ok(JsValue::NULL);
+        };
+        let page = Object::new();
+        if let Some(title) = &game.title {
+            Reflect::set(&page, &"title".into(), &title.into())?;
+        }
+        if let Some(ips) = game.ips {
+            Reflect::set(&page, &"ips".into(), &ips.into())?;
+        }
+        Ok(page.into())
+    }
+}
+
+impl Chip8Wasm {
+    /// Switch to the quirks the settings file gives `rom`, if it has an
+    /// entry for it
+    pub(crate) fn apply_game_settings(&mut self, rom: &[u8]) {
+        self.settings.game = self.settings.file.game(rom).cloned();
+        if self.settings.game.is_none() {
+            return;
+        }
+        let settings = self.settings.file.for_rom(rom);
+        let mut quirks = settings
+            .variant
+            .map_or(self.chip8.quirks(), Variant::quirks);
+        settings.quirks.apply(&mut quirks);
+        self.chip8.set_quirks(quirks);
+    }
+}
@@ -0,0 +1,39 @@
+// 60Hz frame clock for the emulator, run as a worker.
+//
+// requestAnimationFrame follows the display (so 120Hz screens run it twice as
+// often) and is heavily throttled when the tab is in the background. Worker
+// timers are neither, so DT/ST count down at the right speed and beeps last
+// as long as the game asked for on every device.
+
+const FRAME_MS = 1000 / 60;
+const MAX_CATCHUP_FRAMES = 4;
+let timer = 0;
+let next = 0;
+
+function schedule() {
+  const now = performance.now();
+  let frames = 0;
+  while (next <= now) {
+    frames++;
+    next += FRAME_MS;
+  }
+  if (frames > MAX_CATCHUP_FRAMES) {
+    // We were stalled, drop the backlog rather than fast-forward through it
+    frames = MAX_CATCHUP_FRAMES;
+    next = now + FRAME_MS;
+  }
+  if (frames > 0) {
+    postMessage(frames);
+  }
+  // Aim for the next deadline rather than a fixed delay so errors don't drift
+  timer = setTimeout(schedule, Math.max(0, next - performance.now()));
+}
+
+onmessage = (evt) => {
+  clearTimeout(timer);
+  timer = 0;
+  if (evt.data === "start") {
+    next = performance.now() + FRAME_MS;
+    timer = setTimeout(schedule, FRAME_MS);
+  }
+};
@@ -1,10 +1,21 @@
-import init, * as wasm from "./wasm.js";
+import init, * as wasm from "./chip8_wasm.js";
 
 const WIDTH = 64;
 const HEIGHT = 32;
 const SCALE = 15;
-const TICKS_PER_FRAME = 10;
+const DEFAULT_TICKS_PER_FRAME = 10;
+// chip8.toml can change the speed and background, packages the speed again
+let default_ticks_per_frame = DEFAULT_TICKS_PER_FRAME;
+let ticks_per_frame = DEFAULT_TICKS_PER_FRAME;
+let background = "black";
 let anim_frame = 0;
+let running = false;
+let loaded = false;
+// Backspace held runs the game backwards
+let rewinding = false;
+
+// Emulated frames are paced by a worker clock, rendering by requestAnimationFrame
+const clock = new Worker(new URL("./clock.js", import.meta.url));
 
 const canvas = document.getElementById("canvas");
 canvas.width = WIDTH * SCALE;
@@ -15,26 +26,55 @@ ctx.fillStyle = "black";
 ctx.fillRect(0, 0, WIDTH * SCALE, HEIGHT * SCALE);
 
 const input = document.getElementById("fileinput");
+const style = document.getElementById("style");
 
 async function run() {
   await init();
-  let chip8 = new wasm.Chip8Wasm();
+  let chip8 = new wasm.Chip8Wasm(canvas);
+  await load_settings(chip8);
 
   document.addEventListener("keydown", (evt) => {
+    if (evt.key === "F3" && chip8.toggle_overlay) {
+      // F3 toggles the debug overlay, when it's built in
+      evt.preventDefault();
+      chip8.toggle_overlay();
+      return;
+    }
+    if (evt.key === "Backspace") {
+      evt.preventDefault();
+      rewinding = true;
+      return;
+    }
     chip8.keypress(evt, true);
   });
 
+  style.addEventListener("change", () => {
+    // Gaps scale with the pixels so the look is the same at any size
+    chip8.set_render_style(style.value, Math.max(1, SCALE / 8), 0.5);
+  });
+
   document.addEventListener("keyup", (evt) => {
+    if (evt.key === "Backspace") {
+      rewinding = false;
+      return;
+    }
     chip8.keypress(evt, false);
   });
 
+  // Don't let games run ahead (or drain battery) while the tab is hidden
+  document.addEventListener("visibilitychange", () => {
+    if (document.hidden) {
+      pause();
+    } else if (loaded) {
+      start(chip8);
+    }
+  });
+
   input.addEventListener(
     "change",
     (evt) => {
       // handle file loading
-      if (anim_frame != 0) {
-        window.cancelAnimationFrame(anim_frame);
-      }
+      pause();
 
       let file = evt.target.files[0];
       if (!file) {
@@ -47,8 +87,24 @@ async function run() {
         let buffer = fr.result;
         const rom = new Uint8Array(buffer);
         chip8.reset();
-        chip8.load_game(rom);
-        mainloop(chip8);
+        try {
+          if (file.name.endsWith(".c8p") && chip8.load_package) {
+            chip8.load_package(rom);
+          } else {
+            chip8.load_game(rom);
+          }
+        } catch (err) {
+          loaded = false;
+          alert(err);
+          return;
+        }
+        loaded = true;
+        // Games keep high scores in the SCHIP flags, one set per ROM
+        chip8.persist_flags(`chip8-flags:${file.name}`);
+        apply_rom_info(chip8, file.name);
+        if (!document.hidden) {
+          start(chip8);
+        }
       };
       fr.readAsArrayBuffer(file);
     },
@@ -56,19 +112,108 @@ async function run() {
   );
 }
 
-function mainloop(chip8) {
-  for (let i = 0; i < TICKS_PER_FRAME; i++) {
-    chip8.tick();
+// Keys, colours, quirks and speed from a chip8.toml next to the page, if
+// there is one
+async function load_settings(chip8) {
+  if (!chip8.load_settings) {
+    return;
   }
+  const response = await fetch("chip8.toml").catch(() => null);
+  if (!response || !response.ok) {
+    return;
+  }
+  try {
+    const page = chip8.load_settings(await response.text());
+    if (page.ips) {
+      default_ticks_per_frame = Math.max(1, Math.round(page.ips / 60));
+      ticks_per_frame = default_ticks_per_frame;
+    }
+    background = page.background ?? background;
+  } catch (err) {
+    console.error(err);
+  }
+}
 
-  chip8.tick_timers();
+// Show the package's title and use the speed it was written for
+function apply_rom_info(chip8, file_name) {
+  const info = chip8.rom_info ? chip8.rom_info() : null;
+  const game = chip8.game_settings ? chip8.game_settings() : null;
+  const game_ticks = game?.ips ? Math.max(1, Math.round(game.ips / 60)) : null;
+  ticks_per_frame = info?.settings?.ticks_per_frame ?? game_ticks ?? default_ticks_per_frame;
+  if (info && info.title) {
+    document.title = info.author ? `${info.title} by ${info.author}` : info.title;
+  } else {
+    document.title = game?.title ?? file_name;
+  }
+}
 
-  ctx.fillStyle = "black";
-  ctx.fillRect(0, 0, WIDTH * SCALE, HEIGHT * SCALE);
+function start(chip8) {
+  if (running) {
+    return;
+  }
+  running = true;
+  clock.onmessage = (evt) => run_frames(chip8, evt.data);
+  clock.postMessage("start");
+  anim_frame = window.requestAnimationFrame(() => draw(chip8));
+}
+
+function pause() {
+  if (!running) {
+    return;
+  }
+  running = false;
+  clock.postMessage("stop");
+  window.cancelAnimationFrame(anim_frame);
+  anim_frame = 0;
+}
+
+// Run the emulated frames the clock says are due
+function run_frames(chip8, frames) {
+  if (!running) {
+    // A tick posted just before we paused
+    return;
+  }
+  if (rewinding) {
+    // Twice as fast as the game ran, it's usually a few seconds at most
+    chip8.rewind(2 * frames);
+    return;
+  }
+  for (let frame = 0; frame < frames; frame++) {
+    try {
+      chip8.run_frame(ticks_per_frame);
+    } catch (err) {
+      // The ROM faulted, stop rather than spin on the bad instruction
+      pause();
+      loaded = false;
+      alert(err);
+      return;
+    }
+    if (chip8.halted()) {
+      // The program exited, leave its last screen up
+      pause();
+      loaded = false;
+      return;
+    }
+    if (chip8.state() === "break") {
+      // Resuming runs the instruction at the breakpoint
+      pause();
+      return;
+    }
+  }
+}
+
+function draw(chip8) {
+  // The 64x64 HIRES CHIP-8 mode needs a taller canvas
+  const height = (WIDTH * SCALE * chip8.screen_height()) / chip8.screen_width();
+  if (canvas.height !== height) {
+    canvas.height = height;
+  }
+  ctx.fillStyle = background;
+  ctx.fillRect(0, 0, canvas.width, canvas.height);
   ctx.fillStyle = "white";
   chip8.draw_screen(SCALE);
 
-  anim_frame = window.requestAnimationFrame(() => mainloop(chip8));
+  anim_frame = window.requestAnimationFrame(() => draw(chip8));
 }
 
 run().catch(console.error);